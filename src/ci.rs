@@ -0,0 +1,62 @@
+use std::env;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::OnceLock;
+use std::time::Instant;
+
+use crate::error_code::Code;
+
+/// Set by most CI services (ex. GitHub Actions, GitLab CI), turning CI mode on like `-ci`
+/// unless it is `false` or `0`.
+pub const CI_VARIABLE: &str = "CI";
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// When build++ started, for the duration in [`print_summary`].
+static STARTED: OnceLock<Instant> = OnceLock::new();
+
+/// Turns CI mode on if [`CI_VARIABLE`] is set.
+pub fn init() {
+    STARTED.get_or_init(Instant::now);
+
+    let enabled = env::var(CI_VARIABLE).is_ok_and(|ci| {
+        !matches!(
+            ci.trim()
+                .to_lowercase()
+                .as_str(),
+            "" | "0" | "false"
+        )
+    });
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Turns CI mode on (ex. by `-ci`).
+pub fn enable() { ENABLED.store(true, Ordering::Relaxed); }
+
+/// Whether build++ runs non-interactively for CI: nothing is prompted for, progress is logged
+/// line by line without escape codes, output of dependency builds is always shown, and
+/// warnings that can be denied (`-strict`, `-deny-new-warnings`) fail the build.
+pub fn is_enabled() -> bool { ENABLED.load(Ordering::Relaxed) }
+
+/// Prints the last line of build++ in CI mode, for scripts to parse
+/// (ex. `buildpp: status=failed exit=4 code=BPP0318 duration=12.48s`).
+pub fn print_summary(exit: i32, code: Option<Code>) {
+    if !is_enabled() {
+        return;
+    }
+    let elapsed = STARTED
+        .get()
+        .map(Instant::elapsed)
+        .unwrap_or_default();
+    eprintln!(
+        "buildpp: status={} exit={} code={} duration={:.2}s",
+        match exit {
+            0 => "ok",
+            _ => "failed",
+        },
+        exit,
+        code.map_or("-".to_string(), |code| code
+            .to_string()),
+        elapsed.as_secs_f64()
+    );
+}
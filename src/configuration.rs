@@ -1,42 +1,176 @@
+use std::cell::RefCell;
+use std::cmp::Reverse;
+use std::collections::HashSet;
+use std::env;
+use std::ffi::OsStr;
+use std::ffi::OsString;
 use std::fs;
 use std::fs::File;
+use std::fs::OpenOptions;
 use std::io;
+use std::io::Write;
+use std::iter;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Child;
 use std::process::Command;
+use std::process::ExitStatus;
 use std::process::Stdio;
 use std::rc::Rc;
+use std::str::FromStr;
+use std::time::Duration;
+use std::time::Instant;
+use std::time::SystemTime;
 
+use indexmap::IndexMap;
+
+use crate::ci;
 use crate::dependency;
 use crate::dependency::Dependency;
+use crate::jobserver;
 use crate::key;
 use crate::lsd::LSDGetExt;
 use crate::lsd::LSDParseError;
+use crate::lsd::Level;
 use crate::lsd::Map;
 use crate::lsd::Value;
 use crate::lsd::LSD;
+use crate::probe;
 use crate::profile;
+use crate::progress;
+use crate::remote_cache;
+use crate::stats;
+use crate::targets;
 use crate::util;
+use crate::util::concat_os;
 use crate::util::last_modified_recursive;
+use crate::util::replace_os;
+use crate::util::split_file_name;
 use crate::util::BoolGuardExt;
+use crate::util::PushFrom;
+use crate::warnings;
 use crate::BuildError;
 use crate::BuildType;
 use crate::Dir;
+use crate::Emit;
 use crate::Profile;
 use crate::RunError;
+use crate::Target;
+use crate::TestError;
 use crate::Version;
 
 //
 // Run
 //
 
+/// Placeholders replaced in the command and arguments of `run` (ex. `{artifact_dir}`),
+/// braces that are not a placeholder are written doubled (ex. `{{}}` for `{}`).
+#[derive(Debug, Clone, Copy)]
+enum RunPlaceholder {
+    /// `{artifact}` or `{}`, the binary being run.
+    Artifact,
+    /// `{artifact_dir}`, the dir the binary is in.
+    ArtifactDir,
+    /// `{project_dir}`
+    ProjectDir,
+    /// `{profile}`, name of the profile the binary was built with.
+    Profile,
+    /// `{version}` of the project.
+    Version,
+}
+
+impl FromStr for RunPlaceholder {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "" | "artifact" => Self::Artifact,
+            "artifact_dir" => Self::ArtifactDir,
+            "project_dir" => Self::ProjectDir,
+            "profile" => Self::Profile,
+            "version" => Self::Version,
+            _ => return Err(()),
+        })
+    }
+}
+
+/// `text` with its placeholders replaced by `value` of them and doubled braces undoubled,
+/// or the first unknown placeholder. Unclosed braces are kept as they are.
+fn substitute(
+    text: &str,
+    mut value: impl FnMut(RunPlaceholder) -> OsString,
+) -> Result<OsString, Value> {
+    let mut result = OsString::new();
+    let mut rest = text;
+    while let Some(position) = rest.find(['{', '}']) {
+        result.push(&rest[..position]);
+        rest = &rest[position..];
+
+        if rest.starts_with("{{") || rest.starts_with("}}") {
+            result.push(&rest[..1]);
+            rest = &rest[2..];
+            continue;
+        }
+
+        match rest
+            .strip_prefix('{')
+            .and_then(|rest| rest.split_once('}'))
+        {
+            Some((name, after)) => {
+                let placeholder = name
+                    .parse()
+                    .map_err(|()| Value::from(name))?;
+                result.push(value(placeholder));
+                rest = after;
+            },
+            None => {
+                result.push(&rest[..1]);
+                rest = &rest[1..];
+            },
+        }
+    }
+    result.push(rest);
+    Ok(result)
+}
+
+/// What the program of `buildpp run` reads as its stdin.
+#[derive(Debug, Clone, Default)]
+enum RunStdin {
+    /// The stdin of build++ (ex. the terminal), `stdin inherit`.
+    #[default]
+    Inherit,
+    /// Nothing, reading gives end of file right away, `stdin null`.
+    Null,
+    /// A file relative to the project dir (ex. `stdin tests/input.txt`).
+    File(Value),
+}
+
+impl From<Value> for RunStdin {
+    fn from(value: Value) -> Self {
+        match &*value {
+            "inherit" => RunStdin::Inherit,
+            "null" => RunStdin::Null,
+            _ => RunStdin::File(value),
+        }
+    }
+}
+
+/// Command run by `buildpp run`, with [`RunPlaceholder`]s.
 struct Run {
     command: Value,
     arguments: Vec<Value>,
+    /// Whether to only show output of the program (as with `buildpp run -quiet`).
+    quiet: bool,
+    stdin: RunStdin,
+    /// Whether to run the program in a pseudo-terminal, so that it behaves interactively
+    /// (ex. colors, line editing) even when output of build++ is piped.
+    tty: bool,
 }
 
 impl Run {
     fn parse(lsd: LSD) -> Result<Run, LoadError> {
         use LoadError::*;
-        Ok(match lsd {
+        let run = match lsd {
             // Parse `run "full command with spaces and with {} substitution"`
             LSD::Value(value) => {
                 let mut value = value.split_whitespace();
@@ -48,6 +182,9 @@ impl Run {
                     arguments: value
                         .map(Value::from)
                         .collect(),
+                    quiet: false,
+                    stdin: RunStdin::default(),
+                    tty: false,
                 }
             },
 
@@ -77,6 +214,9 @@ impl Run {
                             .unwrap_or("{}")
                             .into(),
                         arguments: list.collect(),
+                        quiet: false,
+                        stdin: RunStdin::default(),
+                        tty: false,
                     }
                 },
 
@@ -128,20 +268,510 @@ impl Run {
                             .transpose()?
                             .unwrap_or_default()
                     },
+
+                    quiet: level
+                        .get_parse(
+                            key!(quiet),
+                            RunQuietIsNotABool,
+                        )?
+                        .unwrap_or(false),
+
+                    stdin: level
+                        .get_value(
+                            key!(stdin),
+                            RunStdinIsNotAValue,
+                        )?
+                        .map(RunStdin::from)
+                        .unwrap_or_default(),
+
+                    tty: level
+                        .get_parse(key!(tty), RunTtyIsNotABool)?
+                        .unwrap_or(false),
                 },
             },
+        };
+
+        // unknown placeholders are most likely typos, so they are not passed on as they are
+        for piece in iter::once(&run.command).chain(&run.arguments) {
+            substitute(piece, |_| OsString::new()).map_err(UnknownRunPlaceholder)?;
+        }
+
+        Ok(run)
+    }
+}
+
+//
+// Test
+//
+
+#[derive(Default)]
+struct Test {
+    runner: Option<Rc<[Value]>>,
+}
+
+impl Test {
+    fn parse(lsd: LSD) -> Result<Test, LoadError> {
+        use LoadError::*;
+
+        let level = lsd
+            .to_level()
+            .ok_or(TestIsNotALevel)?;
+
+        Ok(Test {
+            runner: level
+                .get_inner(key!(runner))
+                .map(|runner| match runner {
+                    // Parse `runner "wrapper command with spaces"`
+                    LSD::Value(value) => Ok(value
+                        .split_whitespace()
+                        .map(Rc::from)
+                        .collect()),
+
+                    // Parse `runner [ each list item being a command or arg ]`
+                    LSD::Level(list) => list
+                        .values()
+                        .map(|arg| {
+                            arg.to_value()
+                                .ok_or(TestRunnerPieceIsNotAValue)
+                        })
+                        .collect(),
+                })
+                .transpose()?,
+        })
+    }
+}
+
+//
+// Analyze
+//
+
+#[derive(Default)]
+struct Analyze {
+    analyzer: Option<Rc<[Value]>>,
+    suppressions: Option<Dir>,
+}
+
+impl Analyze {
+    fn parse(lsd: LSD, config_dir: &Path) -> Result<Analyze, LoadError> {
+        use LoadError::*;
+
+        let level = lsd
+            .to_level()
+            .ok_or(AnalyzeIsNotALevel)?;
+
+        Ok(Analyze {
+            analyzer: level
+                .get_inner(key!(analyzer))
+                .map(|analyzer| match analyzer {
+                    // Parse `analyzer "command with spaces"`
+                    LSD::Value(value) => Ok(value
+                        .split_whitespace()
+                        .map(Rc::from)
+                        .collect()),
+
+                    // Parse `analyzer [ each list item being a command or arg ]`
+                    LSD::Level(list) => list
+                        .values()
+                        .map(|arg| {
+                            arg.to_value()
+                                .ok_or(AnalyzerPieceIsNotAValue)
+                        })
+                        .collect(),
+                })
+                .transpose()?,
+
+            suppressions: level
+                .get_value(
+                    key!(suppressions),
+                    AnalyzeSuppressionsIsNotAValue,
+                )?
+                .map(|suppressions| Dir::from(config_dir.join(&*suppressions))),
+        })
+    }
+}
+
+//
+// Deploy
+//
+
+/// Profile deployed when `deploy.profile` is not given.
+const DEFAULT_DEPLOY_PROFILE: &str = "release";
+
+/// Commands uploading the artifact (ex. with scp, rsync or a cloud CLI), with [`RunPlaceholder`]s.
+struct Deploy {
+    profile: profile::Name,
+    /// Whether `buildpp build` of the profile also deploys once it succeeds.
+    after_build: bool,
+    commands: Vec<Rc<[Value]>>,
+}
+
+impl Deploy {
+    fn parse(lsd: LSD) -> Result<Deploy, LoadError> {
+        use LoadError::*;
+
+        let level = lsd
+            .to_level()
+            .ok_or(DeployIsNotALevel)?;
+
+        let deploy = Deploy {
+            profile: level
+                .get_value(
+                    key!(profile),
+                    DeployProfileIsNotAValue,
+                )?
+                .unwrap_or_else(|| DEFAULT_DEPLOY_PROFILE.into()),
+
+            after_build: level
+                .get_parse(
+                    key!(after_build),
+                    DeployAfterBuildIsNotABool,
+                )?
+                .unwrap_or(false),
+
+            commands: match level.get_inner(key!(commands)) {
+                Some(LSD::Level(list)) => list
+                    .values()
+                    .map(|command| match command {
+                        // Parse `"command with spaces and with {} substitution"`
+                        LSD::Value(value) => Ok(value
+                            .split_whitespace()
+                            .map(Rc::from)
+                            .collect::<Rc<[_]>>()),
+
+                        // Parse `[ each list item being a command or arg ]`
+                        LSD::Level(pieces) => pieces
+                            .values()
+                            .map(|piece| {
+                                if piece
+                                    .to_level()
+                                    .is_some_and(|l| l.is_empty())
+                                {
+                                    return Ok("{}".into());
+                                }
+
+                                piece
+                                    .to_value()
+                                    .ok_or(DeployCommandPieceIsNotAValue)
+                            })
+                            .collect(),
+                    })
+                    .filter(|command| {
+                        command
+                            .as_ref()
+                            .map_or(true, |command| {
+                                !command.is_empty()
+                            })
+                    })
+                    .collect::<Result<_, _>>()?,
+                Some(LSD::Value(_)) => return Err(DeployCommandsIsNotAList),
+                None => Vec::new(),
+            },
+        };
+
+        // unknown placeholders are most likely typos, so they are not passed on as they are
+        for piece in deploy
+            .commands
+            .iter()
+            .flat_map(|command| command.iter())
+        {
+            substitute(piece, |_| OsString::new()).map_err(UnknownDeployPlaceholder)?;
+        }
+
+        Ok(deploy)
+    }
+}
+
+//
+// Toolchain
+//
+
+/// Cross-compilation settings (ex. of an embedded SDK) described once in their own file,
+/// applied on top of every profile. Other keys of the file are read as profile keys
+/// (ex. `compiler_path`).
+#[derive(Debug, Clone, Default)]
+pub struct Toolchain {
+    pub sysroot: Option<Dir>,
+    /// Target triple (ex. `arm-none-eabi`).
+    pub target: Option<Value>,
+    /// Passed to the compiler as-is.
+    pub flags: Rc<[Value]>,
+}
+
+impl Toolchain {
+    fn load(toolchain_file: &Path) -> Result<(Toolchain, Level), LoadError> {
+        use LoadError::*;
+
+        let file = File::open(toolchain_file)
+            .map_err(Rc::new)
+            .map_err(ToolchainCouldNotOpen)?;
+        let lsd = LSD::parse(file).map_err(ToolchainCouldNotParseLSD)?;
+        let level = lsd
+            .to_level()
+            .ok_or(ToolchainIsNotALevel)?;
+
+        // relative paths inside of the toolchain file are relative to the toolchain file
+        let toolchain_dir = toolchain_file
+            .parent()
+            .unwrap_or(Path::new(""));
+
+        let toolchain = Toolchain {
+            sysroot: level
+                .get_value(
+                    key!(sysroot),
+                    ToolchainSysrootIsNotAValue,
+                )?
+                .map(|sysroot| Dir::from(toolchain_dir.join(&*sysroot))),
+
+            target: level.get_value(
+                key!(target),
+                ToolchainTargetIsNotAValue,
+            )?,
+
+            flags: match level.get_inner(key!(flags)) {
+                // Parse `flags "flags separated with spaces"`
+                Some(LSD::Value(value)) => value
+                    .split_whitespace()
+                    .map(Rc::from)
+                    .collect(),
+
+                // Parse `flags [ each list item being a flag ]`
+                Some(LSD::Level(list)) => list
+                    .values()
+                    .map(|flag| {
+                        flag.to_value()
+                            .ok_or(ToolchainFlagIsNotAValue)
+                    })
+                    .collect::<Result<_, _>>()?,
+
+                None => Rc::from([]),
+            },
+        };
+
+        Ok((toolchain, level))
+    }
+}
+
+/// Compile settings of one kind of target (`binary`, `library`, `tests`), of a single test, or of
+/// sources matching a pattern (`file."src/generated/*.cpp"`), layered on top of the profile.
+/// Other keys are read as profile keys (ex. `standard`).
+#[derive(Debug, Clone, Default)]
+pub struct TargetOverride {
+    /// `NAME` or `NAME=VALUE`.
+    pub defines: Rc<[Value]>,
+    /// Passed to the compiler as-is.
+    pub flags: Rc<[Value]>,
+    level: Level,
+}
+
+impl TargetOverride {
+    fn parse(name: &Value, lsd: &LSD) -> Result<TargetOverride, LoadError> {
+        use LoadError::*;
+
+        let level = lsd
+            .to_level()
+            .ok_or_else(|| TargetIsNotALevel(name.clone()))?;
+
+        let list = |key: Vec<Value>| match level.get_inner(key) {
+            // Parse `key "items separated with spaces"`
+            Some(LSD::Value(value)) => Ok(value
+                .split_whitespace()
+                .map(Rc::from)
+                .collect()),
+
+            // Parse `key [ each list item being an item ]`
+            Some(LSD::Level(list)) => list
+                .values()
+                .map(|item| {
+                    item.to_value()
+                        .ok_or_else(|| TargetPieceIsNotAValue(name.clone()))
+                })
+                .collect::<Result<_, _>>(),
+
+            None => Ok(Rc::from([])),
+        };
+
+        Ok(TargetOverride {
+            defines: list(key!(defines))?,
+            flags: list(key!(flags))?,
+            level,
+        })
+    }
+}
+
+/// Overrides applying to a target by their names, and the profile with them applied.
+type Overrides<'a> = (
+    Vec<(Value, &'a TargetOverride)>,
+    Option<Rc<dyn Profile>>,
+);
+
+/// Wrapper commands known by name, set up to fail when the wrapper itself finds errors.
+pub fn runner_preset(name: &str) -> Option<Rc<[Value]>> {
+    let preset: &[&str] = match name
+        .to_lowercase()
+        .as_str()
+    {
+        "valgrind" | "memcheck" => &[
+            "valgrind",
+            "--quiet",
+            "--error-exitcode=101",
+            "--leak-check=full",
+        ],
+        "helgrind" => &[
+            "valgrind",
+            "--quiet",
+            "--tool=helgrind",
+            "--error-exitcode=101",
+        ],
+        _ => return None,
+    };
+    Some(
+        preset
+            .iter()
+            .copied()
+            .map(Value::from)
+            .collect(),
+    )
+}
+
+#[derive(Debug, Clone)]
+pub struct TestOutcome {
+    pub name: Value,
+    pub code: Option<i32>,
+    pub duration: Duration,
+    /// Output of the test, empty unless it was captured.
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+}
+
+impl TestOutcome {
+    pub fn passed(&self) -> bool { self.code == Some(0) }
+}
+
+/// Start of a comment in a test source declaring its tags (ex. `// buildpp: tags=slow,gpu`).
+const TEST_TAGS_MARKER: &str = "buildpp: tags=";
+
+/// Tests ran by `buildpp test`, by their tags.
+#[derive(Debug, Clone, Default)]
+pub struct TestSelection {
+    /// Only tests with any of these are ran, every test when empty.
+    pub tags: Vec<Value>,
+    /// Tests with any of these are not ran, even if they have one of `tags`.
+    pub skip_tags: Vec<Value>,
+}
+
+impl TestSelection {
+    fn selects(&self, tags: &[Value]) -> bool {
+        (self
+            .tags
+            .is_empty()
+            || self
+                .tags
+                .iter()
+                .any(|tag| tags.contains(tag)))
+            && !self
+                .skip_tags
+                .iter()
+                .any(|tag| tags.contains(tag))
+    }
+}
+
+/// Tags of the test `name`: parts of its name after the first `.` (ex. `matmul.gpu.slow.cu`),
+/// and those listed by [`TEST_TAGS_MARKER`] comments in `src_file`.
+fn test_tags(name: &str, src_file: &Path) -> Result<Vec<Value>, io::Error> {
+    let mut tags = name
+        .split('.')
+        .skip(1)
+        .map(Value::from)
+        .collect::<Vec<_>>();
+    for line in String::from_utf8_lossy(&fs::read(src_file)?).lines() {
+        let Some((_, listed)) = line.split_once(TEST_TAGS_MARKER) else {
+            continue;
+        };
+        tags.extend(
+            listed
+                .split_whitespace()
+                .next()
+                .unwrap_or_default()
+                .split(',')
+                .filter(|tag| !tag.is_empty())
+                .map(Value::from),
+        );
+    }
+    Ok(tags)
+}
+
+//
+// Diagnostics
+//
+
+/// `compiler` is the compiler of `profile`, or another tool it uses (ex. `ar`).
+fn report_compiler_not_found(profile: &dyn Profile, profile_name: &str, compiler: &str) {
+    eprintln!(
+        "error: could not find compiler `{}` requested by profile `{}`",
+        compiler, profile_name,
+    );
+
+    if Path::new(compiler)
+        .parent()
+        .is_some_and(|parent| {
+            !parent
+                .as_os_str()
+                .is_empty()
         })
+    {
+        eprintln!("  the path does not exist or is not executable");
+    } else {
+        eprintln!("  searched PATH:");
+        for dir in util::search_path() {
+            eprintln!("    {}", dir.display());
+        }
     }
+
+    eprintln!(
+        "  hint: {}",
+        profile.installation_hint()
+    );
 }
 
 //
 // Configuration
 //
 
+/// Where the configuration of a project is read from.
+#[derive(Debug, Clone, Default)]
+pub enum ConfigSource {
+    /// `build++.lsd` in the project dir.
+    #[default]
+    Project,
+    /// Any manifest, relative paths inside of it are relative to it.
+    File(Dir),
+    /// Manifest given through stdin, relative paths inside of it are relative to the project dir.
+    Stdin,
+}
+
+impl ConfigSource {
+    /// Value of `-config` that reads the configuration from stdin.
+    pub const STDIN: &'static str = "-";
+
+    /// Source named by a `-config` value, paths being relative to `project_dir`.
+    pub fn from_argument(project_dir: &Path, argument: &str) -> Self {
+        match argument {
+            Self::STDIN => Self::Stdin,
+            file => Self::File(Dir::from(
+                project_dir.join(file),
+            )),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum LoadError {
+    CouldNotReadStdin(Rc<io::Error>),
+    CouldNotPersistStdin(Rc<io::Error>),
     CouldNotOpenConfiguration(Rc<io::Error>),
     CouldNotParseLSD(LSDParseError),
+    CouldNotOpenLocalConfiguration(Rc<io::Error>),
+    CouldNotParseLocalLSD(LSDParseError),
 
     MissingProjectName,
     ProjectNameIsNotAValue,
@@ -149,8 +779,14 @@ pub enum LoadError {
     MissingVersion,
     VersionIsNotAValue,
 
+    LicenseIsNotAValue,
+    DescriptionIsNotAValue,
+    RegistryIsNotAValue,
+
     DependenciesIsNotALevel,
     DependenciesErrors(Vec<dependency::ParseError>),
+    DependsOnItself(Dir),
+    DependencyCycle(Rc<[Dir]>),
 
     ProfilesIsNotALevel,
     ProfilesErrors(Vec<profile::ParseError>),
@@ -158,101 +794,1266 @@ pub enum LoadError {
     MissingCommandInRun,
     RunCommandIsNotAValue,
     RunPieceIsNotAValue,
+    UnknownRunPlaceholder(Value),
+    RunQuietIsNotABool,
+    RunStdinIsNotAValue,
+    RunTtyIsNotABool,
+
+    GroupsIsNotALevel,
+    GroupMemberIsNotAValue,
+
+    DeployIsNotALevel,
+    DeployProfileIsNotAValue,
+    DeployAfterBuildIsNotABool,
+    DeployCommandsIsNotAList,
+    DeployCommandPieceIsNotAValue,
+    UnknownDeployPlaceholder(Value),
+
+    TestIsNotALevel,
+    TestRunnerPieceIsNotAValue,
+
+    AnalyzeIsNotALevel,
+    AnalyzerPieceIsNotAValue,
+    AnalyzeSuppressionsIsNotAValue,
+
+    PkgConfigIsNotABool,
+    ChecksumsIsNotABool,
+    SignPieceIsNotAValue,
+
+    UnknownBackend,
+
+    ToolchainIsNotAValue,
+    ToolchainCouldNotOpen(Rc<io::Error>),
+    ToolchainCouldNotParseLSD(LSDParseError),
+    ToolchainIsNotALevel,
+    ToolchainSysrootIsNotAValue,
+    ToolchainTargetIsNotAValue,
+    ToolchainFlagIsNotAValue,
+    ToolchainProfileError(profile::ParseError),
+
+    TargetsIsNotALevel,
+    TargetIsNotALevel(Value),
+    TargetPieceIsNotAValue(Value),
+    FileOverridesIsNotALevel,
+
+    IncludeDirIsNotAValue,
+    IncludeExtensionIsNotAValue,
+
+    ExcludePatternIsNotAValue,
+
+    GeneratedSrcDirIsNotAValue,
+
+    RequiresBuildppIsNotAValue,
+    InvalidBuildppRequirement(Value),
+    /// With the requirement and the version of this build++.
+    BuildppVersionNotSupported(Value, Value),
+
+    /// Each with the key path it was found at.
+    KeyErrors(Vec<(Value, LoadError)>),
 }
 
 impl From<LSDParseError> for LoadError {
     fn from(value: LSDParseError) -> Self { Self::CouldNotParseLSD(value) }
 }
 
-pub struct Configuration {
-    config_file: Dir,
-    project_dir: Dir,
+impl LoadError {
+    /// What caused this error (ex. the I/O error), shown after it.
+    pub fn note(&self) -> Option<String> {
+        use LoadError::*;
+        match self {
+            CouldNotReadStdin(err)
+            | CouldNotPersistStdin(err)
+            | CouldNotOpenConfiguration(err)
+            | CouldNotOpenLocalConfiguration(err)
+            | ToolchainCouldNotOpen(err) => Some(err.to_string()),
+            CouldNotParseLSD(err) | CouldNotParseLocalLSD(err) | ToolchainCouldNotParseLSD(err) =>
+                Some(err.to_string()),
+            DependenciesErrors(errs) => errs
+                .iter()
+                .find_map(dependency::ParseError::note),
+            KeyErrors(errs) => errs
+                .iter()
+                .find_map(|(_, err)| err.note()),
+            _ => None,
+        }
+    }
+}
 
-    name: Value,
-    version: Version,
+/// Errors of keys of a configuration, collected instead of stopping at the first one.
+#[derive(Default)]
+struct CollectedErrors(Vec<(Value, LoadError)>);
 
-    dependencies: Map<dependency::Alias, Rc<dyn Dependency>>,
-    profiles: Map<profile::Name, Rc<dyn Profile>>,
+impl CollectedErrors {
+    fn check<T>(&mut self, key: impl Into<Value>, result: Result<T, LoadError>) -> Option<T> {
+        result
+            .map_err(|err| {
+                self.0
+                    .push((key.into(), err))
+            })
+            .ok()
+    }
 
-    run: Option<Run>,
+    /// A single error is returned as-is.
+    fn into_result(mut self) -> Result<(), LoadError> {
+        match self
+            .0
+            .len()
+        {
+            0 => Ok(()),
+            1 => Err(self
+                .0
+                .remove(0)
+                .1),
+            _ => Err(LoadError::KeyErrors(self.0)),
+        }
+    }
 }
 
-impl Configuration {
-    // Basic info
+thread_local! {
+    /// Canonical dirs of projects whose dependencies are currently being loaded (and built).
+    static LOADING: RefCell<Vec<Dir>> = RefCell::default();
+}
 
-    pub fn load(project_dir: Dir) -> Result<Self, LoadError> {
-        use LoadError::*;
+/// Options of the current build++ invocation, applying to builds of dependencies as well.
+#[derive(Debug, Clone, Default)]
+pub struct BuildOptions {
+    /// Image to run compilers (and ninja) in, with the project mounted at the same path.
+    pub container_image: Option<Value>,
+    /// Toolchain file used instead of the one set by `toolchain` of each configuration.
+    pub toolchain: Option<Dir>,
+    /// Show output of dependency builds even when they succeed.
+    pub verbose: bool,
+    /// Show no progress, and keep stdout for the program being run (tools write to stderr).
+    pub quiet: bool,
+    /// Fail instead of warning when a vendored dependency differs from its pinned contents.
+    pub strict: bool,
+    /// Fail when a file has more compiler warnings than in the baseline of the profile.
+    pub deny_new_warnings: bool,
+}
 
-        const CONFIG_FILENAME: &str = "build++.lsd";
-        let config_file = project_dir
-            .join(CONFIG_FILENAME)
-            .into();
+thread_local! {
+    static BUILD_OPTIONS: RefCell<BuildOptions> = RefCell::default();
+}
 
-        let file = File::open(&config_file)
-            .map_err(Rc::new)
-            .map_err(CouldNotOpenConfiguration)?;
-        let lsd = LSD::parse(file)?;
+pub fn set_build_options(options: BuildOptions) {
+    progress::set_quiet(options.quiet);
+    BUILD_OPTIONS.with(|build_options| *build_options.borrow_mut() = options);
+}
 
-        Ok(Configuration {
-            config_file,
-            project_dir,
+fn build_options() -> BuildOptions {
+    let mut options = BUILD_OPTIONS.with(|options| {
+        options
+            .borrow()
+            .clone()
+    });
+    // CI keeps full logs, and fails on what would only be warned about
+    if ci::is_enabled() {
+        options.verbose = true;
+        options.strict = true;
+        options.deny_new_warnings = true;
+    }
+    options
+}
 
-            name: lsd
-                .get_value(
-                    key!(name),
-                    ProjectNameIsNotAValue,
-                )?
-                .ok_or(MissingProjectName)?,
+/// Output of tools run while caching a dependency, kept in its cache dir.
+const BUILD_LOG_FILENAME: &str = "build.log";
 
-            version: lsd
-                .get_value(
-                    key!(version),
-                    VersionIsNotAValue,
-                )?
-                .ok_or(MissingVersion)?,
-
-            dependencies: match lsd.get_level(
-                key!(dependency),
-                DependenciesIsNotALevel,
-            )? {
-                Some(dependency) =>
-                    dependency::parse_all(dependency).map_err(DependenciesErrors)?,
-                None => Map::default(),
-            },
+/// Dir in the cache dir with stamps of when each cached dependency was last used, kept
+/// outside of them so that they do not look newer than what they were cached from.
+const CACHE_USED_DIRNAME: &str = ".used";
 
-            profiles: match lsd.get_level(
-                key!(profile),
-                ProfilesIsNotALevel,
-            )? {
-                Some(profile) => profile::parse_all(profile).map_err(ProfilesErrors)?,
-                None => Map::default(),
-            },
+thread_local! {
+    /// Logs of dependencies currently being cached, innermost last.
+    static BUILD_LOGS: RefCell<Vec<PathBuf>> = RefCell::default();
+}
 
-            run: lsd
-                .get_inner(key!(run))
-                .map(Run::parse)
-                .transpose()?,
-        })
+/// Stdout and stderr for tools (ex. compilers): the log of the dependency being cached,
+/// so that their output does not interleave with the main build's, inherited otherwise.
+pub fn tool_output() -> Result<(Stdio, Stdio), io::Error> {
+    match BUILD_LOGS.with_borrow(|logs| {
+        logs.last()
+            .cloned()
+    }) {
+        Some(log) => {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(log)?;
+            Ok((
+                file.try_clone()?
+                    .into(),
+                file.into(),
+            ))
+        },
+        None if build_options().quiet => Ok((
+            io::stderr().into(),
+            Stdio::inherit(),
+        )),
+        None => Ok((
+            Stdio::inherit(),
+            Stdio::inherit(),
+        )),
     }
+}
 
-    pub fn project_name(&self) -> Version {
-        self.name
-            .clone()
+/// Whether output of compilers is taken instead of [`tool_output`], to count [`warnings`] in it.
+/// Not for dependencies being cached, as their warnings are not for the project to fix.
+fn captures_tool_output() -> bool {
+    warnings::is_counting() && BUILD_LOGS.with_borrow(Vec::is_empty)
+}
+
+/// Waits for a tool, counting warnings in its output and passing it on if it was taken
+/// (see [`captures_tool_output`]).
+fn wait_tool(mut child: Child) -> Result<ExitStatus, io::Error> {
+    if child
+        .stdout
+        .is_none()
+    {
+        return child.wait();
     }
 
-    pub fn version(&self) -> Version {
-        self.version
-            .clone()
+    let output = child.wait_with_output()?;
+    warnings::record(&String::from_utf8_lossy(
+        &output.stdout,
+    ));
+    warnings::record(&String::from_utf8_lossy(
+        &output.stderr,
+    ));
+    match build_options().quiet {
+        true => io::stderr().write_all(&output.stdout)?,
+        false => io::stdout().write_all(&output.stdout)?,
     }
+    io::stderr().write_all(&output.stderr)?;
+    Ok(output.status)
+}
 
-    pub fn dependencies(&self) -> Map<Value, Rc<dyn Dependency>> {
-        self.dependencies
-            .clone()
+/// `major.minor.patch` of `version`, missing parts being 0,
+/// pre-release and build metadata (ex. `-rc.1`) are ignored.
+fn version_parts(version: &str) -> Option<[u64; 3]> {
+    let mut parts = [0; 3];
+    let mut given = version
+        .split(['-', '+'])
+        .next()?
+        .split('.');
+    for part in parts.iter_mut() {
+        if let Some(given) = given.next() {
+            *part = given
+                .parse()
+                .ok()?;
+        }
     }
+    given
+        .next()
+        .is_none()
+        .then_some(parts)
+}
 
-    pub fn profiles(&self) -> Map<Value, Rc<dyn Profile>> {
-        self.profiles
-            .clone()
+/// Whether this build++ satisfies `requirement` (ex. `>=0.3` or `>=0.3, <0.5`), comparators
+/// separated by commas or spaces, `None` if it is not a valid requirement.
+/// A version without an operator means at least that version, `=0.3` means any `0.3.x`.
+fn buildpp_satisfies(requirement: &str) -> Option<bool> {
+    let current = version_parts(env!("CARGO_PKG_VERSION"))?;
+
+    const OPERATORS: [&str; 5] = ["<=", ">=", "<", ">", "="];
+
+    // operators may be written apart from their versions (ex. `>= 0.3`)
+    let mut comparators = Vec::<String>::new();
+    for piece in requirement
+        .split([',', ' '])
+        .filter(|piece| !piece.is_empty())
+    {
+        match comparators.last_mut() {
+            Some(last) if OPERATORS.contains(&last.as_str()) => last.push_str(piece),
+            _ => comparators.push(piece.to_string()),
+        }
+    }
+    if comparators.is_empty() {
+        return None;
+    }
+
+    let mut satisfied = true;
+    for comparator in &comparators {
+        let (operator, version) = OPERATORS
+            .into_iter()
+            .find_map(|operator| {
+                comparator
+                    .strip_prefix(operator)
+                    .map(|version| (operator, version))
+            })
+            .unwrap_or((">=", comparator));
+        let required = version_parts(version)?;
+        satisfied &= match operator {
+            "<=" => current <= required,
+            ">=" => current >= required,
+            "<" => current < required,
+            ">" => current > required,
+            _ => {
+                let given = version
+                    .split('.')
+                    .count()
+                    .min(3);
+                current[..given] == required[..given]
+            },
+        };
+    }
+    Some(satisfied)
+}
+
+//
+// Groups
+//
+
+/// Parses `group.<name> [ members ]` (or `group.<name> "members separated by spaces"`).
+fn parse_groups(level: Level) -> Result<IndexMap<Value, Rc<[Value]>>, LoadError> {
+    use LoadError::*;
+    level
+        .into_iter()
+        .map(|(name, members)| {
+            let members = match members {
+                LSD::Value(value) => value
+                    .split_whitespace()
+                    .map(Value::from)
+                    .collect(),
+                LSD::Level(list) => list
+                    .values()
+                    .map(|member| {
+                        member
+                            .to_value()
+                            .ok_or(GroupMemberIsNotAValue)
+                    })
+                    .collect::<Result<_, _>>()?,
+            };
+            Ok((name, members))
+        })
+        .collect()
+}
+
+/// A target of a group, as written in `group` (see [`Configuration::target_names`]).
+pub enum GroupMember {
+    /// The artifact of the project itself (`binary` or `library`).
+    Project,
+    /// A test, bench, additional binary or example (ex. `tests.parser`).
+    Program(Value, Target),
+}
+
+//
+// Deprecated keys
+//
+
+/// What became of a key that older configurations used.
+#[derive(Debug, Clone, Copy)]
+pub enum Deprecation {
+    /// Same meaning under another name, on the same level.
+    Renamed(&'static str),
+    /// No longer has any effect, with what to do instead.
+    Removed(&'static str),
+}
+
+/// Keys of older configurations, warned about on load and rewritten by `buildpp fix`.
+const DEPRECATED_KEYS: &[(&[&str], Deprecation)] = &[
+    (
+        &["profiles"],
+        Deprecation::Renamed("profile"),
+    ),
+    (
+        &["dependencies"],
+        Deprecation::Renamed("dependency"),
+    ),
+    (
+        &["include_directory"],
+        Deprecation::Renamed("include_dir"),
+    ),
+    (
+        &["excludes"],
+        Deprecation::Renamed("exclude"),
+    ),
+    (
+        &["pkgconfig"],
+        Deprecation::Renamed("pkg_config"),
+    ),
+    (
+        &["registry_token"],
+        Deprecation::Removed(
+            "set `token` under `host.\"<name>\"` of the global configuration instead",
+        ),
+    ),
+];
+
+/// A deprecated key that was found in a configuration and migrated out of it.
+#[derive(Debug, Clone)]
+pub struct Migration {
+    pub key: Value,
+    pub deprecation: Deprecation,
+    /// Whether the new key was set as well, so the value of the old one was dropped.
+    pub conflicted: bool,
+}
+
+impl std::fmt::Display for Migration {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (
+            self.deprecation,
+            self.conflicted,
+        ) {
+            (Deprecation::Renamed(new), false) => write!(
+                f,
+                "`{}` is deprecated, use `{}` instead",
+                self.key, new
+            ),
+            (Deprecation::Renamed(new), true) => write!(
+                f,
+                "`{}` is deprecated and ignored, as `{}` is set as well",
+                self.key, new
+            ),
+            (Deprecation::Removed(hint), _) => write!(
+                f,
+                "`{}` is no longer supported, {}",
+                self.key, hint
+            ),
+        }
+    }
+}
+
+fn level_mut<'a>(lsd: &'a mut LSD, key: &[&str]) -> Option<&'a mut Level> {
+    let lsd = key
+        .iter()
+        .try_fold(lsd, |lsd, part| match lsd {
+            LSD::Level(level) => level.get_mut(*part),
+            LSD::Value(_) => None,
+        })?;
+    match lsd {
+        LSD::Level(level) => Some(level),
+        LSD::Value(_) => None,
+    }
+}
+
+/// Moves deprecated keys of `lsd` to their new names (keeping their position) and drops
+/// removed ones, returning what was done.
+pub fn migrate(lsd: &mut LSD) -> Vec<Migration> {
+    let mut migrations = Vec::new();
+    for (key, deprecation) in DEPRECATED_KEYS {
+        let Some((name, parents)) = key.split_last() else {
+            continue;
+        };
+        let Some(level) = level_mut(lsd, parents) else {
+            continue;
+        };
+        let Some((index, _, value)) = level.shift_remove_full(*name) else {
+            continue;
+        };
+
+        let conflicted = match deprecation {
+            Deprecation::Renamed(new) if !level.contains_key(*new) => {
+                level.shift_insert(index, (*new).into(), value);
+                false
+            },
+            Deprecation::Renamed(_) => true,
+            Deprecation::Removed(_) => false,
+        };
+        migrations.push(Migration {
+            key: key
+                .join(".")
+                .into(),
+            deprecation: *deprecation,
+            conflicted,
+        });
+    }
+    migrations
+}
+
+/// Name of the configuration file in a project dir.
+pub const CONFIG_FILENAME: &str = "build++.lsd";
+
+/// Name of the untracked configuration file next to [`CONFIG_FILENAME`], merged over it
+/// (ex. for compiler paths or dependency locations of one machine).
+pub const LOCAL_CONFIG_FILENAME: &str = "build++.local.lsd";
+
+/// Contents of vendored dependencies pinned when they were first cached, next to
+/// [`CONFIG_FILENAME`] (see [`Dependency::vendor_hash`]).
+pub const LOCK_FILENAME: &str = "build++.lock";
+
+/// Manifest of artifact checksums, signatures are expected to be named after it.
+const CHECKSUMS_FILENAME: &str = "SHA256SUMS";
+
+/// Container engines tried in order, all of them accept the same `run` flags.
+const CONTAINER_ENGINES: &[&str] = &["docker", "podman"];
+
+/// Sets [`Profile::run_environment`] for `command` starting an artifact, except variables the
+/// user has set themselves.
+fn set_run_environment(command: &mut Command, profile: &dyn Profile) {
+    for (variable, value) in profile.run_environment() {
+        if env::var_os(variable).is_none() {
+            command.env(variable, value);
+        }
+    }
+}
+
+/// `command` with `args` run in a pseudo-terminal by `script`, which comes with every unix
+/// (from util-linux on Linux, from BSD elsewhere, which take different flags).
+#[cfg(target_os = "linux")]
+fn in_pseudo_terminal(command: OsString, args: Vec<OsString>) -> Command {
+    // util-linux only takes the command as a single line for the shell
+    let line = iter::once(command)
+        .chain(args)
+        .map(|arg| util::posix_quote(&arg.to_string_lossy()))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let mut script = Command::new("script");
+    script
+        .args(["--quiet", "--return", "--command"])
+        .arg(line)
+        .arg("/dev/null");
+    script
+}
+
+/// `command` with `args` run in a pseudo-terminal by `script`, which comes with every unix
+/// (from util-linux on Linux, from BSD elsewhere, which take different flags).
+#[cfg(all(unix, not(target_os = "linux")))]
+fn in_pseudo_terminal(command: OsString, args: Vec<OsString>) -> Command {
+    let mut script = Command::new("script");
+    script
+        .args(["-q", "/dev/null"])
+        .arg(command)
+        .args(args);
+    script
+}
+
+/// Windows programs get the console of build++ instead, there is no pseudo-terminal to make.
+#[cfg(not(unix))]
+fn in_pseudo_terminal(command: OsString, args: Vec<OsString>) -> Command {
+    eprintln!("warning: `run.tty` is only supported on unix, running without a pseudo-terminal");
+    let mut command = Command::new(command);
+    command.args(args);
+    command
+}
+
+/// Variables compilers find headers through, which change what a compilation does.
+const COMPILER_ENVIRONMENT_VARIABLES: &[&str] =
+    &["INCLUDE", "CPATH", "C_INCLUDE_PATH", "CPLUS_INCLUDE_PATH"];
+
+fn canonical_dir(project_dir: &Path) -> Dir {
+    Dir::from(
+        project_dir
+            .canonicalize()
+            .unwrap_or_else(|_| project_dir.to_path_buf()),
+    )
+}
+
+/// How arguments of a compilation are kept next to its object, to notice when they change.
+fn recorded_arguments(arguments: &[OsString]) -> String {
+    arguments
+        .iter()
+        .map(|argument| argument.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Touches a stamp of [`Configuration::cache_dep_used_stamp`], which only `cache` reads, so
+/// failing to is not worth failing the build over.
+fn mark_used(stamp: &Path) {
+    let marked = stamp
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|()| File::create(stamp))
+        .and_then(|stamp| stamp.set_modified(SystemTime::now()));
+    if let Err(err) = marked {
+        eprintln!(
+            "warning: could not mark {} as used: {}",
+            stamp.display(),
+            err
+        );
+    }
+}
+
+/// A version and profile of a dependency, as found in the cache dir.
+pub struct CachedDependency {
+    pub alias: dependency::Alias,
+    /// Dirs between the alias and the cached files (ex. version and profile), none for
+    /// dependencies without them (ex. `local pair`).
+    pub parts: Vec<Value>,
+    pub dir: Dir,
+    /// When a build last used it, `None` if unknown.
+    pub last_used: Option<SystemTime>,
+}
+
+pub struct Configuration {
+    config_file: Dir,
+    /// Where [`LOCAL_CONFIG_FILENAME`] is looked for, whether it exists or not.
+    local_config_file: Dir,
+    project_dir: Dir,
+
+    name: Value,
+    version: Version,
+    license: Option<Value>,
+    description: Option<Value>,
+    /// Package registry endpoint used by `publish`.
+    registry: Option<Value>,
+
+    dependencies: Map<dependency::Alias, Rc<dyn Dependency>>,
+    dependency_links: Map<dependency::Alias, dependency::Link>,
+    /// Macros defined for every source of the project by its dependencies.
+    dependency_defines: Map<dependency::Alias, profile::Defines>,
+    profiles: Map<profile::Name, Rc<dyn Profile>>,
+    /// Profiles replaced by their `fallback`, reported when built.
+    fallbacks: Map<profile::Name, profile::Fallback>,
+
+    run: Option<Run>,
+    /// Targets built and ran together by `-group`, by group name.
+    groups: IndexMap<Value, Rc<[Value]>>,
+    test: Test,
+    analyze: Analyze,
+    deploy: Option<Deploy>,
+
+    /// Whether library builds also emit a pkg-config `<name>.pc` file.
+    pkg_config: bool,
+
+    /// Whether builds write a `SHA256SUMS` manifest of artifacts.
+    checksums: bool,
+
+    /// Command signing the manifest (ex. `minisign -Sm {}`), implies `checksums`.
+    sign: Option<Rc<[Value]>>,
+
+    backend: Backend,
+
+    toolchain: Option<Toolchain>,
+
+    /// By target kind or test name, see [`Configuration::target_names`].
+    target_overrides: Map<Value, TargetOverride>,
+
+    /// By pattern of sources relative to the project (ex. `src/third_party/**`), applied after
+    /// target overrides to every source that matches, in the order they are written.
+    file_overrides: Map<Value, TargetOverride>,
+
+    /// Where public headers are kept and exported from, relative to the project, `src` if unset.
+    include_dir: Option<Dir>,
+
+    /// Extensions (without the dot) of exported headers, [`util::is_header_extension`] if unset.
+    include_extensions: Option<Rc<[Value]>>,
+
+    /// Globs of paths (relative to the project) left out of test discovery, analysis and
+    /// copied includes (ex. `src/detail/**`), see [`Configuration::is_excluded`].
+    exclude: Rc<[Value]>,
+
+    /// Dirs of sources made by codegen steps (ex. `target/generated-src`), searched after `src`
+    /// for sources of the project and included from like it.
+    generated_src_dirs: Rc<[Dir]>,
+
+    /// Appended to compile arguments of this project only (not of its dependencies).
+    extra_flags: Rc<[Value]>,
+}
+
+//
+// Backend
+//
+
+/// What runs the compiler.
+#[derive(Debug, Clone, Copy, Default)]
+enum Backend {
+    /// Build++ itself.
+    #[default]
+    BuildPP,
+    /// Generated `build.ninja`, ran with `ninja`.
+    Ninja,
+}
+
+impl FromStr for Backend {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use Backend::*;
+        match s
+            .to_lowercase()
+            .as_str()
+        {
+            "build++" | "buildpp" | "builtin" => Ok(BuildPP),
+            "ninja" => Ok(Ninja),
+            _ => Err(()),
+        }
+    }
+}
+
+impl Configuration {
+    // Basic info
+
+    pub fn load(project_dir: Dir) -> Result<Self, LoadError> {
+        Self::load_from(
+            project_dir,
+            ConfigSource::Project,
+        )
+    }
+
+    pub fn load_from(project_dir: Dir, source: ConfigSource) -> Result<Self, LoadError> {
+        use LoadError::*;
+
+        const STDIN_CONFIG_FILENAME: &str = "stdin.build++.lsd";
+        let (config_file, config_dir) = match source {
+            // relative paths inside of the configuration are relative to the configuration file
+            ConfigSource::Project => (
+                Dir::from(project_dir.join(CONFIG_FILENAME)),
+                project_dir.clone(),
+            ),
+            ConfigSource::File(config_file) => {
+                let config_dir = Dir::from(
+                    config_file
+                        .parent()
+                        .unwrap_or(&project_dir),
+                );
+                (config_file, config_dir)
+            },
+            // kept in target, so that modification times can be compared against it,
+            // only rewritten when changed to not cause rebuilds
+            ConfigSource::Stdin => {
+                let text = io::read_to_string(io::stdin())
+                    .map_err(Rc::new)
+                    .map_err(CouldNotReadStdin)?;
+                let target_dir = project_dir.join("target");
+                let config_file = Dir::from(target_dir.join(STDIN_CONFIG_FILENAME));
+                if fs::read_to_string(&config_file)
+                    .ok()
+                    .as_ref()
+                    != Some(&text)
+                {
+                    fs::create_dir_all(&target_dir)
+                        .and_then(|()| fs::write(&config_file, text))
+                        .map_err(Rc::new)
+                        .map_err(CouldNotPersistStdin)?;
+                }
+                (
+                    config_file,
+                    project_dir.clone(),
+                )
+            },
+        };
+
+        let file = File::open(&config_file)
+            .map_err(Rc::new)
+            .map_err(CouldNotOpenConfiguration)?;
+        let mut lsd = LSD::parse(file)?;
+
+        // meant to be gitignored, so a missing one is not an error
+        let local_config_file = Dir::from(config_dir.join(LOCAL_CONFIG_FILENAME));
+        match File::open(&local_config_file) {
+            Ok(file) => lsd.overlay(LSD::parse(file).map_err(CouldNotParseLocalLSD)?),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {},
+            Err(err) => return Err(CouldNotOpenLocalConfiguration(Rc::new(err))),
+        }
+
+        // checked before anything else, as newer keys would otherwise fail in confusing ways
+        if let Some(requirement) = lsd.get_value(
+            key!(requires_buildpp),
+            RequiresBuildppIsNotAValue,
+        )? {
+            buildpp_satisfies(&requirement)
+                .ok_or_else(|| InvalidBuildppRequirement(requirement.clone()))?
+                .or_else_err(|| {
+                    BuildppVersionNotSupported(
+                        requirement.clone(),
+                        env!("CARGO_PKG_VERSION").into(),
+                    )
+                })?;
+        }
+
+        // older configurations keep working, deprecated keys being read under their new names
+        for migration in migrate(&mut lsd) {
+            eprintln!(
+                "warning: {}: {} (`buildpp fix` updates the configuration)",
+                config_file.display(),
+                migration
+            );
+        }
+
+        // dependencies load their configurations too, so a project that (even indirectly)
+        // depends on itself would otherwise never stop loading
+        let canonical_dir = canonical_dir(&project_dir);
+        if let Some(position) = LOADING.with_borrow(|loading| {
+            loading
+                .iter()
+                .position(|dir| *dir == canonical_dir)
+        }) {
+            return Err(LOADING.with_borrow(
+                |loading| match &loading[position..] {
+                    [_] => DependsOnItself(canonical_dir),
+                    cycle => DependencyCycle(cycle.into()),
+                },
+            ));
+        }
+
+        // every key is checked, so that all mistakes can be fixed at once
+        let mut errors = CollectedErrors::default();
+
+        // the whole build (dependencies included) is usually cross-compiled with one toolchain
+        let toolchain = errors
+            .check(
+                "toolchain",
+                match build_options().toolchain {
+                    Some(toolchain_file) => Toolchain::load(&toolchain_file).map(Some),
+                    None => lsd
+                        .get_value(
+                            key!(toolchain),
+                            ToolchainIsNotAValue,
+                        )
+                        .and_then(|toolchain_file| {
+                            toolchain_file
+                                .map(|toolchain_file| {
+                                    Toolchain::load(&config_dir.join(&*toolchain_file))
+                                })
+                                .transpose()
+                        }),
+                },
+            )
+            .flatten();
+
+        let (mut profiles, fallbacks) = errors
+            .check(
+                "profile",
+                lsd.get_level(
+                    key!(profile),
+                    ProfilesIsNotALevel,
+                )
+                .and_then(|profile| match profile {
+                    Some(profile) => profile::parse_all(profile).map_err(ProfilesErrors),
+                    None => Ok(Default::default()),
+                }),
+            )
+            .unwrap_or_default();
+        if let Some((_, toolchain_level)) = &toolchain {
+            profiles = Map::new(
+                profiles
+                    .iter()
+                    .filter_map(|(name, profile)| {
+                        errors
+                            .check(
+                                format!("profile.{}", name),
+                                profile
+                                    .inherit_with(toolchain_level.clone())
+                                    .map_err(ToolchainProfileError),
+                            )
+                            .map(|profile| (name.clone(), profile))
+                    })
+                    .collect(),
+            );
+        }
+
+        let (dependencies, dependency_links, dependency_defines) = errors
+            .check(
+                "dependency",
+                lsd.get_level(
+                    key!(dependency),
+                    DependenciesIsNotALevel,
+                )
+                .and_then(
+                    |dependency| match dependency {
+                        Some(dependency) => dependency::parse_all(dependency, &config_dir)
+                            .map_err(DependenciesErrors),
+                        None => Ok(Default::default()),
+                    },
+                ),
+            )
+            .unwrap_or_default();
+
+        // other cycles are only noticed once dependencies load their configurations
+        if dependencies
+            .values()
+            .any(|dep| dep.project_dir() == Some(&canonical_dir))
+        {
+            errors.check(
+                "dependency",
+                Err::<(), _>(DependsOnItself(
+                    canonical_dir.clone(),
+                )),
+            );
+        }
+
+        let name = errors.check(
+            "name",
+            lsd.get_value(
+                key!(name),
+                ProjectNameIsNotAValue,
+            )
+            .and_then(|name| name.ok_or(MissingProjectName)),
+        );
+
+        let version = errors.check(
+            "version",
+            lsd.get_value(
+                key!(version),
+                VersionIsNotAValue,
+            )
+            .and_then(|version| version.ok_or(MissingVersion)),
+        );
+
+        let license = errors
+            .check(
+                "license",
+                lsd.get_value(
+                    key!(license),
+                    LicenseIsNotAValue,
+                ),
+            )
+            .flatten();
+
+        let description = errors
+            .check(
+                "description",
+                lsd.get_value(
+                    key!(description),
+                    DescriptionIsNotAValue,
+                ),
+            )
+            .flatten();
+
+        let registry = errors
+            .check(
+                "registry",
+                lsd.get_value(
+                    key!(registry),
+                    RegistryIsNotAValue,
+                ),
+            )
+            .flatten();
+
+        let run = errors
+            .check(
+                "run",
+                lsd.get_inner(key!(run))
+                    .map(Run::parse)
+                    .transpose(),
+            )
+            .flatten();
+
+        let groups = errors
+            .check(
+                "group",
+                lsd.get_level(key!(group), GroupsIsNotALevel)
+                    .and_then(|group| {
+                        group
+                            .map(parse_groups)
+                            .transpose()
+                    }),
+            )
+            .flatten()
+            .unwrap_or_default();
+
+        let test = errors
+            .check(
+                "test",
+                lsd.get_inner(key!(test))
+                    .map(Test::parse)
+                    .transpose(),
+            )
+            .flatten()
+            .unwrap_or_default();
+
+        let analyze = errors
+            .check(
+                "analyze",
+                lsd.get_inner(key!(analyze))
+                    .map(|analyze| Analyze::parse(analyze, &config_dir))
+                    .transpose(),
+            )
+            .flatten()
+            .unwrap_or_default();
+
+        let deploy = errors
+            .check(
+                "deploy",
+                lsd.get_inner(key!(deploy))
+                    .map(Deploy::parse)
+                    .transpose(),
+            )
+            .flatten();
+
+        let pkg_config = errors
+            .check(
+                "pkg_config",
+                lsd.get_parse(
+                    key!(pkg_config),
+                    PkgConfigIsNotABool,
+                ),
+            )
+            .flatten()
+            .unwrap_or_default();
+
+        let checksums = errors
+            .check(
+                "checksums",
+                lsd.get_parse(
+                    key!(checksums),
+                    ChecksumsIsNotABool,
+                ),
+            )
+            .flatten()
+            .unwrap_or_default();
+
+        let sign = errors
+            .check(
+                "sign",
+                lsd.get_inner(key!(sign))
+                    .map(|sign| match sign {
+                        // Parse `sign "command with spaces and with {} substitution"`
+                        LSD::Value(value) => Ok(value
+                            .split_whitespace()
+                            .map(Rc::from)
+                            .collect::<Rc<[_]>>()),
+
+                        // Parse `sign [ each list item being a command or arg ]`
+                        LSD::Level(list) => list
+                            .values()
+                            .map(|arg| {
+                                if arg
+                                    .to_level()
+                                    .is_some_and(|l| l.is_empty())
+                                {
+                                    return Ok("{}".into());
+                                }
+
+                                arg.to_value()
+                                    .ok_or(SignPieceIsNotAValue)
+                            })
+                            .collect(),
+                    })
+                    .transpose(),
+            )
+            .flatten()
+            .filter(|sign| !sign.is_empty());
+
+        let backend = errors
+            .check(
+                "backend",
+                lsd.get_parse(key!(backend), UnknownBackend),
+            )
+            .flatten()
+            .unwrap_or_default();
+
+        let target_overrides = Map::new(
+            errors
+                .check(
+                    "target",
+                    lsd.get_level(
+                        key!(target),
+                        TargetsIsNotALevel,
+                    ),
+                )
+                .flatten()
+                .unwrap_or_default()
+                .iter()
+                .filter_map(|(name, target)| {
+                    errors
+                        .check(
+                            format!("target.{}", name),
+                            TargetOverride::parse(name, target),
+                        )
+                        .map(|target_override| (name.clone(), target_override))
+                })
+                .collect(),
+        );
+
+        let file_overrides = Map::new(
+            errors
+                .check(
+                    "file",
+                    lsd.get_level(
+                        key!(file),
+                        FileOverridesIsNotALevel,
+                    ),
+                )
+                .flatten()
+                .unwrap_or_default()
+                .iter()
+                .filter_map(|(pattern, file)| {
+                    errors
+                        .check(
+                            format!("file.{}", pattern),
+                            TargetOverride::parse(pattern, file),
+                        )
+                        .map(|file_override| (pattern.clone(), file_override))
+                })
+                .collect(),
+        );
+
+        let include_dir = errors
+            .check(
+                "include_dir",
+                lsd.get_value(
+                    key!(include_dir),
+                    IncludeDirIsNotAValue,
+                ),
+            )
+            .flatten()
+            .map(|include_dir| Dir::from(project_dir.join(&*include_dir)));
+
+        let include_extensions = errors
+            .check(
+                "include_extensions",
+                lsd.get_inner(key!(include_extensions))
+                    .map(
+                        |include_extensions| match include_extensions {
+                            // Parse `include_extensions "h hpp inl"`
+                            LSD::Value(value) => Ok(value
+                                .split_whitespace()
+                                .map(|extension| Rc::from(extension.trim_start_matches('.')))
+                                .collect::<Rc<[_]>>()),
+
+                            // Parse `include_extensions [ each list item being an extension ]`
+                            LSD::Level(list) => list
+                                .values()
+                                .map(|extension| {
+                                    extension
+                                        .to_value()
+                                        .map(|extension| {
+                                            Rc::from(extension.trim_start_matches('.'))
+                                        })
+                                        .ok_or(IncludeExtensionIsNotAValue)
+                                })
+                                .collect(),
+                        },
+                    )
+                    .transpose(),
+            )
+            .flatten();
+
+        let exclude = errors
+            .check(
+                "exclude",
+                lsd.get_inner(key!(exclude))
+                    .map(|exclude| match exclude {
+                        // Parse `exclude "src/detail/** src/**/*_impl.hpp"`
+                        LSD::Value(value) => Ok(value
+                            .split_whitespace()
+                            .map(Rc::from)
+                            .collect::<Rc<[_]>>()),
+
+                        // Parse `exclude [ each list item being a pattern ]`
+                        LSD::Level(list) => list
+                            .values()
+                            .map(|pattern| {
+                                pattern
+                                    .to_value()
+                                    .ok_or(ExcludePatternIsNotAValue)
+                            })
+                            .collect(),
+                    })
+                    .transpose(),
+            )
+            .flatten()
+            .unwrap_or_else(|| Rc::from([]));
+
+        let generated_src_dirs = errors
+            .check(
+                "generated_src_dirs",
+                lsd.get_inner(key!(generated_src_dirs))
+                    .map(
+                        |generated_src_dirs| match generated_src_dirs {
+                            // Parse `generated_src_dirs "target/generated-src gen"`
+                            LSD::Value(value) => Ok(value
+                                .split_whitespace()
+                                .map(|dir| Dir::from(project_dir.join(dir)))
+                                .collect::<Rc<[_]>>()),
+
+                            // Parse `generated_src_dirs [ each list item being a dir ]`
+                            LSD::Level(list) => list
+                                .values()
+                                .map(|dir| {
+                                    dir.to_value()
+                                        .map(|dir| Dir::from(project_dir.join(&*dir)))
+                                        .ok_or(GeneratedSrcDirIsNotAValue)
+                                })
+                                .collect(),
+                        },
+                    )
+                    .transpose(),
+            )
+            .flatten()
+            .unwrap_or_else(|| Rc::from([]));
+
+        errors.into_result()?;
+
+        Ok(Configuration {
+            config_file,
+            local_config_file,
+            project_dir,
+
+            // present when there were no errors
+            name: name.unwrap_or_default(),
+            version: version.unwrap_or_default(),
+            license,
+            description,
+            registry,
+
+            dependencies,
+            dependency_links,
+            dependency_defines,
+
+            profiles,
+            fallbacks,
+
+            run,
+            groups,
+            test,
+            analyze,
+            deploy,
+
+            pkg_config,
+            checksums,
+            sign,
+
+            backend,
+
+            toolchain: toolchain.map(|(toolchain, _)| toolchain),
+
+            target_overrides,
+            file_overrides,
+
+            include_dir,
+            include_extensions,
+
+            exclude,
+
+            generated_src_dirs,
+
+            extra_flags: Rc::from([]),
+        })
+    }
+
+    pub fn with_extra_flags(self, extra_flags: Rc<[Value]>) -> Self {
+        Self {
+            extra_flags,
+            ..self
+        }
+    }
+
+    pub fn project_name(&self) -> Version {
+        self.name
+            .clone()
+    }
+
+    pub fn version(&self) -> Version {
+        self.version
+            .clone()
+    }
+
+    pub fn license(&self) -> Option<Value> {
+        self.license
+            .clone()
+    }
+
+    pub fn description(&self) -> Option<Value> {
+        self.description
+            .clone()
+    }
+
+    pub fn registry(&self) -> Option<Value> {
+        self.registry
+            .clone()
+    }
+
+    pub fn dependencies(&self) -> Map<Value, Rc<dyn Dependency>> {
+        self.dependencies
+            .clone()
+    }
+
+    pub fn profiles(&self) -> Map<Value, Rc<dyn Profile>> {
+        self.profiles
+            .clone()
     }
 
     pub fn profile(&self, value: &str) -> Option<&dyn Profile> {
@@ -261,421 +2062,3597 @@ impl Configuration {
             .map(Rc::as_ref)
     }
 
-    pub fn run_command(&self, profile_name: &str, profile: &dyn Profile) -> String {
-        self.run
-            .as_ref()
-            .map(|run| &run.command)
-            .cloned()
-            .unwrap_or_else(|| "{}".into())
-            .replace(
-                "{}", // TODO do not touch {{}}
-                &self
-                    .target_artifact_file(
-                        BuildType::Binary,
-                        profile_name,
-                        profile,
-                    )
-                    .display()
-                    .to_string(),
-            )
+    pub fn toolchain(&self) -> Option<&Toolchain> {
+        self.toolchain
+            .as_ref()
+    }
+
+    /// Preprocessor defines (`NAME` or `NAME=VALUE`) the project is compiled with (see
+    /// [`Self::compile_arguments`]), for tools that do not run the compiler itself.
+    pub fn defines(
+        &self,
+        build_type: Option<BuildType>,
+        profile_name: &str,
+        profile: &dyn Profile,
+    ) -> Result<Vec<Value>, BuildError> {
+        // a project that is both (or neither yet) is described by its binary
+        let build_type = self
+            .detect_build_type(build_type, profile)
+            .unwrap_or(BuildType::Binary);
+        let target = self.target(
+            build_type,
+            profile_name,
+            profile,
+        );
+        let (overrides, overridden_profile) = self.overrides(profile, &target)?;
+        let profile = overridden_profile
+            .as_deref()
+            .unwrap_or(profile);
+
+        let mut defines = profile
+            .defines()
+            .iter()
+            .chain(
+                self.dependency_defines
+                    .values()
+                    .flat_map(profile::Defines::iter),
+            )
+            .map(Value::from)
+            .collect::<Vec<_>>();
+        for (_, target_override) in &overrides {
+            defines.extend(
+                target_override
+                    .defines
+                    .iter()
+                    .cloned(),
+            );
+        }
+
+        // ones passed as flags as well
+        let override_flags = overrides
+            .iter()
+            .flat_map(|(_, target_override)| {
+                target_override
+                    .flags
+                    .iter()
+            });
+        defines.extend(
+            self.toolchain
+                .iter()
+                .flat_map(|toolchain| {
+                    toolchain
+                        .flags
+                        .iter()
+                })
+                .chain(override_flags)
+                .chain(
+                    self.extra_flags
+                        .iter(),
+                )
+                .filter_map(|flag| {
+                    flag.strip_prefix("-D")
+                        .or_else(|| flag.strip_prefix("/D"))
+                })
+                .map(Value::from),
+        );
+        Ok(defines)
+    }
+
+    pub fn test_runner(&self) -> Option<Rc<[Value]>> {
+        self.test
+            .runner
+            .clone()
+    }
+
+    pub fn analyzer(&self) -> Option<Rc<[Value]>> {
+        self.analyze
+            .analyzer
+            .clone()
+    }
+
+    pub fn analyze_suppressions(&self) -> Option<Dir> {
+        self.analyze
+            .suppressions
+            .clone()
+    }
+
+    /// `text` of `run` with its placeholders replaced for the binary built with `profile`.
+    fn substitute_run(&self, text: &str, profile_name: &str, profile: &dyn Profile) -> OsString {
+        self.substitute_artifact(
+            text,
+            profile_name,
+            &self.target_artifact_file(
+                BuildType::Binary,
+                profile_name,
+                profile,
+            ),
+        )
+    }
+
+    /// `text` with its placeholders replaced for `artifact_file` built with `profile_name`.
+    fn substitute_artifact(
+        &self,
+        text: &str,
+        profile_name: &str,
+        artifact_file: &Path,
+    ) -> OsString {
+        substitute(
+            text,
+            |placeholder| match placeholder {
+                RunPlaceholder::Artifact => artifact_file
+                    .as_os_str()
+                    .into(),
+                RunPlaceholder::ArtifactDir => self
+                    .target_artifact_dir(profile_name)
+                    .as_os_str()
+                    .into(),
+                RunPlaceholder::ProjectDir => self
+                    .project_dir
+                    .as_os_str()
+                    .into(),
+                RunPlaceholder::Profile => profile_name.into(),
+                RunPlaceholder::Version => (*self.version).into(),
+            },
+        )
+        // placeholders were checked on load
+        .unwrap_or_else(|_| text.into())
+    }
+
+    pub fn run_command(&self, profile_name: &str, profile: &dyn Profile) -> OsString {
+        self.substitute_run(
+            self.run
+                .as_ref()
+                .map_or("{}", |run| &run.command),
+            profile_name,
+            profile,
+        )
+    }
+
+    /// Whether `run.quiet` is set.
+    pub fn run_quiet(&self) -> bool {
+        self.run
+            .as_ref()
+            .is_some_and(|run| run.quiet)
+    }
+
+    pub fn run_arguments(&self, profile_name: &str, profile: &dyn Profile) -> Vec<OsString> {
+        self.run
+            .as_ref()
+            .map(|run| {
+                run.arguments
+                    .iter()
+                    .map(|arg| self.substitute_run(arg, profile_name, profile))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    // Dirs
+
+    /// Profile deployed by `buildpp deploy` unless another is given.
+    pub fn deploy_profile(&self) -> Result<profile::Name, BuildError> {
+        self.deploy
+            .as_ref()
+            .filter(|deploy| {
+                !deploy
+                    .commands
+                    .is_empty()
+            })
+            .map(|deploy| {
+                deploy
+                    .profile
+                    .clone()
+            })
+            .ok_or(BuildError::NoDeployCommands)
+    }
+
+    /// Whether `buildpp build` of `profile_name` deploys once it succeeds.
+    pub fn deploys_after_build(&self, profile_name: &str) -> bool {
+        self.deploy
+            .as_ref()
+            .is_some_and(|deploy| deploy.after_build && *deploy.profile == *profile_name)
+    }
+
+    pub fn config_file(&self) -> Dir {
+        self.config_file
+            .clone()
+    }
+
+    pub fn project_dir(&self) -> Dir {
+        self.project_dir
+            .clone()
+    }
+
+    fn canonical_dir(&self) -> Dir { canonical_dir(&self.project_dir) }
+
+    /// Where public headers are kept, which are copied into the target's include dir.
+    pub fn include_dir(&self) -> Dir {
+        self.include_dir
+            .clone()
+            .unwrap_or_else(|| self.src_dir())
+    }
+
+    /// Dirs headers of this project are included from: `src`, `include_dir` if apart from it,
+    /// and `generated_src_dirs` that were generated already.
+    pub fn include_dirs(&self) -> Vec<Dir> {
+        let mut include_dirs = vec![self.src_dir()];
+        if self.include_dir() != self.src_dir() {
+            include_dirs.push(self.include_dir());
+        }
+        for generated_src_dir in self
+            .generated_src_dirs
+            .iter()
+        {
+            if generated_src_dir.is_dir() && !include_dirs.contains(generated_src_dir) {
+                include_dirs.push(generated_src_dir.clone());
+            }
+        }
+        include_dirs
+    }
+
+    /// Latest change to the configuration, sources or headers of this project.
+    pub fn sources_last_modified(&self) -> Result<SystemTime, io::Error> {
+        let mut modified = last_modified_recursive(self.config_file())?;
+        if self
+            .local_config_file
+            .is_file()
+        {
+            modified = modified.max(last_modified_recursive(
+                &self.local_config_file,
+            )?);
+        }
+        for include_dir in self.include_dirs() {
+            modified = modified.max(last_modified_recursive(
+                include_dir,
+            )?);
+        }
+        Ok(modified)
+    }
+
+    /// Configuration files and sources of this project and of its dependencies, changes to
+    /// which `watch` rebuilds on. Paths may not exist (ex. an absent local configuration).
+    pub fn watched_paths(&self) -> Result<Vec<PathBuf>, io::Error> {
+        let mut paths = vec![
+            self.config_file
+                .to_path_buf(),
+            self.local_config_file
+                .to_path_buf(),
+        ];
+        paths.extend(
+            self.include_dirs()
+                .iter()
+                .map(|include_dir| include_dir.to_path_buf()),
+        );
+        for dependency in self
+            .dependencies
+            .values()
+        {
+            for path in dependency.watched_paths()? {
+                if !paths.contains(&path) {
+                    paths.push(path);
+                }
+            }
+        }
+        Ok(paths)
+    }
+
+    /// Whether `path` has one of `include_extensions`, or is a header if they are unset.
+    pub fn is_header(&self, path: &Path) -> bool {
+        let extension = split_file_name(path.as_os_str()).1;
+        match &self.include_extensions {
+            Some(include_extensions) => include_extensions
+                .iter()
+                .any(|include_extension| extension == &**include_extension),
+            None => util::is_header_extension(extension),
+        }
+    }
+
+    /// Whether `path` or a directory it is in matches an `exclude` pattern. Patterns are
+    /// matched against paths relative to the project, or against names when they have no `/`
+    /// (ex. `detail` excludes every `detail` file or directory).
+    pub fn is_excluded(&self, path: &Path) -> bool {
+        // paths may also come from the canonical project dir (ex. through a symlink)
+        let canonical_dir;
+        let path = match path.strip_prefix(&*self.project_dir) {
+            Ok(path) => path,
+            Err(_) => {
+                canonical_dir = self.canonical_dir();
+                match path.strip_prefix(&*canonical_dir) {
+                    Ok(path) => path,
+                    Err(_) => return false,
+                }
+            },
+        };
+
+        let mut relative = String::new();
+        path.components()
+            .any(|component| {
+                let name = component
+                    .as_os_str()
+                    .to_string_lossy();
+                if !relative.is_empty() {
+                    relative.push('/');
+                }
+                relative.push_str(&name);
+
+                self.exclude
+                    .iter()
+                    .any(
+                        |pattern| match pattern.contains('/') {
+                            true => util::glob_match(pattern, &relative),
+                            false => util::glob_match(pattern, &name),
+                        },
+                    )
+            })
+    }
+
+    pub fn src_dir(&self) -> Dir {
+        self.project_dir
+            .join("src")
+            .into()
+    }
+
+    /// The first existing of the sources named after `build_type` (ex. `src/main.cc`), in `src`
+    /// and then in `generated_src_dirs`, or the one in `src` with the first suffix of the profile
+    /// if there is none.
+    pub fn src_file(&self, build_type: BuildType, profile: &dyn Profile) -> Dir {
+        let candidates = [self.src_dir()]
+            .iter()
+            .chain(
+                self.generated_src_dirs
+                    .iter(),
+            )
+            .flat_map(|src_dir| {
+                profile
+                    .src_file_suffixes()
+                    .iter()
+                    .map(|suffix| {
+                        src_dir.join(format!(
+                            "{}{}",
+                            build_type.src_filename(),
+                            suffix
+                        ))
+                    })
+            })
+            .collect::<Vec<_>>();
+
+        candidates
+            .iter()
+            .find(|candidate| candidate.is_file())
+            .unwrap_or(&candidates[0])
+            .as_path()
+            .into()
+    }
+
+    pub fn target_dir(&self, profile: &str) -> Dir {
+        self.project_dir
+            .join("target")
+            .join(&*self.version)
+            .join(profile)
+            .into()
+    }
+
+    /// Removes dirs of versions and profiles that the configuration no longer has (ex. of a
+    /// version before the last bump) from the `target` dir, returns what was removed.
+    ///
+    /// Those built before they were recorded (see [`targets::RECORD_FILENAME`]) are found by
+    /// their profile dirs having objects or artifacts.
+    pub fn clean_old_targets(&self) -> Result<Vec<Dir>, io::Error> {
+        let target_root = self
+            .project_dir
+            .join("target");
+        let is_current = |version: &str, profile: &str| {
+            version == &*self.version
+                && self
+                    .profiles
+                    .contains_key(profile)
+        };
+
+        let mut old = Vec::new();
+        let recorded = targets::read(&target_root);
+        for (version, profiles) in &recorded {
+            match **version == *self.version {
+                true => old.extend(
+                    profiles
+                        .iter()
+                        .filter(|profile| !is_current(version, profile))
+                        .map(|profile| {
+                            target_root
+                                .join(&**version)
+                                .join(&**profile)
+                        }),
+                ),
+                false => old.push(target_root.join(&**version)),
+            }
+        }
+
+        let is_profile_dir = |dir: &Path| {
+            dir.join("obj")
+                .is_dir()
+                || dir
+                    .join("artifact")
+                    .is_dir()
+        };
+        if target_root.is_dir() {
+            for entry in fs::read_dir(&target_root)? {
+                let version_dir = entry?.path();
+                let version = version_dir
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .to_string();
+                if !version_dir.is_dir() {
+                    continue;
+                }
+                for profile_dir in fs::read_dir(&version_dir)? {
+                    let profile_dir = profile_dir?.path();
+                    let profile = profile_dir
+                        .file_name()
+                        .unwrap_or_default()
+                        .to_string_lossy()
+                        .to_string();
+                    let is_recorded = recorded
+                        .get(&*version)
+                        .is_some_and(|profiles| {
+                            profiles
+                                .iter()
+                                .any(|recorded| **recorded == *profile)
+                        });
+                    if !is_recorded
+                        && !is_current(&version, &profile)
+                        && is_profile_dir(&profile_dir)
+                    {
+                        old.push(profile_dir);
+                    }
+                }
+            }
+        }
+
+        let mut removed = Vec::new();
+        for dir in old {
+            if !dir.is_dir() {
+                continue;
+            }
+            util::remove_dir_all(&dir)?;
+            // version dirs are left behind by their last profile
+            if let Some(version_dir) = dir
+                .parent()
+                .filter(|parent| *parent != target_root)
+            {
+                if fs::read_dir(version_dir)?
+                    .next()
+                    .is_none()
+                {
+                    fs::remove_dir(version_dir)?;
+                }
+            }
+            removed.push(Dir::from(dir));
+        }
+        targets::retain(&target_root, is_current)?;
+        Ok(removed)
+    }
+
+    /// Objects of earlier compilations by their [`Self::action_hash`], shared by all profiles.
+    pub fn target_actions_dir(&self) -> Dir {
+        self.project_dir
+            .join("target")
+            .join("actions")
+            .into()
+    }
+
+    pub fn target_include_dir(&self, profile: &str) -> Dir {
+        self.target_dir(profile)
+            .join("include")
+            .into()
+    }
+
+    pub fn target_artifact_dir(&self, profile: &str) -> Dir {
+        self.target_dir(profile)
+            .join("artifact")
+            .into()
+    }
+
+    /// Import libraries of DLLs (with their `.exp`), only needed for linking against them,
+    /// so kept apart from the artifact.
+    pub fn target_lib_dir(&self, profile: &str) -> Dir {
+        self.target_dir(profile)
+            .join("lib")
+            .into()
+    }
+
+    pub fn target_artifact_file(
+        &self,
+        build_type: BuildType,
+        profile_name: &str,
+        profile: &dyn Profile,
+    ) -> Dir {
+        self.target_artifact_dir(profile_name)
+            .join(format!(
+                "{}{}{}",
+                profile.artifact_prefix(build_type),
+                self.name,
+                profile.artifact_suffix(build_type),
+            ))
+            .into()
+    }
+
+    pub fn target(
+        &self,
+        build_type: BuildType,
+        profile_name: &str,
+        profile: &dyn Profile,
+    ) -> Target {
+        Target {
+            build_type,
+            src_file: self.src_file(build_type, profile),
+            object_file: self
+                .target_object_dir(profile_name)
+                .join(format!(
+                    "{}{}",
+                    build_type.src_filename(),
+                    profile.object_suffix()
+                ))
+                .into(),
+            artifact_file: self.target_artifact_file(
+                build_type,
+                profile_name,
+                profile,
+            ),
+            // dir of the source itself is searched by compilers anyway
+            include_dirs: self
+                .include_dirs()
+                .into_iter()
+                .filter(|include_dir| *include_dir != self.src_dir())
+                .collect(),
+        }
+    }
+
+    /// Names `target.<name>` overrides of `target` can be given by, least specific first:
+    /// its kind (`binary`, `library`, `tests`, `benches`, `bins` or `examples`), then the name
+    /// of the program.
+    pub fn target_names(&self, target: &Target) -> Vec<Value> {
+        let kind = if target
+            .src_file
+            .starts_with(self.tests_dir())
+        {
+            Some("tests")
+        } else if target
+            .src_file
+            .starts_with(self.benches_dir())
+        {
+            Some("benches")
+        } else if target
+            .src_file
+            .starts_with(self.bins_dir())
+        {
+            Some("bins")
+        } else if target
+            .src_file
+            .starts_with(self.examples_dir())
+        {
+            Some("examples")
+        } else {
+            None
+        };
+        match kind {
+            Some(kind) => vec![
+                Value::from(kind),
+                Value::from(
+                    split_file_name(
+                        target
+                            .src_file
+                            .as_os_str(),
+                    )
+                    .0
+                    .to_string_lossy(),
+                ),
+            ],
+            None => vec![Value::from(
+                target
+                    .build_type
+                    .to_string(),
+            )],
+        }
+    }
+
+    pub fn tests_dir(&self) -> Dir {
+        self.project_dir
+            .join("tests")
+            .into()
+    }
+
+    pub fn target_object_dir(&self, profile: &str) -> Dir {
+        self.target_dir(profile)
+            .join("obj")
+            .into()
+    }
+
+    pub fn target_module_dir(&self, profile: &str) -> Dir {
+        self.target_dir(profile)
+            .join("modules")
+            .into()
+    }
+
+    /// Standard library module (ex. `std.ifc`, `std.pcm`) or its object, by `suffix`.
+    pub fn target_std_module_file(&self, profile: &str, suffix: &str) -> Dir {
+        self.target_module_dir(profile)
+            .join(format!("std{}", suffix))
+            .into()
+    }
+
+    pub fn target_test_dir(&self, profile: &str) -> Dir {
+        self.target_dir(profile)
+            .join("test")
+            .into()
+    }
+
+    pub fn benches_dir(&self) -> Dir {
+        self.project_dir
+            .join("benches")
+            .into()
+    }
+
+    pub fn target_bench_dir(&self, profile: &str) -> Dir {
+        self.target_dir(profile)
+            .join("bench")
+            .into()
+    }
+
+    /// Additional binaries of the project besides `src/main`.
+    pub fn bins_dir(&self) -> Dir {
+        self.src_dir()
+            .join("bin")
+            .into()
+    }
+
+    pub fn target_bins_dir(&self, profile: &str) -> Dir {
+        self.target_dir(profile)
+            .join("bin")
+            .into()
+    }
+
+    pub fn examples_dir(&self) -> Dir {
+        self.project_dir
+            .join("examples")
+            .into()
+    }
+
+    pub fn target_examples_dir(&self, profile: &str) -> Dir {
+        self.target_dir(profile)
+            .join("example")
+            .into()
+    }
+
+    /// Every `tests/<name><src suffix>` file, as a binary to build and run.
+    pub fn test_targets(
+        &self,
+        profile_name: &str,
+        profile: &dyn Profile,
+    ) -> Result<Vec<(Value, Target)>, io::Error> {
+        self.program_targets(
+            &self.tests_dir(),
+            &self.target_test_dir(profile_name),
+            profile,
+        )
+    }
+
+    /// Every `benches/<name><src suffix>` file, as a binary to build and time.
+    pub fn bench_targets(
+        &self,
+        profile_name: &str,
+        profile: &dyn Profile,
+    ) -> Result<Vec<(Value, Target)>, io::Error> {
+        self.program_targets(
+            &self.benches_dir(),
+            &self.target_bench_dir(profile_name),
+            profile,
+        )
+    }
+
+    /// Every `src/bin/<name><src suffix>` file, as a binary to build (ex. in a group).
+    pub fn bin_targets(
+        &self,
+        profile_name: &str,
+        profile: &dyn Profile,
+    ) -> Result<Vec<(Value, Target)>, io::Error> {
+        self.program_targets(
+            &self.bins_dir(),
+            &self.target_bins_dir(profile_name),
+            profile,
+        )
+    }
+
+    /// Every `examples/<name><src suffix>` file, as a binary to build (ex. in a group).
+    pub fn example_targets(
+        &self,
+        profile_name: &str,
+        profile: &dyn Profile,
+    ) -> Result<Vec<(Value, Target)>, io::Error> {
+        self.program_targets(
+            &self.examples_dir(),
+            &self.target_examples_dir(profile_name),
+            profile,
+        )
+    }
+
+    /// Every source file directly in `src_dir`, as a binary built into `out_dir`.
+    fn program_targets(
+        &self,
+        src_dir: &Path,
+        out_dir: &Path,
+        profile: &dyn Profile,
+    ) -> Result<Vec<(Value, Target)>, io::Error> {
+        if !src_dir.is_dir() {
+            return Ok(Vec::new());
+        }
+
+        let mut targets = Vec::new();
+        for entry in fs::read_dir(src_dir)? {
+            let entry = entry?;
+            if !entry
+                .file_type()?
+                .is_file()
+                || self.is_excluded(&entry.path())
+            {
+                continue;
+            }
+
+            let filename = entry.file_name();
+            let Some(name) = filename
+                .to_str()
+                .and_then(|filename| {
+                    profile
+                        .src_file_suffixes()
+                        .iter()
+                        .find_map(|suffix| filename.strip_suffix(suffix))
+                })
+            else {
+                continue;
+            };
+
+            targets.push((
+                Value::from(name),
+                Target {
+                    build_type: BuildType::Binary,
+                    src_file: entry
+                        .path()
+                        .into(),
+                    object_file: out_dir
+                        .join(format!(
+                            "{}{}",
+                            name,
+                            profile.object_suffix()
+                        ))
+                        .into(),
+                    artifact_file: out_dir
+                        .join(format!(
+                            "{}{}{}",
+                            profile.artifact_prefix(BuildType::Binary),
+                            name,
+                            profile.artifact_suffix(BuildType::Binary),
+                        ))
+                        .into(),
+                    include_dirs: self.include_dirs(),
+                },
+            ));
+        }
+        targets.sort_by(|(a, _), (b, _)| a.cmp(b));
+        Ok(targets)
+    }
+
+    pub fn cache_dir(&self) -> Dir {
+        self.project_dir
+            .join("cache")
+            .into()
+    }
+
+    /// Dir of a version and profile of a dependency in [`Self::cache_dir`], each part encoded
+    /// (see [`util::encode_path_component`]) so that no alias, version or profile can name a
+    /// dir outside of it (ex. `..` or `C:`).
+    pub fn cache_dep_dir(
+        &self,
+        dependency: dependency::Alias,
+        version: Version,
+        profile: &str,
+    ) -> Dir {
+        let mut res = self
+            .cache_dir()
+            .join(util::encode_path_component(
+                &dependency,
+            ));
+        if !version.is_empty() {
+            res = res.join(util::encode_path_component(
+                &version,
+            ));
+        }
+        if !profile.is_empty() {
+            res = res.join(util::encode_path_component(
+                profile,
+            ));
+        }
+        res.into()
+    }
+
+    pub fn cache_dep_include_dir(
+        &self,
+        dependency: dependency::Alias,
+        version: Version,
+        profile: &str,
+    ) -> Dir {
+        self.cache_dep_dir(dependency, version, profile)
+            .join("include")
+            .into()
+    }
+
+    pub fn cache_dep_lib_dir(
+        &self,
+        dependency: dependency::Alias,
+        version: Version,
+        profile: &str,
+    ) -> Dir {
+        self.cache_dep_dir(dependency, version, profile)
+            .join("lib")
+            .into()
+    }
+
+    /// Modified whenever the cached dependency is used by a build (see [`CACHE_USED_DIRNAME`]).
+    pub fn cache_dep_used_stamp(
+        &self,
+        dependency: dependency::Alias,
+        version: Version,
+        profile: &str,
+    ) -> Dir {
+        let cache_dir = self.cache_dir();
+        let cache_dep_dir = self.cache_dep_dir(dependency, version, profile);
+        cache_dir
+            .join(CACHE_USED_DIRNAME)
+            .join(
+                cache_dep_dir
+                    .strip_prefix(&cache_dir)
+                    .unwrap_or(&cache_dep_dir),
+            )
+            .into()
+    }
+
+    /// Every version and profile of dependencies found in the cache dir, including those no
+    /// longer depended on, sorted by alias.
+    pub fn cached_dependencies(&self) -> Result<Vec<CachedDependency>, io::Error> {
+        /// Cached dirs are the ones with both `include` and `lib`, at most a version and a
+        /// profile deep.
+        fn find(
+            dir: &Path,
+            parts: &mut Vec<Value>,
+            found: &mut Vec<(Vec<Value>, Dir)>,
+        ) -> Result<(), io::Error> {
+            if dir
+                .join("include")
+                .is_dir()
+                && dir
+                    .join("lib")
+                    .is_dir()
+            {
+                found.push((parts.clone(), dir.into()));
+                return Ok(());
+            }
+            if parts.len() == 2 {
+                return Ok(());
+            }
+            for entry in fs::read_dir(dir)? {
+                let entry = entry?;
+                if !entry
+                    .file_type()?
+                    .is_dir()
+                {
+                    continue;
+                }
+                parts.push(
+                    util::decode_path_component(
+                        &entry
+                            .file_name()
+                            .to_string_lossy(),
+                    )
+                    .into(),
+                );
+                find(&entry.path(), parts, found)?;
+                parts.pop();
+            }
+            Ok(())
+        }
+
+        let cache_dir = self.cache_dir();
+        if !cache_dir.is_dir() {
+            return Ok(Vec::new());
+        }
+
+        let mut cached = Vec::new();
+        for entry in fs::read_dir(&cache_dir)? {
+            let entry = entry?;
+            let file_name = entry.file_name();
+            let file_name = file_name.to_string_lossy();
+            // encoded aliases never start with a dot, unlike `.used` and other files of build++
+            if file_name.starts_with('.')
+                || !entry
+                    .file_type()?
+                    .is_dir()
+            {
+                continue;
+            }
+
+            let alias = Value::from(util::decode_path_component(
+                &file_name,
+            ));
+
+            let mut found = Vec::new();
+            find(
+                &entry.path(),
+                &mut Vec::new(),
+                &mut found,
+            )?;
+            for (parts, dir) in found {
+                let stamp = cache_dir
+                    .join(CACHE_USED_DIRNAME)
+                    .join(
+                        dir.strip_prefix(&cache_dir)
+                            .unwrap_or(&dir),
+                    );
+                // caches from before stamps were kept count as used when they were made
+                let last_used = [stamp, dir.join(".cached")]
+                    .iter()
+                    .find_map(|stamp| {
+                        stamp
+                            .metadata()
+                            .and_then(|metadata| metadata.modified())
+                            .ok()
+                    });
+                cached.push(CachedDependency {
+                    alias: alias.clone(),
+                    parts,
+                    dir,
+                    last_used,
+                });
+            }
+        }
+        cached.sort_by(|a, b| (&a.alias, &a.parts).cmp(&(&b.alias, &b.parts)));
+        Ok(cached)
+    }
+
+    /// Removes every version and profile of `alias` from the cache dir, so that it is cached
+    /// anew by the next build. Returns whether anything was cached.
+    pub fn uncache_dependency(&self, alias: &str) -> Result<bool, io::Error> {
+        let cache_dir = self.cache_dir();
+        let alias = util::encode_path_component(alias);
+        let alias = alias.as_str();
+        let cached = cache_dir
+            .join(alias)
+            .exists();
+        util::remove_dir_all(cache_dir.join(alias))?;
+        util::remove_dir_all(
+            cache_dir
+                .join(CACHE_USED_DIRNAME)
+                .join(alias),
+        )?;
+        Ok(cached)
+    }
+
+    /// Cached include and lib dirs of every dependency.
+    pub fn dependency_dirs(&self, selected_profile: &str) -> Result<Vec<(Dir, Dir)>, io::Error> {
+        let mut dirs = Vec::new();
+        for (alias, dep) in self
+            .dependencies
+            .iter()
+        {
+            let version = dep.current_version()?; // TODO move this to dep's parse
+            let profile = dep.current_profile(selected_profile)?;
+
+            dirs.push((
+                self.cache_dep_include_dir(
+                    alias.clone(),
+                    version.clone(),
+                    &profile,
+                ),
+                self.cache_dep_lib_dir(
+                    alias.clone(),
+                    version.clone(),
+                    &profile,
+                ),
+            ));
+        }
+        Ok(dirs)
+    }
+
+    /// Cached lib dirs of every dependency in the order they are linked (see [`dependency::Link`]),
+    /// with how they are linked.
+    pub fn dependency_lib_dirs(
+        &self,
+        selected_profile: &str,
+    ) -> Result<Vec<(Dir, dependency::Link)>, io::Error> {
+        let mut lib_dirs = Vec::new();
+        for ((_, lib_dir), alias) in self
+            .dependency_dirs(selected_profile)?
+            .into_iter()
+            .zip(
+                self.dependencies
+                    .keys(),
+            )
+        {
+            lib_dirs.push((
+                lib_dir,
+                self.dependency_links
+                    .get(alias)
+                    .copied()
+                    .unwrap_or_default(),
+            ));
+        }
+        // stable, so that the same priority keeps the written order
+        lib_dirs.sort_by_key(|(_, link)| Reverse(link.priority));
+        Ok(lib_dirs)
+    }
+
+    // Actions
+
+    /// Picks between binary and library by which source files exist,
+    /// `build_type` has to be given if both do.
+    pub fn detect_build_type(
+        &self,
+        build_type: Option<BuildType>,
+        profile: &dyn Profile,
+    ) -> Result<BuildType, BuildError> {
+        use BuildError::*;
+        use BuildType::*;
+
+        Ok(
+            match (
+                build_type,
+                self.src_file(Binary, &*profile)
+                    .is_file(),
+                self.src_file(Library, &*profile)
+                    .is_file(),
+            ) {
+                (Some(build_type), true, true) => build_type,
+                (Some(Binary), true, _) => Binary,
+                (Some(Library), _, true) => Library,
+                (None, true, true) => return Err(BuildTypeNeedsToBeSpecified)?,
+                (None, true, _) => Binary,
+                (None, _, true) => Library,
+                // also ensures that /src/ exists
+                _ => return Err(CouldNotDetectSourceFile)?,
+            },
+        )
+    }
+
+    pub fn build(
+        &self,
+        build_type: Option<BuildType>,
+        profile_name: &str,
+        force_rebuild: bool,
+    ) -> Result<&dyn Profile, BuildError> {
+        let started = Instant::now();
+        stats::begin();
+        warnings::begin();
+        let built = self.build_counted(
+            build_type,
+            profile_name,
+            force_rebuild,
+        );
+        let warning_counts = warnings::end();
+        let counters = stats::end();
+        let (profile, artifact_file) = built?;
+
+        // only `clean -old` reads it, so it does not fail the build either
+        if let Err(err) = targets::record(
+            &self
+                .project_dir
+                .join("target"),
+            &self.version,
+            profile_name,
+        ) {
+            eprintln!(
+                "warning: could not record the target dir: {}",
+                err
+            );
+        }
+
+        // history is only a help for noticing regressions, so it does not fail the build
+        let record = stats::Record {
+            finished: SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map_or(0, |since| since.as_secs()),
+            version: self.version(),
+            profile: profile_name.into(),
+            duration: started.elapsed(),
+            counters,
+            artifact_size: fs::metadata(&artifact_file).map_or(0, |metadata| metadata.len()),
+        };
+        if let Err(err) = stats::append(
+            &self
+                .project_dir
+                .join("target"),
+            &record,
+        ) {
+            eprintln!(
+                "warning: could not record build stats: {}",
+                err
+            );
+        }
+
+        // warnings are only known for what was compiled, and not for dependencies being cached
+        if counters.compiled > 0 && BUILD_LOGS.with_borrow(Vec::is_empty) {
+            self.check_warnings(profile_name, warning_counts)?;
+        }
+
+        Ok(profile)
+    }
+
+    /// Reports warnings of a build against the baseline of the profile (made by the first build
+    /// that counts them), fails on new ones if [`BuildOptions::deny_new_warnings`].
+    /// The baseline is lowered as warnings get fixed.
+    fn check_warnings(
+        &self,
+        profile_name: &str,
+        counts: warnings::Counts,
+    ) -> Result<(), BuildError> {
+        let mut relative_counts = warnings::Counts::new();
+        for (file, count) in counts {
+            let file = Path::new(&*file)
+                .strip_prefix(&self.project_dir)
+                .map_or(file.clone(), |file| {
+                    file.to_string_lossy()
+                        .into()
+                });
+            *relative_counts
+                .entry(file)
+                .or_default() += count;
+        }
+        let counts = relative_counts;
+
+        let total = counts
+            .values()
+            .sum::<usize>();
+        if total > 0 {
+            eprintln!(
+                "warning: {} compiler warnings in {} files",
+                total,
+                counts.len()
+            );
+        }
+
+        let target_dir = self
+            .project_dir
+            .join("target");
+        let baseline = match warnings::read_baseline(&target_dir, profile_name) {
+            Some(baseline) => {
+                let increased = warnings::increased(&counts, &baseline);
+                for (file, count, allowed) in &increased {
+                    eprintln!(
+                        "warning: {} new compiler warnings in {} ({} in the baseline)",
+                        count - allowed,
+                        file,
+                        allowed
+                    );
+                }
+                if !increased.is_empty() && build_options().deny_new_warnings {
+                    return Err(
+                        BuildError::NewCompilerWarnings(
+                            increased
+                                .into_iter()
+                                .map(|(file, ..)| file.clone())
+                                .collect(),
+                        ),
+                    );
+                }
+                warnings::lowered(&baseline, &counts)
+            },
+            None => counts,
+        };
+
+        // the baseline is only a help for keeping warnings down, so it does not fail the build
+        if let Err(err) = warnings::write_baseline(
+            &target_dir,
+            profile_name,
+            &baseline,
+        ) {
+            eprintln!(
+                "warning: could not record the warning baseline: {}",
+                err
+            );
+        }
+        Ok(())
+    }
+
+    /// Builds while [`stats`] counts what was done, returns the artifact as well.
+    fn build_counted(
+        &self,
+        build_type: Option<BuildType>,
+        profile_name: &str,
+        force_rebuild: bool,
+    ) -> Result<(&dyn Profile, Dir), BuildError> {
+        use BuildError::*;
+
+        // detect profile
+        let profile = self
+            .profile(&profile_name)
+            .ok_or_else(|| InvalidProfile(profile_name.into()))?;
+        if let Some(fallback) = self
+            .fallbacks
+            .get(profile_name)
+        {
+            eprintln!(
+                "warning: {}, profile `{}` falls back to profile `{}`",
+                fallback.reason, profile_name, fallback.profile
+            );
+        }
+
+        // detect build_type
+        let build_type = self.detect_build_type(build_type, profile)?;
+
+        // linkers report two copies of a library as baffling duplicate or missing symbols
+        self.load_dependencies()?;
+        self.check_dependency_conflicts()?;
+
+        // cache dependencies
+        let any_recached = self.prepare_dependencies(profile_name)?;
+
+        let target = self.target(
+            build_type,
+            &profile_name,
+            profile,
+        );
+
+        // arguments (ex. flags after `--`) can change without any file being modified
+        let arguments_changed = self.arguments_changed(profile, profile_name, &target)?;
+
+        // ensure needs a rebuild
+        // NOTE: target dir is kept between builds, so only the artifact tells if the last one succeeded
+        if !force_rebuild
+            && !any_recached
+            && !arguments_changed
+            && target
+                .artifact_file
+                .is_file()
+            && last_modified_recursive(&target.artifact_file)
+                .map_err(Rc::new)
+                .map_err(TargetCouldNotReadChanges)?
+                >= self
+                    .sources_last_modified()
+                    .map_err(Rc::new)
+                    .map_err(TargetCouldNotReadChanges)?
+        {
+            return Ok((
+                &*profile,
+                target.artifact_file,
+            ));
+        }
+
+        // object only has to be recompiled if sources (or headers they include) changed,
+        // otherwise (ex. only dependency libraries changed) linking again is enough
+        let mut sources_modified = self
+            .sources_last_modified()
+            .map_err(Rc::new)
+            .map_err(TargetCouldNotReadChanges)?;
+        for (include_dir, _) in self
+            .dependency_dirs(profile_name)
+            .map_err(Rc::new)
+            .map_err(TargetCouldNotReadChanges)?
+        {
+            sources_modified = sources_modified.max(
+                last_modified_recursive(include_dir)
+                    .map_err(Rc::new)
+                    .map_err(TargetCouldNotReadChanges)?,
+            );
+        }
+        let mut recompile = force_rebuild
+            || arguments_changed
+            || !target
+                .object_file
+                .is_file()
+            || last_modified_recursive(&target.object_file)
+                .map_err(Rc::new)
+                .map_err(TargetCouldNotReadChanges)?
+                < sources_modified;
+
+        // prepare target dirs
+        // NOTE: objects, artifacts and includes are kept for incremental compilation and linking,
+        //       unchanged files are not copied over again so that dependents do not recompile
+        if force_rebuild {
+            util::remove_dir_all(self.target_dir(&profile_name))
+                .map_err(Rc::new)
+                .map_err(TargetCouldNotPrepareDirs)?;
+        }
+        fs::create_dir_all(self.target_object_dir(&profile_name))
+            .map_err(Rc::new)
+            .map_err(TargetCouldNotPrepareDirs)?;
+        fs::create_dir_all(self.target_artifact_dir(&profile_name))
+            .map_err(Rc::new)
+            .map_err(TargetCouldNotPrepareDirs)?;
+        fs::create_dir_all(self.target_lib_dir(&profile_name))
+            .map_err(Rc::new)
+            .map_err(TargetCouldNotPrepareDirs)?;
+        fs::create_dir_all(self.target_include_dir(&profile_name))
+            .map_err(Rc::new)
+            .map_err(TargetCouldNotPrepareDirs)?;
+
+        // `import std;` needs the standard library compiled as a module first
+        if self.compile_std_module(
+            profile,
+            &profile_name,
+            &target,
+        )? {
+            recompile = true;
+        }
+
+        // run compiler
+        match self.backend {
+            Backend::BuildPP => self.compile(
+                profile,
+                &profile_name,
+                &target,
+                recompile,
+            )?,
+            Backend::Ninja => self.compile_with_ninja(
+                profile,
+                &profile_name,
+                &target,
+            )?,
+        }
+
+        // artifact and its less specific versions point to the linked one
+        let versions = profile.artifact_versions(self, build_type);
+        if let Some((linked, versions)) = versions.split_first() {
+            let mut link_target = linked;
+            for version in versions {
+                util::replace_symlink(
+                    &**link_target,
+                    target
+                        .artifact_file
+                        .with_file_name(&**version),
+                )
+                .map_err(Rc::new)
+                .map_err(PostBuildCouldNotLinkVersions)?;
+                link_target = version;
+            }
+            util::replace_symlink(
+                &**link_target,
+                &target.artifact_file,
+            )
+            .map_err(Rc::new)
+            .map_err(PostBuildCouldNotLinkVersions)?;
+        }
+
+        // copy over includes to resulting dir
+        util::copy_dir_all_filter(
+            self.include_dir(),
+            self.target_include_dir(&profile_name),
+            &|path, is_dir| !self.is_excluded(path) && (is_dir || self.is_header(path)),
+        )
+        .map_err(Rc::new)
+        .map_err(PostBuildCouldNotCopyIncludes)?;
+
+        // copy over cached libs to target
+        for (alias, dep) in self
+            .dependencies
+            .iter()
+        {
+            let version = dep
+                .current_version() // FIXME do not repeat this
+                .map_err(Rc::new)
+                .map_err(CacheCouldNotGetCurrentVersion)?;
+            let profile = dep
+                .current_profile(profile_name)
+                .map_err(Rc::new)
+                .map_err(CacheCouldNotGetCurrentProfile)?;
+
+            let include_dir = self.cache_dep_include_dir(
+                alias.clone(),
+                version.clone(),
+                &profile,
+            );
+            let lib_dir = self.cache_dep_lib_dir(
+                alias.clone(),
+                version.clone(),
+                &profile,
+            );
+
+            util::copy_dir_all(
+                include_dir,
+                self.target_include_dir(&profile_name),
+            )
+            .map_err(Rc::new)
+            .map_err(PostBuildCouldNotCopyDependencies)?;
+
+            util::copy_dir_all(
+                lib_dir,
+                self.target_artifact_dir(&profile_name),
+            )
+            .map_err(Rc::new)
+            .map_err(PostBuildCouldNotCopyDependencies)?;
+        }
+
+        if build_type == BuildType::Library && self.pkg_config {
+            self.write_pkg_config(&profile_name, &target)
+                .map_err(Rc::new)
+                .map_err(PostBuildCouldNotWritePkgConfig)?;
+        }
+
+        if self.checksums
+            || self
+                .sign
+                .is_some()
+        {
+            let manifest = self
+                .write_checksums(&profile_name)
+                .map_err(Rc::new)
+                .map_err(PostBuildCouldNotWriteChecksums)?;
+            if let Some(sign) = &self.sign {
+                self.sign_checksums(sign, &manifest)?;
+            }
+        }
+
+        Ok((
+            &*profile,
+            target.artifact_file,
+        ))
+    }
+
+    /// Writes `SHA256SUMS` (in `sha256sum` format) of every file in the artifact dir,
+    /// returns its path.
+    fn write_checksums(&self, profile_name: &str) -> Result<Dir, io::Error> {
+        let artifact_dir = self.target_artifact_dir(profile_name);
+
+        let mut files = fs::read_dir(&artifact_dir)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<Vec<_>, _>>()?;
+        files.sort();
+
+        let mut manifest = String::new();
+        for file in files {
+            let name = file
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string();
+            // the manifest itself and its signatures
+            if !file.is_file() || name.starts_with(CHECKSUMS_FILENAME) {
+                continue;
+            }
+
+            manifest.push_str(&format!(
+                "{}  {}\n",
+                util::sha256_hex(&fs::read(&file)?),
+                name
+            ));
+        }
+
+        let manifest_file = Dir::from(artifact_dir.join(CHECKSUMS_FILENAME));
+        fs::write(&manifest_file, manifest)?;
+        util::forget_last_modified();
+        Ok(manifest_file)
+    }
+
+    /// Runs `sign` with `{}` replaced by the manifest (or with it appended if there is no `{}`).
+    fn sign_checksums(&self, sign: &[Value], manifest: &Path) -> Result<(), BuildError> {
+        use BuildError::*;
+
+        let has_placeholder = sign
+            .iter()
+            .any(|piece| piece.contains("{}"));
+        let mut command = Command::new(replace_os(
+            &sign[0],
+            "{}",
+            manifest.as_os_str(),
+        ));
+        for piece in sign[1..].iter() {
+            command.arg(replace_os(
+                piece,
+                "{}",
+                manifest.as_os_str(),
+            ));
+        }
+        if !has_placeholder {
+            command.arg(manifest);
+        }
+
+        let (stdout, stderr) = tool_output()
+            .map_err(Rc::new)
+            .map_err(PostBuildSignFailedSpawn)?;
+        let code = command
+            .current_dir(
+                manifest
+                    .parent()
+                    .unwrap_or(&self.project_dir),
+            )
+            .stdin(Stdio::inherit())
+            .stdout(stdout)
+            .stderr(stderr)
+            .spawn()
+            .map_err(Rc::new)
+            .map_err(PostBuildSignFailedSpawn)?
+            .wait()
+            .map_err(Rc::new)
+            .map_err(PostBuildSignFailedWait)?
+            .code()
+            .ok_or(PostBuildSignKilled)?;
+
+        // signature was written next to the manifest
+        util::forget_last_modified();
+
+        (code == 0).or_err(PostBuildSignFailedExitCode(
+            code,
+        ))
+    }
+
+    /// Writes `<name>.pc` next to the library, so that it can be found by non-build++ consumers.
+    fn write_pkg_config(&self, profile_name: &str, target: &Target) -> Result<(), io::Error> {
+        // pkg-config splits on whitespace
+        fn escape(path: &Path) -> String {
+            path.to_string_lossy()
+                .replace('\\', "/")
+                .replace(' ', "\\ ")
+        }
+
+        // `-l` takes the name without `lib` prefix and extension
+        fn link_name(file_name: &OsStr) -> String {
+            let (stem, _) = util::split_file_name(file_name);
+            let stem = stem.to_string_lossy();
+            stem.strip_prefix("lib")
+                .unwrap_or(&stem)
+                .to_string()
+        }
+
+        let artifact_dir = self.target_artifact_dir(profile_name);
+
+        // dependency libraries are copied over next to the library
+        let mut libs = vec![link_name(
+            target
+                .artifact_file
+                .file_name()
+                .unwrap_or_default(),
+        )];
+        for entry in fs::read_dir(&artifact_dir)? {
+            let path = entry?.path();
+            let (_, ext) = util::split_file_name(path.as_os_str());
+            if path != *target.artifact_file
+                && (ext == "lib" || ext == "a" || ext == "so" || ext == "dylib")
+            {
+                libs.push(link_name(
+                    path.file_name()
+                        .unwrap_or_default(),
+                ));
+            }
+        }
+        libs.sort();
+        libs.dedup();
+
+        let pc = [
+            format!(
+                "prefix={}",
+                escape(&self.target_dir(profile_name))
+            ),
+            String::from("includedir=${prefix}/include"),
+            String::from("libdir=${prefix}/artifact"),
+            String::new(),
+            format!("Name: {}", self.name),
+            format!(
+                "Description: {} (built with build++)",
+                self.name
+            ),
+            format!("Version: {}", self.version),
+            String::from("Cflags: -I${includedir}"),
+            format!(
+                "Libs: -L${{libdir}} {}",
+                libs.iter()
+                    .map(|lib| format!("-l{}", lib))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            ),
+            String::new(),
+        ];
+
+        fs::write(
+            artifact_dir.join(format!("{}.pc", self.name)),
+            pc.join("\n"),
+        )?;
+        util::forget_last_modified();
+        Ok(())
+    }
+
+    /// Caches (building if needed) dependencies for the profile,
+    /// returns whether any of them were recached.
+    /// Hash of everything that a build of this project with `profile_name` depends on: sources,
+    /// headers, configuration, resolved profile, platform, and dependencies' own hashes.
+    /// `None` if a dependency cannot be identified that way.
+    pub fn content_hash(&self, profile_name: &str) -> Result<Option<Value>, io::Error> {
+        let Some(profile) = self.profile(profile_name) else {
+            return Ok(None);
+        };
+
+        let mut lines = vec![
+            format!(
+                "buildpp {}",
+                env!("CARGO_PKG_VERSION")
+            ),
+            format!(
+                "platform {} {}",
+                env::consts::OS,
+                env::consts::ARCH
+            ),
+            format!(
+                "profile {} {}",
+                profile_name,
+                profile.kind()
+            ),
+        ];
+        for (key, value) in profile.settings() {
+            lines.push(format!(
+                "setting {} {}",
+                key, value
+            ));
+        }
+        if let Some(toolchain) = self.toolchain() {
+            lines.push(format!(
+                "toolchain {} {} {}",
+                toolchain
+                    .target
+                    .as_deref()
+                    .unwrap_or_default(),
+                toolchain
+                    .sysroot
+                    .as_ref()
+                    .map(|sysroot| sysroot.to_string_lossy())
+                    .unwrap_or_default(),
+                toolchain
+                    .flags
+                    .join(" ")
+            ));
+        }
+
+        // paths are relative, so that the hash is the same wherever the project is
+        let mut files = vec![self
+            .config_file()
+            .to_path_buf()];
+        for include_dir in self.include_dirs() {
+            util::list_files(&include_dir, &mut files)?;
+        }
+        files.sort();
+        for file in files {
+            lines.push(format!(
+                "file {} {}",
+                file.strip_prefix(&*self.project_dir)
+                    .unwrap_or(&file)
+                    .to_string_lossy()
+                    .replace('\\', "/"),
+                util::sha256_hex(&fs::read(&file)?)
+            ));
+        }
+
+        for (alias, dep) in self
+            .dependencies
+            .iter()
+        {
+            let Some(hash) = dep.content_hash(profile_name)? else {
+                return Ok(None);
+            };
+            lines.push(format!(
+                "dependency {} {}",
+                alias, hash
+            ));
+        }
+
+        Ok(Some(
+            util::sha256_hex(
+                lines
+                    .join("\n")
+                    .as_bytes(),
+            )
+            .into(),
+        ))
+    }
+
+    /// Pins [`Dependency::vendor_hash`] of `dep` in [`LOCK_FILENAME`] if it is not yet (trust on
+    /// first use), warns (or fails, with [`BuildOptions::strict`]) if it differs from the pinned one.
+    fn check_pinned(
+        &self,
+        alias: &dependency::Alias,
+        dep: &dyn Dependency,
+    ) -> Result<(), BuildError> {
+        use BuildError::*;
+
+        let Some(hash) = dep
+            .vendor_hash()
+            .map_err(Rc::new)
+            .map_err(CacheCouldNotCheckIfNeedsRecaching)?
+        else {
+            return Ok(());
+        };
+
+        let lock_file = self
+            .project_dir
+            .join(LOCK_FILENAME);
+        let mut lock = File::open(&lock_file)
+            .ok()
+            .and_then(|file| LSD::parse(file).ok())
+            .and_then(|lsd| lsd.to_level())
+            .unwrap_or_default();
+        let pinned = lock
+            .get_value(
+                [Value::from("dependency"), alias.clone()],
+                (),
+            )
+            .ok()
+            .flatten();
+        match pinned {
+            Some(pinned) if pinned == hash => return Ok(()),
+            Some(_) if build_options().strict => return Err(CacheVendoredDependencyChanged(alias.clone())),
+            Some(_) => eprintln!(
+                "warning: contents of dependency `{}` changed since they were pinned in {}, pinning the new contents",
+                alias, LOCK_FILENAME
+            ),
+            None => {},
+        }
+
+        let LSD::Level(dependencies) = lock
+            .entry("dependency".into())
+            .or_insert_with(|| LSD::Level(Level::new()))
+        else {
+            unreachable!("lockfiles are only written by build++");
+        };
+        dependencies.insert(
+            alias.clone(),
+            LSD::Value(hash),
+        );
+        if let Err(err) = fs::write(
+            &lock_file,
+            LSD::Level(lock).to_string(),
+        ) {
+            eprintln!(
+                "warning: could not write {}: {}",
+                lock_file.display(),
+                err
+            );
+        }
+        Ok(())
+    }
+
+    /// Fails if the same library (by name) is reachable at different versions or through
+    /// different types of dependencies, listing the aliases that lead to each copy of it.
+    fn check_dependency_conflicts(&self) -> Result<(), BuildError> {
+        use BuildError::*;
+
+        /// Name, version and type of a dependency, with the aliases that lead to it.
+        type Found = (
+            Value,
+            Version,
+            &'static str,
+            Vec<Value>,
+        );
+
+        fn collect(
+            dependencies: Map<dependency::Alias, Rc<dyn Dependency>>,
+            path: &[Value],
+            seen: &mut HashSet<Value>,
+            copies: &mut Vec<Found>,
+        ) -> Result<(), io::Error> {
+            for (alias, dep) in dependencies.iter() {
+                if !seen.insert(dep.identity()) {
+                    continue;
+                }
+                let mut path = path.to_vec();
+                path.push(alias.clone());
+                copies.push((
+                    dep.name()?
+                        .unwrap_or_else(|| alias.clone()),
+                    dep.current_version()?,
+                    dep.kind(),
+                    path.clone(),
+                ));
+                collect(
+                    dep.dependencies()?,
+                    &path,
+                    seen,
+                    copies,
+                )?;
+            }
+            Ok(())
+        }
+
+        let mut copies = Vec::new();
+        collect(
+            self.dependencies(),
+            &[],
+            &mut HashSet::new(),
+            &mut copies,
+        )
+        .map_err(Rc::new)
+        .map_err(CouldNotResolveDependencies)?;
+
+        let mut by_name = IndexMap::<Value, Vec<Found>>::new();
+        for copy in copies {
+            by_name
+                .entry(
+                    copy.0
+                        .clone(),
+                )
+                .or_default()
+                .push(copy);
+        }
+
+        let mut conflicting = Vec::new();
+        for (name, copies) in by_name {
+            let (_, version, kind, _) = &copies[0];
+            if copies
+                .iter()
+                .all(
+                    |(_, other_version, other_kind, _)| {
+                        other_version == version && other_kind == kind
+                    },
+                )
+            {
+                continue;
+            }
+
+            eprintln!(
+                "error: `{}` is pulled in more than once:",
+                name
+            );
+            for (_, version, kind, path) in &copies {
+                eprintln!(
+                    "  {} ({}) through {}",
+                    match version.is_empty() {
+                        true => "unversioned",
+                        false => version,
+                    },
+                    kind,
+                    path.join(" > ")
+                );
+            }
+            conflicting.push(name);
+        }
+
+        match conflicting.is_empty() {
+            true => Ok(()),
+            false => Err(ConflictingDependencies(
+                conflicting.into(),
+            )),
+        }
+    }
+
+    pub fn prepare_dependencies(&self, profile_name: &str) -> Result<bool, BuildError> {
+        self.load_dependencies()?;
+        self.while_loading(|| self.cache_dependencies(profile_name))
+    }
+
+    /// A dependency that cannot be read (ex. one in a cycle) fails with its own error,
+    /// not with whatever needed it first.
+    pub fn load_dependencies(&self) -> Result<(), BuildError> {
+        self.while_loading(|| {
+            for dep in self
+                .dependencies
+                .values()
+            {
+                dep.load()?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Dependencies load their configurations lazily, so cycles are noticed in `f`.
+    fn while_loading<T>(&self, f: impl FnOnce() -> T) -> T {
+        LOADING.with_borrow_mut(|loading| loading.push(self.canonical_dir()));
+        let result = f();
+        LOADING.with_borrow_mut(|loading| loading.pop());
+        result
+    }
+
+    fn cache_dependencies(&self, profile_name: &str) -> Result<bool, BuildError> {
+        use BuildError::*;
+
+        // NOTE: do not make cache folder for no reason: every dep will do it themselves
+        let mut any_recached = false;
+        for (alias, dep) in self
+            .dependencies
+            .iter()
+        {
+            let version = dep
+                .current_version()
+                .map_err(Rc::new)
+                .map_err(CacheCouldNotGetCurrentVersion)?;
+            let current_profile = dep
+                .current_profile(profile_name)
+                .map_err(Rc::new)
+                .map_err(CacheCouldNotGetCurrentProfile)?;
+
+            let cache_dep_dir = self.cache_dep_dir(
+                alias.clone(),
+                version.clone(),
+                &current_profile,
+            );
+
+            let used_stamp = self.cache_dep_used_stamp(
+                alias.clone(),
+                version.clone(),
+                &current_profile,
+            );
+
+            if cache_dep_dir.is_dir()
+                && !dep
+                    .needs_recaching(
+                        &current_profile,
+                        cache_dep_dir.clone(),
+                    )
+                    .map_err(Rc::new)
+                    .map_err(CacheCouldNotCheckIfNeedsRecaching)?
+            {
+                stats::cache_hit();
+                mark_used(&used_stamp);
+                continue;
+            }
+            stats::cache_miss();
+
+            self.check_pinned(alias, &**dep)?;
+
+            let step = progress::step(format!(
+                "caching dependency {} {}",
+                alias, version
+            ));
+
+            let include_dir = self.cache_dep_include_dir(
+                alias.clone(),
+                version.clone(),
+                &current_profile,
+            );
+            let lib_dir = self.cache_dep_lib_dir(
+                alias.clone(),
+                version.clone(),
+                &current_profile,
+            );
+
+            fs::create_dir_all(&cache_dep_dir)
+                .map_err(Rc::new)
+                .map_err(CacheCouldNotMakeCacheDirs)?;
+            fs::create_dir_all(&include_dir)
+                .map_err(Rc::new)
+                .map_err(CacheCouldNotMakeCacheDirs)?;
+            fs::create_dir_all(&lib_dir)
+                .map_err(Rc::new)
+                .map_err(CacheCouldNotMakeCacheDirs)?;
+
+            // built caches may be shared between machines, keyed by what they are built from
+            let remote = remote_cache::endpoint().and_then(|endpoint| {
+                match dep.content_hash(profile_name) {
+                    Ok(hash) => hash.map(|hash| (endpoint, hash)),
+                    Err(err) => {
+                        eprintln!(
+                            "warning: not using remote cache for {} (could not hash it: {})",
+                            alias, err
+                        );
+                        None
+                    },
+                }
+            });
+            let fetched = remote
+                .as_ref()
+                .is_some_and(|(endpoint, hash)| {
+                    remote_cache::fetch(endpoint, hash, &cache_dep_dir).unwrap_or_else(|err| {
+                        eprintln!(
+                            "warning: could not fetch {} from remote cache: {}",
+                            alias, err
+                        );
+                        false
+                    })
+                });
+
+            if !fetched {
+                let build_log = cache_dep_dir.join(BUILD_LOG_FILENAME);
+                File::create(&build_log)
+                    .map_err(Rc::new)
+                    .map_err(CacheCouldNotMakeCacheDirs)?;
+                BUILD_LOGS.with_borrow_mut(|logs| logs.push(build_log.clone()));
+                let cached = dep.cache(
+                    &current_profile,
+                    include_dir.clone(),
+                    lib_dir.clone(),
+                );
+                BUILD_LOGS.with_borrow_mut(|logs| logs.pop());
+
+                // output is only worth reading when something went wrong
+                if cached.is_err() || build_options().verbose {
+                    drop(step);
+                    match fs::read_to_string(&build_log) {
+                        Ok(output) if output.is_empty() => {},
+                        Ok(output) => {
+                            eprintln!(
+                                "output of caching dependency {} {} (kept in {}):",
+                                alias,
+                                version,
+                                build_log.display()
+                            );
+                            eprint!("{}", output);
+                        },
+                        Err(err) => eprintln!(
+                            "warning: could not read {}: {}",
+                            build_log.display(),
+                            err
+                        ),
+                    }
+                }
+                cached?;
+
+                if let Some((endpoint, hash)) = &remote {
+                    let entries = [&include_dir, &lib_dir].map(|dir| {
+                        dir.file_name()
+                            .unwrap_or_default()
+                            .to_str()
+                            .unwrap_or_default()
+                    });
+                    if let Err(err) = remote_cache::store(
+                        endpoint,
+                        hash,
+                        &cache_dep_dir,
+                        &entries,
+                    ) {
+                        eprintln!(
+                            "warning: could not store {} in remote cache: {}",
+                            alias, err
+                        );
+                    }
+                }
+            }
+            any_recached = true;
+
+            // unchanged files are not copied again, so mark the whole cache as fresh
+            File::create(cache_dep_dir.join(".cached"))
+                .and_then(|stamp| stamp.set_modified(SystemTime::now()))
+                .map_err(Rc::new)
+                .map_err(CacheCouldNotMakeCacheDirs)?;
+            util::forget_last_modified();
+            mark_used(&used_stamp);
+        }
+
+        Ok(any_recached)
+    }
+
+    /// Compiles `target` (only if `recompile` or there is no object yet) and links it.
+    /// Runs the compiler to leave `emit` of the source in the `emit` dir of the profile's target
+    /// dir instead of building, returns the file it was written to.
+    pub fn emit(
+        &self,
+        build_type: Option<BuildType>,
+        profile_name: &str,
+        emit: Emit,
+    ) -> Result<Dir, BuildError> {
+        use BuildError::*;
+
+        let profile = self
+            .profile(profile_name)
+            .ok_or_else(|| InvalidProfile(profile_name.into()))?;
+        let build_type = self.detect_build_type(build_type, profile)?;
+        let suffix = profile
+            .emit_suffix(emit)
+            .ok_or_else(|| {
+                EmitNotSupported(
+                    profile
+                        .kind()
+                        .into(),
+                    emit,
+                )
+            })?;
+
+        // headers of dependencies are included just like when building
+        self.prepare_dependencies(profile_name)?;
+
+        let mut target = self.target(
+            build_type,
+            profile_name,
+            profile,
+        );
+        let emit_dir = self
+            .target_dir(profile_name)
+            .join("emit");
+        fs::create_dir_all(&emit_dir)
+            .map_err(Rc::new)
+            .map_err(TargetCouldNotPrepareDirs)?;
+        target.object_file = Dir::from(emit_dir.join(format!(
+            "{}{}",
+            target
+                .object_file
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy(),
+            suffix
+        )));
+
+        let _step = progress::step(format!(
+            "emitting {} of {}",
+            emit,
+            target
+                .src_file
+                .strip_prefix(&self.project_dir)
+                .unwrap_or(&target.src_file)
+                .display()
+        ));
+        let mut arguments = self.compile_arguments(profile, profile_name, &target)?;
+        profile.emit_arguments(
+            emit,
+            &target.object_file,
+            &mut arguments,
+        );
+        self.run_compiler(
+            profile,
+            profile_name,
+            profile.compiler_command(),
+            &target.object_file,
+            arguments,
+        )?;
+
+        Ok(target.object_file)
+    }
+
+    /// Analyzes `src_file` with the compiler (for `buildpp analyze`), returning its exit code
+    /// and output, `None` if the profile has no analysis enabled.
+    pub fn compiler_analysis(
+        &self,
+        profile_name: &str,
+        src_file: &Path,
+    ) -> Result<Option<(i32, String)>, BuildError> {
+        use BuildError::*;
+
+        let profile = self
+            .profile(profile_name)
+            .ok_or_else(|| InvalidProfile(profile_name.into()))?;
+        let Some(analysis_arguments) = profile.analysis_only_arguments() else {
+            return Ok(None);
+        };
+
+        // any source is analyzed like the main one of the project
+        let build_type = self
+            .detect_build_type(None, profile)
+            .unwrap_or(BuildType::Binary);
+        let mut target = self.target(
+            build_type,
+            profile_name,
+            profile,
+        );
+        let analyze_dir = self
+            .target_dir(profile_name)
+            .join("analyze");
+        fs::create_dir_all(&analyze_dir)
+            .map_err(Rc::new)
+            .map_err(TargetCouldNotPrepareDirs)?;
+        target.object_file = Dir::from(analyze_dir.join(format!(
+            "{}{}",
+            src_file
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy(),
+            profile.object_suffix()
+        )));
+        target.src_file = Dir::from(src_file.to_path_buf());
+
+        let mut arguments = self.compile_arguments(profile, profile_name, &target)?;
+        arguments.extend(analysis_arguments);
+        let output = self
+            .compiler(
+                profile,
+                profile.compiler_command(),
+                &target.object_file,
+                arguments,
+            )?
+            .stdin(Stdio::null())
+            .output()
+            .map_err(|err| match err.kind() {
+                io::ErrorKind::NotFound => {
+                    report_compiler_not_found(
+                        profile,
+                        profile_name,
+                        profile.compiler_command(),
+                    );
+                    CompilerNotFound(
+                        profile
+                            .compiler_command()
+                            .into(),
+                        profile_name.into(),
+                    )
+                },
+                _ => CompilerFailedSpawn(Rc::new(err)),
+            })?;
+        let code = output
+            .status
+            .code()
+            .ok_or(CompilerKilled)?;
+
+        // cl reports diagnostics on stdout, others on stderr
+        let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+        text.push_str(&String::from_utf8_lossy(
+            &output.stderr,
+        ));
+        Ok(Some((code, text)))
+    }
+
+    /// Where the arguments an object was compiled with are kept, see [`Self::arguments_changed`].
+    fn target_arguments_file(&self, target: &Target) -> PathBuf {
+        target
+            .object_file
+            .with_extension("args")
+    }
+
+    /// Whether the object of `target` was compiled with other arguments than it would be now.
+    /// Only the `buildpp` backend keeps them, `ninja` compares commands itself.
+    fn arguments_changed(
+        &self,
+        profile: &dyn Profile,
+        profile_name: &str,
+        target: &Target,
+    ) -> Result<bool, BuildError> {
+        if !matches!(self.backend, Backend::BuildPP) {
+            return Ok(false);
+        }
+        let recorded =
+            recorded_arguments(&self.compile_arguments(profile, profile_name, target)?);
+        Ok(
+            fs::read_to_string(self.target_arguments_file(target))
+                .map_or(true, |old| old != recorded),
+        )
+    }
+
+    fn compile(
+        &self,
+        profile: &dyn Profile,
+        profile_name: &str,
+        target: &Target,
+        recompile: bool,
+    ) -> Result<(), BuildError> {
+        use BuildError::*;
+
+        if recompile
+            || !target
+                .object_file
+                .is_file()
+        {
+            let _step = progress::step(format!(
+                "compiling {}",
+                target
+                    .src_file
+                    .strip_prefix(&self.project_dir)
+                    .unwrap_or(&target.src_file)
+                    .display()
+            ));
+            let arguments = self.compile_arguments(profile, profile_name, target)?;
+            let recorded = recorded_arguments(&arguments);
+
+            // an identical compilation done before (ex. before switching branches back and forth)
+            // gives the same object, even though modification times say otherwise
+            let action_file = self
+                .action_hash(
+                    profile,
+                    profile_name,
+                    target,
+                    &arguments,
+                )
+                .map_err(Rc::new)
+                .map_err(TargetCouldNotReadChanges)?
+                .map(|hash| {
+                    self.target_actions_dir()
+                        .join(&*hash)
+                });
+            match action_file
+                .as_ref()
+                .filter(|action_file| action_file.is_file())
+            {
+                Some(action_file) => {
+                    fs::copy(
+                        action_file,
+                        &target.object_file,
+                    )
+                    .map_err(Rc::new)
+                    .map_err(TargetCouldNotReuseAction)?;
+                    util::forget_last_modified();
+                },
+                None => {
+                    self.run_compiler(
+                        profile,
+                        profile_name,
+                        profile.compiler_command(),
+                        &target.object_file,
+                        arguments,
+                    )?;
+                    stats::compiled();
+
+                    // keeping the object is only an optimization, so failing to is not an error
+                    if let Some(action_file) = &action_file {
+                        let _ = fs::create_dir_all(self.target_actions_dir()).and_then(|()| {
+                            fs::copy(
+                                &target.object_file,
+                                action_file,
+                            )
+                        });
+                    }
+                },
+            }
+            fs::write(
+                self.target_arguments_file(target),
+                recorded,
+            )
+            .map_err(Rc::new)
+            .map_err(TargetCouldNotPrepareDirs)?;
+        }
+
+        let _step = progress::step(format!(
+            "linking {}",
+            target
+                .artifact_file
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+        ));
+        let arguments = profile
+            .link_arguments(self, target, profile_name)
+            .map_err(Rc::new)
+            .map_err(CompilerCouldNotCollectArguments)?;
+        self.run_compiler(
+            profile,
+            profile_name,
+            profile.linker_command(target.build_type),
+            &target.artifact_file,
+            arguments,
+        )
+        .map_err(|err| match err {
+            CompilerFailedExitCode(code) => LinkerFailedExitCode(code),
+            CompilerKilled => LinkerKilled,
+            err => err,
+        })
+    }
+
+    /// Compiles the standard library module (see [`Profile::std_module_arguments`]) unless it
+    /// was already compiled with the same arguments, returns whether it was compiled.
+    fn compile_std_module(
+        &self,
+        profile: &dyn Profile,
+        profile_name: &str,
+        target: &Target,
+    ) -> Result<bool, BuildError> {
+        use BuildError::*;
+
+        let Some(arguments) = profile
+            .std_module_arguments(self, target, profile_name)
+            .map_err(Rc::new)
+            .map_err(CompilerCouldNotCollectArguments)?
+        else {
+            return Ok(false);
+        };
+
+        // sources importing it have to be compiled with matching arguments, so the module is
+        // compiled again whenever those change (ex. another `optimize`)
+        let object_file = self.target_std_module_file(
+            profile_name,
+            profile.object_suffix(),
+        );
+        let arguments_file = object_file.with_extension("args");
+        let recorded = recorded_arguments(&arguments);
+        if object_file.is_file()
+            && fs::read_to_string(&arguments_file).is_ok_and(|old| old == recorded)
+        {
+            return Ok(false);
+        }
+
+        fs::create_dir_all(self.target_module_dir(profile_name))
+            .map_err(Rc::new)
+            .map_err(TargetCouldNotPrepareDirs)?;
+        let _step = progress::step("compiling standard library module");
+        self.run_compiler(
+            profile,
+            profile_name,
+            profile.compiler_command(),
+            &object_file,
+            arguments,
+        )?;
+        stats::compiled();
+        fs::write(&arguments_file, recorded)
+            .map_err(Rc::new)
+            .map_err(TargetCouldNotPrepareDirs)?;
+
+        Ok(true)
+    }
+
+    fn compile_arguments(
+        &self,
+        profile: &dyn Profile,
+        profile_name: &str,
+        target: &Target,
+    ) -> Result<Vec<OsString>, BuildError> {
+        use BuildError::*;
+
+        let (overrides, overridden_profile) = self.overrides(profile, target)?;
+        let profile = overridden_profile
+            .as_deref()
+            .unwrap_or(profile);
+
+        let mut arguments = profile
+            .compile_arguments(self, target, profile_name)
+            .map_err(Rc::new)
+            .map_err(CompilerCouldNotCollectArguments)?;
+        for define in self
+            .dependency_defines
+            .values()
+            .flat_map(profile::Defines::iter)
+        {
+            arguments.push_from(format!("-D{}", define));
+        }
+        for (_, target_override) in &overrides {
+            for define in target_override
+                .defines
+                .iter()
+            {
+                arguments.push_from(format!("-D{}", define));
+            }
+            for flag in target_override
+                .flags
+                .iter()
+            {
+                arguments.push_from(&**flag);
+            }
+        }
+        for flag in self
+            .extra_flags
+            .iter()
+        {
+            arguments.push_from(&**flag);
+        }
+        Ok(arguments)
+    }
+
+    /// `target` and `file` overrides of `target`, in the order they apply, and `profile` with
+    /// them applied (`None` without any).
+    fn overrides(
+        &self,
+        profile: &dyn Profile,
+        target: &Target,
+    ) -> Result<Overrides<'_>, BuildError> {
+        use BuildError::*;
+
+        // more specific names are applied later, so that they win, and sources are more
+        // specific than any target
+        let src_file = self.relative_src_file(&target.src_file);
+        let overrides = self
+            .target_names(target)
+            .into_iter()
+            .filter_map(|name| {
+                self.target_overrides
+                    .get(&name)
+                    .map(|target_override| (name, target_override))
+            })
+            .chain(
+                self.file_overrides
+                    .iter()
+                    .filter(|(pattern, _)| util::glob_match(pattern, &src_file))
+                    .map(|(pattern, file_override)| (pattern.clone(), file_override)),
+            )
+            .collect::<Vec<_>>();
+
+        let mut overridden_profile: Option<Rc<dyn Profile>> = None;
+        for (name, target_override) in &overrides {
+            overridden_profile = Some(
+                overridden_profile
+                    .as_deref()
+                    .unwrap_or(profile)
+                    .inherit_with(
+                        target_override
+                            .level
+                            .clone(),
+                    )
+                    .map_err(|err| TargetOverrideError(name.clone(), err))?,
+            );
+        }
+        Ok((overrides, overridden_profile))
+    }
+
+    /// `src_file` relative to the project and `/`-separated, as `file` patterns are written.
+    fn relative_src_file(&self, src_file: &Path) -> String {
+        src_file
+            .strip_prefix(&*self.project_dir)
+            .unwrap_or(src_file)
+            .components()
+            .map(|component| {
+                component
+                    .as_os_str()
+                    .to_string_lossy()
+            })
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    /// Writes arguments into `<output_file>.rsp`, returns its path.
+    fn write_response_file(
+        &self,
+        output_file: &Path,
+        arguments: Vec<OsString>,
+    ) -> Result<PathBuf, BuildError> {
+        use BuildError::*;
+
+        // response files are text, so arguments have to be representable as such
+        let arguments = arguments
+            .into_iter()
+            .map(|arg| {
+                arg.into_string()
+                    .map_err(CompilerArgumentIsNotUnicode)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let response_file = output_file.with_extension("rsp");
+        util::write_response_file(&response_file, &arguments)
+            .map_err(Rc::new)
+            .map_err(CompilerCouldNotWriteResponseFile)?;
+        Ok(response_file)
+    }
+
+    /// Compiles and links `target` by generating `build.ninja` and running ninja on it,
+    /// which then keeps track of changed sources, included headers and commands by itself.
+    fn compile_with_ninja(
+        &self,
+        profile: &dyn Profile,
+        profile_name: &str,
+        target: &Target,
+    ) -> Result<(), BuildError> {
+        use BuildError::*;
+
+        let depfile = target
+            .object_file
+            .with_extension("d");
+        let deps = profile.ninja_deps(&depfile);
+
+        let mut compile_arguments = self.compile_arguments(profile, profile_name, target)?;
+        if let Some((_, deps_arguments)) = &deps {
+            compile_arguments.extend(
+                deps_arguments
+                    .iter()
+                    .cloned(),
+            );
+        }
+        let link_arguments = profile
+            .link_arguments(self, target, profile_name)
+            .map_err(Rc::new)
+            .map_err(CompilerCouldNotCollectArguments)?;
+
+        // recompiling is needed when the standard library module changes
+        let std_module = self.target_std_module_file(
+            profile_name,
+            profile.object_suffix(),
+        );
+        let std_module = match std_module.is_file() {
+            true => vec![std_module.to_path_buf()],
+            false => Vec::new(),
+        };
+
+        // relinking is needed when any of the dependency libraries change
+        let mut dependency_libs = Vec::new();
+        for (_, lib_dir) in self
+            .dependency_dirs(profile_name)
+            .map_err(Rc::new)
+            .map_err(CompilerCouldNotCollectArguments)?
+        {
+            for lib in fs::read_dir(lib_dir)
+                .map_err(Rc::new)
+                .map_err(CompilerCouldNotCollectArguments)?
+            {
+                dependency_libs.push(
+                    lib.map_err(Rc::new)
+                        .map_err(CompilerCouldNotCollectArguments)?
+                        .path(),
+                );
+            }
+        }
+
+        let path = |path: &Path| util::ninja_escape(&path.to_string_lossy(), true);
+        let mut lines = vec![
+            String::from("# Generated by build++, changes will be overwritten."),
+            String::from("ninja_required_version = 1.3"),
+            String::new(),
+            String::from("rule compile"),
+            String::from("  command = $cmd"),
+            String::from("  description = compiling $in"),
+        ];
+        match &deps {
+            Some(("gcc", _)) => {
+                lines.push(String::from("  deps = gcc"));
+                lines.push(format!(
+                    "  depfile = {}",
+                    path(&depfile)
+                ));
+            },
+            Some((deps, _)) => lines.push(format!("  deps = {}", deps)),
+            None => {},
+        }
+        lines.push(String::new());
+        lines.push(String::from("rule link"));
+        lines.push(String::from(
+            "  command = $cmd",
+        ));
+        lines.push(String::from(
+            "  description = linking $out",
+        ));
+        lines.push(String::new());
+
+        for (rule, program, output_file, input_file, implicit_inputs, arguments) in [
+            (
+                "compile",
+                profile.compiler_command(),
+                &target.object_file,
+                &target.src_file,
+                std_module,
+                compile_arguments,
+            ),
+            (
+                "link",
+                profile.linker_command(target.build_type),
+                &target.artifact_file,
+                &target.object_file,
+                dependency_libs,
+                link_arguments,
+            ),
+        ] {
+            let mut implicit_inputs = implicit_inputs;
+            let mut command = vec![OsString::from(program)];
+            if profile.uses_response_file() {
+                // only rewritten when arguments change, so ninja can tell when they did
+                let response_file = self.write_response_file(output_file, arguments)?;
+                command.push(concat_os(&[
+                    "@".as_ref(),
+                    response_file.as_os_str(),
+                ]));
+                implicit_inputs.push(response_file);
+            } else {
+                command.extend(arguments);
+            }
+
+            let command = command
+                .into_iter()
+                .map(|arg| {
+                    arg.into_string()
+                        .map(|arg| util::shell_quote(&arg))
+                        .map_err(CompilerArgumentIsNotUnicode)
+                })
+                .collect::<Result<Vec<_>, _>>()?
+                .join(" ");
+
+            let mut statement = format!(
+                "build {}: {} {}",
+                path(output_file),
+                rule,
+                path(input_file)
+            );
+            if !implicit_inputs.is_empty() {
+                statement.push_str(" |");
+                for implicit_input in implicit_inputs {
+                    statement.push(' ');
+                    statement.push_str(&path(&implicit_input));
+                }
+            }
+            lines.push(statement);
+            lines.push(format!(
+                "  cmd = {}",
+                util::ninja_escape(&command, false)
+            ));
+        }
+        lines.push(format!(
+            "default {}",
+            path(&target.artifact_file)
+        ));
+        lines.push(String::new());
+
+        let target_dir = self.target_dir(profile_name);
+        let build_file = target_dir.join("build.ninja");
+        if !fs::read_to_string(&build_file).is_ok_and(|old| old == lines.join("\n")) {
+            fs::write(&build_file, lines.join("\n"))
+                .map_err(Rc::new)
+                .map_err(NinjaCouldNotWriteBuildFile)?;
+        }
+
+        // ninja decides what to compile, so tell from the object whether it did
+        let object_modified = || {
+            fs::metadata(&target.object_file)
+                .and_then(|metadata| metadata.modified())
+                .ok()
+        };
+        let object_modified_before = object_modified();
+
+        let (stdout, stderr) = match captures_tool_output() {
+            true => (Stdio::piped(), Stdio::piped()),
+            false => tool_output()
+                .map_err(Rc::new)
+                .map_err(NinjaFailedSpawn)?,
+        };
+        let child = self
+            .command(
+                "ninja",
+                &target_dir,
+                profile.environment(),
+            )?
+            .arg("-C")
+            .arg(&*target_dir)
+            .stdin(Stdio::inherit())
+            .stdout(stdout)
+            .stderr(stderr)
+            .spawn()
+            .map_err(|err| match err.kind() {
+                io::ErrorKind::NotFound => NinjaNotFound,
+                _ => NinjaFailedSpawn(Rc::new(err)),
+            })?;
+        let code = wait_tool(child)
+            .map_err(Rc::new)
+            .map_err(NinjaFailedWait)?
+            .code()
+            .ok_or(NinjaKilled)?;
+
+        // ninja has run the compiler
+        util::forget_last_modified();
+
+        if object_modified() != object_modified_before {
+            stats::compiled();
+        }
+
+        (code == 0).or_err(NinjaFailedExitCode(code))
+    }
+
+    /// Command running `program` in `working_dir` with `environment`,
+    /// inside of a container if [`BuildOptions::container_image`] is set.
+    fn command(
+        &self,
+        program: &str,
+        working_dir: &Path,
+        environment: &profile::Environment,
+    ) -> Result<Command, BuildError> {
+        use BuildError::*;
+
+        let Some(image) = build_options().container_image else {
+            let mut command = Command::new(program);
+            environment.apply_to(&mut command);
+            command.current_dir(working_dir);
+            jobserver::share_with(&mut command);
+            return Ok(command);
+        };
+
+        let engine = probe::cached("container_engine", || {
+            CONTAINER_ENGINES
+                .iter()
+                .find(|engine| {
+                    Command::new(engine)
+                        .arg("--version")
+                        .stdin(Stdio::null())
+                        .stdout(Stdio::null())
+                        .stderr(Stdio::null())
+                        .status()
+                        .is_ok()
+                })
+                .map(|engine| Value::from(*engine))
+        })
+        .ok_or(ContainerEngineNotFound)?;
+
+        // mounted at the same path, so that absolute paths in arguments stay valid
+        let project_dir = canonical_dir(&self.project_dir);
+        let mut command = Command::new(&*engine);
+        command
+            .arg("run")
+            .arg("--rm")
+            .arg("--volume")
+            .arg(concat_os(&[
+                project_dir.as_os_str(),
+                ":".as_ref(),
+                project_dir.as_os_str(),
+            ]))
+            .arg("--workdir")
+            .arg(working_dir);
+
+        // containers get nothing from the environment anyway, so only set variables are passed
+        for variable in environment.set() {
+            command
+                .arg("--env")
+                .arg(variable);
+        }
+
+        // outputs should stay owned by whoever owns the project, not by root of the container
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            if let Ok(metadata) = fs::metadata(&*project_dir) {
+                command
+                    .arg("--user")
+                    .arg(format!(
+                        "{}:{}",
+                        metadata.uid(),
+                        metadata.gid()
+                    ));
+            }
+        }
+
+        command
+            .arg(&*image)
+            .arg(program);
+        Ok(command)
     }
 
-    pub fn run_arguments(&self, profile_name: &str, profile: &dyn Profile) -> Vec<String> {
-        self.run
-            .as_ref()
-            .map(|run| {
-                run.arguments
+    /// Hash of everything a compilation of `target` with `arguments` depends on: the compiler,
+    /// the arguments, files they name (except outputs), headers of the project and of
+    /// dependencies, and variables the compiler reads.
+    /// `None` if the compiler cannot be found (the compilation will fail anyway).
+    fn action_hash(
+        &self,
+        profile: &dyn Profile,
+        profile_name: &str,
+        target: &Target,
+        arguments: &[OsString],
+    ) -> Result<Option<Value>, io::Error> {
+        let compiler = profile.compiler_command();
+        let Some(compiler_file) = util::find_executable(compiler) else {
+            return Ok(None);
+        };
+        let compiler_metadata = fs::metadata(&compiler_file)?;
+
+        let mut lines = vec![
+            format!(
+                "buildpp {}",
+                env!("CARGO_PKG_VERSION")
+            ),
+            format!(
+                "compiler {} {} {} {:?}",
+                compiler,
+                compiler_file.display(),
+                compiler_metadata.len(),
+                compiler_metadata.modified()?
+            ),
+            format!(
+                "container {}",
+                build_options()
+                    .container_image
+                    .unwrap_or_default()
+            ),
+        ];
+        for variable in COMPILER_ENVIRONMENT_VARIABLES {
+            lines.push(format!(
+                "variable {} {}",
+                variable,
+                env::var_os(variable)
+                    .unwrap_or_default()
+                    .to_string_lossy()
+            ));
+        }
+
+        let outputs = [
+            target
+                .object_file
+                .to_path_buf(),
+            target
+                .object_file
+                .with_extension("d"),
+        ];
+        for argument in arguments {
+            lines.push(format!(
+                "argument {}",
+                argument.to_string_lossy()
+            ));
+            let file = Path::new(argument);
+            if file.is_file()
+                && !outputs
                     .iter()
-                    .map(|arg| {
-                        arg.replace(
-                            "{}", // TODO do not touch {{}}
-                            &self
-                                .target_artifact_file(
-                                    BuildType::Binary,
-                                    profile_name,
-                                    profile,
-                                )
-                                .display()
-                                .to_string(),
-                        )
-                    })
-                    .collect()
-            })
-            .unwrap_or_default()
-    }
+                    .any(|output| output == file)
+            {
+                lines.push(format!(
+                    "file {} {}",
+                    file.display(),
+                    util::sha256_hex(&fs::read(file)?)
+                ));
+            }
+        }
 
-    // Dirs
+        // header trees are shared by every translation unit, so each is only read once
+        let dependency_dirs = self
+            .dependency_dirs(profile_name)?
+            .into_iter()
+            .map(|(include_dir, _)| include_dir);
+        for include_dir in self
+            .include_dirs()
+            .into_iter()
+            .chain(dependency_dirs)
+        {
+            lines.push(format!(
+                "tree {} {}",
+                include_dir.display(),
+                util::tree_hash(&include_dir)?
+            ));
+        }
 
-    pub fn config_file(&self) -> Dir {
-        self.config_file
-            .clone()
+        Ok(Some(
+            util::sha256_hex(
+                lines
+                    .join("\n")
+                    .as_bytes(),
+            )
+            .into(),
+        ))
     }
 
-    pub fn project_dir(&self) -> Dir {
-        self.project_dir
-            .clone()
-    }
+    /// `program` (the compiler, or the linker of the profile) with `arguments`,
+    /// ran where `output_file` is written to.
+    fn compiler(
+        &self,
+        profile: &dyn Profile,
+        program: &str,
+        output_file: &Path,
+        arguments: Vec<OsString>,
+    ) -> Result<Command, BuildError> {
+        // relative response file paths below rely on this
+        let working_dir = output_file
+            .parent()
+            .unwrap_or(&self.project_dir);
 
-    pub fn src_dir(&self) -> Dir {
-        self.project_dir
-            .join("src")
-            .into()
+        let mut command = self.command(
+            program,
+            working_dir,
+            profile.environment(),
+        )?;
+        if profile.uses_response_file() {
+            // relative to current_dir below, so the path itself never needs quoting
+            let response_file = self.write_response_file(output_file, arguments)?;
+            command.arg(concat_os(&[
+                "@".as_ref(),
+                response_file
+                    .file_name()
+                    .unwrap_or_default(),
+            ]));
+        } else {
+            command.args(arguments);
+        }
+        Ok(command)
     }
 
-    pub fn src_file(&self, build_type: BuildType, profile: &dyn Profile) -> Dir {
-        self.src_dir()
-            .join(format!(
-                "{}{}",
-                build_type.src_filename(),
-                profile.src_file_suffix()
-            ))
-            .into()
-    }
+    fn run_compiler(
+        &self,
+        profile: &dyn Profile,
+        profile_name: &str,
+        program: &str,
+        output_file: &Path,
+        arguments: Vec<OsString>,
+    ) -> Result<(), BuildError> {
+        use BuildError::*;
 
-    pub fn target_dir(&self, profile: &str) -> Dir {
-        self.project_dir
-            .join("target")
-            .join(&*self.version)
-            .join(profile)
-            .into()
-    }
+        let mut command = self.compiler(
+            profile,
+            program,
+            output_file,
+            arguments,
+        )?;
+        let (stdout, stderr) = match captures_tool_output() {
+            true => (Stdio::piped(), Stdio::piped()),
+            false => tool_output()
+                .map_err(Rc::new)
+                .map_err(CompilerFailedSpawn)?,
+        };
+        let child = command
+            .stdin(Stdio::inherit())
+            .stdout(stdout)
+            .stderr(stderr)
+            .spawn()
+            .map_err(|err| match err.kind() {
+                io::ErrorKind::NotFound => {
+                    report_compiler_not_found(profile, profile_name, program);
+                    CompilerNotFound(
+                        program.into(),
+                        profile_name.into(),
+                    )
+                },
+                _ => CompilerFailedSpawn(Rc::new(err)),
+            })?;
+        let code = wait_tool(child)
+            .map_err(Rc::new)
+            .map_err(CompilerFailedWait)?
+            .code()
+            .ok_or(CompilerKilled)?;
 
-    pub fn target_include_dir(&self, profile: &str) -> Dir {
-        self.target_dir(profile)
-            .join("include")
-            .into()
-    }
+        // compiler has written its outputs
+        util::forget_last_modified();
 
-    pub fn target_artifact_dir(&self, profile: &str) -> Dir {
-        self.target_dir(profile)
-            .join("artifact")
-            .into()
+        (code == 0).or_err(CompilerFailedExitCode(code))
     }
 
-    pub fn target_artifact_file(
+    /// Builds `targets` (ex. tests) that changed since their last build.
+    pub fn build_programs(
         &self,
-        build_type: BuildType,
-        profile_name: &str,
         profile: &dyn Profile,
-    ) -> Dir {
-        self.target_artifact_dir(profile_name)
-            .join(format!(
-                "{}{}{}",
-                profile.artifact_prefix(build_type),
-                self.name,
-                profile.artifact_suffix(build_type),
-            ))
-            .into()
-    }
+        profile_name: &str,
+        targets: &[(Value, Target)],
+    ) -> Result<(), BuildError> {
+        for (_, target) in targets {
+            if let Some(out_dir) = target
+                .object_file
+                .parent()
+            {
+                fs::create_dir_all(out_dir)
+                    .map_err(Rc::new)
+                    .map_err(BuildError::TargetCouldNotPrepareDirs)?;
+            }
 
-    pub fn cache_dir(&self) -> Dir {
-        self.project_dir
-            .join("cache")
-            .into()
+            if target
+                .artifact_file
+                .is_file()
+                && last_modified_recursive(&target.artifact_file)
+                    .map_err(Rc::new)
+                    .map_err(BuildError::TargetCouldNotReadChanges)?
+                    >= last_modified_recursive(&target.src_file)
+                        .map_err(Rc::new)
+                        .map_err(BuildError::TargetCouldNotReadChanges)?
+            {
+                continue;
+            }
+
+            self.compile(
+                profile,
+                profile_name,
+                target,
+                true,
+            )?;
+        }
+        Ok(())
     }
 
-    pub fn cache_dep_dir(
+    pub fn test(
         &self,
-        dependency: dependency::Alias,
-        version: Version,
-        profile: &str,
-    ) -> Dir {
-        let mut res = self
-            .cache_dir()
-            .join(&*dependency);
-        if !version.is_empty() {
-            res = res.join(&*version);
+        build_type: Option<BuildType>,
+        profile_name: &str,
+        runner: Option<Rc<[Value]>>,
+        selection: &TestSelection,
+        capture_output: bool,
+    ) -> Result<Vec<TestOutcome>, TestError> {
+        // build project first (caches dependencies, ensures sources compile)
+        let profile = self.build(
+            build_type,
+            profile_name,
+            false,
+        )?;
+
+        let mut targets = Vec::new();
+        for (name, target) in self
+            .test_targets(profile_name, profile)
+            .map_err(Rc::new)
+            .map_err(TestError::CouldNotListTests)?
+        {
+            let tags = test_tags(&name, &target.src_file)
+                .map_err(Rc::new)
+                .map_err(TestError::CouldNotListTests)?;
+            if selection.selects(&tags) {
+                targets.push((name, target));
+            }
         }
-        if !profile.is_empty() {
-            res = res.join(profile);
+
+        self.build_programs(
+            profile,
+            profile_name,
+            &targets,
+        )?;
+
+        // run each test, wrapped by runner if any (which wraps the profile's runner if any)
+        let runner = runner
+            .iter()
+            .chain(
+                profile
+                    .runner()
+                    .iter(),
+            )
+            .flat_map(|runner| runner.iter())
+            .cloned()
+            .collect::<Vec<_>>();
+        let mut outcomes = Vec::new();
+        for (name, target) in targets {
+            let mut command = match runner.split_first() {
+                Some((runner, runner_args)) => {
+                    let mut command = Command::new(&**runner);
+                    command
+                        .args(
+                            runner_args
+                                .iter()
+                                .map(Rc::as_ref),
+                        )
+                        .arg(&*target.artifact_file);
+                    command
+                },
+                None => Command::new(&*target.artifact_file),
+            };
+            set_run_environment(&mut command, profile);
+
+            println!("running test {}", name);
+            let (stdout, stderr) = match capture_output {
+                true => (Stdio::piped(), Stdio::piped()),
+                false => (
+                    Stdio::inherit(),
+                    Stdio::inherit(),
+                ),
+            };
+            let started = Instant::now();
+            let output = command
+                .current_dir(&self.project_dir)
+                .stdin(Stdio::null())
+                .stdout(stdout)
+                .stderr(stderr)
+                .spawn()
+                .map_err(Rc::new)
+                .map_err(|err| TestError::FailedSpawn(name.clone(), err))?
+                .wait_with_output()
+                .map_err(Rc::new)
+                .map_err(|err| TestError::FailedWait(name.clone(), err))?;
+            let duration = started.elapsed();
+
+            // captured output is still shown, once the test is done
+            let _ = io::stdout().write_all(&output.stdout);
+            let _ = io::stderr().write_all(&output.stderr);
+
+            outcomes.push(TestOutcome {
+                name,
+                code: output
+                    .status
+                    .code(),
+                duration,
+                stdout: output.stdout,
+                stderr: output.stderr,
+            });
         }
-        res.into()
+
+        Ok(outcomes)
     }
 
-    pub fn cache_dep_include_dir(
+    /// Members of `group`, failing on names that match no target of the project.
+    pub fn group_members(
         &self,
-        dependency: dependency::Alias,
-        version: Version,
-        profile: &str,
-    ) -> Dir {
-        self.cache_dep_dir(dependency, version, profile)
-            .join("include")
-            .into()
+        group: &str,
+        build_type: BuildType,
+        profile_name: &str,
+        profile: &dyn Profile,
+    ) -> Result<Vec<GroupMember>, BuildError> {
+        use BuildError::*;
+
+        let members = self
+            .groups
+            .get(group)
+            .ok_or_else(|| UnknownGroup(group.into()))?;
+
+        let mut programs = self
+            .test_targets(profile_name, profile)
+            .and_then(|mut targets| {
+                targets.extend(self.bench_targets(profile_name, profile)?);
+                targets.extend(self.bin_targets(profile_name, profile)?);
+                targets.extend(self.example_targets(profile_name, profile)?);
+                Ok(targets)
+            })
+            .map_err(Rc::new)
+            .map_err(TargetCouldNotReadChanges)?;
+
+        members
+            .iter()
+            .map(|member| {
+                if **member == *build_type.to_string() {
+                    return Ok(GroupMember::Project);
+                }
+                programs
+                    .iter()
+                    .position(|(_, target)| {
+                        self.target_names(target)
+                            .join(".")
+                            == **member
+                    })
+                    .map(|position| {
+                        let (name, target) = programs.remove(position);
+                        GroupMember::Program(name, target)
+                    })
+                    .ok_or_else(|| UnknownGroupMember(member.clone()))
+            })
+            .collect()
     }
 
-    pub fn cache_dep_lib_dir(
+    /// Builds the project, then tests and benches of `group`, returns its members.
+    pub fn build_group(
         &self,
-        dependency: dependency::Alias,
-        version: Version,
-        profile: &str,
-    ) -> Dir {
-        self.cache_dep_dir(dependency, version, profile)
-            .join("lib")
-            .into()
+        group: &str,
+        build_type: Option<BuildType>,
+        profile_name: &str,
+        force_rebuild: bool,
+    ) -> Result<(&dyn Profile, Vec<GroupMember>), BuildError> {
+        let profile = self.build(
+            build_type,
+            profile_name,
+            force_rebuild,
+        )?;
+        let build_type = self.detect_build_type(build_type, profile)?;
+
+        let members = self.group_members(
+            group,
+            build_type,
+            profile_name,
+            profile,
+        )?;
+        let programs = members
+            .iter()
+            .filter_map(|member| match member {
+                GroupMember::Project => None,
+                GroupMember::Program(name, target) => Some((name.clone(), target.clone())),
+            })
+            .collect::<Vec<_>>();
+        self.build_programs(
+            profile,
+            profile_name,
+            &programs,
+        )?;
+
+        Ok((profile, members))
+    }
+
+    /// Builds `group`, then runs its members one after another with `additional_args`,
+    /// stopping at the first one that fails. Returns the exit code of the last one ran.
+    pub fn run_group(
+        &self,
+        group: &str,
+        profile_name: profile::Name,
+        additional_args: Rc<[Value]>,
+    ) -> Result<i32, RunError> {
+        use RunError::*;
+
+        let (profile, members) = self.build_group(
+            group,
+            None,
+            &profile_name,
+            false,
+        )?;
+
+        for member in members {
+            let code = match member {
+                GroupMember::Project => self.run_built(
+                    profile_name.clone(),
+                    profile,
+                    additional_args.clone(),
+                )?,
+                GroupMember::Program(name, target) => {
+                    let mut command_line = profile
+                        .runner()
+                        .iter()
+                        .flat_map(|runner| runner.iter())
+                        .map(|piece| OsString::from(&**piece))
+                        .collect::<Vec<_>>();
+                    command_line.push(
+                        target
+                            .artifact_file
+                            .as_os_str()
+                            .into(),
+                    );
+                    command_line.extend(
+                        additional_args
+                            .iter()
+                            .map(|arg| OsString::from(&**arg)),
+                    );
+
+                    if !build_options().quiet {
+                        println!("running {}", name);
+                    }
+                    let command = command_line.remove(0);
+                    Command::new(command)
+                        .args(command_line)
+                        .current_dir(&self.project_dir)
+                        .stdin(Stdio::inherit())
+                        .stdout(Stdio::inherit())
+                        .stderr(Stdio::inherit())
+                        .spawn()
+                        .map_err(Rc::new)
+                        .map_err(FailedSpawn)?
+                        .wait()
+                        .map_err(Rc::new)
+                        .map_err(FailedWait)?
+                        .code()
+                        .ok_or(Killed)?
+                },
+            };
+            if code != 0 {
+                return Ok(code);
+            }
+        }
+
+        Ok(0)
     }
 
-    // Actions
+    pub fn run(
+        &self,
+        profile_name: profile::Name,
+        additional_args: Rc<[Value]>,
+    ) -> Result<i32, RunError> {
+        // build binary first (will error if not binary / not runnable)
+        let profile = self.build(
+            Some(BuildType::Binary),
+            &profile_name,
+            false,
+        )?;
 
-    pub fn build(
+        // then run
+        self.run_built(
+            profile_name,
+            profile,
+            additional_args,
+        )
+    }
+
+    /// Runs the commands of `deploy` one after another for the artifact, which has already been
+    /// built with `profile_name`, stopping at the first one that fails.
+    pub fn deploy(
         &self,
         build_type: Option<BuildType>,
         profile_name: &str,
-        force_rebuild: bool,
-    ) -> Result<&dyn Profile, BuildError> {
+    ) -> Result<(), BuildError> {
         use BuildError::*;
-        use BuildType::*;
 
-        // detect profile
+        let commands = self
+            .deploy
+            .as_ref()
+            .map(|deploy| &deploy.commands)
+            .filter(|commands| !commands.is_empty())
+            .ok_or(NoDeployCommands)?;
         let profile = self
-            .profile(&profile_name)
+            .profile(profile_name)
             .ok_or_else(|| InvalidProfile(profile_name.into()))?;
+        let artifact_file = self.target_artifact_file(
+            self.detect_build_type(build_type, profile)?,
+            profile_name,
+            profile,
+        );
 
-        // detect build_type
-        let build_type = match (
-            build_type,
-            self.src_file(Binary, &*profile)
-                .is_file(),
-            self.src_file(Library, &*profile)
-                .is_file(),
-        ) {
-            (Some(build_type), true, true) => build_type,
-            (Some(Binary), true, _) => Binary,
-            (Some(Library), _, true) => Library,
-            (None, true, true) => return Err(BuildTypeNeedsToBeSpecified)?,
-            (None, true, _) => Binary,
-            (None, _, true) => Library,
-            // also ensures that /src/ exists
-            _ => return Err(CouldNotDetectSourceFile)?,
-        };
-
-        // cache dependencies
-        // NOTE: do not make cache folder for no reason: every dep will do it themselves
-        let mut any_recached = false;
-        for (alias, dep) in self
-            .dependencies
-            .iter()
-        {
-            let version = dep
-                .current_version()
-                .map_err(Rc::new)
-                .map_err(CacheCouldNotGetCurrentVersion)?;
-            let current_profile = dep
-                .current_profile(profile_name)
-                .map_err(Rc::new)
-                .map_err(CacheCouldNotGetCurrentProfile)?;
-
-            let cache_dep_dir = self.cache_dep_dir(
-                alias.clone(),
-                version.clone(),
-                &current_profile,
-            );
-
-            if cache_dep_dir.is_dir()
-                && !dep
-                    .needs_recaching(
-                        &current_profile,
-                        cache_dep_dir.clone(),
+        for command in commands {
+            let pieces = command
+                .iter()
+                .map(|piece| {
+                    self.substitute_artifact(
+                        piece,
+                        profile_name,
+                        &artifact_file,
                     )
-                    .map_err(Rc::new)
-                    .map_err(CacheCouldNotCheckIfNeedsRecaching)?
-            {
-                continue;
-            }
+                })
+                .collect::<Vec<_>>();
 
-            let include_dir = self.cache_dep_include_dir(
-                alias.clone(),
-                version.clone(),
-                &current_profile,
-            );
-            let lib_dir = self.cache_dep_lib_dir(
-                alias.clone(),
-                version.clone(),
-                &current_profile,
+            println!(
+                "deploying with {}",
+                pieces
+                    .iter()
+                    .map(|piece| util::shell_quote(&piece.to_string_lossy()))
+                    .collect::<Vec<_>>()
+                    .join(" ")
             );
+            let code = Command::new(&pieces[0])
+                .args(&pieces[1..])
+                .current_dir(&self.project_dir)
+                .stdin(Stdio::inherit())
+                .stdout(Stdio::inherit())
+                .stderr(Stdio::inherit())
+                .spawn()
+                .map_err(|err| {
+                    DeployFailedSpawn(
+                        command[0].clone(),
+                        Rc::new(err),
+                    )
+                })?
+                .wait()
+                .map_err(|err| {
+                    DeployFailedWait(
+                        command[0].clone(),
+                        Rc::new(err),
+                    )
+                })?
+                .code()
+                .ok_or_else(|| DeployKilled(command[0].clone()))?;
 
-            fs::create_dir_all(&cache_dep_dir)
-                .map_err(Rc::new)
-                .map_err(CacheCouldNotMakeCacheDirs)?;
-            fs::create_dir_all(&include_dir)
-                .map_err(Rc::new)
-                .map_err(CacheCouldNotMakeCacheDirs)?;
-            fs::create_dir_all(&lib_dir)
-                .map_err(Rc::new)
-                .map_err(CacheCouldNotMakeCacheDirs)?;
-
-            dep.cache(
-                &current_profile,
-                include_dir,
-                lib_dir,
-            )?;
-            any_recached = true;
+            (code == 0).or_else_err(|| DeployFailedExitCode(command[0].clone(), code))?;
         }
 
-        // ensure needs a rebuild
-        let target_dir = self.target_dir(&profile_name);
-        if !force_rebuild
-            && !any_recached
-            && target_dir.is_dir()
-            && last_modified_recursive(target_dir)
-                .map_err(Rc::new)
-                .map_err(TargetCouldNotReadChanges)?
-                >= Ord::max(
-                    last_modified_recursive(self.config_file())
-                        .map_err(Rc::new)
-                        .map_err(TargetCouldNotReadChanges)?,
-                    last_modified_recursive(self.src_dir())
-                        .map_err(Rc::new)
-                        .map_err(TargetCouldNotReadChanges)?,
+        Ok(())
+    }
+
+    /// Stdin for the program of `buildpp run`, as set by `run.stdin`.
+    fn run_stdin(&self) -> Result<Stdio, RunError> {
+        Ok(
+            match self
+                .run
+                .as_ref()
+                .map(|run| &run.stdin)
+            {
+                None | Some(RunStdin::Inherit) => Stdio::inherit(),
+                Some(RunStdin::Null) => Stdio::null(),
+                Some(RunStdin::File(file)) => File::open(
+                    self.project_dir
+                        .join(&**file),
                 )
-        {
-            return Ok(&*profile);
-        }
+                .map_err(|err| RunError::CouldNotOpenStdin(file.clone(), Rc::new(err)))?
+                .into(),
+            },
+        )
+    }
 
-        // prepare target dirs
-        util::remove_dir_all(self.target_dir(&profile_name))
-            .map_err(Rc::new)
-            .map_err(TargetCouldNotPrepareDirs)?;
-        fs::create_dir_all(self.target_dir(&profile_name))
-            .map_err(Rc::new)
-            .map_err(TargetCouldNotPrepareDirs)?;
-        fs::create_dir_all(self.target_artifact_dir(&profile_name))
-            .map_err(Rc::new)
-            .map_err(TargetCouldNotPrepareDirs)?;
-        fs::create_dir_all(self.target_include_dir(&profile_name))
-            .map_err(Rc::new)
-            .map_err(TargetCouldNotPrepareDirs)?;
+    fn run_tty(&self) -> bool {
+        self.run
+            .as_ref()
+            .is_some_and(|run| run.tty)
+    }
 
-        // run compiler
-        let code = Command::new(profile.compiler_command())
-            .args(
-                profile
-                    .compiler_arguments(
-                        self,
-                        build_type,
-                        &profile_name,
-                    )
-                    .map_err(Rc::new)
-                    .map_err(CompilerCouldNotCollectArguments)?
-                    .into_iter()
-                    .map(|s| s.to_string())
-                    .collect::<Vec<_>>(),
-            )
-            .current_dir(&self.target_artifact_dir(&profile_name))
-            .stdin(Stdio::inherit())
-            .stdout(Stdio::inherit())
-            .stderr(Stdio::inherit())
-            .spawn()
-            .map_err(Rc::new)
-            .map_err(CompilerFailedSpawn)?
-            .wait()
-            .map_err(Rc::new)
-            .map_err(CompilerFailedWait)?
-            .code()
-            .ok_or(CompilerKilled)?;
+    /// Where `run -remote` copies the artifact dir to, relative to the home of the remote user.
+    fn remote_run_dir(&self, profile_name: &str) -> String {
+        format!(
+            ".buildpp/run/{}/{}",
+            self.name, profile_name
+        )
+    }
 
-        (code == 0).ok_or(CompilerFailedExitCode(code))?;
+    /// Builds the binary, copies its artifact dir (with shared libraries of dependencies) to
+    /// `remote` (`user@host`, or a host of the SSH configuration) and runs it there over SSH.
+    /// Paths in the artifact dir given by `run` are changed to those of the copies.
+    pub fn run_remote(
+        &self,
+        remote: &str,
+        profile_name: profile::Name,
+        additional_args: Rc<[Value]>,
+    ) -> Result<i32, RunError> {
+        use RunError::*;
 
-        // copy over includes to resulting dir
-        util::copy_dir_all_filter_extension(
-            self.src_dir(),
-            self.target_include_dir(&profile_name),
-            &|extension| {
-                // https://gcc.gnu.org/onlinedocs/gcc/Overall-Options.html
-                extension == "h" // c
-                    || extension == "cuh" // cuda
-                    || extension == "hh"
-                    || extension == "H"
-                    || extension == "hp"
-                    || extension == "hxx"
-                    || extension == "hpp"
-                    || extension == "HPP"
-                    || extension == "h++"
-                    || extension == "tcc"
-            },
-        )
-        .map_err(Rc::new)
-        .map_err(PostBuildCouldNotCopyIncludes)?;
+        let profile = self.build(
+            Some(BuildType::Binary),
+            &profile_name,
+            false,
+        )?;
 
-        // remove .objs
-        util::remove_dir_all_filter_extension(
-            self.target_artifact_dir(&profile_name),
-            &|extension| extension == "obj",
-        )
-        .map_err(Rc::new)
-        .map_err(PostBuildCouldNotDeleteObjectFiles)?;
+        let artifact_dir = self.target_artifact_dir(&profile_name);
+        let remote_dir = self.remote_run_dir(&profile_name);
+        let copy = || -> Result<(), io::Error> {
+            let _step = progress::step(format!(
+                "copying {} to {}",
+                artifact_dir.display(),
+                remote
+            ));
 
-        // copy over cached libs to target
-        for (alias, dep) in self
-            .dependencies
+            let status = Command::new("ssh")
+                .arg(remote)
+                .arg(format!(
+                    "mkdir -p {}",
+                    util::posix_quote(&remote_dir)
+                ))
+                .stdin(Stdio::null())
+                .status()?;
+            if !status.success() {
+                return Err(io::Error::other(format!(
+                    "ssh failed with {}",
+                    status
+                )));
+            }
+
+            let mut scp = Command::new("scp");
+            scp.args(["-q", "-r"]);
+            for entry in fs::read_dir(&artifact_dir)? {
+                scp.arg(entry?.path());
+            }
+            let status = scp
+                .arg(format!(
+                    "{}:{}/",
+                    remote, remote_dir
+                ))
+                .stdin(Stdio::null())
+                .status()?;
+            if !status.success() {
+                return Err(io::Error::other(format!(
+                    "scp failed with {}",
+                    status
+                )));
+            }
+            Ok(())
+        };
+        copy()
+            .map_err(Rc::new)
+            .map_err(CouldNotCopyToRemote)?;
+
+        let local_dir = artifact_dir.to_string_lossy();
+        let mut command_line = vec![self.run_command(&profile_name, profile)];
+        command_line.extend(self.run_arguments(&profile_name, profile));
+        for add_arg in additional_args.iter() {
+            command_line.push_from(&**add_arg);
+        }
+        let command_line = command_line
             .iter()
-        {
-            let version = dep
-                .current_version() // FIXME do not repeat this
-                .map_err(Rc::new)
-                .map_err(CacheCouldNotGetCurrentVersion)?;
-            let profile = dep
-                .current_profile(profile_name)
-                .map_err(Rc::new)
-                .map_err(CacheCouldNotGetCurrentProfile)?;
+            .map(|arg| {
+                util::posix_quote(
+                    &arg.to_string_lossy()
+                        .replace(&*local_dir, "."),
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
 
-            let include_dir = self.cache_dep_include_dir(
-                alias.clone(),
-                version.clone(),
-                &profile,
-            );
-            let lib_dir = self.cache_dep_lib_dir(
-                alias.clone(),
-                version.clone(),
-                &profile,
+        if !build_options().quiet {
+            println!(
+                "running {} on {}",
+                command_line, remote
             );
-
-            util::copy_dir_all(
-                include_dir,
-                self.target_include_dir(&profile_name),
-            )
+        }
+        let mut ssh = Command::new("ssh");
+        if self.run_tty() {
+            ssh.arg("-t");
+        }
+        // shared libraries of dependencies are next to the binary
+        let code = ssh
+            .arg(remote)
+            .arg(format!(
+                "cd {} && LD_LIBRARY_PATH=.${{LD_LIBRARY_PATH:+:$LD_LIBRARY_PATH}} exec {}",
+                util::posix_quote(&remote_dir),
+                command_line
+            ))
+            .current_dir(&self.project_dir)
+            .stdin(self.run_stdin()?)
+            .stdout(Stdio::inherit())
+            .stderr(Stdio::inherit())
+            .spawn()
             .map_err(Rc::new)
-            .map_err(PostBuildCouldNotCopyDependencies)?;
-
-            util::copy_dir_all(
-                lib_dir,
-                self.target_artifact_dir(&profile_name),
-            )
+            .map_err(FailedSpawn)?
+            .wait()
             .map_err(Rc::new)
-            .map_err(PostBuildCouldNotCopyDependencies)?;
-        }
+            .map_err(FailedWait)?
+            .code()
+            .ok_or(Killed)?;
 
-        Ok(&*profile)
+        Ok(code)
     }
 
-    pub fn run(
+    /// Runs the binary of the project, which has already been built.
+    fn run_built(
         &self,
         profile_name: profile::Name,
+        profile: &dyn Profile,
         additional_args: Rc<[Value]>,
     ) -> Result<i32, RunError> {
         use RunError::*;
 
-        // build binary first (will error if not binary / not runnable)
-        let profile = self.build(
-            Some(BuildType::Binary),
-            &profile_name,
-            false,
-        )?;
-
-        // then run
-        let command = self.run_command(&profile_name, profile);
-        let mut args = self.run_arguments(&profile_name, profile);
+        let mut command_line = vec![self.run_command(&profile_name, profile)];
+        command_line.extend(self.run_arguments(&profile_name, profile));
         for add_arg in additional_args.iter() {
-            args.push(add_arg.to_string());
+            command_line.push_from(&**add_arg);
         }
-        println!(
-            "running {} {}",
-            command,
-            args.join(" ")
-        );
-        let code = Command::new(command)
-            .args(args)
+
+        // artifact cannot be executed on the host by itself, so the runner goes right before it
+        if let Some(runner) = profile.runner() {
+            let artifact_file = self.target_artifact_file(
+                BuildType::Binary,
+                &profile_name,
+                profile,
+            );
+            let position = command_line
+                .iter()
+                .position(|arg| arg == artifact_file.as_os_str())
+                .unwrap_or(0);
+            command_line.splice(
+                position..position,
+                runner
+                    .iter()
+                    .map(|piece| OsString::from(&**piece)),
+            );
+        }
+
+        let command = command_line.remove(0);
+        let args = command_line;
+        if !build_options().quiet {
+            println!(
+                "running {} {}",
+                command.to_string_lossy(),
+                args.iter()
+                    .map(|arg| arg.to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            );
+        }
+        let stdin = self.run_stdin()?;
+        let mut command = match self.run_tty() {
+            true => in_pseudo_terminal(command, args),
+            false => {
+                let mut command = Command::new(command);
+                command.args(args);
+                command
+            },
+        };
+        set_run_environment(&mut command, profile);
+        let code = command
             .current_dir(&self.project_dir)
-            .stdin(Stdio::inherit())
+            .stdin(stdin)
             .stdout(Stdio::inherit())
             .stderr(Stdio::inherit())
             .spawn()
@@ -690,3 +5667,125 @@ impl Configuration {
         Ok(code)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::Component;
+
+    use super::*;
+
+    /// Configuration of an empty project in a fresh temporary dir.
+    fn load_temporary(name: &str) -> Configuration {
+        let project_dir = env::temp_dir().join(format!(
+            "buildpp-test-{}-{}",
+            name,
+            std::process::id()
+        ));
+        fs::create_dir_all(&project_dir).unwrap();
+        fs::write(
+            project_dir.join(CONFIG_FILENAME),
+            "name test\nversion 0.1.0\n",
+        )
+        .unwrap();
+        Configuration::load(project_dir.into()).unwrap()
+    }
+
+    #[test]
+    fn hostile_aliases_stay_in_cache_dir() {
+        let config = load_temporary("hostile-aliases");
+        let cache_dir = config.cache_dir();
+        for hostile in [
+            "..",
+            "../..",
+            "../outside",
+            "/etc",
+            "C:",
+            "C:\\Windows",
+            "..\\..\\outside",
+            "\\\\server\\share",
+            ".used",
+            "nul",
+            "",
+        ] {
+            for (alias, version, profile) in [
+                (hostile, "1.0.0", "default"),
+                ("dep", hostile, "default"),
+                ("dep", "1.0.0", hostile),
+            ] {
+                let dir = config.cache_dep_dir(
+                    alias.into(),
+                    version.into(),
+                    profile,
+                );
+                let relative = dir
+                    .strip_prefix(&cache_dir)
+                    .unwrap_or_else(|_| {
+                        panic!(
+                            "{:?} is outside of the cache",
+                            dir
+                        )
+                    });
+                assert!(
+                    relative
+                        .components()
+                        .all(|component| matches!(
+                            component,
+                            Component::Normal(_)
+                        )),
+                    "{:?}",
+                    dir
+                );
+                assert!(
+                    !relative.starts_with(CACHE_USED_DIRNAME),
+                    "{:?}",
+                    dir
+                );
+            }
+        }
+        fs::remove_dir_all(config.project_dir()).unwrap();
+    }
+
+    #[test]
+    fn cached_hostile_aliases_are_listed_and_removed_by_name() {
+        let config = load_temporary("cached-aliases");
+        for alias in ["../escape", "a/b", "C:", "plain"] {
+            let dir = config.cache_dep_dir(
+                alias.into(),
+                "1.0.0".into(),
+                "default",
+            );
+            fs::create_dir_all(dir.join("include")).unwrap();
+            fs::create_dir_all(dir.join("lib")).unwrap();
+        }
+
+        let cached = config
+            .cached_dependencies()
+            .unwrap();
+        let aliases = cached
+            .iter()
+            .map(|cached| &*cached.alias)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            aliases,
+            ["../escape", "C:", "a/b", "plain"]
+        );
+        assert!(cached
+            .iter()
+            .all(|cached| *cached.parts == ["1.0.0".into(), "default".into()]));
+
+        assert!(config
+            .uncache_dependency("../escape")
+            .unwrap());
+        assert!(config
+            .project_dir()
+            .is_dir());
+        assert_eq!(
+            config
+                .cached_dependencies()
+                .unwrap()
+                .len(),
+            3
+        );
+        fs::remove_dir_all(config.project_dir()).unwrap();
+    }
+}
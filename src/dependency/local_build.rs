@@ -1,6 +1,10 @@
+use std::cell::OnceCell;
+use std::cell::RefCell;
+use std::collections::HashSet;
 use std::convert::Infallible;
 use std::io;
 use std::path::Path;
+use std::path::PathBuf;
 use std::rc::Rc;
 use std::str::FromStr;
 
@@ -10,6 +14,8 @@ use crate::configuration::LoadError;
 use crate::key;
 use crate::lsd::LSDGetExt;
 use crate::lsd::Level;
+use crate::lsd::Map;
+use crate::lsd::Value;
 use crate::profile;
 use crate::profile::DEFAULT_PROFILE;
 use crate::util;
@@ -19,8 +25,20 @@ use crate::Dir;
 use crate::Version;
 
 pub(crate) struct Dependency {
-    config: Configuration,
+    project_dir: Dir,
+    /// Resolved only once needed, so that a moved dependency only fails commands using it.
+    canonical_dir: OnceCell<Result<PathBuf, Rc<io::Error>>>,
     profile: Profile,
+
+    /// Loaded only once actually needed, so that commands not building anything
+    /// do not pay for (or fail because of) every dependency's configuration.
+    config: OnceCell<Result<Configuration, LoadError>>,
+}
+
+thread_local! {
+    /// Projects (by canonical dir) and profiles already built during this invocation,
+    /// so that a project reachable through multiple dependencies is only built once.
+    static BUILT: RefCell<HashSet<(PathBuf, profile::Name)>> = RefCell::default();
 }
 
 #[derive(Debug, Clone)]
@@ -51,8 +69,6 @@ enum InnerParseError {
     MissingProjectPath,
     ProjectPathIsNotAValue,
 
-    ConfigurationLoadError(LoadError),
-
     ProfileIsNotAValue,
 }
 
@@ -63,9 +79,69 @@ impl From<InnerParseError> for Rc<dyn super::InnerParseError> {
     fn from(value: InnerParseError) -> Self { Rc::new(value) }
 }
 
+impl Dependency {
+    fn canonical_dir(&self) -> Result<&Path, Rc<io::Error>> {
+        self.canonical_dir
+            .get_or_init(|| {
+                self.project_dir
+                    .canonicalize()
+                    .map_err(Rc::new)
+            })
+            .as_ref()
+            .map(PathBuf::as_path)
+            .map_err(Rc::clone)
+    }
+
+    /// [`Self::canonical_dir`], or the directory as written while it cannot be resolved.
+    fn resolved_dir(&self) -> &Path {
+        self.canonical_dir()
+            .unwrap_or(&self.project_dir)
+    }
+
+    fn loaded_config(&self) -> Result<&Configuration, CacheError> {
+        self.canonical_dir()
+            .map_err(|err| {
+                CacheError::CouldNotResolveProjectPath(
+                    self.project_dir
+                        .clone(),
+                    err,
+                )
+            })?;
+        self.config
+            .get_or_init(|| {
+                Configuration::load(
+                    self.project_dir
+                        .clone(),
+                )
+            })
+            .as_ref()
+            .map_err(|err| {
+                CacheError::ConfigurationLoadError(
+                    self.project_dir
+                        .clone(),
+                    err.clone(),
+                )
+            })
+    }
+
+    /// Builds report the error of [`Self::loaded_config`] with [`super::Dependency::load`]
+    /// before any of the methods using this are called.
+    fn config(&self) -> Result<&Configuration, io::Error> {
+        self.loaded_config()
+            .map_err(|_| {
+                io::Error::other(format!(
+                    "configuration of {} could not be loaded (`buildpp build` shows why)",
+                    self.project_dir
+                        .display()
+                ))
+            })
+    }
+}
+
 impl super::Dependency for Dependency {
     fn try_parse(
         level: &Level,
+        config_dir: &Path,
     ) -> Result<Rc<dyn super::Dependency>, Rc<dyn super::InnerParseError>>
     where
         Self: Sized, {
@@ -78,12 +154,9 @@ impl super::Dependency for Dependency {
                 ProjectPathIsNotAValue,
             )?
             .ok_or(MissingProjectPath)?;
-        let project_dir = Dir::from(Path::new(&*project_dir));
+        let project_dir = Dir::from(config_dir.join(&*project_dir));
 
-        // 2. try loading configuration file at location
-        let config = Configuration::load(project_dir).map_err(ConfigurationLoadError)?;
-
-        // 3. try grabbing profile
+        // 2. try grabbing profile
         let profile = level
             .get_value(
                 key!(profile),
@@ -98,14 +171,40 @@ impl super::Dependency for Dependency {
             ));
 
         Ok(Rc::new(Dependency {
-            config,
+            project_dir,
+            canonical_dir: OnceCell::new(),
             profile,
+            config: OnceCell::new(),
         }))
     }
 
+    fn identity(&self) -> Value {
+        format!(
+            "local build {} {}",
+            self.resolved_dir()
+                .display(),
+            match &self.profile {
+                Profile::Inherit => "inherit",
+                Profile::OfName(name) => name,
+            },
+        )
+        .into()
+    }
+
+    fn project_dir(&self) -> Option<&Path> {
+        self.canonical_dir()
+            .ok()
+    }
+
+    fn load(&self) -> Result<(), CacheError> {
+        Ok(self
+            .loaded_config()?
+            .load_dependencies()?)
+    }
+
     fn current_version(&self) -> Result<Version, io::Error> {
         Ok(self
-            .config
+            .config()?
             .version())
     }
 
@@ -116,32 +215,60 @@ impl super::Dependency for Dependency {
         })
     }
 
+    fn content_hash(&self, selected_profile: &str) -> Result<Option<Value>, io::Error> {
+        self.config()?
+            .content_hash(&self.current_profile(selected_profile)?)
+    }
+
+    fn kind(&self) -> &'static str { "local build" }
+
+    fn name(&self) -> Result<Option<Value>, io::Error> {
+        Ok(Some(
+            self.config()?
+                .project_name(),
+        ))
+    }
+
+    fn source(&self) -> Value {
+        self.resolved_dir()
+            .to_string_lossy()
+            .into()
+    }
+
+    fn license(&self) -> Result<Option<Value>, io::Error> {
+        Ok(self
+            .config()?
+            .license())
+    }
+
+    fn notice_files(&self) -> Result<Vec<PathBuf>, io::Error> {
+        super::notice_files_in(&self.project_dir)
+    }
+
+    fn dependencies(&self) -> Result<Map<super::Alias, Rc<dyn super::Dependency>>, io::Error> {
+        Ok(self
+            .config()?
+            .dependencies())
+    }
+
+    fn watched_paths(&self) -> Result<Vec<PathBuf>, io::Error> {
+        self.config()?
+            .watched_paths()
+    }
+
     fn needs_recaching(
         &self,
         selected_profile: &str,
         cache_dep_dir: Dir,
     ) -> Result<bool, io::Error> {
-        let target_dir = self
-            .config
-            .target_dir(selected_profile);
+        let config = self.config()?;
+        let target_dir = config.target_dir(selected_profile);
         Ok(!target_dir.is_dir()
             || last_modified_recursive(cache_dep_dir)?
-                < [
-                    last_modified_recursive(
-                        &self
-                            .config
-                            .config_file(),
-                    )?,
-                    last_modified_recursive(
-                        &self
-                            .config
-                            .src_dir(),
-                    )?,
+                < Ord::max(
+                    config.sources_last_modified()?,
                     last_modified_recursive(target_dir)?,
-                ]
-                .into_iter()
-                .max()
-                .unwrap())
+                ))
     }
 
     fn cache(
@@ -150,25 +277,36 @@ impl super::Dependency for Dependency {
         include_dir: Dir,
         lib_dir: Dir,
     ) -> Result<(), CacheError> {
-        // 1. ensure dependency is built
-        self.config
-            .build(
+        let config = self.loaded_config()?;
+
+        // 1. ensure dependency is built (once per invocation)
+        let built_key = (
+            self.resolved_dir()
+                .to_path_buf(),
+            profile::Name::from(selected_profile),
+        );
+        if !BUILT.with_borrow(|built| built.contains(&built_key)) {
+            config.build(
                 Some(BuildType::Library),
                 selected_profile,
                 false,
             )?;
+            BUILT.with_borrow_mut(|built| built.insert(built_key));
+        }
 
-        // 2. copy over results (include -> include_dir, artifact -> lib_dir)
+        // 2. copy over results (include -> include_dir, artifact and import libs -> lib_dir)
         util::copy_dir_all(
-            self.config
-                .target_include_dir(selected_profile),
+            config.target_include_dir(selected_profile),
             include_dir,
         )?;
         util::copy_dir_all(
-            self.config
-                .target_artifact_dir(selected_profile),
-            lib_dir,
+            config.target_artifact_dir(selected_profile),
+            &lib_dir,
         )?;
+        let target_lib_dir = config.target_lib_dir(selected_profile);
+        if target_lib_dir.is_dir() {
+            util::copy_dir_all(target_lib_dir, lib_dir)?;
+        }
 
         // now the version is considered cached, so:
         // - include_dir can be -I'd,
@@ -1,11 +1,14 @@
+use std::fs;
 use std::io;
 use std::path::Path;
+use std::path::PathBuf;
 use std::rc::Rc;
 
 use super::CacheError;
 use crate::key;
 use crate::lsd::LSDGetExt;
 use crate::lsd::Level;
+use crate::lsd::Value;
 use crate::util;
 use crate::util::last_modified_recursive;
 use crate::util::BoolGuardExt;
@@ -15,6 +18,10 @@ use crate::Version;
 pub(crate) struct Dependency {
     include_dir: Dir,
     lib_dir: Dir,
+
+    /// Prebuilt libraries have no configuration to read these from.
+    name: Option<Value>,
+    license: Option<Value>,
 }
 
 #[derive(Debug, Clone)]
@@ -26,6 +33,9 @@ enum InnerParseError {
     MissingLibraryPath,
     LibraryPathIsNotAValue,
     LibDirIsNotADir,
+
+    NameIsNotAValue,
+    LicenseIsNotAValue,
 }
 
 impl super::InnerParseError for InnerParseError {
@@ -38,6 +48,7 @@ impl From<InnerParseError> for Rc<dyn super::InnerParseError> {
 impl super::Dependency for Dependency {
     fn try_parse(
         level: &Level,
+        config_dir: &Path,
     ) -> Result<Rc<dyn super::Dependency>, Rc<dyn super::InnerParseError>>
     where
         Self: Sized, {
@@ -50,7 +61,7 @@ impl super::Dependency for Dependency {
                 IncludePathIsNotAValue,
             )?
             .ok_or(MissingIncludePath)?;
-        let include_dir = Dir::from(Path::new(&*include_path));
+        let include_dir = Dir::from(config_dir.join(&*include_path));
 
         let library_path = level
             .get_value(
@@ -58,28 +69,133 @@ impl super::Dependency for Dependency {
                 LibraryPathIsNotAValue,
             )?
             .ok_or(MissingLibraryPath)?;
-        let lib_dir = Dir::from(Path::new(&*library_path));
+        let lib_dir = Dir::from(config_dir.join(&*library_path));
 
         // Ensure dirs exist
         include_dir
             .is_dir()
-            .ok_or(IncludeDirIsNotADir)?;
+            .or_err(IncludeDirIsNotADir)?;
         lib_dir
             .is_dir()
-            .ok_or(LibDirIsNotADir)?;
+            .or_err(LibDirIsNotADir)?;
+
+        let name = level.get_value(key!(name), NameIsNotAValue)?;
+        let license = level.get_value(
+            key!(license),
+            LicenseIsNotAValue,
+        )?;
 
         Ok(Rc::new(Dependency {
             include_dir,
             lib_dir,
+            name,
+            license,
         }))
     }
 
+    fn identity(&self) -> Value {
+        // dirs are ensured to exist on parse, fall back to the path as written just in case
+        let canonical = |dir: &Dir| {
+            dir.canonicalize()
+                .unwrap_or_else(|_| dir.to_path_buf())
+        };
+        format!(
+            "local pair {} {}",
+            canonical(&self.include_dir).display(),
+            canonical(&self.lib_dir).display(),
+        )
+        .into()
+    }
+
     fn current_version(&self) -> Result<Version, io::Error> { Ok("".into()) }
 
     fn current_profile(&self, _selected_profile: &str) -> Result<crate::profile::Name, io::Error> {
         Ok("".into())
     }
 
+    fn kind(&self) -> &'static str { "local pair" }
+
+    fn name(&self) -> Result<Option<Value>, io::Error> {
+        Ok(self
+            .name
+            .clone())
+    }
+
+    fn license(&self) -> Result<Option<Value>, io::Error> {
+        Ok(self
+            .license
+            .clone())
+    }
+
+    /// Looked for next to the include and library dirs (ex. `vendor/lib/LICENSE`),
+    /// and in the dir containing both (ex. `vendor/LICENSE`).
+    fn notice_files(&self) -> Result<Vec<PathBuf>, io::Error> {
+        let mut dirs = vec![
+            self.include_dir
+                .to_path_buf(),
+            self.lib_dir
+                .to_path_buf(),
+        ];
+        dirs.extend(
+            self.include_dir
+                .parent()
+                .filter(|parent| {
+                    self.lib_dir
+                        .starts_with(parent)
+                })
+                .map(Path::to_path_buf),
+        );
+        dirs.dedup();
+
+        let mut files = Vec::new();
+        for dir in dirs {
+            for file in super::notice_files_in(&dir)? {
+                if !files.contains(&file) {
+                    files.push(file);
+                }
+            }
+        }
+        Ok(files)
+    }
+
+    /// Paths relative to the include and library dirs with contents of the files.
+    fn vendor_hash(&self) -> Result<Option<Value>, io::Error> {
+        let mut lines = Vec::new();
+        for (kind, dir) in [("include", &self.include_dir), ("library", &self.lib_dir)] {
+            let mut files = Vec::new();
+            util::list_files(dir, &mut files)?;
+            files.sort();
+            for file in files {
+                lines.push(format!(
+                    "{} {} {}",
+                    kind,
+                    file.strip_prefix(dir)
+                        .unwrap_or(&file)
+                        .to_string_lossy()
+                        .replace('\\', "/"),
+                    util::sha256_hex(&fs::read(&file)?)
+                ));
+            }
+        }
+        Ok(Some(
+            util::sha256_hex(
+                lines
+                    .join("\n")
+                    .as_bytes(),
+            )
+            .into(),
+        ))
+    }
+
+    fn watched_paths(&self) -> Result<Vec<PathBuf>, io::Error> {
+        Ok(vec![
+            self.include_dir
+                .to_path_buf(),
+            self.lib_dir
+                .to_path_buf(),
+        ])
+    }
+
     fn needs_recaching(
         &self,
         _selected_profile: &str,
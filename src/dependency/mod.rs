@@ -1,12 +1,18 @@
 mod local_build;
 mod local_pair;
 
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
 use std::fmt::Debug;
+use std::fs;
 use std::io;
+use std::path::Path;
+use std::path::PathBuf;
 use std::rc::Rc;
 
 use indexmap::IndexMap;
 
+use crate::configuration::LoadError;
 use crate::key;
 use crate::lsd::LSDGetExt;
 use crate::lsd::Level;
@@ -21,6 +27,31 @@ use crate::Version;
 
 pub type Alias = Value;
 
+/// Top-level files (by uppercase prefix) that carry a license or notices to be redistributed.
+pub const NOTICE_FILE_PREFIXES: &[&str] = &["LICENSE", "LICENCE", "COPYING", "NOTICE"];
+
+/// Files in `dir` named by [`NOTICE_FILE_PREFIXES`], sorted.
+pub fn notice_files_in(dir: &Path) -> Result<Vec<PathBuf>, io::Error> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let upper = path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_uppercase();
+        if path.is_file()
+            && NOTICE_FILE_PREFIXES
+                .iter()
+                .any(|prefix| upper.starts_with(prefix))
+        {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
 //
 // Parse
 //
@@ -35,30 +66,87 @@ pub enum ParseError {
     // TODO will have a default type for remote or smt
     DependenciesWithoutTypeAreNotSupportedYet,
     DependenciesAsVersionsAreNotSupportedYet,
+
+    InvalidValueForKey(&'static str),
 }
 
 impl From<Rc<dyn InnerParseError>> for ParseError {
     fn from(value: Rc<dyn InnerParseError>) -> Self { Self::Inner(value) }
 }
 
-pub fn parse_all(level: Level) -> Result<Map<Alias, Rc<dyn Dependency>>, Vec<ParseError>> {
+impl ParseError {
+    /// What caused this error, shown after it.
+    pub fn note(&self) -> Option<String> {
+        match self {
+            Self::Inner(err) => err.note(),
+            _ => None,
+        }
+    }
+}
+
+/// Dependencies by alias, how each of them is linked (see [`Link`]), and macros defined for
+/// sources using each of them (`define { SDL_MAIN_HANDLED {} }`).
+pub type Parsed = (
+    Map<Alias, Rc<dyn Dependency>>,
+    Map<Alias, Link>,
+    Map<Alias, profile::Defines>,
+);
+
+pub fn parse_all(level: Level, config_dir: &Path) -> Result<Parsed, Vec<ParseError>> {
     let mut dependencies = IndexMap::new();
+    let mut links = IndexMap::new();
+    let mut defines = IndexMap::new();
     let mut dependencies_errors = Vec::new();
 
+    let mut aliases_by_identity = HashMap::new();
+
     for (alias, dependency_lsd) in level.iter() {
-        match parse_one(dependency_lsd.clone()) {
-            Ok(dep) => drop(dependencies.insert(alias.clone(), dep)),
+        match parse_one(
+            dependency_lsd.clone(),
+            config_dir,
+        ) {
+            Ok(dep) => match aliases_by_identity.entry(dep.identity()) {
+                // same dependency under multiple aliases should only be cached and linked once
+                Entry::Occupied(first_alias) => eprintln!(
+                    "warning: dependency `{}` is the same as `{}`, using `{}`",
+                    alias,
+                    first_alias.get(),
+                    first_alias.get(),
+                ),
+                Entry::Vacant(entry) => {
+                    entry.insert(alias.clone());
+                    dependencies.insert(alias.clone(), dep);
+                },
+            },
             Err(err) => dependencies_errors.push(err),
         }
+
+        if let Some(level) = dependency_lsd.to_level() {
+            match Link::parse(&level) {
+                Ok(link) => drop(links.insert(alias.clone(), link)),
+                Err(err) => dependencies_errors.push(err),
+            }
+
+            if let Some(define) = level.get_inner(key!(define)) {
+                match profile::Defines::parse(&define) {
+                    Some(define) => drop(defines.insert(alias.clone(), define)),
+                    None => dependencies_errors.push(ParseError::InvalidValueForKey("define")),
+                }
+            }
+        }
     }
 
     match dependencies_errors.is_empty() {
-        true => Ok(Map::new(dependencies)),
+        true => Ok((
+            Map::new(dependencies),
+            Map::new(links),
+            Map::new(defines),
+        )),
         false => Err(dependencies_errors),
     }
 }
 
-fn parse_one(value: LSD) -> Result<Rc<dyn Dependency>, ParseError> {
+fn parse_one(value: LSD, config_dir: &Path) -> Result<Rc<dyn Dependency>, ParseError> {
     use ParseError::*;
     match value {
         LSD::Level(level) => {
@@ -71,7 +159,7 @@ fn parse_one(value: LSD) -> Result<Rc<dyn Dependency>, ParseError> {
 
             let dependency_type = dependency_type.to_lowercase();
             match dependency_type.as_str() {
-                "local" => return Ok(local_build::Dependency::try_parse(&level)?),
+                "local" => return Ok(local_build::Dependency::try_parse(&level, config_dir)?),
                 _ => {},
             }
 
@@ -81,10 +169,10 @@ fn parse_one(value: LSD) -> Result<Rc<dyn Dependency>, ParseError> {
             {
                 // Add more implementations here...
                 ["local", "build"] | ["local", "build++"] | ["local", "buildpp"] =>
-                    return Ok(local_build::Dependency::try_parse(&level)?),
+                    return Ok(local_build::Dependency::try_parse(&level, config_dir)?),
 
                 ["local", "pair"] | ["local", "include"] | ["local", "library"] =>
-                    return Ok(local_pair::Dependency::try_parse(&level)?),
+                    return Ok(local_pair::Dependency::try_parse(&level, config_dir)?),
 
                 _ => return Err(CouldNotFindMatchingDependencyType)?,
             }
@@ -93,16 +181,61 @@ fn parse_one(value: LSD) -> Result<Rc<dyn Dependency>, ParseError> {
     }
 }
 
+//
+// Link
+//
+
+/// How libraries of a dependency are linked into what uses it, the same for every type of
+/// dependency.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Link {
+    /// Dependencies with a higher one are linked before the others (`link_priority 10`), so that
+    /// single-pass linkers find what they need in dependencies linked after them. Dependencies
+    /// of the same priority are linked in the order they are written.
+    pub priority: i64,
+    /// Every object of its static libraries is linked, even if nothing refers to it
+    /// (`whole_archive true`), as linkers drop objects that only register themselves from
+    /// static constructors (ex. factories and test cases).
+    pub whole_archive: bool,
+}
+
+impl Link {
+    fn parse(level: &Level) -> Result<Link, ParseError> {
+        use ParseError::*;
+        Ok(Link {
+            priority: level
+                .get_parse(
+                    key!(link_priority),
+                    InvalidValueForKey("link_priority"),
+                )?
+                .unwrap_or_default(),
+            whole_archive: level
+                .get_parse(
+                    key!(whole_archive),
+                    InvalidValueForKey("whole_archive"),
+                )?
+                .unwrap_or_default(),
+        })
+    }
+}
+
 //
 // Dependency
 //
 
-pub trait InnerParseError: Debug {}
+pub trait InnerParseError: Debug {
+    /// What caused this error (ex. the I/O error), shown after it.
+    fn note(&self) -> Option<String> { None }
+}
 
 #[derive(Debug, Clone)]
 pub enum CacheError {
     IOError(Rc<io::Error>),
     BuildError(Rc<BuildError>),
+    /// Project directory of a local build dependency that could not be resolved (ex. moved).
+    CouldNotResolveProjectPath(Dir, Rc<io::Error>),
+    /// Configuration of a local build dependency, by its project directory.
+    ConfigurationLoadError(Dir, LoadError),
 }
 
 impl From<io::Error> for CacheError {
@@ -116,12 +249,28 @@ impl From<BuildError> for CacheError {
 pub trait Dependency {
     // parse
 
-    fn try_parse(level: &Level) -> Result<Rc<dyn Dependency>, Rc<dyn InnerParseError>>
+    /// Relative paths in `level` are relative to `config_dir`,
+    /// the directory of the configuration file that declared the dependency.
+    fn try_parse(
+        level: &Level,
+        config_dir: &Path,
+    ) -> Result<Rc<dyn Dependency>, Rc<dyn InnerParseError>>
     where
         Self: Sized;
 
+    /// Uniquely identifies what this dependency points to regardless of how it was written
+    /// (ex. by using canonical paths), so that it is not cached multiple times.
+    fn identity(&self) -> Value;
+
+    /// Canonical directory of the build++ project this dependency is built from, if any.
+    fn project_dir(&self) -> Option<&Path> { None }
+
     // caching
 
+    /// Reads what the other methods need (ex. the configuration of a local build),
+    /// so that a broken dependency fails with its own error before anything is built.
+    fn load(&self) -> Result<(), CacheError> { Ok(()) }
+
     /// Selected version of the dependency.
     ///
     /// In some cases, this may represent latest version, or the only possible version.
@@ -145,6 +294,50 @@ pub trait Dependency {
         Ok(false)
     }
 
+    /// Identifies what `cache` would produce for `selected_profile` across machines
+    /// (ex. a hash of sources and settings), so that it can be shared through a remote cache.
+    ///
+    /// Default implementation is `None`, meaning it is never shared.
+    fn content_hash(&self, _selected_profile: &str) -> Result<Option<Value>, io::Error> { Ok(None) }
+
+    /// Hash of what the dependency is vendored from (ex. a prebuilt SDK), pinned in the lockfile
+    /// when first cached, so that later (or other machines') changes to it are noticed.
+    ///
+    /// Default implementation is `None`, for dependencies that are expected to change
+    /// (ex. projects built along with the one depending on them).
+    fn vendor_hash(&self) -> Result<Option<Value>, io::Error> { Ok(None) }
+
+    /// Paths whose changes make `watch` rebuild (and so recache) what depends on this
+    /// (ex. sources of a project built along with it).
+    ///
+    /// Default implementation is empty, for dependencies that do not change on their own.
+    fn watched_paths(&self) -> Result<Vec<PathBuf>, io::Error> { Ok(Vec::new()) }
+
+    // metadata
+
+    /// Type of the dependency, as written in `is` (ex. `local build`).
+    fn kind(&self) -> &'static str;
+
+    /// Name of what the dependency points to (ex. its project name),
+    /// `None` if it does not have one (then its alias is used instead).
+    fn name(&self) -> Result<Option<Value>, io::Error> { Ok(None) }
+
+    /// Where the dependency comes from, for people reading a package's dependency manifest.
+    ///
+    /// Default implementation is [`Dependency::identity`].
+    fn source(&self) -> Value { self.identity() }
+
+    /// License of the dependency (ex. an SPDX expression like `MIT`), `None` if unknown.
+    fn license(&self) -> Result<Option<Value>, io::Error> { Ok(None) }
+
+    /// License and notice files of the dependency, to be redistributed with what uses it.
+    fn notice_files(&self) -> Result<Vec<PathBuf>, io::Error> { Ok(Vec::new()) }
+
+    /// Dependencies of the dependency itself, which end up in what uses it as well.
+    fn dependencies(&self) -> Result<Map<Alias, Rc<dyn Dependency>>, io::Error> {
+        Ok(Map::default())
+    }
+
     /// Download/Copy/Link version and pre-build it.
     ///
     /// If `output_dir` already exists, version is considered already cached.
@@ -0,0 +1,480 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use crate::configuration::LoadError;
+use crate::profile;
+use crate::BuildError;
+
+/// Stable identifier of an error variant (ex. `BPP0042`), explained by `buildpp explain`.
+/// Ranges: configuration loading from 1, building from 300, profiles from 600.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Code(u16);
+
+impl Display for Code {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "BPP{:04}", self.0)
+    }
+}
+
+impl FromStr for Code {
+    type Err = ();
+
+    /// Accepts `BPP0042`, `bpp42` and `42`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let number = match s.get(..3) {
+            Some(prefix) if prefix.eq_ignore_ascii_case("bpp") => &s[3..],
+            _ => s,
+        };
+        number
+            .parse()
+            .map(Code)
+            .map_err(|_| ())
+    }
+}
+
+//
+// Exit codes
+//
+
+/// What failed, as the exit code of build++, so that scripts can tell failures apart without
+/// reading the output. Shown by `buildpp explain` for each error code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Exit {
+    /// Anything not listed below (ex. invalid flags, failing tests).
+    Failure = 1,
+    /// The configuration or a profile is invalid.
+    Configuration = 2,
+    /// A dependency is invalid or could not be built.
+    Dependency = 3,
+    /// Building the project failed before linking (ex. a source file did not compile).
+    Compile = 4,
+    /// Linking the project failed.
+    Link = 5,
+    /// The program of `buildpp run` could not be started or was killed.
+    Run = 6,
+}
+
+impl Display for Exit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Exit::Failure => "failure",
+            Exit::Configuration => "configuration error",
+            Exit::Dependency => "dependency error",
+            Exit::Compile => "compile error",
+            Exit::Link => "link error",
+            Exit::Run => "run failure",
+        };
+        write!(
+            f,
+            "{} ({})",
+            *self as i32, name
+        )
+    }
+}
+
+impl Exit {
+    /// What another build++ exited with (ex. a build of one of `-profiles`).
+    pub fn from_status(code: Option<i32>) -> Self {
+        [
+            Exit::Configuration,
+            Exit::Dependency,
+            Exit::Compile,
+            Exit::Link,
+            Exit::Run,
+        ]
+        .into_iter()
+        .find(|exit| Some(*exit as i32) == code)
+        .unwrap_or(Exit::Failure)
+    }
+}
+
+impl From<Code> for Exit {
+    fn from(code: Code) -> Self {
+        match code.0 {
+            // DependenciesErrors, DependsOnItself, DependencyCycle,
+            // CacheCouldNotGetCurrentVersion, CacheCouldNotGetCurrentProfile,
+            // CacheCouldNotCheckIfNeedsRecaching, CacheCouldNotMakeCacheDirs,
+            // CacheError, PostBuildCouldNotCopyDependencies, CacheVendoredDependencyChanged,
+            // ConflictingDependencies, CouldNotResolveDependencies
+            13..=15 | 304..=308 | 328 | 338 | 348 | 349 => Exit::Dependency,
+            // LinkerFailedExitCode, LinkerKilled
+            336 | 337 => Exit::Link,
+            // source file and build type detection, InvalidProfile, UnknownGroup(Member),
+            // NoDeployCommands, EmitNotSupported
+            300..=303 | 339..=341 | 347 => Exit::Configuration,
+            // DeployFailedSpawn, DeployFailedWait, DeployFailedExitCode, DeployKilled
+            342..=345 => Exit::Failure,
+            304..600 => Exit::Compile,
+            _ => Exit::Configuration,
+        }
+    }
+}
+
+pub trait ErrorCode {
+    fn code(&self) -> Code;
+}
+
+/// Every code with the name of its variant and its explanation, by code.
+pub fn all() -> impl Iterator<
+    Item = (
+        Code,
+        &'static str,
+        &'static str,
+    ),
+> {
+    EXPLANATIONS
+        .iter()
+        .copied()
+}
+
+/// Name of the variant and its explanation.
+pub fn explain(code: Code) -> Option<(&'static str, &'static str)> {
+    all()
+        .find(|(other, ..)| *other == code)
+        .map(|(_, name, explanation)| (name, explanation))
+}
+
+/// Every variant has to be listed (matches are exhaustive), codes must never be reused.
+macro_rules! error_codes {
+    ($($error:path { $($code:literal $variant:ident $explanation:literal,)* })*) => {
+        $(
+            impl ErrorCode for $error {
+                fn code(&self) -> Code {
+                    match self {
+                        $(Self::$variant { .. } => Code($code),)*
+                    }
+                }
+            }
+        )*
+
+        const EXPLANATIONS: &[(Code, &str, &str)] = &[
+            $($((Code($code), stringify!($variant), $explanation),)*)*
+        ];
+    };
+}
+
+error_codes! {
+    LoadError {
+        1 CouldNotReadStdin
+            "`-config -` was given, but the configuration could not be read from stdin. \
+             Pipe the configuration in (ex. `generate-config | buildpp build -config -`).",
+        2 CouldNotPersistStdin
+            "The configuration read from stdin is kept as `target/stdin.build++.lsd`, \
+             which could not be written. Check permissions of the `target` directory.",
+        3 CouldNotOpenConfiguration
+            "The configuration file could not be opened. Run build++ from the project \
+             directory (the one with `build++.lsd`), create one with `buildpp new`, \
+             or point to it with `-config`.",
+        4 CouldNotParseLSD
+            "The configuration is not valid LSD. Check for unclosed `{`, `[` or quotes, \
+             and for keys written twice.",
+        5 MissingProjectName
+            "The configuration has no `name`. Add `name my-project` to it.",
+        6 ProjectNameIsNotAValue
+            "`name` has to be a single value, not a level or list.",
+        7 MissingVersion
+            "The configuration has no `version`. Add `version 0.1.0` to it.",
+        8 VersionIsNotAValue
+            "`version` has to be a single value, not a level or list.",
+        9 LicenseIsNotAValue
+            "`license` has to be a single value (ex. an SPDX expression like `MIT`).",
+        10 DescriptionIsNotAValue
+            "`description` has to be a single value, quote it if it has spaces.",
+        11 RegistryIsNotAValue
+            "`registry` has to be a single value, the URL of the registry.",
+        12 DependenciesIsNotALevel
+            "`dependency` has to be a level of dependencies by alias \
+             (ex. `dependency.lib.is local`).",
+        13 DependenciesErrors
+            "Some dependencies could not be parsed, see the listed errors. \
+             Each dependency needs a known type (`is local`, `is local pair`) and its keys.",
+        14 DependsOnItself
+            "The project lists itself as a dependency. Remove that dependency.",
+        15 DependencyCycle
+            "Projects depend on each other in a cycle, listed in order. \
+             Break the cycle by moving the shared code into its own library.",
+        16 ProfilesIsNotALevel
+            "`profile` has to be a level of profiles by name (ex. `profile.default nvcc`).",
+        17 ProfilesErrors
+            "Some profiles could not be parsed, see the listed errors.",
+        18 MissingCommandInRun
+            "`run` has to have a command to run the artifact with.",
+        19 RunCommandIsNotAValue
+            "`run` has to be a command written as a value or a list of values.",
+        20 RunPieceIsNotAValue
+            "Every item of the `run` list has to be a single value.",
+        21 TestIsNotALevel
+            "`test` has to be a level (ex. `test.runner valgrind`).",
+        22 TestRunnerPieceIsNotAValue
+            "Every item of the `test.runner` list has to be a single value.",
+        23 AnalyzeIsNotALevel
+            "`analyze` has to be a level (ex. `analyze.analyzer cppcheck`).",
+        24 AnalyzerPieceIsNotAValue
+            "Every item of the `analyze.analyzer` list has to be a single value.",
+        25 AnalyzeSuppressionsIsNotAValue
+            "`analyze.suppressions` has to be a single value, the path to the suppressions file.",
+        26 PkgConfigIsNotABool
+            "`pkg_config` has to be `true` or `false`.",
+        27 ChecksumsIsNotABool
+            "`checksums` has to be `true` or `false`.",
+        28 SignPieceIsNotAValue
+            "Every item of the `sign` list has to be a single value, `{}` standing for the manifest.",
+        29 UnknownBackend
+            "`backend` has to be `buildpp` or `ninja`.",
+        30 ToolchainIsNotAValue
+            "`toolchain` has to be a single value, the path to the toolchain file.",
+        31 ToolchainCouldNotOpen
+            "The toolchain file could not be opened. Check the path given by `toolchain` \
+             or `-toolchain` (relative to the configuration, or to the current directory).",
+        32 ToolchainCouldNotParseLSD
+            "The toolchain file is not valid LSD.",
+        33 ToolchainIsNotALevel
+            "The toolchain file has to contain keys (ex. `sysroot`, `target`, `flags`).",
+        34 ToolchainSysrootIsNotAValue
+            "`sysroot` of the toolchain has to be a single value, the path to the sysroot.",
+        35 ToolchainTargetIsNotAValue
+            "`target` of the toolchain has to be a single value, the target triple.",
+        36 ToolchainFlagIsNotAValue
+            "Every item of the toolchain `flags` list has to be a single value.",
+        37 ToolchainProfileError
+            "Keys of the toolchain file are applied to every profile, and one of them \
+             is not valid for a profile. See the profile error.",
+        38 TargetsIsNotALevel
+            "`target` has to be a level of overrides by target (ex. `target.binary.standard c++20`).",
+        39 TargetIsNotALevel
+            "An override of `target` or `file` has to be a level of profile keys, `defines` \
+             and `flags`.",
+        40 TargetPieceIsNotAValue
+            "Every item of `defines` and `flags` of a target or file override has to be a \
+             single value.",
+        41 KeyErrors
+            "Several keys of the configuration have mistakes, each listed with its key path \
+             and its own error.",
+        42 ExcludePatternIsNotAValue
+            "Every item of the `exclude` list has to be a single value, a glob \
+             (ex. `src/detail/**`).",
+        43 IncludeDirIsNotAValue
+            "`include_dir` has to be a single value, the dir of public headers \
+             relative to the project (ex. `include`).",
+        44 IncludeExtensionIsNotAValue
+            "Every item of the `include_extensions` list has to be a single value \
+             (ex. `hpp`).",
+        45 RequiresBuildppIsNotAValue
+            "`requires_buildpp` has to be a single value, the versions of build++ the \
+             configuration needs (ex. `>=0.3`).",
+        46 InvalidBuildppRequirement
+            "`requires_buildpp` has to be comparators of versions separated by commas \
+             (ex. `>=0.3, <0.5`), using `>=`, `>`, `<=`, `<` or `=`.",
+        47 BuildppVersionNotSupported
+            "The project (or one of its dependencies) needs another version of build++, as \
+             given by its `requires_buildpp`. Please upgrade build++ (ex. `cargo install --git \
+             https://github.com/kirillsemyonkin/buildpp`), `buildpp version` shows the current one.",
+        48 UnknownRunPlaceholder
+            "`run` can only use the placeholders `{}` (or `{artifact}`), `{artifact_dir}`, \
+             `{project_dir}`, `{profile}` and `{version}`, other braces have to be written \
+             doubled (ex. `{{}}`).",
+        49 RunQuietIsNotABool
+            "`run.quiet` has to be `true` or `false`.",
+        50 RunStdinIsNotAValue
+            "`run.stdin` has to be `inherit`, `null` or a path of a file relative to the \
+             project dir.",
+        51 RunTtyIsNotABool
+            "`run.tty` has to be `true` or `false`.",
+        52 GroupsIsNotALevel
+            "`group` has to be a level of groups by name (ex. `group.tools [ binary benches.parse ]`).",
+        53 GroupMemberIsNotAValue
+            "Every member of a group has to be a single value, the name of a target.",
+        54 DeployIsNotALevel
+            "`deploy` has to be a level (ex. with `commands`).",
+        55 DeployProfileIsNotAValue
+            "`deploy.profile` has to be a single value, the name of the profile to deploy \
+             (`release` if not given).",
+        56 DeployAfterBuildIsNotABool
+            "`deploy.after_build` has to be `true` or `false`.",
+        57 DeployCommandsIsNotAList
+            "`deploy.commands` has to be a list of commands, each either a value with spaces \
+             or a list of a command and its arguments.",
+        58 DeployCommandPieceIsNotAValue
+            "Every item of a command of `deploy.commands` has to be a single value.",
+        59 UnknownDeployPlaceholder
+            "`deploy.commands` can only use the placeholders `{}` (or `{artifact}`), \
+             `{artifact_dir}`, `{project_dir}`, `{profile}` and `{version}`, other braces have \
+             to be written doubled (ex. `{{}}`).",
+        60 CouldNotOpenLocalConfiguration
+            "`build++.local.lsd` exists next to the configuration, but could not be opened. \
+             Check its permissions, or remove it.",
+        61 CouldNotParseLocalLSD
+            "`build++.local.lsd` is not valid LSD. Check for unclosed `{`, `[` or quotes, \
+             and for keys written twice.",
+        62 FileOverridesIsNotALevel
+            "`file` has to be a level of overrides by source pattern \
+             (ex. `file.\"src/generated/*.cpp\".flags -O0`).",
+        63 GeneratedSrcDirIsNotAValue
+            "Every item of the `generated_src_dirs` list has to be a single value, a dir \
+             relative to the project (ex. `target/generated-src`).",
+    }
+
+    BuildError {
+        300 CouldNotDetectSourceFile
+            "Neither `src/main` nor `src/lib` source exists for the profile's language \
+             (ex. `src/main.cu` for nvcc). Create one, or check `is` of the profile.",
+        301 RequiredBuildTypeDoesNotHaveMatchingSourceFile
+            "`-is` asked for a binary or library, but its source (`src/main` or `src/lib`) \
+             does not exist.",
+        302 BuildTypeNeedsToBeSpecified
+            "Both `src/main` and `src/lib` exist. Choose one with `-is binary` or `-is library`.",
+        303 InvalidProfile
+            "The selected profile is not in `profile` of the configuration. \
+             Check its name or add it.",
+        304 CacheCouldNotGetCurrentVersion
+            "The version of a dependency could not be found out. Check that its \
+             configuration is readable.",
+        305 CacheCouldNotGetCurrentProfile
+            "The profile a dependency should be built with could not be found out.",
+        306 CacheCouldNotCheckIfNeedsRecaching
+            "Modification times of a dependency could not be compared to its cache. \
+             Check permissions, or remove the cache directory.",
+        307 CacheCouldNotMakeCacheDirs
+            "Directories of the dependency cache could not be created. Check permissions \
+             and free space.",
+        308 CacheError
+            "A dependency could not be cached (copied or built), or the directory or configuration \
+             of a local build dependency could not be loaded, see the inner error.",
+        309 TargetCouldNotReadChanges
+            "Modification times of sources could not be read to decide what to rebuild.",
+        310 TargetCouldNotPrepareDirs
+            "Directories inside `target` could not be created. Check permissions and free space.",
+        311 TargetOverrideError
+            "A `target.<name>` override has a key that is not valid for the profile. \
+             See the profile error.",
+        312 CompilerCouldNotCollectArguments
+            "Arguments for the compiler could not be collected (ex. dependency dirs \
+             could not be listed).",
+        313 CompilerCouldNotWriteResponseFile
+            "The `.rsp` file passing arguments to the compiler could not be written.",
+        314 CompilerArgumentIsNotUnicode
+            "An argument (usually a path) is not valid Unicode, which the compiler \
+             cannot be given. Rename the file or directory.",
+        315 CompilerNotFound
+            "The compiler of the profile was not found. Install it and add it to PATH, \
+             or set `compiler_path` of the profile.",
+        316 CompilerFailedSpawn
+            "The compiler could not be started. Check that `compiler_path` is executable.",
+        317 CompilerFailedWait
+            "The compiler was started, but waiting for it failed.",
+        318 CompilerFailedExitCode
+            "The compiler reported errors, see its output above.",
+        319 CompilerKilled
+            "The compiler was killed by a signal (ex. out of memory).",
+        320 NinjaCouldNotWriteBuildFile
+            "`build.ninja` could not be written into `target`.",
+        321 NinjaNotFound
+            "`backend ninja` is set, but ninja was not found. Install it and add it to PATH, \
+             or remove `backend`.",
+        322 NinjaFailedSpawn
+            "ninja could not be started.",
+        323 NinjaFailedWait
+            "ninja was started, but waiting for it failed.",
+        324 NinjaFailedExitCode
+            "ninja reported errors, see its output above.",
+        325 NinjaKilled
+            "ninja was killed by a signal.",
+        326 ContainerEngineNotFound
+            "`-in-container` needs docker or podman. Install one and add it to PATH.",
+        327 PostBuildCouldNotCopyIncludes
+            "Headers of the library could not be copied into `target`.",
+        328 PostBuildCouldNotCopyDependencies
+            "Shared libraries of dependencies could not be copied next to the artifact.",
+        329 PostBuildCouldNotWritePkgConfig
+            "The pkg-config `.pc` file could not be written.",
+        330 PostBuildCouldNotWriteChecksums
+            "`SHA256SUMS` could not be written next to the artifacts.",
+        331 PostBuildSignFailedSpawn
+            "The `sign` command could not be started. Check that it is installed.",
+        332 PostBuildSignFailedWait
+            "The `sign` command was started, but waiting for it failed.",
+        333 PostBuildSignFailedExitCode
+            "The `sign` command failed, see its output above (ex. a missing key).",
+        334 PostBuildSignKilled
+            "The `sign` command was killed by a signal.",
+        335 PostBuildCouldNotLinkVersions
+            "Symlinks to the versioned shared library (`versioned_so`) could not be made \
+             next to it. Check that the file system of `target` supports symlinks.",
+        336 LinkerFailedExitCode
+            "Linking reported errors, see its output above (ex. an undefined reference \
+             to a function of a library that is not a dependency).",
+        337 LinkerKilled
+            "Linking was killed by a signal (ex. out of memory).",
+        338 CacheVendoredDependencyChanged
+            "Contents of a vendored dependency (ex. `local pair`) differ from those pinned in \
+             `build++.lock` when it was first cached, and `-strict` was given. If the change is \
+             intended (ex. an SDK upgrade), build without `-strict` once to pin the new contents.",
+        339 UnknownGroup
+            "`-group` names a group that is not in `group` of the configuration.",
+        340 UnknownGroupMember
+            "A member of the group is not a target of the project. Members are `binary` or \
+             `library` (whichever the project is), `tests.<name>` for `tests/<name>.*` and \
+             `benches.<name>` for `benches/<name>.*`.",
+        341 NoDeployCommands
+            "`buildpp deploy` needs `deploy.commands` in the configuration \
+             (ex. `scp {} user@host:/srv/`).",
+        342 DeployFailedSpawn
+            "A command of `deploy` could not be started. Check that it is installed.",
+        343 DeployFailedWait
+            "A command of `deploy` was started, but waiting for it failed.",
+        344 DeployFailedExitCode
+            "A command of `deploy` failed, see its output above (ex. the host could not be \
+             reached). Commands after it were not run.",
+        345 DeployKilled
+            "A command of `deploy` was killed by a signal.",
+        346 NewCompilerWarnings
+            "`-deny-new-warnings` was given, and files have more compiler warnings than in \
+             `target/warnings.lsd`, the baseline made by the first build of the profile (and \
+             lowered as warnings get fixed). Fix the new warnings listed above, or remove the \
+             baseline to accept them.",
+        347 EmitNotSupported
+            "The compiler of the profile cannot emit what `-emit` asks for (ex. `ir` with nvcc). \
+             nvcc emits `preprocessed` and `asm` (PTX), msvc emits `preprocessed` and `asm`, \
+             universal binaries (more than one `arch`) cannot be emitted.",
+        348 ConflictingDependencies
+            "The same library (by name) is pulled in at different versions, or by different \
+             types of dependencies, which linkers report as duplicate or missing symbols. \
+             Make the dependencies listed above agree on one copy of it.",
+        349 CouldNotResolveDependencies
+            "Versions, names or dependencies of dependencies could not be read while looking \
+             for conflicting copies of a library.",
+        350 TargetCouldNotReuseAction
+            "The object of an identical earlier compilation could not be copied from \
+             `target/actions`. Check permissions and free space, or remove that directory.",
+    }
+
+    profile::ParseError {
+        600 CouldNotFindMatchingCompiler
+            "`is` of a profile names an unknown compiler. Use `gcc` (or `g++`, `gnu`), `mingw` \
+             (or `mingw-w64`, `mingw64`), `zig` (or `zig-cc`, `zigcc`), `apple-clang` \
+             (or `appleclang`, `xcode`), `msvc`, `clang-cl` (or `clangcl`), `nvcc` (or `cuda`) \
+             or `hipcc` (or `hip`, `rocm`).",
+        601 InheritingFromNonExistentProfile
+            "`inherit` names a profile that does not exist. Profiles can only inherit \
+             from profiles written before them.",
+        602 InheritIsNotAValue
+            "`inherit` has to be a single value, the name of a profile.",
+        603 MissingProfileType
+            "A profile needs either `is` (the compiler) or `inherit` (another profile).",
+        604 ProfileTypeIsNotAValue
+            "`is` of a profile has to be a single value (ex. `is nvcc`).",
+        605 InvalidValueForKey
+            "A key of the profile has a value the compiler does not understand \
+             (ex. `standard c++20`, `optimize fast`). Check the accepted values.",
+        606 FallbackIsNotAValue
+            "`fallback` of a profile has to be a single value, the name of a profile used \
+             when the compiler (or, with `requires_gpu true`, a GPU) is missing.",
+        607 FallbackToNonExistentProfile
+            "`fallback` names a profile that does not exist.",
+        608 ImportStdNeedsStandard
+            "`import_std true` compiles the standard library module, which needs the standard \
+             shown (`c++23` for gcc and clang, `c++latest` for msvc). Remove `standard` to use \
+             it, or set it to that one.",
+    }
+}
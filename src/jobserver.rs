@@ -0,0 +1,182 @@
+use std::cell::RefCell;
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io;
+use std::io::Read;
+use std::io::Write;
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
+use std::process::Command;
+
+// NOTE: https://www.gnu.org/software/make/manual/html_node/Job-Slots.html
+//       every process taking part holds one job slot implicitly, and takes a token out of
+//       the jobserver (then puts it back) for each job it runs besides that
+
+/// Variable through which make (and tools compatible with it, ex. ninja 1.13+ or another
+/// build++) find the jobserver they share.
+const MAKEFLAGS_VARIABLE: &str = "MAKEFLAGS";
+
+/// Jobserver used by this build++, either started by it (`-jobs`) or inherited from
+/// whatever ran it (ex. `make -j8`). Only named pipe (`fifo:`) jobservers are supported,
+/// older makes pass pipe file descriptors instead.
+#[derive(Debug, Clone)]
+struct Jobserver {
+    fifo: PathBuf,
+    makeflags: OsString,
+}
+
+fn from_environment() -> Option<Jobserver> {
+    let makeflags = env::var_os(MAKEFLAGS_VARIABLE)?;
+    let fifo = makeflags
+        .to_str()?
+        .split_whitespace()
+        .find_map(|flag| flag.strip_prefix("--jobserver-auth=fifo:"))?
+        .into();
+    Some(Jobserver { fifo, makeflags })
+}
+
+thread_local! {
+    static CURRENT: RefCell<Option<Jobserver>> = RefCell::new(from_environment());
+}
+
+fn current() -> Option<Jobserver> { CURRENT.with_borrow(Clone::clone) }
+
+fn open(fifo: &PathBuf) -> Result<File, io::Error> {
+    OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(fifo)
+}
+
+/// Jobserver started by `-jobs`, no longer used (and removed) when dropped.
+pub struct Server {
+    fifo: PathBuf,
+    previous: Option<Jobserver>,
+    /// Kept open, so that tokens in the pipe are not lost while nobody else has it open.
+    _tokens: File,
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        CURRENT.set(
+            self.previous
+                .take(),
+        );
+        let _ = fs::remove_file(&self.fifo);
+    }
+}
+
+/// Starts a jobserver with `jobs` slots, used by this build++ and everything it runs.
+#[cfg(unix)]
+pub fn start(jobs: NonZeroUsize) -> Result<Server, io::Error> {
+    let fifo = env::temp_dir().join(format!(
+        "buildpp-jobserver-{}",
+        std::process::id()
+    ));
+    let _ = fs::remove_file(&fifo);
+    let status = Command::new("mkfifo")
+        .arg("-m")
+        .arg("600")
+        .arg(&fifo)
+        .status()?;
+    if !status.success() {
+        return Err(io::Error::other(format!(
+            "mkfifo failed with {}",
+            status
+        )));
+    }
+
+    let mut tokens = open(&fifo)?;
+    tokens.write_all(&vec![b'+'; jobs.get() - 1])?;
+
+    let previous = CURRENT.replace(Some(Jobserver {
+        makeflags: format!(
+            "-j{} --jobserver-auth=fifo:{}",
+            jobs,
+            fifo.display()
+        )
+        .into(),
+        fifo: fifo.clone(),
+    }));
+    Ok(Server {
+        fifo,
+        previous,
+        _tokens: tokens,
+    })
+}
+
+/// Starts a jobserver with `jobs` slots, used by this build++ and everything it runs.
+#[cfg(not(unix))]
+pub fn start(_jobs: NonZeroUsize) -> Result<Server, io::Error> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "jobservers are only supported on unix",
+    ))
+}
+
+/// Lets `command` take job slots from the jobserver, if there is one.
+pub fn share_with(command: &mut Command) {
+    if let Some(jobserver) = current() {
+        command.env(
+            MAKEFLAGS_VARIABLE,
+            jobserver.makeflags,
+        );
+    }
+}
+
+/// A job slot taken out of the jobserver, put back when dropped.
+pub struct Token {
+    fifo: File,
+    byte: u8,
+}
+
+impl Drop for Token {
+    fn drop(&mut self) {
+        let _ = self
+            .fifo
+            .write_all(&[self.byte]);
+    }
+}
+
+/// Takes a job slot, waiting until one is free, `None` if there is no jobserver
+/// (and so no limit).
+pub fn acquire() -> Result<Option<Token>, io::Error> {
+    let Some(jobserver) = current() else {
+        return Ok(None);
+    };
+    let mut fifo = open(&jobserver.fifo)?;
+    let mut byte = [0];
+    fifo.read_exact(&mut byte)?;
+    Ok(Some(Token {
+        fifo,
+        byte: byte[0],
+    }))
+}
+
+/// The slot this build++ holds itself, lent to the jobserver, taken back when dropped.
+pub struct Lent {
+    fifo: File,
+}
+
+impl Drop for Lent {
+    fn drop(&mut self) {
+        let mut byte = [0];
+        let _ = self
+            .fifo
+            .read_exact(&mut byte);
+    }
+}
+
+/// Lends the slot this build++ holds itself to the jobserver, for while it does nothing but
+/// wait for jobs it started (each of which takes a slot), `None` if there is no jobserver.
+pub fn lend() -> Result<Option<Lent>, io::Error> {
+    let Some(jobserver) = current() else {
+        return Ok(None);
+    };
+    let mut fifo = open(&jobserver.fifo)?;
+    fifo.write_all(b"+")?;
+    Ok(Some(Lent { fifo }))
+}
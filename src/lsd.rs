@@ -1,4 +1,5 @@
 use std::borrow::Borrow;
+use std::fmt::Display;
 use std::io;
 use std::io::BufRead;
 use std::io::BufReader;
@@ -56,6 +57,43 @@ impl From<io::Error> for LSDParseError {
     fn from(value: io::Error) -> Self { Self::ReadFailure(Rc::from(value)) }
 }
 
+impl Display for LSDParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use LSDParseError::*;
+        match self {
+            ReadFailure(err) => write!(f, "{}", err),
+            EmptyWhenExpectedValue => write!(f, "expected a value"),
+            UnexpectedNonEmptyInlineLevel => write!(
+                f,
+                "inline levels have to be empty"
+            ),
+            UnexpectedLevelEnd => write!(f, "unexpected end of level"),
+            UnexpectedAfterLevelEnd => write!(
+                f,
+                "unexpected text after end of level"
+            ),
+            UnexpectedListEnd => write!(f, "unexpected end of list"),
+            UnexpectedAfterListEnd => write!(
+                f,
+                "unexpected text after end of list"
+            ),
+            UnexpectedStringEnd => write!(f, "unterminated string"),
+            UnexpectedCharEscapeEnd => write!(f, "unterminated escape"),
+            UnexpectedCharEscapeUnicode => write!(f, "invalid unicode escape"),
+            KeyCollisionValueWhenShouldBeLevel => write!(
+                f,
+                "key is already a value, not a level"
+            ),
+            KeyCollisionValueAlreadyExists(key) => write!(
+                f,
+                "`{}` is given more than once",
+                key
+            ),
+            ExpectedKeyContinuation => write!(f, "expected key to continue"),
+        }
+    }
+}
+
 impl LSD {
     pub fn parse<S: Read>(stream: S) -> Result<LSD, LSDParseError> {
         let mut reader = BufReader::new(stream);
@@ -108,7 +146,7 @@ fn merge_level(insert_into: &mut Level, level: Level) -> Result<(), LSDParseErro
             LSD::Value(value) => insert_into
                 .insert(key.clone(), LSD::Value(value))
                 .is_none()
-                .ok_or_else(|| KeyCollisionValueAlreadyExists(key))?,
+                .or_else_err(|| KeyCollisionValueAlreadyExists(key))?,
             LSD::Level(lvl) => match insert_into
                 .entry(key)
                 .or_insert_with(|| LSD::Level(Level::default()))
@@ -136,7 +174,7 @@ fn as_level(key_parts: Vec<String>, value: LSD) -> Level {
             insert_into.insert(key_part, value);
             return result;
         } else {
-            insert_into = match result
+            insert_into = match insert_into
                 .entry(key_part)
                 .or_insert_with(|| LSD::Level(Level::default()))
             {
@@ -444,6 +482,107 @@ fn read_until_whitespace<'a, S: Read>(
     }
 }
 
+//
+// Serialize
+//
+
+/// Writes a string that reads back as `value`, escaped the way `parse_string` unescapes it.
+fn write_string(f: &mut std::fmt::Formatter<'_>, value: &str) -> std::fmt::Result {
+    write!(f, "\"")?;
+    for ch in value.chars() {
+        match ch {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            ch if (ch as u32) < 0x20 => write!(f, "\\u{:04x}", ch as u32)?,
+            ch => write!(f, "{}", ch)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+/// Keys end at whitespace or `.`, values of levels at the line end and values of lists
+/// at whitespace, anything else has to be quoted to read back the same.
+fn write_plain_or_string(
+    f: &mut std::fmt::Formatter<'_>,
+    value: &str,
+    ends_at: impl Fn(char) -> bool,
+) -> std::fmt::Result {
+    let needs_quotes = value.is_empty()
+        || value.starts_with(['{', '[', '}', ']', '"', '\''])
+        || value.starts_with(char::is_whitespace)
+        || value.ends_with(char::is_whitespace)
+        || value
+            .chars()
+            .any(|ch| ch.is_control() || ends_at(ch));
+    match needs_quotes {
+        true => write_string(f, value),
+        false => write!(f, "{}", value),
+    }
+}
+
+fn write_indent(f: &mut std::fmt::Formatter<'_>, depth: usize) -> std::fmt::Result {
+    write!(f, "{:1$}", "", depth * 4)
+}
+
+/// Writes `lsd` as a value of a level (`in_list` false) or as an item of a list.
+fn write_lsd(
+    f: &mut std::fmt::Formatter<'_>,
+    lsd: &LSD,
+    depth: usize,
+    in_list: bool,
+) -> std::fmt::Result {
+    match lsd {
+        LSD::Value(value) => write_plain_or_string(f, value, |ch| {
+            in_list && ch.is_whitespace()
+        }),
+        // inline levels and lists have to be empty
+        LSD::Level(level) if level.is_empty() => write!(f, "{{}}"),
+        LSD::Level(level) if level.is_list() => {
+            writeln!(f, "[")?;
+            for item in level.values() {
+                write_indent(f, depth + 1)?;
+                write_lsd(f, item, depth + 1, true)?;
+                writeln!(f)?;
+            }
+            write_indent(f, depth)?;
+            write!(f, "]")
+        },
+        LSD::Level(level) => {
+            writeln!(f, "{{")?;
+            write_level(f, level, depth + 1)?;
+            write_indent(f, depth)?;
+            write!(f, "}}")
+        },
+    }
+}
+
+fn write_level(f: &mut std::fmt::Formatter<'_>, level: &Level, depth: usize) -> std::fmt::Result {
+    for (key, lsd) in level {
+        write_indent(f, depth)?;
+        write_plain_or_string(f, key, |ch| {
+            ch.is_whitespace() || ch == '.'
+        })?;
+        write!(f, " ")?;
+        write_lsd(f, lsd, depth, false)?;
+        writeln!(f)?;
+    }
+    Ok(())
+}
+
+/// Writes a root level as the contents of a file, so that [`LSD::parse`] reads it back.
+/// Formatting of the original file (ex. dotted keys) is not kept.
+impl Display for LSD {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LSD::Level(level) => write_level(f, level, 0),
+            lsd => write_lsd(f, lsd, 0, false),
+        }
+    }
+}
+
 //
 // KeyPath and values
 //
@@ -496,6 +635,26 @@ impl LSD {
             _ => None,
         }
     }
+
+    /// Puts keys of `other` over these, levels being overlaid key by key while values and
+    /// lists are replaced as a whole.
+    pub fn overlay(&mut self, other: LSD) {
+        match (self, other) {
+            (LSD::Level(level), LSD::Level(other))
+                if !level.is_list() && (other.is_empty() || !other.is_list()) =>
+            {
+                for (key, value) in other {
+                    match level.get_mut(&key) {
+                        Some(existing) => existing.overlay(value),
+                        None => {
+                            level.insert(key, value);
+                        },
+                    }
+                }
+            },
+            (this, other) => *this = other,
+        }
+    }
 }
 
 pub trait LSDGetExt {
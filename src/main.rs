@@ -1,11 +1,23 @@
+pub mod ci;
 pub mod configuration;
 pub mod dependency;
+pub mod error_code;
+pub mod jobserver;
 pub mod lsd;
+pub mod network;
+pub mod probe;
 pub mod profile;
+pub mod progress;
+pub mod remote_cache;
+pub mod stats;
 mod subcommand;
+pub mod targets;
 pub mod util;
+pub mod warnings;
 
 use std::env::args;
+use std::ffi::OsString;
+use std::fmt::Display;
 use std::io;
 use std::path::Path;
 use std::rc::Rc;
@@ -34,6 +46,71 @@ impl BuildType {
     }
 }
 
+impl Display for BuildType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use BuildType::*;
+        write!(
+            f,
+            "{}",
+            match self {
+                Binary => "binary",
+                Library => "library",
+            }
+        )
+    }
+}
+
+/// What `build -emit` leaves in the target dir instead of building, for debugging.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Emit {
+    /// Source after the preprocessor (ex. to see how macros expanded).
+    Preprocessed,
+    /// Assembly (ex. PTX for nvcc).
+    Asm,
+    /// Intermediate representation of the compiler (ex. LLVM IR).
+    Ir,
+}
+
+impl FromStr for Emit {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use Emit::*;
+        Ok(match &*s.to_lowercase() {
+            "preprocessed" | "pp" => Preprocessed,
+            "asm" | "assembly" => Asm,
+            "ir" => Ir,
+            _ => return Err(()),
+        })
+    }
+}
+
+impl Display for Emit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use Emit::*;
+        write!(
+            f,
+            "{}",
+            match self {
+                Preprocessed => "preprocessed",
+                Asm => "asm",
+                Ir => "ir",
+            }
+        )
+    }
+}
+
+/// Single source file compiled into a single artifact.
+#[derive(Debug, Clone)]
+pub struct Target {
+    pub build_type: BuildType,
+    pub src_file: Dir,
+    /// Kept between builds, so that only linking is redone when just dependencies changed.
+    pub object_file: Dir,
+    pub artifact_file: Dir,
+    pub include_dirs: Vec<Dir>,
+}
+
 impl FromStr for BuildType {
     type Err = ();
 
@@ -67,19 +144,58 @@ pub enum BuildError {
     CacheCouldNotCheckIfNeedsRecaching(Rc<io::Error>),
     CacheCouldNotMakeCacheDirs(Rc<io::Error>),
     CacheError(CacheError),
+    CacheVendoredDependencyChanged(dependency::Alias),
+    /// Names of libraries pulled in at different versions or by different types of dependencies.
+    ConflictingDependencies(Rc<[Value]>),
+    CouldNotResolveDependencies(Rc<io::Error>),
+
+    UnknownGroup(Value),
+    UnknownGroupMember(Value),
+
+    NewCompilerWarnings(Rc<[Value]>),
+
+    EmitNotSupported(Value, Emit),
+
+    NoDeployCommands,
+    DeployFailedSpawn(Value, Rc<io::Error>),
+    DeployFailedWait(Value, Rc<io::Error>),
+    DeployFailedExitCode(Value, i32),
+    DeployKilled(Value),
 
     TargetCouldNotReadChanges(Rc<io::Error>),
     TargetCouldNotPrepareDirs(Rc<io::Error>),
+    TargetCouldNotReuseAction(Rc<io::Error>),
+    TargetOverrideError(Value, profile::ParseError),
 
     CompilerCouldNotCollectArguments(Rc<io::Error>),
+    CompilerCouldNotWriteResponseFile(Rc<io::Error>),
+    CompilerArgumentIsNotUnicode(OsString),
+    CompilerNotFound(Value, profile::Name),
     CompilerFailedSpawn(Rc<io::Error>),
     CompilerFailedWait(Rc<io::Error>),
     CompilerFailedExitCode(i32),
     CompilerKilled,
+    LinkerFailedExitCode(i32),
+    LinkerKilled,
+
+    NinjaCouldNotWriteBuildFile(Rc<io::Error>),
+    NinjaNotFound,
+    NinjaFailedSpawn(Rc<io::Error>),
+    NinjaFailedWait(Rc<io::Error>),
+    NinjaFailedExitCode(i32),
+    NinjaKilled,
+
+    ContainerEngineNotFound,
 
+    PostBuildCouldNotLinkVersions(Rc<io::Error>),
     PostBuildCouldNotCopyIncludes(Rc<io::Error>),
-    PostBuildCouldNotDeleteObjectFiles(Rc<io::Error>),
     PostBuildCouldNotCopyDependencies(Rc<io::Error>),
+    PostBuildCouldNotWritePkgConfig(Rc<io::Error>),
+    PostBuildCouldNotWriteChecksums(Rc<io::Error>),
+    PostBuildSignFailedSpawn(Rc<io::Error>),
+    PostBuildSignFailedWait(Rc<io::Error>),
+    PostBuildSignFailedExitCode(i32),
+    PostBuildSignKilled,
 }
 
 impl From<CacheError> for BuildError {
@@ -89,6 +205,8 @@ impl From<CacheError> for BuildError {
 #[derive(Debug, Clone)]
 pub enum RunError {
     BuildError(BuildError),
+    CouldNotOpenStdin(Value, Rc<io::Error>),
+    CouldNotCopyToRemote(Rc<io::Error>),
     FailedSpawn(Rc<io::Error>),
     FailedWait(Rc<io::Error>),
     Killed,
@@ -98,6 +216,18 @@ impl From<BuildError> for RunError {
     fn from(value: BuildError) -> Self { Self::BuildError(value) }
 }
 
+#[derive(Debug, Clone)]
+pub enum TestError {
+    BuildError(BuildError),
+    CouldNotListTests(Rc<io::Error>),
+    FailedSpawn(Value, Rc<io::Error>),
+    FailedWait(Value, Rc<io::Error>),
+}
+
+impl From<BuildError> for TestError {
+    fn from(value: BuildError) -> Self { Self::BuildError(value) }
+}
+
 fn main_res() -> Result<(), subcommand::Error> {
     // process argv (split off after `-`, `/`, `--` for subcommands that may need it)
     let mut pre_dash_dash = Vec::new();
@@ -123,8 +253,30 @@ fn main_res() -> Result<(), subcommand::Error> {
 }
 
 fn main() {
-    match main_res() {
-        Ok(_) => {},
-        Err(err) => Err(err).unwrap(),
+    let res = main_res();
+
+    if let Err(err) = &res {
+        eprintln!("error: {:?}", err);
+        if let Some(note) = err.note() {
+            eprintln!("note: {}", note);
+        }
+        if let Some(hint) = err.hint() {
+            eprintln!("help: {}", hint);
+        }
+        if let Some(code) = err.code() {
+            eprintln!(
+                "error[{}], run `buildpp explain {}` for details",
+                code, code
+            );
+        }
+    }
+
+    match &res {
+        Ok(_) => ci::print_summary(0, None),
+        Err(err) => ci::print_summary(err.exit() as i32, err.code()),
+    }
+
+    if let Err(err) = res {
+        std::process::exit(err.exit() as i32)
     }
 }
@@ -0,0 +1,266 @@
+use std::cell::OnceCell;
+use std::env;
+use std::ffi::OsStr;
+use std::fs::File;
+use std::io;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::Command;
+use std::process::Output;
+use std::process::Stdio;
+
+use indexmap::IndexMap;
+
+use crate::key;
+use crate::lsd::LSDGetExt;
+use crate::lsd::Value;
+use crate::lsd::LSD;
+use crate::util;
+
+/// Path of the global configuration, used instead of `~/.buildpp/config.lsd`.
+pub const GLOBAL_CONFIG_VARIABLE: &str = "BUILDPP_CONFIG";
+
+/// Proxy for every request (ex. `http://proxy.corp:3128`), used instead of `proxy` of
+/// the global configuration. curl also honors the usual `HTTPS_PROXY` and `NO_PROXY`.
+pub const PROXY_VARIABLE: &str = "BUILDPP_PROXY";
+
+/// Comma-separated hosts reached without the proxy, used instead of `no_proxy`
+/// of the global configuration.
+pub const NO_PROXY_VARIABLE: &str = "BUILDPP_NO_PROXY";
+
+/// How requests to a host are authenticated, from `host."<name>"` of the global configuration
+/// (quoted, as names of hosts have dots in them).
+#[derive(Debug, Clone, Default)]
+pub struct Credentials {
+    /// Sent as a bearer token.
+    pub token: Option<Value>,
+    /// Sent as basic authentication together with `password`.
+    pub user: Option<Value>,
+    pub password: Option<Value>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Settings {
+    proxy: Option<Value>,
+    no_proxy: Option<Value>,
+    /// By host, with or without port (ex. `cache.corp:8080` or `cache.corp`).
+    hosts: IndexMap<Value, Credentials>,
+}
+
+thread_local! {
+    static SETTINGS: OnceCell<Settings> = const { OnceCell::new() };
+}
+
+fn global_config_file() -> Option<PathBuf> {
+    if let Some(file) = env::var_os(GLOBAL_CONFIG_VARIABLE).filter(|file| !file.is_empty()) {
+        return Some(file.into());
+    }
+    util::user_dir().map(|user_dir| user_dir.join("config.lsd"))
+}
+
+/// Reads the global configuration, warning about (and skipping) anything that is wrong with it,
+/// so that a broken file does not stop builds that do not touch the network.
+fn load_settings() -> Settings {
+    let mut settings = Settings::default();
+
+    let lsd = global_config_file()
+        .filter(|file| file.is_file())
+        .and_then(|file| {
+            File::open(&file)
+                .map_err(|err| format!("{:?}", err))
+                .and_then(|file| LSD::parse(file).map_err(|err| format!("{:?}", err)))
+                .map_err(|err| {
+                    eprintln!(
+                        "warning: ignoring global configuration {}: {}",
+                        file.display(),
+                        err
+                    )
+                })
+                .ok()
+        });
+
+    if let Some(lsd) = lsd {
+        let value = |key: Vec<Value>| {
+            lsd.get_value(&*key, ())
+                .unwrap_or_else(|()| {
+                    eprintln!(
+                        "warning: ignoring `{}` of global configuration, it is not a value",
+                        key.join(".")
+                    );
+                    None
+                })
+        };
+        settings.proxy = value(key!(proxy));
+        settings.no_proxy = value(key!(no_proxy));
+
+        let hosts = lsd
+            .get_level(key!(host), ())
+            .unwrap_or_else(|()| {
+                eprintln!("warning: ignoring `host` of global configuration, it is not a level");
+                None
+            })
+            .unwrap_or_default();
+        for host in hosts.keys() {
+            let credential = |name: &str| {
+                value(vec![
+                    Value::from("host"),
+                    host.clone(),
+                    Value::from(name),
+                ])
+            };
+            settings
+                .hosts
+                .insert(
+                    host.clone(),
+                    Credentials {
+                        token: credential("token"),
+                        user: credential("user"),
+                        password: credential("password"),
+                    },
+                );
+        }
+    }
+
+    let variable = |name| {
+        env::var(name)
+            .ok()
+            .filter(|value| !value.is_empty())
+            .map(Value::from)
+    };
+    settings.proxy = variable(PROXY_VARIABLE).or(settings.proxy);
+    settings.no_proxy = variable(NO_PROXY_VARIABLE).or(settings.no_proxy);
+
+    settings
+}
+
+fn settings() -> Settings {
+    SETTINGS.with(|settings| {
+        settings
+            .get_or_init(load_settings)
+            .clone()
+    })
+}
+
+/// `host:port` (or just `host`) of `url`, without user info.
+fn authority(url: &str) -> &str {
+    let rest = url
+        .split_once("://")
+        .map_or(url, |(_, rest)| rest);
+    let authority = rest
+        .split(['/', '?', '#'])
+        .next()
+        .unwrap_or_default();
+    authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, authority)| {
+            authority
+        })
+}
+
+/// Credentials for the host of `url` from the global configuration,
+/// matched with its port first (ex. `cache.corp:8080`), then without it.
+pub fn credentials(url: &str) -> Credentials {
+    let authority = authority(url);
+    let host = authority
+        .rsplit_once(':')
+        .filter(|(_, port)| {
+            port.chars()
+                .all(|ch| ch.is_ascii_digit())
+        })
+        .map_or(authority, |(host, _)| host);
+
+    let settings = settings();
+    settings
+        .hosts
+        .get(authority)
+        .or_else(|| {
+            settings
+                .hosts
+                .get(host)
+        })
+        .cloned()
+        .unwrap_or_default()
+}
+
+/// Quoted for a curl config file.
+fn quote(value: &str) -> String {
+    format!(
+        "\"{}\"",
+        value
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+    )
+}
+
+/// Runs curl on `url` (the last argument) with `args`, through the proxy and with
+/// the credentials of its host, `token` taking precedence over those.
+///
+/// Proxy and credentials are passed through stdin as a curl config (never as arguments,
+/// which other users could see). Returns its exit status and stdout, stderr is inherited.
+pub fn curl(url: &str, token: Option<&str>, args: &[&OsStr]) -> Result<Output, io::Error> {
+    let settings = settings();
+    let credentials = credentials(url);
+
+    let mut config = String::new();
+    let mut option = |name: &str, value: &str| {
+        config.push_str(&format!(
+            "{} = {}\n",
+            name,
+            quote(value)
+        ))
+    };
+    if let Some(proxy) = &settings.proxy {
+        option("proxy", proxy);
+    }
+    if let Some(no_proxy) = &settings.no_proxy {
+        option("noproxy", no_proxy);
+    }
+    match (
+        token.or(credentials
+            .token
+            .as_deref()),
+        &credentials.user,
+    ) {
+        (Some(token), _) => option(
+            "header",
+            &format!(
+                "Authorization: Bearer {}",
+                token
+            ),
+        ),
+        (None, Some(user)) => option(
+            "user",
+            &format!(
+                "{}:{}",
+                user,
+                credentials
+                    .password
+                    .as_deref()
+                    .unwrap_or_default()
+            ),
+        ),
+        (None, None) => {},
+    }
+
+    let mut child = Command::new("curl")
+        .arg("--silent")
+        .arg("--show-error")
+        .arg("--location")
+        .arg("--config")
+        .arg("-")
+        .args(args)
+        .arg(url)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()?;
+
+    if let Some(mut stdin) = child
+        .stdin
+        .take()
+    {
+        stdin.write_all(config.as_bytes())?;
+    }
+
+    child.wait_with_output()
+}
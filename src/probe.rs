@@ -0,0 +1,123 @@
+use std::cell::RefCell;
+use std::env;
+use std::fs;
+use std::fs::File;
+use std::path::PathBuf;
+
+use indexmap::IndexMap;
+
+use crate::lsd::Level;
+use crate::lsd::Value;
+use crate::lsd::LSD;
+use crate::util;
+
+/// Results of probing the system for tools (ex. which container engine is installed),
+/// kept across builds in the user dir, as each probe can take hundreds of milliseconds.
+pub const PROBES_FILENAME: &str = "probes.lsd";
+
+/// Set to `0` to always probe, without reading or writing [`PROBES_FILENAME`].
+pub const PROBE_CACHE_VARIABLE: &str = "BUILDPP_PROBE_CACHE";
+
+/// Results by probe name, `None` when nothing was found (so that missing tools are not
+/// looked for again every build either).
+type Results = IndexMap<Value, Option<Value>>;
+
+thread_local! {
+    /// Results read (or probed) during this invocation.
+    static RESULTS: RefCell<Option<Results>> = RefCell::default();
+}
+
+fn probes_file() -> Option<PathBuf> {
+    if env::var(PROBE_CACHE_VARIABLE).is_ok_and(|value| value == "0") {
+        return None;
+    }
+    util::user_dir().map(|user_dir| user_dir.join(PROBES_FILENAME))
+}
+
+/// What the results depend on besides the tools themselves: results are thrown away
+/// when PATH changes (ex. a tool was installed) or build++ is updated.
+fn fingerprint() -> Value {
+    util::sha256_hex(
+        format!(
+            "buildpp {}\npath {}",
+            env!("CARGO_PKG_VERSION"),
+            env::var_os("PATH")
+                .unwrap_or_default()
+                .to_string_lossy()
+        )
+        .as_bytes(),
+    )
+    .into()
+}
+
+/// Results kept in [`PROBES_FILENAME`], empty if they do not exist, cannot be read
+/// or are for another fingerprint.
+fn read() -> Results {
+    let Some(lsd) = probes_file()
+        .and_then(|file| File::open(file).ok())
+        .and_then(|file| LSD::parse(file).ok())
+    else {
+        return Results::default();
+    };
+    let LSD::Level(mut level) = lsd else {
+        return Results::default();
+    };
+
+    let is_current = level
+        .shift_remove("fingerprint")
+        .and_then(|kept| kept.to_value())
+        .is_some_and(|kept| kept == fingerprint());
+    if !is_current {
+        return Results::default();
+    }
+
+    level
+        .into_iter()
+        .map(|(name, result)| (name, result.to_value()))
+        .collect()
+}
+
+/// Keeping results is only an optimization, so failing to do so is not an error.
+fn write(results: &Results) {
+    let Some(file) = probes_file() else {
+        return;
+    };
+
+    let mut level = Level::new();
+    level.insert(
+        "fingerprint".into(),
+        LSD::Value(fingerprint()),
+    );
+    for (name, result) in results {
+        level.insert(
+            name.clone(),
+            match result {
+                Some(result) => LSD::Value(result.clone()),
+                None => LSD::Level(Level::new()),
+            },
+        );
+    }
+
+    if let Some(dir) = file.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+    let _ = fs::write(
+        file,
+        LSD::Level(level).to_string(),
+    );
+}
+
+/// Result of the probe `name`, running `probe` only if it is not known yet.
+pub fn cached(name: &str, probe: impl FnOnce() -> Option<Value>) -> Option<Value> {
+    RESULTS.with_borrow_mut(|results| {
+        let results = results.get_or_insert_with(read);
+        if let Some(result) = results.get(name) {
+            return result.clone();
+        }
+
+        let result = probe();
+        results.insert(name.into(), result.clone());
+        write(results);
+        result
+    })
+}
@@ -0,0 +1,1327 @@
+use std::ffi::OsString;
+use std::fmt::Display;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Command;
+use std::rc::Rc;
+use std::str::FromStr;
+
+use super::Defines;
+use super::Environment;
+use super::ParseError;
+use super::Sanitizer;
+use super::Warnings;
+use crate::configuration::Configuration;
+use crate::key;
+use crate::lsd::LSDGetExt;
+use crate::lsd::Level;
+use crate::lsd::Value;
+use crate::lsd::LSD;
+use crate::util::concat_os;
+use crate::util::split_file_name;
+use crate::util::BoolGuardExt;
+use crate::util::PushFrom;
+use crate::util::SplitIntoTwoWordsExt;
+use crate::util::TryReplace;
+use crate::BuildType;
+use crate::Emit;
+use crate::Target;
+
+//
+// Standard
+//
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Standard {
+    CPP98,
+    CPP03,
+    CPP11,
+    CPP14,
+    CPP17,
+    CPP20,
+    CPP23,
+    C99,
+    C11,
+    C17,
+    C23,
+}
+
+impl Display for Standard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use Standard::*;
+        write!(
+            f,
+            "{}",
+            match self {
+                CPP98 => "c++98",
+                CPP03 => "c++03",
+                CPP11 => "c++11",
+                CPP14 => "c++14",
+                CPP17 => "c++17",
+                CPP20 => "c++20",
+                CPP23 => "c++23",
+                C99 => "c99",
+                C11 => "c11",
+                C17 => "c17",
+                C23 => "c23",
+            }
+        )
+    }
+}
+
+impl FromStr for Standard {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use Standard::*;
+
+        let s = s.to_lowercase();
+        match s.as_str() {
+            "c++98" | "cpp98" => return Ok(CPP98),
+            "c++03" | "cpp03" => return Ok(CPP03),
+            "c++11" | "cpp11" => return Ok(CPP11),
+            "c++14" | "cpp14" => return Ok(CPP14),
+            "c++17" | "cpp17" => return Ok(CPP17),
+            "c++20" | "cpp20" => return Ok(CPP20),
+            "c++23" | "cpp23" => return Ok(CPP23),
+            "c99" => return Ok(C99),
+            "c11" => return Ok(C11),
+            "c17" | "c18" => return Ok(C17),
+            "c23" => return Ok(C23),
+            _ => {},
+        }
+
+        match s
+            .split_into_words()
+            .ok_or(())?
+        {
+            ["c++", "98"] | ["cpp", "98"] => return Ok(CPP98),
+            ["c++", "03"] | ["cpp", "03"] => return Ok(CPP03),
+            ["c++", "11"] | ["cpp", "11"] => return Ok(CPP11),
+            ["c++", "14"] | ["cpp", "14"] => return Ok(CPP14),
+            ["c++", "17"] | ["cpp", "17"] => return Ok(CPP17),
+            ["c++", "20"] | ["cpp", "20"] => return Ok(CPP20),
+            ["c++", "23"] | ["cpp", "23"] => return Ok(CPP23),
+            ["c", "99"] => return Ok(C99),
+            ["c", "11"] => return Ok(C11),
+            ["c", "17"] | ["c", "18"] => return Ok(C17),
+            ["c", "23"] => return Ok(C23),
+            _ => {},
+        }
+
+        Err(())
+    }
+}
+
+impl Standard {
+    fn is_c(self) -> bool {
+        use Standard::*;
+        matches!(self, C99 | C11 | C17 | C23)
+    }
+}
+
+//
+// Optimization
+//
+
+#[derive(Clone, Copy)]
+enum Optimize {
+    No,
+    Yes,
+    EvenMore,
+    YetMore,
+    Size,
+    UncompliantFast,
+    Debug,
+    SizeAggressive,
+}
+
+impl Display for Optimize {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use Optimize::*;
+        write!(
+            f,
+            "{}",
+            match self {
+                No => "0",
+                Yes => "1",
+                EvenMore => "2",
+                YetMore => "3",
+                Size => "s",
+                UncompliantFast => "fast",
+                Debug => "g",
+                SizeAggressive => "z",
+            }
+        )
+    }
+}
+
+impl FromStr for Optimize {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use Optimize::*;
+
+        let s = s.to_lowercase();
+        match s.as_str() {
+            "0" | "o0" | "no" | "n" | "off" | "false" | "none" => return Ok(No),
+            "1" | "o1" | "yes" | "y" | "on" | "true" | "o" | "optimize" => return Ok(Yes),
+            "2" | "o2" => return Ok(EvenMore),
+            "3" | "o3" => return Ok(YetMore),
+            "s" | "os" | "size" => return Ok(Size),
+            "fast" | "ofast" => return Ok(UncompliantFast),
+            "g" | "og" | "debug" | "odebug" => return Ok(Debug),
+            "z" | "oz" => return Ok(SizeAggressive),
+            _ => {},
+        }
+
+        match s
+            .split_into_words()
+            .ok_or(())?
+        {
+            ["o", "0"] => return Ok(No),
+            ["o", "1"] => return Ok(Yes),
+            ["o", "2"] => return Ok(EvenMore),
+            ["o", "3"] => return Ok(YetMore),
+            ["o", "fast"] => return Ok(UncompliantFast),
+            ["o", "g"] | ["o", "debug"] => return Ok(Debug),
+            ["o", "size"] => return Ok(Size),
+            _ => {},
+        }
+
+        Err(())
+    }
+}
+
+//
+// LibraryType
+//
+
+#[derive(Default, Clone, Copy)]
+enum LibraryType {
+    #[default]
+    Shared,
+    Static,
+}
+
+impl TryReplace for LibraryType {
+    type With = LibraryType;
+}
+
+impl FromStr for LibraryType {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use LibraryType::*;
+        let s = s.to_lowercase();
+        match s.as_str() {
+            "static" | "lib" | "a" => Ok(Static),
+            "shared" | "dll" | "so" => Ok(Shared),
+            _ => Err(()),
+        }
+    }
+}
+
+//
+// Platform
+//
+
+/// System the artifacts are built for, which decides how they are named and linked.
+#[derive(Default, Clone, Copy, PartialEq, Eq)]
+enum Platform {
+    /// The one build++ runs on.
+    #[default]
+    Host,
+    /// Windows, through MinGW-w64 (ex. cross-compiling from Linux).
+    Mingw,
+}
+
+//
+// Driver
+//
+
+/// Compiler taking gcc flags.
+#[derive(Default, Clone, Copy, PartialEq, Eq)]
+enum Driver {
+    #[default]
+    Gcc,
+    /// `zig c++` (or `zig cc`), bundling clang with headers and libraries of many targets.
+    Zig,
+    /// `clang++` (or `clang`) of the Xcode Command Line Tools, building for macOS.
+    AppleClang,
+}
+
+//
+// Profile
+//
+
+#[derive(Default, Clone)]
+pub(crate) struct Profile {
+    driver: Driver,
+    platform: Platform,
+    compiler_path: Option<Value>,
+    /// Target triple passed to zig (ex. `x86_64-linux-gnu`), the host when not given.
+    /// gcc compilers are built for a single target, so they ignore it.
+    target: Option<Value>,
+    standard: Option<Standard>,
+    optimize: Option<Optimize>,
+    library_type: LibraryType,
+    /// Emit debug information.
+    debug: bool,
+    /// Define `NDEBUG`, disabling `assert`s (written as `assertions false`).
+    ndebug: bool,
+    warnings: Option<Warnings>,
+    /// `-Werror`.
+    warnings_as_errors: bool,
+    runner: Option<Rc<[Value]>>,
+    environment: Environment,
+    defines: Defines,
+    /// Appended to compiler arguments as they are, for what the profile has no key for
+    /// (`extra_compiler_flags [ -march=native ]`).
+    extra_compiler_flags: Rc<[Value]>,
+    /// Appended to linker arguments as they are, except when archiving static libraries.
+    extra_linker_flags: Rc<[Value]>,
+    /// Compile libc++ as the `std` module for `import std;` (`import_std true`), which needs
+    /// clang (`compiler_path clang++`) and `standard c++23` (the default with it).
+    import_std: bool,
+    /// `sanitize [ address undefined ]`.
+    sanitizers: Rc<[Sanitizer]>,
+    /// Architectures of macOS artifacts (`arch [ x86_64 arm64 ]`), where more than one makes
+    /// clang build each and combine them into one universal binary with `lipo`. `arch universal`
+    /// is both `x86_64` and `arm64`.
+    archs: Rc<[Value]>,
+    /// Frameworks linked on macOS (`frameworks [ Cocoa Metal ]`).
+    frameworks: Rc<[Value]>,
+    /// Where frameworks not in the SDK are looked for, relative to the project
+    /// (`framework_dirs [ vendor/Frameworks ]`).
+    framework_dirs: Rc<[Value]>,
+}
+
+impl Profile {
+    /// Same keys as the gcc profile, built for Windows by `x86_64-w64-mingw32-g++`.
+    pub(super) fn create_mingw() -> Rc<dyn super::Profile> {
+        Rc::new(Self {
+            platform: Platform::Mingw,
+            ..Self::default()
+        })
+    }
+
+    /// Same keys as the gcc profile and `target`, compiled by `zig c++`.
+    pub(super) fn create_zig() -> Rc<dyn super::Profile> {
+        Rc::new(Self {
+            driver: Driver::Zig,
+            ..Self::default()
+        })
+    }
+
+    /// Same keys as the gcc profile, compiled by AppleClang (`clang++` of the Xcode Command
+    /// Line Tools).
+    pub(super) fn create_apple_clang() -> Rc<dyn super::Profile> {
+        Rc::new(Self {
+            driver: Driver::AppleClang,
+            ..Self::default()
+        })
+    }
+
+    /// Whether artifacts are `.exe`s and `.dll`s, regardless of the host.
+    fn targets_windows(&self) -> bool {
+        match (
+            self.driver,
+            &self.target,
+            self.platform,
+        ) {
+            (Driver::Zig, Some(target), _) => target.contains("windows"),
+            (Driver::AppleClang, ..) => false,
+            (_, _, Platform::Host) => cfg!(windows),
+            (_, _, Platform::Mingw) => true,
+        }
+    }
+
+    /// Whether shared libraries are `.dylib`s, found by their install name instead of a soname.
+    fn targets_macos(&self) -> bool {
+        match (
+            self.driver,
+            &self.target,
+            self.platform,
+        ) {
+            (Driver::Zig, Some(target), _) => target.contains("macos"),
+            (Driver::AppleClang, ..) => true,
+            (_, _, Platform::Host) => cfg!(target_os = "macos"),
+            (_, _, Platform::Mingw) => false,
+        }
+    }
+
+    /// zig's subcommand and target, which come before any other argument of the compiler.
+    fn driver_arguments(&self) -> Vec<OsString> {
+        let mut args = Vec::new();
+        if self.driver == Driver::Zig {
+            args.push_from(match self.is_c() {
+                true => "cc",
+                false => "c++",
+            });
+            if let Some(target) = &self.target {
+                args.push_from("-target");
+                args.push_from(&**target);
+            }
+        }
+        if self.import_std {
+            args.push_from("-stdlib=libc++");
+        }
+        for arch in self
+            .archs
+            .iter()
+        {
+            args.push_from("-arch");
+            args.push_from(&**arch);
+        }
+        args
+    }
+
+    /// Whether artifacts are universal binaries of multiple architectures, which clang can
+    /// only compile and link, not preprocess or report includes of.
+    fn is_universal(&self) -> bool {
+        self.archs
+            .len()
+            > 1
+    }
+
+    /// Arguments affecting how code is compiled, which sources importing the standard library
+    /// module share with it.
+    fn code_arguments(&self, target: &Target) -> Vec<OsString> {
+        let mut args = Vec::new();
+
+        if let Some(opt_level) = &self.optimize {
+            args.push_from(format!("-O{}", opt_level));
+        }
+
+        if self.debug {
+            args.push_from("-g");
+        }
+
+        if self.ndebug {
+            args.push_from("-DNDEBUG");
+        }
+
+        for define in self
+            .defines
+            .iter()
+        {
+            args.push_from(format!("-D{}", define));
+        }
+
+        if let Some(std) = &self.standard {
+            args.push_from(format!("-std={}", std));
+        }
+
+        // objects of shared libraries have to be position independent
+        if target.build_type == BuildType::Library
+            && matches!(
+                self.library_type,
+                LibraryType::Shared
+            )
+            && !self.targets_windows()
+        {
+            args.push_from("-fPIC");
+        }
+
+        // frame pointers make stack traces of reports complete
+        if let Some(sanitize) = super::gcc_sanitize_argument(&self.sanitizers) {
+            args.push_from(sanitize);
+            args.push_from("-fno-omit-frame-pointer");
+        }
+
+        args
+    }
+
+    /// `std.cppm` of libc++, found through `libc++.modules.json` installed next to the libc++
+    /// the compiler links.
+    fn std_module_source(&self) -> Result<PathBuf, io::Error> {
+        let not_found = || {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                "`import_std` needs clang with the modules of libc++ (`libc++.modules.json`)",
+            )
+        };
+
+        let mut command = Command::new(super::Profile::compiler_command(self));
+        self.environment
+            .apply_to(&mut command);
+        let output = command
+            .args(self.driver_arguments())
+            .arg("-print-file-name=libc++.modules.json")
+            .output()?;
+        // only the file name is printed back when there is no such file
+        let manifest = PathBuf::from(
+            String::from_utf8_lossy(&output.stdout)
+                .trim()
+                .to_string(),
+        );
+        let source = fs::read_to_string(&manifest)
+            .map_err(|_| not_found())?
+            .split('"')
+            .find(|part| Path::new(part).file_name() == Some("std.cppm".as_ref()))
+            .map(PathBuf::from)
+            .ok_or_else(not_found)?;
+
+        // relative to the manifest
+        Ok(manifest
+            .parent()
+            .map(|dir| dir.join(&source))
+            .unwrap_or(source))
+    }
+
+    fn is_c(&self) -> bool {
+        self.standard
+            .is_some_and(Standard::is_c)
+    }
+
+    /// Cross-compiling is done with a compiler built for the target (set by `compiler_path`
+    /// of the toolchain), which only needs to be told where the target system is.
+    fn toolchain_arguments(&self, config: &Configuration) -> Vec<OsString> {
+        let mut args = Vec::new();
+        let Some(toolchain) = config.toolchain() else {
+            return args;
+        };
+
+        if let Some(sysroot) = &toolchain.sysroot {
+            args.push(concat_os(&[
+                "--sysroot=".as_ref(),
+                sysroot.as_os_str(),
+            ]));
+        }
+
+        for flag in toolchain
+            .flags
+            .iter()
+        {
+            args.push_from(&**flag);
+        }
+
+        args
+    }
+
+    /// `-F` of each of `framework_dirs`, for finding headers and binaries of frameworks.
+    fn framework_dir_arguments(&self, config: &Configuration) -> Vec<OsString> {
+        self.framework_dirs
+            .iter()
+            .map(|dir| {
+                concat_os(&[
+                    "-F".as_ref(),
+                    config
+                        .project_dir()
+                        .join(&**dir)
+                        .as_os_str(),
+                ])
+            })
+            .collect()
+    }
+}
+
+impl super::Profile for Profile {
+    fn create_default() -> Rc<dyn super::Profile>
+    where
+        Self: Sized, {
+        Rc::new(Self::default())
+    }
+
+    fn inherit_with(&self, level: Level) -> Result<Rc<dyn super::Profile>, ParseError> {
+        let mut res = self.clone();
+        res.apply(level)?;
+        Ok(Rc::new(res))
+    }
+
+    fn apply(&mut self, level: Level) -> Result<(), ParseError> {
+        use ParseError::*;
+
+        self.compiler_path
+            .try_replace(level.get_value(
+                key!(compiler_path),
+                InvalidValueForKey("compiler_path"),
+            )?);
+
+        self.target
+            .try_replace(level.get_value(
+                key!(target),
+                InvalidValueForKey("target"),
+            )?);
+
+        self.standard
+            .try_replace(level.get_parse(
+                key!(standard),
+                InvalidValueForKey("standard"),
+            )?);
+
+        self.optimize
+            .try_replace(level.get_parse(
+                key!(optimize),
+                InvalidValueForKey("optimize"),
+            )?);
+
+        self.library_type
+            .try_replace(level.get_parse(
+                key!(library),
+                InvalidValueForKey("library"),
+            )?);
+
+        self.debug
+            .try_replace(level.get_parse(
+                key!(debug),
+                InvalidValueForKey("debug"),
+            )?);
+
+        self.ndebug
+            .try_replace(
+                level
+                    .get_parse(
+                        key!(assertions),
+                        InvalidValueForKey("assertions"),
+                    )?
+                    .map(|assertions: bool| !assertions),
+            );
+
+        let (warnings, warnings_as_errors) = super::parse_warnings(&level)?;
+        self.warnings
+            .try_replace(warnings);
+        self.warnings_as_errors
+            .try_replace(warnings_as_errors);
+
+        self.runner
+            .try_replace(super::parse_runner(&level)?);
+
+        self.environment
+            .apply(&level)?;
+
+        self.defines
+            .apply(&level)?;
+
+        self.extra_compiler_flags
+            .try_replace(super::parse_arguments(
+                &level,
+                "extra_compiler_flags",
+            )?);
+        self.extra_linker_flags
+            .try_replace(super::parse_arguments(
+                &level,
+                "extra_linker_flags",
+            )?);
+
+        self.import_std
+            .try_replace(level.get_parse(
+                key!(import_std),
+                InvalidValueForKey("import_std"),
+            )?);
+        if self.import_std {
+            // zig bundles libc++ without its modules
+            (self.driver != Driver::Zig).or_err(InvalidValueForKey(
+                "import_std",
+            ))?;
+            // unless given, the standard is the one the module needs
+            match self.standard {
+                None => self.standard = Some(Standard::CPP23),
+                Some(standard) => (standard == Standard::CPP23).or_err(ImportStdNeedsStandard(
+                    "c++23",
+                ))?,
+            }
+        }
+
+        self.sanitizers
+            .try_replace(super::parse_sanitizers(
+                &level,
+            )?);
+
+        if let Some(archs) = super::parse_arguments(&level, "arch")? {
+            self.archs = archs
+                .iter()
+                .flat_map(|arch| match &**arch {
+                    "universal" => vec!["x86_64".into(), "arm64".into()],
+                    _ => vec![arch.clone()],
+                })
+                .collect();
+        }
+        // `-arch` is a flag of Apple's clang, which zig does not take
+        if !self
+            .archs
+            .is_empty()
+        {
+            (self.targets_macos() && self.driver != Driver::Zig)
+                .or_err(InvalidValueForKey("arch"))?;
+        }
+
+        self.frameworks
+            .try_replace(super::parse_arguments(
+                &level,
+                "frameworks",
+            )?);
+        self.framework_dirs
+            .try_replace(super::parse_arguments(
+                &level,
+                "framework_dirs",
+            )?);
+        // only Mach-O has frameworks
+        if !self
+            .frameworks
+            .is_empty()
+        {
+            self.targets_macos()
+                .or_err(InvalidValueForKey(
+                    "frameworks",
+                ))?;
+        }
+
+        Ok(())
+    }
+
+    fn preset(&self, name: &str) -> Level {
+        let preset: &[(&str, &str)] = match name {
+            "release" => &[("optimize", "2"), ("assertions", "false")],
+            "debug" => &[("optimize", "0"), ("debug", "true")],
+            _ => &[],
+        };
+        preset
+            .iter()
+            .map(|(key, value)| {
+                (
+                    Value::from(*key),
+                    LSD::Value(Value::from(*value)),
+                )
+            })
+            .collect()
+    }
+
+    fn src_file_suffixes(&self) -> &'static [&'static str] {
+        match self.is_c() {
+            true => &[".c"],
+            false => &[".cpp", ".cc", ".cxx", ".c++"],
+        }
+    }
+
+    fn artifact_prefix(&self, build_type: BuildType) -> &'static str {
+        use BuildType::*;
+        match (
+            build_type,
+            self.targets_windows(),
+        ) {
+            (Library, false) => "lib",
+            _ => "",
+        }
+    }
+
+    fn artifact_suffix(&self, build_type: BuildType) -> &'static str {
+        use BuildType::*;
+        use LibraryType::*;
+        match (
+            build_type,
+            self.targets_windows(),
+        ) {
+            (Binary, true) => ".exe",
+            (Binary, false) => "",
+            (Library, true) => match self.library_type {
+                Shared => ".dll",
+                Static => ".a",
+            },
+            (Library, false) => match (
+                self.library_type,
+                self.targets_macos(),
+            ) {
+                (Shared, false) => ".so",
+                (Shared, true) => ".dylib",
+                (Static, _) => ".a",
+            },
+        }
+    }
+
+    fn compiler_command(&self) -> &str {
+        self.compiler_path
+            .as_ref()
+            .map(Rc::as_ref)
+            .unwrap_or(
+                match (
+                    self.driver,
+                    self.platform,
+                    self.is_c(),
+                ) {
+                    (Driver::Zig, ..) => "zig",
+                    (Driver::AppleClang, _, true) => "clang",
+                    (Driver::AppleClang, _, false) => "clang++",
+                    (Driver::Gcc, Platform::Host, true) => "gcc",
+                    (Driver::Gcc, Platform::Host, false) => "g++",
+                    (Driver::Gcc, Platform::Mingw, true) => "x86_64-w64-mingw32-gcc",
+                    (Driver::Gcc, Platform::Mingw, false) => "x86_64-w64-mingw32-g++",
+                },
+            )
+    }
+
+    fn linker_command(&self, build_type: BuildType) -> &str {
+        match (build_type, self.library_type) {
+            (BuildType::Library, LibraryType::Static) => match (self.driver, self.platform) {
+                // `zig ar`
+                (Driver::Zig, _) => self.compiler_command(),
+                // ar cannot archive universal objects
+                (Driver::AppleClang, _) | (Driver::Gcc, Platform::Host) if self.is_universal() =>
+                    "libtool",
+                (Driver::AppleClang, _) | (Driver::Gcc, Platform::Host) => "ar",
+                (Driver::Gcc, Platform::Mingw) => "x86_64-w64-mingw32-ar",
+            },
+            _ => self.compiler_command(),
+        }
+    }
+
+    fn installation_hint(&self) -> &'static str {
+        if self.driver == Driver::Zig {
+            return "install Zig (ex. from https://ziglang.org/download) and add it to PATH, \
+                    or set `compiler_path` in the profile";
+        }
+        if self.driver == Driver::AppleClang {
+            return "install the Xcode Command Line Tools with `xcode-select --install`, \
+                    or set `compiler_path` in the profile";
+        }
+        match (self.platform, cfg!(windows)) {
+            (Platform::Mingw, false) =>
+                "install MinGW-w64 with your package manager \
+                 (ex. `apt install g++-mingw-w64-x86-64`), or set `compiler_path` in the profile",
+            (_, true) =>
+                "install GCC for Windows (ex. from https://www.msys2.org) and add its `bin` \
+                 directory to PATH, or set `compiler_path` in the profile",
+            (Platform::Host, false) =>
+                "install GCC with your package manager (ex. `apt install g++`), \
+                 or set `compiler_path` in the profile",
+        }
+    }
+
+    fn object_suffix(&self) -> &'static str { ".o" }
+
+    fn ninja_deps(&self, depfile: &Path) -> Option<(&'static str, Vec<OsString>)> {
+        if self.is_universal() {
+            return None;
+        }
+        Some((
+            "gcc",
+            vec!["-MD".into(), "-MF".into(), depfile.into()],
+        ))
+    }
+
+    fn emit_suffix(&self, emit: Emit) -> Option<&'static str> {
+        if self.is_universal() {
+            return None;
+        }
+        match emit {
+            Emit::Preprocessed => Some(match self.is_c() {
+                true => ".i",
+                false => ".ii",
+            }),
+            Emit::Asm => Some(".s"),
+            // GIMPLE and RTL dumps are not meant to be read as a whole
+            Emit::Ir => None,
+        }
+    }
+
+    fn emit_arguments(&self, emit: Emit, _output_file: &Path, arguments: &mut Vec<OsString>) {
+        let mode = match emit {
+            Emit::Preprocessed => "-E",
+            Emit::Asm => "-S",
+            Emit::Ir => return,
+        };
+        for argument in arguments.iter_mut() {
+            if argument == "-c" {
+                *argument = mode.into();
+            }
+        }
+    }
+
+    fn compile_arguments(
+        &self,
+        config: &Configuration,
+        target: &Target,
+        selected_profile: &str,
+    ) -> Result<Vec<OsString>, io::Error> {
+        let mut args = self.driver_arguments();
+
+        args.push_from("-c");
+
+        args.extend(self.code_arguments(target));
+
+        if let Some(warnings) = self.warnings {
+            for warning in warnings.gcc_arguments() {
+                args.push_from(*warning);
+            }
+        }
+
+        if self.warnings_as_errors {
+            args.push_from("-Werror");
+        }
+
+        if self.import_std {
+            args.push(concat_os(&[
+                "-fmodule-file=std=".as_ref(),
+                config
+                    .target_std_module_file(selected_profile, ".pcm")
+                    .as_os_str(),
+            ]));
+        }
+
+        let include_dirs = config
+            .dependency_dirs(selected_profile)?
+            .into_iter()
+            .map(|(include_dir, _)| include_dir)
+            .chain(
+                target
+                    .include_dirs
+                    .iter()
+                    .cloned(),
+            );
+        for include_dir in include_dirs {
+            args.push(concat_os(&[
+                "-I".as_ref(),
+                include_dir.as_os_str(),
+            ]));
+        }
+
+        // headers of frameworks are included by their name (ex. `<Metal/Metal.h>`)
+        args.extend(self.framework_dir_arguments(config));
+
+        args.extend(self.toolchain_arguments(config));
+
+        args.push_from("-o");
+        args.push_from(&*target.object_file);
+
+        args.push_from(&*target.src_file);
+
+        for flag in self
+            .extra_compiler_flags
+            .iter()
+        {
+            args.push_from(&**flag);
+        }
+
+        Ok(args)
+    }
+
+    fn std_module_arguments(
+        &self,
+        config: &Configuration,
+        target: &Target,
+        selected_profile: &str,
+    ) -> Result<Option<Vec<OsString>>, io::Error> {
+        if !self.import_std {
+            return Ok(None);
+        }
+
+        let mut args = self.driver_arguments();
+
+        args.push_from("-c");
+
+        args.extend(self.code_arguments(target));
+
+        // libc++ names its module `std`, which is reserved for it
+        args.push_from("-Wno-reserved-module-identifier");
+
+        args.extend(self.toolchain_arguments(config));
+
+        args.push(concat_os(&[
+            "-fmodule-output=".as_ref(),
+            config
+                .target_std_module_file(selected_profile, ".pcm")
+                .as_os_str(),
+        ]));
+
+        args.push_from("-o");
+        args.push_from(
+            &*config.target_std_module_file(
+                selected_profile,
+                self.object_suffix(),
+            ),
+        );
+
+        args.push_from(self.std_module_source()?);
+
+        for flag in self
+            .extra_compiler_flags
+            .iter()
+        {
+            args.push_from(&**flag);
+        }
+
+        Ok(Some(args))
+    }
+
+    fn link_arguments(
+        &self,
+        config: &Configuration,
+        target: &Target,
+        selected_profile: &str,
+    ) -> Result<Vec<OsString>, io::Error> {
+        let std_module = self
+            .import_std
+            .then(|| {
+                config.target_std_module_file(
+                    selected_profile,
+                    self.object_suffix(),
+                )
+            });
+
+        // static libraries are archives of objects, dependencies are linked by their users
+        if target.build_type == BuildType::Library
+            && matches!(
+                self.library_type,
+                LibraryType::Static
+            )
+        {
+            let mut args = Vec::new();
+            if self.driver == Driver::Zig {
+                args.push_from("ar");
+            }
+            match self.is_universal() {
+                true => {
+                    args.push_from("-static");
+                    args.push_from("-o");
+                },
+                false => args.push_from("rcs"),
+            }
+            args.push_from(&*target.artifact_file);
+            args.push_from(&*target.object_file);
+            if let Some(std_module) = &std_module {
+                args.push_from(&**std_module);
+            }
+            return Ok(args);
+        }
+
+        let mut args = self.driver_arguments();
+
+        if target.build_type == BuildType::Library {
+            args.push_from("-shared");
+        }
+
+        // links the runtime of the sanitizers
+        if let Some(sanitize) = super::gcc_sanitize_argument(&self.sanitizers) {
+            args.push_from(sanitize);
+        }
+
+        // dependency libraries are copied next to the artifact
+        match (
+            self.targets_windows(),
+            self.targets_macos(),
+        ) {
+            (true, _) => {},
+            (false, false) => args.push_from("-Wl,-rpath,$ORIGIN"),
+            (false, true) => args.push_from("-Wl,-rpath,@loader_path"),
+        }
+
+        // users of a dylib look for it by its install name, which is its path by default
+        if target.build_type == BuildType::Library && self.targets_macos() {
+            if let Some(filename) = Path::new(&*target.artifact_file).file_name() {
+                args.push(concat_os(&[
+                    "-Wl,-install_name,@rpath/".as_ref(),
+                    filename,
+                ]));
+            }
+        }
+
+        args.extend(self.toolchain_arguments(config));
+
+        args.push_from("-o");
+        args.push_from(&*target.artifact_file);
+
+        // the linker only takes what is still missing from each library, so they go after objects
+        args.push_from(&*target.object_file);
+        if let Some(std_module) = &std_module {
+            args.push_from(&**std_module);
+        }
+
+        for (lib_dir, link) in config.dependency_lib_dirs(selected_profile)? {
+            args.push(concat_os(&[
+                "-L".as_ref(),
+                lib_dir.as_os_str(),
+            ]));
+
+            for lib in fs::read_dir(&lib_dir)? {
+                let filename = lib?.file_name();
+                let (_, ext) = split_file_name(&filename);
+                if ext != "a" && ext != "so" && ext != "dll" && ext != "dylib" {
+                    continue;
+                }
+                let whole_archive = link.whole_archive && ext == "a";
+                // ld64 has no `-l:`, libraries are given by path instead
+                match (
+                    self.targets_macos(),
+                    whole_archive,
+                ) {
+                    (true, true) => args.push(concat_os(&[
+                        "-Wl,-force_load,".as_ref(),
+                        lib_dir
+                            .join(&filename)
+                            .as_os_str(),
+                    ])),
+                    (true, false) => args.push(
+                        lib_dir
+                            .join(&filename)
+                            .into(),
+                    ),
+                    (false, _) => {
+                        if whole_archive {
+                            args.push_from("-Wl,--whole-archive");
+                        }
+                        args.push(concat_os(&[
+                            "-l:".as_ref(),
+                            &filename,
+                        ]));
+                        if whole_archive {
+                            args.push_from("-Wl,--no-whole-archive");
+                        }
+                    },
+                }
+            }
+        }
+
+        args.extend(self.framework_dir_arguments(config));
+
+        for framework in self
+            .frameworks
+            .iter()
+        {
+            args.push_from("-framework");
+            args.push_from(&**framework);
+        }
+
+        for flag in self
+            .extra_linker_flags
+            .iter()
+        {
+            args.push_from(&**flag);
+        }
+
+        Ok(args)
+    }
+
+    fn runner(&self) -> Option<Rc<[Value]>> {
+        self.runner
+            .clone()
+    }
+
+    fn run_environment(&self) -> Vec<(&'static str, &'static str)> {
+        super::sanitize_run_environment(&self.sanitizers)
+    }
+
+    fn environment(&self) -> &Environment { &self.environment }
+
+    fn cmake_language(&self) -> &'static str {
+        match self.is_c() {
+            true => "C",
+            false => "CXX",
+        }
+    }
+
+    fn cmake_library_kind(&self) -> &'static str {
+        use LibraryType::*;
+        match self.library_type {
+            Shared => "SHARED",
+            Static => "STATIC",
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        match (self.driver, self.platform) {
+            (Driver::Zig, _) => "zig",
+            (Driver::AppleClang, _) => "apple-clang",
+            (Driver::Gcc, Platform::Host) => "gcc",
+            (Driver::Gcc, Platform::Mingw) => "mingw",
+        }
+    }
+
+    fn settings(&self) -> Vec<(&'static str, Value)> {
+        let mut settings = Vec::new();
+        if let Some(compiler_path) = &self.compiler_path {
+            settings.push((
+                "compiler_path",
+                compiler_path.clone(),
+            ));
+        }
+        if let Some(target) = &self.target {
+            settings.push(("target", target.clone()));
+        }
+        if let Some(std) = &self.standard {
+            settings.push((
+                "standard",
+                std.to_string()
+                    .into(),
+            ));
+        }
+        if let Some(optimize) = &self.optimize {
+            settings.push((
+                "optimize",
+                optimize
+                    .to_string()
+                    .into(),
+            ));
+        }
+        settings.push((
+            "debug",
+            self.debug
+                .to_string()
+                .into(),
+        ));
+        settings.push((
+            "assertions",
+            (!self.ndebug)
+                .to_string()
+                .into(),
+        ));
+        if let Some(warnings) = &self.warnings {
+            settings.push((
+                "warnings",
+                warnings
+                    .to_string()
+                    .into(),
+            ));
+        }
+        settings.push((
+            "warnings_as_errors",
+            self.warnings_as_errors
+                .to_string()
+                .into(),
+        ));
+        settings.push((
+            "library",
+            match self.library_type {
+                LibraryType::Shared => "shared",
+                LibraryType::Static => "static",
+            }
+            .into(),
+        ));
+        if let Some(runner) = &self.runner {
+            settings.push((
+                "runner",
+                runner
+                    .join(" ")
+                    .into(),
+            ));
+        }
+        self.environment
+            .settings(&mut settings);
+        self.defines
+            .settings(&mut settings);
+        if !self
+            .extra_compiler_flags
+            .is_empty()
+        {
+            settings.push((
+                "extra_compiler_flags",
+                self.extra_compiler_flags
+                    .join(" ")
+                    .into(),
+            ));
+        }
+        if !self
+            .extra_linker_flags
+            .is_empty()
+        {
+            settings.push((
+                "extra_linker_flags",
+                self.extra_linker_flags
+                    .join(" ")
+                    .into(),
+            ));
+        }
+        settings.push((
+            "import_std",
+            self.import_std
+                .to_string()
+                .into(),
+        ));
+        if !self
+            .sanitizers
+            .is_empty()
+        {
+            settings.push((
+                "sanitize",
+                super::sanitize_setting(&self.sanitizers),
+            ));
+        }
+        if !self
+            .archs
+            .is_empty()
+        {
+            settings.push((
+                "arch",
+                self.archs
+                    .join(" ")
+                    .into(),
+            ));
+        }
+        if !self
+            .frameworks
+            .is_empty()
+        {
+            settings.push((
+                "frameworks",
+                self.frameworks
+                    .join(" ")
+                    .into(),
+            ));
+        }
+        if !self
+            .framework_dirs
+            .is_empty()
+        {
+            settings.push((
+                "framework_dirs",
+                self.framework_dirs
+                    .join(" ")
+                    .into(),
+            ));
+        }
+        settings
+    }
+
+    fn cmake_properties(&self) -> Vec<(&'static str, Value)> {
+        use Standard::*;
+        let mut properties = Vec::new();
+        if let Some(std) = &self.standard {
+            properties.push(match std {
+                CPP98 | CPP03 => ("CXX_STANDARD", "98".into()),
+                CPP11 => ("CXX_STANDARD", "11".into()),
+                CPP14 => ("CXX_STANDARD", "14".into()),
+                CPP17 => ("CXX_STANDARD", "17".into()),
+                CPP20 => ("CXX_STANDARD", "20".into()),
+                CPP23 => ("CXX_STANDARD", "23".into()),
+                C99 => ("C_STANDARD", "99".into()),
+                C11 => ("C_STANDARD", "11".into()),
+                C17 => ("C_STANDARD", "17".into()),
+                C23 => ("C_STANDARD", "23".into()),
+            });
+        }
+        properties
+    }
+
+    fn defines(&self) -> &Defines { &self.defines }
+
+    fn clang_flags(&self) -> Vec<Value> {
+        let mut flags = vec![match self.is_c() {
+            true => "-xc".into(),
+            false => "-xc++".into(),
+        }];
+        if let (Driver::Zig, Some(target)) = (self.driver, &self.target) {
+            flags.push(format!("--target={}", target).into());
+        }
+        if let Some(std) = &self.standard {
+            flags.push(format!("-std={}", std).into());
+        }
+        if let Some(warnings) = self.warnings {
+            for warning in warnings.gcc_arguments() {
+                flags.push((*warning).into());
+            }
+        }
+        if self.import_std {
+            flags.push("-stdlib=libc++".into());
+        }
+        // relative to the project, as is `compile_flags.txt`
+        for dir in self
+            .framework_dirs
+            .iter()
+        {
+            flags.push(format!("-F{}", dir).into());
+        }
+        flags
+    }
+}
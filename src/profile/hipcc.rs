@@ -0,0 +1,815 @@
+use std::ffi::OsString;
+use std::fmt::Display;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::rc::Rc;
+use std::str::FromStr;
+
+use super::Defines;
+use super::Environment;
+use super::ParseError;
+use super::Warnings;
+use crate::configuration::Configuration;
+use crate::key;
+use crate::lsd::LSDGetExt;
+use crate::lsd::Level;
+use crate::lsd::Value;
+use crate::lsd::LSD;
+use crate::util::concat_os;
+use crate::util::split_file_name;
+use crate::util::PushFrom;
+use crate::util::SplitIntoTwoWordsExt;
+use crate::util::TryReplace;
+use crate::BuildType;
+use crate::Emit;
+use crate::Target;
+
+//
+// Standard
+//
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Standard {
+    CPP11,
+    CPP14,
+    CPP17,
+    CPP20,
+    CPP23,
+}
+
+impl Display for Standard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use Standard::*;
+        write!(
+            f,
+            "{}",
+            match self {
+                CPP11 => "c++11",
+                CPP14 => "c++14",
+                CPP17 => "c++17",
+                CPP20 => "c++20",
+                CPP23 => "c++23",
+            }
+        )
+    }
+}
+
+impl FromStr for Standard {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use Standard::*;
+
+        let s = s.to_lowercase();
+        match s.as_str() {
+            "c++11" | "cpp11" => return Ok(CPP11),
+            "c++14" | "cpp14" => return Ok(CPP14),
+            "c++17" | "cpp17" => return Ok(CPP17),
+            "c++20" | "cpp20" => return Ok(CPP20),
+            "c++23" | "cpp23" => return Ok(CPP23),
+            _ => {},
+        }
+
+        match s
+            .split_into_words()
+            .ok_or(())?
+        {
+            ["c++", "11"] | ["cpp", "11"] => return Ok(CPP11),
+            ["c++", "14"] | ["cpp", "14"] => return Ok(CPP14),
+            ["c++", "17"] | ["cpp", "17"] => return Ok(CPP17),
+            ["c++", "20"] | ["cpp", "20"] => return Ok(CPP20),
+            ["c++", "23"] | ["cpp", "23"] => return Ok(CPP23),
+            _ => {},
+        }
+
+        Err(())
+    }
+}
+
+//
+// Optimization
+//
+
+#[derive(Clone, Copy)]
+enum Optimize {
+    No,
+    Yes,
+    EvenMore,
+    YetMore,
+    Size,
+    UncompliantFast,
+    Debug,
+    SizeAggressive,
+}
+
+impl Display for Optimize {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use Optimize::*;
+        write!(
+            f,
+            "{}",
+            match self {
+                No => "0",
+                Yes => "1",
+                EvenMore => "2",
+                YetMore => "3",
+                Size => "s",
+                UncompliantFast => "fast",
+                Debug => "g",
+                SizeAggressive => "z",
+            }
+        )
+    }
+}
+
+impl FromStr for Optimize {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use Optimize::*;
+
+        let s = s.to_lowercase();
+        match s.as_str() {
+            "0" | "o0" | "no" | "n" | "off" | "false" | "none" => return Ok(No),
+            "1" | "o1" | "yes" | "y" | "on" | "true" | "o" | "optimize" => return Ok(Yes),
+            "2" | "o2" => return Ok(EvenMore),
+            "3" | "o3" => return Ok(YetMore),
+            "s" | "os" | "size" => return Ok(Size),
+            "fast" | "ofast" => return Ok(UncompliantFast),
+            "g" | "og" | "debug" | "odebug" => return Ok(Debug),
+            "z" | "oz" => return Ok(SizeAggressive),
+            _ => {},
+        }
+
+        match s
+            .split_into_words()
+            .ok_or(())?
+        {
+            ["o", "0"] => return Ok(No),
+            ["o", "1"] => return Ok(Yes),
+            ["o", "2"] => return Ok(EvenMore),
+            ["o", "3"] => return Ok(YetMore),
+            ["o", "fast"] => return Ok(UncompliantFast),
+            ["o", "g"] | ["o", "debug"] => return Ok(Debug),
+            ["o", "size"] => return Ok(Size),
+            _ => {},
+        }
+
+        Err(())
+    }
+}
+
+//
+// LibraryType
+//
+
+#[derive(Default, Clone, Copy)]
+enum LibraryType {
+    #[default]
+    Shared,
+    Static,
+}
+
+impl TryReplace for LibraryType {
+    type With = LibraryType;
+}
+
+impl FromStr for LibraryType {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use LibraryType::*;
+        let s = s.to_lowercase();
+        match s.as_str() {
+            "static" | "lib" | "a" => Ok(Static),
+            "shared" | "dll" | "so" => Ok(Shared),
+            _ => Err(()),
+        }
+    }
+}
+
+/// AMD GPU architectures (ex. `gfx90a`), either separated with spaces or a list.
+fn parse_archs(level: &Level) -> Result<Option<Rc<[Value]>>, ParseError> {
+    use ParseError::*;
+
+    level
+        .get_inner(key!(arch))
+        .map(|archs| match archs {
+            LSD::Value(value) => Ok(value
+                .split_whitespace()
+                .map(Rc::from)
+                .collect()),
+            LSD::Level(list) => list
+                .values()
+                .map(|arch| {
+                    arch.to_value()
+                        .ok_or(InvalidValueForKey("arch"))
+                })
+                .collect(),
+        })
+        .transpose()
+}
+
+//
+// Profile
+//
+
+#[derive(Default, Clone)]
+pub(crate) struct Profile {
+    compiler_path: Option<Value>,
+    standard: Option<Standard>,
+    optimize: Option<Optimize>,
+    library_type: LibraryType,
+    /// Emit debug information.
+    debug: bool,
+    /// Define `NDEBUG`, disabling `assert`s (written as `assertions false`).
+    ndebug: bool,
+    warnings: Option<Warnings>,
+    /// `-Werror`.
+    warnings_as_errors: bool,
+    /// GPUs device code is compiled for (`--offload-arch`), the ones of this machine when empty.
+    archs: Rc<[Value]>,
+    runner: Option<Rc<[Value]>>,
+    environment: Environment,
+    defines: Defines,
+    /// Appended to compiler arguments as they are, for what the profile has no key for
+    /// (`extra_compiler_flags [ -march=native ]`).
+    extra_compiler_flags: Rc<[Value]>,
+    /// Appended to linker arguments as they are, except when archiving static libraries.
+    extra_linker_flags: Rc<[Value]>,
+}
+
+impl Profile {
+    /// Cross-compiling is done with a compiler set up for the target (set by `compiler_path`
+    /// of the toolchain), which only needs to be told where the target system is.
+    fn toolchain_arguments(&self, config: &Configuration) -> Vec<OsString> {
+        let mut args = Vec::new();
+        let Some(toolchain) = config.toolchain() else {
+            return args;
+        };
+
+        if let Some(sysroot) = &toolchain.sysroot {
+            args.push(concat_os(&[
+                "--sysroot=".as_ref(),
+                sysroot.as_os_str(),
+            ]));
+        }
+
+        for flag in toolchain
+            .flags
+            .iter()
+        {
+            args.push_from(&**flag);
+        }
+
+        args
+    }
+}
+
+impl super::Profile for Profile {
+    fn create_default() -> Rc<dyn super::Profile>
+    where
+        Self: Sized, {
+        Rc::new(Self::default())
+    }
+
+    fn inherit_with(&self, level: Level) -> Result<Rc<dyn super::Profile>, ParseError> {
+        let mut res = self.clone();
+        res.apply(level)?;
+        Ok(Rc::new(res))
+    }
+
+    fn apply(&mut self, level: Level) -> Result<(), ParseError> {
+        use ParseError::*;
+
+        self.compiler_path
+            .try_replace(level.get_value(
+                key!(compiler_path),
+                InvalidValueForKey("compiler_path"),
+            )?);
+
+        self.standard
+            .try_replace(level.get_parse(
+                key!(standard),
+                InvalidValueForKey("standard"),
+            )?);
+
+        self.optimize
+            .try_replace(level.get_parse(
+                key!(optimize),
+                InvalidValueForKey("optimize"),
+            )?);
+
+        self.library_type
+            .try_replace(level.get_parse(
+                key!(library),
+                InvalidValueForKey("library"),
+            )?);
+
+        self.debug
+            .try_replace(level.get_parse(
+                key!(debug),
+                InvalidValueForKey("debug"),
+            )?);
+
+        self.ndebug
+            .try_replace(
+                level
+                    .get_parse(
+                        key!(assertions),
+                        InvalidValueForKey("assertions"),
+                    )?
+                    .map(|assertions: bool| !assertions),
+            );
+
+        let (warnings, warnings_as_errors) = super::parse_warnings(&level)?;
+        self.warnings
+            .try_replace(warnings);
+        self.warnings_as_errors
+            .try_replace(warnings_as_errors);
+
+        if let Some(archs) = parse_archs(&level)? {
+            self.archs = archs;
+        }
+
+        self.runner
+            .try_replace(super::parse_runner(&level)?);
+
+        self.environment
+            .apply(&level)?;
+
+        self.defines
+            .apply(&level)?;
+
+        self.extra_compiler_flags
+            .try_replace(super::parse_arguments(
+                &level,
+                "extra_compiler_flags",
+            )?);
+        self.extra_linker_flags
+            .try_replace(super::parse_arguments(
+                &level,
+                "extra_linker_flags",
+            )?);
+
+        Ok(())
+    }
+
+    fn preset(&self, name: &str) -> Level {
+        let preset: &[(&str, &str)] = match name {
+            "release" => &[("optimize", "2"), ("assertions", "false")],
+            "debug" => &[("optimize", "0"), ("debug", "true")],
+            _ => &[],
+        };
+        preset
+            .iter()
+            .map(|(key, value)| {
+                (
+                    Value::from(*key),
+                    LSD::Value(Value::from(*value)),
+                )
+            })
+            .collect()
+    }
+
+    fn src_file_suffixes(&self) -> &'static [&'static str] { &[".hip", ".cpp", ".cc", ".cxx"] }
+
+    fn artifact_prefix(&self, build_type: BuildType) -> &'static str {
+        use BuildType::*;
+        match (build_type, cfg!(windows)) {
+            (Library, false) => "lib",
+            _ => "",
+        }
+    }
+
+    fn artifact_suffix(&self, build_type: BuildType) -> &'static str {
+        use BuildType::*;
+        use LibraryType::*;
+        match (build_type, cfg!(windows)) {
+            (Binary, true) => ".exe",
+            (Binary, false) => "",
+            (Library, true) => match self.library_type {
+                Shared => ".dll",
+                Static => ".lib",
+            },
+            (Library, false) => match self.library_type {
+                Shared => ".so",
+                Static => ".a",
+            },
+        }
+    }
+
+    fn compiler_command(&self) -> &str {
+        self.compiler_path
+            .as_ref()
+            .map(Rc::as_ref)
+            .unwrap_or("hipcc")
+    }
+
+    fn linker_command(&self, build_type: BuildType) -> &str {
+        match (build_type, self.library_type) {
+            // the HIP SDK for Windows comes with LLVM tools only
+            (BuildType::Library, LibraryType::Static) => match cfg!(windows) {
+                true => "llvm-ar",
+                false => "ar",
+            },
+            _ => self.compiler_command(),
+        }
+    }
+
+    fn installation_hint(&self) -> &'static str {
+        match cfg!(windows) {
+            true =>
+                "install the HIP SDK from https://www.amd.com/en/developer/resources/rocm-hub/hip-sdk.html \
+                 and add its `bin` directory to PATH, or set `compiler_path` in the profile",
+            false =>
+                "install ROCm (ex. from https://rocm.docs.amd.com or your package manager) \
+                 and add its `bin` directory (usually `/opt/rocm/bin`) to PATH, \
+                 or set `compiler_path` in the profile",
+        }
+    }
+
+    fn object_suffix(&self) -> &'static str { ".o" }
+
+    fn ninja_deps(&self, depfile: &Path) -> Option<(&'static str, Vec<OsString>)> {
+        Some((
+            "gcc",
+            vec!["-MD".into(), "-MF".into(), depfile.into()],
+        ))
+    }
+
+    fn emit_suffix(&self, emit: Emit) -> Option<&'static str> {
+        match emit {
+            Emit::Preprocessed => Some(".ii"),
+            // device code is assembled once per architecture, which does not fit a single file
+            Emit::Asm | Emit::Ir => None,
+        }
+    }
+
+    fn emit_arguments(&self, emit: Emit, _output_file: &Path, arguments: &mut Vec<OsString>) {
+        if emit != Emit::Preprocessed {
+            return;
+        }
+        for argument in arguments.iter_mut() {
+            if argument == "-c" {
+                *argument = "-E".into();
+            }
+        }
+        // the device side is preprocessed once per architecture as well
+        arguments.push_from("--cuda-host-only");
+    }
+
+    fn compile_arguments(
+        &self,
+        config: &Configuration,
+        target: &Target,
+        selected_profile: &str,
+    ) -> Result<Vec<OsString>, io::Error> {
+        let mut args = Vec::new();
+
+        args.push_from("-c");
+
+        if let Some(opt_level) = &self.optimize {
+            args.push_from(format!("-O{}", opt_level));
+        }
+
+        if self.debug {
+            args.push_from("-g");
+        }
+
+        if self.ndebug {
+            args.push_from("-DNDEBUG");
+        }
+
+        for define in self
+            .defines
+            .iter()
+        {
+            args.push_from(format!("-D{}", define));
+        }
+
+        if let Some(std) = &self.standard {
+            args.push_from(format!("-std={}", std));
+        }
+
+        if let Some(warnings) = self.warnings {
+            for warning in warnings.gcc_arguments() {
+                args.push_from(*warning);
+            }
+        }
+
+        if self.warnings_as_errors {
+            args.push_from("-Werror");
+        }
+
+        for arch in self
+            .archs
+            .iter()
+        {
+            args.push_from(format!(
+                "--offload-arch={}",
+                arch
+            ));
+        }
+
+        // objects of shared libraries have to be position independent
+        if target.build_type == BuildType::Library
+            && matches!(
+                self.library_type,
+                LibraryType::Shared
+            )
+            && !cfg!(windows)
+        {
+            args.push_from("-fPIC");
+        }
+
+        let include_dirs = config
+            .dependency_dirs(selected_profile)?
+            .into_iter()
+            .map(|(include_dir, _)| include_dir)
+            .chain(
+                target
+                    .include_dirs
+                    .iter()
+                    .cloned(),
+            );
+        for include_dir in include_dirs {
+            args.push(concat_os(&[
+                "-I".as_ref(),
+                include_dir.as_os_str(),
+            ]));
+        }
+
+        args.extend(self.toolchain_arguments(config));
+
+        args.push_from("-o");
+        args.push_from(&*target.object_file);
+
+        args.push_from(&*target.src_file);
+
+        for flag in self
+            .extra_compiler_flags
+            .iter()
+        {
+            args.push_from(&**flag);
+        }
+
+        Ok(args)
+    }
+
+    fn link_arguments(
+        &self,
+        config: &Configuration,
+        target: &Target,
+        selected_profile: &str,
+    ) -> Result<Vec<OsString>, io::Error> {
+        // static libraries are archives of objects, dependencies are linked by their users
+        if target.build_type == BuildType::Library
+            && matches!(
+                self.library_type,
+                LibraryType::Static
+            )
+        {
+            let mut args = Vec::new();
+            args.push_from("rcs");
+            args.push_from(&*target.artifact_file);
+            args.push_from(&*target.object_file);
+            return Ok(args);
+        }
+
+        let mut args = Vec::new();
+
+        if target.build_type == BuildType::Library {
+            args.push_from("-shared");
+        }
+
+        // dependency libraries are copied next to the artifact
+        if !cfg!(windows) {
+            args.push_from("-Wl,-rpath,$ORIGIN");
+        }
+
+        args.extend(self.toolchain_arguments(config));
+
+        args.push_from("-o");
+        args.push_from(&*target.artifact_file);
+
+        // the linker only takes what is still missing from each library, so they go after objects
+        args.push_from(&*target.object_file);
+
+        for (lib_dir, link) in config.dependency_lib_dirs(selected_profile)? {
+            args.push(concat_os(&[
+                "-L".as_ref(),
+                lib_dir.as_os_str(),
+            ]));
+
+            for lib in fs::read_dir(&lib_dir)? {
+                let filename = lib?.file_name();
+                let (_, ext) = split_file_name(&filename);
+                if ext != "a" && ext != "so" && ext != "lib" {
+                    continue;
+                }
+                let whole_archive = link.whole_archive && ext != "so";
+                match (whole_archive, cfg!(windows)) {
+                    (true, true) => args.push(concat_os(&[
+                        "-Wl,/WHOLEARCHIVE:".as_ref(),
+                        &filename,
+                    ])),
+                    (true, false) => args.push_from("-Wl,--whole-archive"),
+                    (false, _) => {},
+                }
+                args.push(concat_os(&[
+                    "-l:".as_ref(),
+                    &filename,
+                ]));
+                if whole_archive && !cfg!(windows) {
+                    args.push_from("-Wl,--no-whole-archive");
+                }
+            }
+        }
+
+        for flag in self
+            .extra_linker_flags
+            .iter()
+        {
+            args.push_from(&**flag);
+        }
+
+        Ok(args)
+    }
+
+    fn runner(&self) -> Option<Rc<[Value]>> {
+        self.runner
+            .clone()
+    }
+
+    fn environment(&self) -> &Environment { &self.environment }
+
+    fn cmake_language(&self) -> &'static str { "HIP" }
+
+    fn cmake_library_kind(&self) -> &'static str {
+        use LibraryType::*;
+        match self.library_type {
+            Shared => "SHARED",
+            Static => "STATIC",
+        }
+    }
+
+    fn kind(&self) -> &'static str { "hipcc" }
+
+    fn settings(&self) -> Vec<(&'static str, Value)> {
+        let mut settings = Vec::new();
+        if let Some(compiler_path) = &self.compiler_path {
+            settings.push((
+                "compiler_path",
+                compiler_path.clone(),
+            ));
+        }
+        if let Some(std) = &self.standard {
+            settings.push((
+                "standard",
+                std.to_string()
+                    .into(),
+            ));
+        }
+        if let Some(optimize) = &self.optimize {
+            settings.push((
+                "optimize",
+                optimize
+                    .to_string()
+                    .into(),
+            ));
+        }
+        settings.push((
+            "debug",
+            self.debug
+                .to_string()
+                .into(),
+        ));
+        settings.push((
+            "assertions",
+            (!self.ndebug)
+                .to_string()
+                .into(),
+        ));
+        if let Some(warnings) = &self.warnings {
+            settings.push((
+                "warnings",
+                warnings
+                    .to_string()
+                    .into(),
+            ));
+        }
+        settings.push((
+            "warnings_as_errors",
+            self.warnings_as_errors
+                .to_string()
+                .into(),
+        ));
+        settings.push((
+            "library",
+            match self.library_type {
+                LibraryType::Shared => "shared",
+                LibraryType::Static => "static",
+            }
+            .into(),
+        ));
+        if !self
+            .archs
+            .is_empty()
+        {
+            settings.push((
+                "arch",
+                self.archs
+                    .join(" ")
+                    .into(),
+            ));
+        }
+        if let Some(runner) = &self.runner {
+            settings.push((
+                "runner",
+                runner
+                    .join(" ")
+                    .into(),
+            ));
+        }
+        self.environment
+            .settings(&mut settings);
+        self.defines
+            .settings(&mut settings);
+        if !self
+            .extra_compiler_flags
+            .is_empty()
+        {
+            settings.push((
+                "extra_compiler_flags",
+                self.extra_compiler_flags
+                    .join(" ")
+                    .into(),
+            ));
+        }
+        if !self
+            .extra_linker_flags
+            .is_empty()
+        {
+            settings.push((
+                "extra_linker_flags",
+                self.extra_linker_flags
+                    .join(" ")
+                    .into(),
+            ));
+        }
+        settings
+    }
+
+    fn cmake_properties(&self) -> Vec<(&'static str, Value)> {
+        use Standard::*;
+        let mut properties = Vec::new();
+        if let Some(std) = &self.standard {
+            properties.push((
+                "HIP_STANDARD",
+                match std {
+                    CPP11 => "11",
+                    CPP14 => "14",
+                    CPP17 => "17",
+                    CPP20 => "20",
+                    CPP23 => "23",
+                }
+                .into(),
+            ));
+        }
+        if !self
+            .archs
+            .is_empty()
+        {
+            properties.push((
+                "HIP_ARCHITECTURES",
+                self.archs
+                    .join(";")
+                    .into(),
+            ));
+        }
+        properties
+    }
+
+    fn defines(&self) -> &Defines { &self.defines }
+
+    fn clang_flags(&self) -> Vec<Value> {
+        let mut flags = vec!["-xhip".into()];
+        if let Some(std) = &self.standard {
+            flags.push(format!("-std={}", std).into());
+        }
+        if let Some(warnings) = self.warnings {
+            for warning in warnings.gcc_arguments() {
+                flags.push((*warning).into());
+            }
+        }
+        for arch in self
+            .archs
+            .iter()
+        {
+            flags.push(format!("--offload-arch={}", arch).into());
+        }
+        flags
+    }
+}
@@ -1,8 +1,17 @@
+mod gcc;
+mod hipcc;
 mod msvc;
 mod nvcc;
 
+use std::env;
+use std::ffi::OsString;
+use std::fmt::Display;
 use std::io;
+use std::path::Path;
+use std::process::Command;
+use std::process::Stdio;
 use std::rc::Rc;
+use std::str::FromStr;
 
 use indexmap::IndexMap;
 
@@ -13,7 +22,12 @@ use crate::lsd::Level;
 use crate::lsd::Map;
 use crate::lsd::Value;
 use crate::lsd::LSD;
+use crate::probe;
+use crate::util;
+use crate::util::BoolGuardExt;
 use crate::BuildType;
+use crate::Emit;
+use crate::Target;
 
 pub type Name = Value;
 
@@ -30,28 +44,405 @@ pub enum ParseError {
     ProfileTypeIsNotAValue,
 
     InvalidValueForKey(&'static str),
+
+    FallbackIsNotAValue,
+    FallbackToNonExistentProfile(Value),
+
+    /// `import_std` with a `standard` the standard library module cannot be compiled with,
+    /// with the one it needs.
+    ImportStdNeedsStandard(&'static str),
 }
 
-pub fn parse_all(level: Level) -> Result<Map<Name, Rc<dyn Profile>>, Vec<ParseError>> {
+/// Profiles by name, and which of them fell back to another one (see [`Fallback`]).
+pub type Parsed = (
+    Map<Name, Rc<dyn Profile>>,
+    Map<Name, Fallback>,
+);
+
+pub fn parse_all(level: Level) -> Result<Parsed, Vec<ParseError>> {
     let mut profiles = IndexMap::new();
     let mut profiles_errors = Vec::new();
 
     for (key, profile_lsd) in level.iter() {
-        match parse_one(&profiles, profile_lsd.clone()) {
+        match parse_one(
+            &profiles,
+            key,
+            profile_lsd.clone(),
+        ) {
             Ok(compiler) => drop(profiles.insert(key.clone(), compiler)),
             Err(err) => profiles_errors.push(err),
         }
     }
 
-    if profiles_errors.is_empty() {
-        Ok(Map::new(profiles))
-    } else {
-        Err(profiles_errors)
+    if !profiles_errors.is_empty() {
+        return Err(profiles_errors);
+    }
+
+    let fallbacks = apply_fallbacks(&level, &mut profiles).map_err(|err| vec![err])?;
+    Ok((
+        Map::new(profiles),
+        Map::new(fallbacks),
+    ))
+}
+
+//
+// Environment
+//
+
+/// Variables hermetic compilers still get, as processes cannot even start without them on some
+/// systems (ex. `SystemRoot` on Windows), and compilers look for their own tools in PATH.
+const HERMETIC_BASE_VARIABLES: &[&str] = &["PATH", "SystemRoot", "TEMP", "TMP", "TMPDIR"];
+
+/// Environment compilers of a profile are started with.
+#[derive(Debug, Clone, Default)]
+pub struct Environment {
+    /// Whether compilers only get [`HERMETIC_BASE_VARIABLES`], `allow`ed and `set` variables
+    /// instead of the whole environment of build++, so that builds do not depend on whatever
+    /// is set on the machine (`hermetic true`).
+    hermetic: bool,
+    /// Variables of build++ passed on to hermetic compilers (`env_allow [ INCLUDE LIB ]`).
+    allow: Rc<[Value]>,
+    /// Variables set for compilers, hermetic or not (`env.CUDA_CACHE_DISABLE 1`).
+    set: IndexMap<Value, Value>,
+}
+
+impl Environment {
+    /// Inheriting profiles replace `hermetic` and `env_allow`, but add to `env`.
+    fn apply(&mut self, level: &Level) -> Result<(), ParseError> {
+        use ParseError::*;
+
+        if let Some(hermetic) = level.get_parse(
+            key!(hermetic),
+            InvalidValueForKey("hermetic"),
+        )? {
+            self.hermetic = hermetic;
+        }
+
+        if let Some(allow) = level.get_inner(key!(env_allow)) {
+            self.allow = match allow {
+                LSD::Value(value) => value
+                    .split_whitespace()
+                    .map(Rc::from)
+                    .collect(),
+                LSD::Level(list) => list
+                    .values()
+                    .map(|variable| {
+                        variable
+                            .to_value()
+                            .ok_or(InvalidValueForKey(
+                                "env_allow",
+                            ))
+                    })
+                    .collect::<Result<_, _>>()?,
+            };
+        }
+
+        if let Some(set) = level.get_level(
+            key!(env),
+            InvalidValueForKey("env"),
+        )? {
+            for (variable, value) in set {
+                let value = value
+                    .to_value()
+                    .ok_or(InvalidValueForKey("env"))?;
+                self.set
+                    .insert(variable, value);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Starts `command` with this environment.
+    pub fn apply_to(&self, command: &mut Command) {
+        if self.hermetic {
+            command.env_clear();
+            for variable in HERMETIC_BASE_VARIABLES
+                .iter()
+                .copied()
+                .chain(
+                    self.allow
+                        .iter()
+                        .map(Rc::as_ref),
+                )
+            {
+                if let Some(value) = env::var_os(variable) {
+                    command.env(variable, value);
+                }
+            }
+        }
+        command.envs(
+            self.set
+                .iter()
+                .map(|(variable, value)| (&**variable, &**value)),
+        );
+    }
+
+    /// Variables set for compilers, as `NAME=VALUE`.
+    pub fn set(&self) -> impl Iterator<Item = String> + '_ {
+        self.set
+            .iter()
+            .map(|(variable, value)| format!("{}={}", variable, value))
+    }
+
+    fn settings(&self, settings: &mut Vec<(&'static str, Value)>) {
+        settings.push((
+            "hermetic",
+            self.hermetic
+                .to_string()
+                .into(),
+        ));
+        if !self
+            .allow
+            .is_empty()
+        {
+            settings.push((
+                "env_allow",
+                self.allow
+                    .join(" ")
+                    .into(),
+            ));
+        }
+        for variable in self.set() {
+            settings.push(("env", variable.into()));
+        }
+    }
+}
+
+//
+// Defines
+//
+
+/// Preprocessor macros defined for every source (`define { NDEBUG {} VERSION 2 }`).
+#[derive(Debug, Clone, Default)]
+pub struct Defines(IndexMap<Value, Option<Value>>);
+
+impl Defines {
+    /// Macros of a `define` level, where an empty level defines a macro without a value, `None`
+    /// if it is anything else.
+    pub fn parse(define: &LSD) -> Option<Self> {
+        define
+            .to_level()?
+            .into_iter()
+            .map(|(name, value)| match value {
+                LSD::Value(value) => Some((name, Some(value))),
+                LSD::Level(level) if level.is_empty() => Some((name, None)),
+                LSD::Level(_) => None,
+            })
+            .collect::<Option<_>>()
+            .map(Self)
+    }
+
+    /// Inheriting profiles add to (and may redefine) macros of the profile they inherit from.
+    fn apply(&mut self, level: &Level) -> Result<(), ParseError> {
+        use ParseError::*;
+
+        if let Some(define) = level.get_inner(key!(define)) {
+            self.0
+                .extend(
+                    Self::parse(&define)
+                        .ok_or(InvalidValueForKey("define"))?
+                        .0,
+                );
+        }
+
+        Ok(())
+    }
+
+    /// Defined macros, as `NAME` or `NAME=VALUE`.
+    pub fn iter(&self) -> impl Iterator<Item = String> + '_ {
+        self.0
+            .iter()
+            .map(|(name, value)| match value {
+                Some(value) => format!("{}={}", name, value),
+                None => name.to_string(),
+            })
+    }
+
+    fn settings(&self, settings: &mut Vec<(&'static str, Value)>) {
+        for define in self.iter() {
+            settings.push(("define", define.into()));
+        }
+    }
+}
+
+//
+// Fallbacks
+//
+
+/// Profile used instead of one that cannot be used on this machine.
+#[derive(Debug, Clone)]
+pub struct Fallback {
+    pub profile: Name,
+    /// Why the profile cannot be used (ex. `compiler `nvcc` was not found`).
+    pub reason: String,
+}
+
+/// Why a profile cannot be used on this machine, making it fall back to its `fallback` profile.
+fn unavailable_because(profile: &dyn Profile, level: &Level) -> Result<Option<String>, ParseError> {
+    use ParseError::*;
+
+    if util::find_executable(profile.compiler_command()).is_none() {
+        return Ok(Some(format!(
+            "compiler `{}` was not found",
+            profile.compiler_command()
+        )));
+    }
+
+    let requires_gpu = level
+        .get_parse(
+            key!(requires_gpu),
+            InvalidValueForKey("requires_gpu"),
+        )?
+        .unwrap_or(false);
+    if requires_gpu && !has_gpu(profile) {
+        return Ok(Some(
+            "no GPU was found".to_string(),
+        ));
+    }
+
+    Ok(None)
+}
+
+/// Whether there is a GPU the compiler of `profile` builds for.
+fn has_gpu(profile: &dyn Profile) -> bool {
+    match profile.kind() {
+        "hipcc" => has_amd_gpu(),
+        _ => has_nvidia_gpu(),
+    }
+}
+
+/// Whether `nvidia-smi` lists a GPU (cached like other probes, as it takes a while to start).
+fn has_nvidia_gpu() -> bool {
+    probe::cached("cuda_gpu", || {
+        Command::new("nvidia-smi")
+            .arg("-L")
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()
+            .ok()
+            .filter(|output| {
+                output
+                    .status
+                    .success()
+                    && String::from_utf8_lossy(&output.stdout).contains("GPU")
+            })
+            .map(|_| Value::from("true"))
+    })
+    .is_some()
+}
+
+/// Whether `rocminfo` lists a GPU agent (named after its architecture, ex. `gfx90a`).
+fn has_amd_gpu() -> bool {
+    probe::cached("rocm_gpu", || {
+        Command::new("rocminfo")
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()
+            .ok()
+            .filter(|output| {
+                output
+                    .status
+                    .success()
+                    && String::from_utf8_lossy(&output.stdout).contains("gfx")
+            })
+            .map(|_| Value::from("true"))
+    })
+    .is_some()
+}
+
+/// Replaces each profile that cannot be used on this machine (see [`unavailable_because`]) and has
+/// `fallback <profile>` by that profile (or by its own fallback), so that one configuration works
+/// both with and without (ex.) CUDA installed. Fallbacks are not inherited.
+fn apply_fallbacks(
+    level: &Level,
+    profiles: &mut IndexMap<Name, Rc<dyn Profile>>,
+) -> Result<IndexMap<Name, Fallback>, ParseError> {
+    use ParseError::*;
+
+    let mut fallbacks = IndexMap::new();
+    for (name, profile_lsd) in level.iter() {
+        let LSD::Level(profile_level) = profile_lsd else {
+            continue;
+        };
+        let Some(fallback) = profile_level.get_value(
+            key!(fallback),
+            FallbackIsNotAValue,
+        )?
+        else {
+            continue;
+        };
+        profiles
+            .contains_key(&fallback)
+            .or_else_err(|| FallbackToNonExistentProfile(fallback.clone()))?;
+        fallbacks.insert(
+            name.clone(),
+            (fallback, profile_level),
+        );
+    }
+
+    let original = profiles.clone();
+    let mut applied = IndexMap::new();
+    for (name, (fallback, profile_level)) in &fallbacks {
+        let Some(reason) = unavailable_because(
+            &*original[name],
+            profile_level,
+        )?
+        else {
+            continue;
+        };
+
+        // fallbacks may have fallbacks of their own, which are not followed in circles
+        let mut used = fallback;
+        for _ in 0..fallbacks.len() {
+            let Some((next, next_level)) = fallbacks.get(used) else {
+                break;
+            };
+            if unavailable_because(&*original[used], next_level)?.is_none() {
+                break;
+            }
+            used = next;
+        }
+
+        profiles.insert(
+            name.clone(),
+            original[used].clone(),
+        );
+        applied.insert(
+            name.clone(),
+            Fallback {
+                profile: used.clone(),
+                reason,
+            },
+        );
+    }
+
+    Ok(applied)
+}
+
+/// Names of profiles that get [`Profile::preset`] keys under their own.
+pub const PRESET_PROFILES: &[&str] = &["debug", "release"];
+
+/// `profile` with its preset (if `name` has one) applied, then `level`.
+fn with_preset(
+    profile: &dyn Profile,
+    name: &str,
+    level: Level,
+) -> Result<Rc<dyn Profile>, ParseError> {
+    match PRESET_PROFILES
+        .iter()
+        .find(|preset| preset.eq_ignore_ascii_case(name))
+    {
+        Some(preset) => profile
+            .inherit_with(profile.preset(preset))?
+            .inherit_with(level),
+        None => profile.inherit_with(level),
     }
 }
 
 fn parse_one(
     profiles: &IndexMap<Name, Rc<dyn Profile>>,
+    name: &str,
     entry: LSD,
 ) -> Result<Rc<dyn Profile>, ParseError> {
     use ParseError::*;
@@ -65,7 +456,7 @@ fn parse_one(
                 let profile = profiles
                     .get(&inherit)
                     .ok_or(InheritingFromNonExistentProfile(inherit))?;
-                return profile.inherit_with(level);
+                return with_preset(&**profile, name, level);
             }
 
             // No inherit, base profile, check profile type (`is`)
@@ -81,8 +472,46 @@ fn parse_one(
                 .as_str()
             {
                 // Add more implementations here...
-                "nvcc" | "cuda" => nvcc::Profile::create_default().inherit_with(level),
-                "msvc" => msvc::Profile::create_default().inherit_with(level),
+                "nvcc" | "cuda" => with_preset(
+                    &*nvcc::Profile::create_default(),
+                    name,
+                    level,
+                ),
+                "hipcc" | "hip" | "rocm" => with_preset(
+                    &*hipcc::Profile::create_default(),
+                    name,
+                    level,
+                ),
+                "msvc" => with_preset(
+                    &*msvc::Profile::create_default(),
+                    name,
+                    level,
+                ),
+                "clang-cl" | "clangcl" => with_preset(
+                    &*msvc::Profile::create_clang_cl(),
+                    name,
+                    level,
+                ),
+                "gcc" | "g++" | "gnu" => with_preset(
+                    &*gcc::Profile::create_default(),
+                    name,
+                    level,
+                ),
+                "mingw" | "mingw-w64" | "mingw64" => with_preset(
+                    &*gcc::Profile::create_mingw(),
+                    name,
+                    level,
+                ),
+                "zig" | "zig-cc" | "zigcc" => with_preset(
+                    &*gcc::Profile::create_zig(),
+                    name,
+                    level,
+                ),
+                "apple-clang" | "appleclang" | "xcode" => with_preset(
+                    &*gcc::Profile::create_apple_clang(),
+                    name,
+                    level,
+                ),
                 _ => Err(CouldNotFindMatchingCompiler),
             }
         },
@@ -93,14 +522,327 @@ fn parse_one(
             .as_str()
         {
             // Add more implementations here...
-            "nvcc" | "cuda" => Ok(nvcc::Profile::create_default()),
-            "msvc" => Ok(msvc::Profile::create_default()),
+            "nvcc" | "cuda" => with_preset(
+                &*nvcc::Profile::create_default(),
+                name,
+                Level::new(),
+            ),
+            "hipcc" | "hip" | "rocm" => with_preset(
+                &*hipcc::Profile::create_default(),
+                name,
+                Level::new(),
+            ),
+            "msvc" => with_preset(
+                &*msvc::Profile::create_default(),
+                name,
+                Level::new(),
+            ),
+            "clang-cl" | "clangcl" => with_preset(
+                &*msvc::Profile::create_clang_cl(),
+                name,
+                Level::new(),
+            ),
+            "gcc" | "g++" | "gnu" => with_preset(
+                &*gcc::Profile::create_default(),
+                name,
+                Level::new(),
+            ),
+            "mingw" | "mingw-w64" | "mingw64" => with_preset(
+                &*gcc::Profile::create_mingw(),
+                name,
+                Level::new(),
+            ),
+            "zig" | "zig-cc" | "zigcc" => with_preset(
+                &*gcc::Profile::create_zig(),
+                name,
+                Level::new(),
+            ),
+            "apple-clang" | "appleclang" | "xcode" => with_preset(
+                &*gcc::Profile::create_apple_clang(),
+                name,
+                Level::new(),
+            ),
             // TODO allow inline inherit too
             _ => Err(CouldNotFindMatchingCompiler),
         },
     }
 }
 
+//
+// Sanitizers
+//
+
+/// Runtime checks compiled into artifacts, reporting bugs as they happen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Sanitizer {
+    /// Out-of-bounds and use-after-free accesses (ASan).
+    Address,
+    /// Undefined behavior, such as signed overflow (UBSan).
+    Undefined,
+    /// Data races (TSan).
+    Thread,
+    /// Memory that is never freed (LSan, also part of ASan).
+    Leak,
+}
+
+impl Display for Sanitizer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use Sanitizer::*;
+        write!(
+            f,
+            "{}",
+            match self {
+                Address => "address",
+                Undefined => "undefined",
+                Thread => "thread",
+                Leak => "leak",
+            }
+        )
+    }
+}
+
+impl FromStr for Sanitizer {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use Sanitizer::*;
+        let s = s.to_lowercase();
+        match s.as_str() {
+            "address" | "asan" => Ok(Address),
+            "undefined" | "ubsan" => Ok(Undefined),
+            "thread" | "tsan" => Ok(Thread),
+            "leak" | "lsan" => Ok(Leak),
+            _ => Err(()),
+        }
+    }
+}
+
+impl Sanitizer {
+    /// Options of its runtime set for `run` and `test` (unless set already), so that reports
+    /// come with a stack trace and fail the run.
+    fn run_variable(self) -> Option<(&'static str, &'static str)> {
+        use Sanitizer::*;
+        match self {
+            Address => Some((
+                "ASAN_OPTIONS",
+                "detect_stack_use_after_return=1",
+            )),
+            Undefined => Some((
+                "UBSAN_OPTIONS",
+                "print_stacktrace=1:halt_on_error=1",
+            )),
+            Thread => Some((
+                "TSAN_OPTIONS",
+                "second_deadlock_stack=1",
+            )),
+            Leak => None,
+        }
+    }
+}
+
+/// Parses `sanitize address` or `sanitize [ address undefined ]`. Thread checks cannot be
+/// combined with address or leak ones, as their runtimes track memory differently.
+fn parse_sanitizers(level: &Level) -> Result<Option<Rc<[Sanitizer]>>, ParseError> {
+    use ParseError::*;
+
+    let Some(items) = parse_arguments(level, "sanitize")? else {
+        return Ok(None);
+    };
+    let sanitizers = items
+        .iter()
+        .map(|item| {
+            item.parse()
+                .map_err(|_| InvalidValueForKey("sanitize"))
+        })
+        .collect::<Result<Rc<[Sanitizer]>, _>>()?;
+
+    if sanitizers.contains(&Sanitizer::Thread)
+        && (sanitizers.contains(&Sanitizer::Address) || sanitizers.contains(&Sanitizer::Leak))
+    {
+        return Err(InvalidValueForKey("sanitize"));
+    }
+
+    Ok(Some(sanitizers))
+}
+
+/// `-fsanitize=` of gcc and compilers taking its flags (ex. clang), `None` without sanitizers.
+fn gcc_sanitize_argument(sanitizers: &[Sanitizer]) -> Option<String> {
+    (!sanitizers.is_empty()).then(|| {
+        format!(
+            "-fsanitize={}",
+            sanitizers
+                .iter()
+                .map(Sanitizer::to_string)
+                .collect::<Vec<_>>()
+                .join(",")
+        )
+    })
+}
+
+/// Variables of [`Sanitizer::run_variable`] of each of `sanitizers`.
+fn sanitize_run_environment(sanitizers: &[Sanitizer]) -> Vec<(&'static str, &'static str)> {
+    sanitizers
+        .iter()
+        .filter_map(|sanitizer| sanitizer.run_variable())
+        .collect()
+}
+
+/// `sanitize` as written in a configuration.
+fn sanitize_setting(sanitizers: &[Sanitizer]) -> Value {
+    sanitizers
+        .iter()
+        .map(Sanitizer::to_string)
+        .collect::<Vec<_>>()
+        .join(" ")
+        .into()
+}
+
+//
+// Warnings
+//
+
+/// Which warnings a compiler reports, from none to the pedantic ones.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Warnings {
+    /// `-w`, `/W0`.
+    Off,
+    /// Those the compiler reports without being asked.
+    Default,
+    /// `-Wall`, `/W3`.
+    All,
+    /// `-Wall -Wextra`, `/W4`.
+    Extra,
+    /// `-Wall -Wextra -Wpedantic`, `/Wall`.
+    Pedantic,
+}
+
+impl Display for Warnings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use Warnings::*;
+        write!(
+            f,
+            "{}",
+            match self {
+                Off => "off",
+                Default => "default",
+                All => "all",
+                Extra => "extra",
+                Pedantic => "pedantic",
+            }
+        )
+    }
+}
+
+impl FromStr for Warnings {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use Warnings::*;
+        let s = s.to_lowercase();
+        match s.as_str() {
+            "off" | "none" | "no" | "false" | "w" => Ok(Off),
+            "default" => Ok(Default),
+            "all" | "wall" | "on" | "true" => Ok(All),
+            "extra" | "wextra" => Ok(Extra),
+            "pedantic" | "wpedantic" => Ok(Pedantic),
+            _ => Err(()),
+        }
+    }
+}
+
+impl Warnings {
+    /// Arguments of gcc and compilers taking its flags (ex. clang).
+    fn gcc_arguments(self) -> &'static [&'static str] {
+        use Warnings::*;
+        match self {
+            Off => &["-w"],
+            Default => &[],
+            All => &["-Wall"],
+            Extra => &["-Wall", "-Wextra"],
+            Pedantic => &["-Wall", "-Wextra", "-Wpedantic"],
+        }
+    }
+
+    /// Arguments of cl and compilers taking its flags (ex. clang-cl).
+    fn msvc_arguments(self) -> &'static [&'static str] {
+        use Warnings::*;
+        match self {
+            Off => &["/W0"],
+            Default => &[],
+            All => &["/W3"],
+            Extra => &["/W4"],
+            Pedantic => &["/Wall"],
+        }
+    }
+}
+
+/// Parses `warnings pedantic`, or `warnings [ all extra error ]` where the most warnings listed
+/// are reported and `error` makes them errors. Returns what is given of the warnings and whether
+/// they are errors, `warnings_as_errors true` being the same as `error`.
+fn parse_warnings(level: &Level) -> Result<(Option<Warnings>, Option<bool>), ParseError> {
+    use ParseError::*;
+
+    let items: Vec<Value> = match level.get_inner(key!(warnings)) {
+        Some(LSD::Value(value)) => vec![value],
+        Some(LSD::Level(list)) => list
+            .values()
+            .map(|item| {
+                item.to_value()
+                    .ok_or(InvalidValueForKey("warnings"))
+            })
+            .collect::<Result<_, _>>()?,
+        None => Vec::new(),
+    };
+
+    let mut warnings = None;
+    let mut as_errors = level.get_parse(
+        key!(warnings_as_errors),
+        InvalidValueForKey("warnings_as_errors"),
+    )?;
+    for item in items {
+        match item
+            .to_lowercase()
+            .as_str()
+        {
+            "error" | "errors" | "werror" | "wx" => as_errors = Some(true),
+            item => {
+                let item = item
+                    .parse()
+                    .map_err(|_| InvalidValueForKey("warnings"))?;
+                warnings = warnings.max(Some(item));
+            },
+        }
+    }
+
+    Ok((warnings, as_errors))
+}
+
+/// Parses `runner "command with spaces"` or `runner [ each list item being a command or arg ]`.
+fn parse_runner(level: &Level) -> Result<Option<Rc<[Value]>>, ParseError> {
+    parse_arguments(level, "runner")
+}
+
+/// Parses `key "-a -b"` or `key [ -a "-b with spaces" ]`, each list item being an argument.
+fn parse_arguments(level: &Level, key: &'static str) -> Result<Option<Rc<[Value]>>, ParseError> {
+    use ParseError::*;
+    level
+        .get_inner(vec![Value::from(key)])
+        .map(|arguments| match arguments {
+            LSD::Value(value) => Ok(value
+                .split_whitespace()
+                .map(Rc::from)
+                .collect()),
+            LSD::Level(list) => list
+                .values()
+                .map(|arg| {
+                    arg.to_value()
+                        .ok_or(InvalidValueForKey(key))
+                })
+                .collect(),
+        })
+        .transpose()
+}
+
 pub trait Profile {
     // parse
 
@@ -112,9 +854,14 @@ pub trait Profile {
 
     fn inherit_with(&self, level: Level) -> Result<Rc<dyn Profile>, ParseError>;
 
+    /// Keys that profiles named `debug` or `release` (see [`PRESET_PROFILES`]) get
+    /// unless they set them themselves.
+    fn preset(&self, name: &str) -> Level;
+
     // pre-build
 
-    fn src_file_suffix(&self) -> &'static str;
+    /// Suffixes of sources the compiler accepts, in the order they are looked for.
+    fn src_file_suffixes(&self) -> &'static [&'static str];
 
     // build
 
@@ -122,15 +869,115 @@ pub trait Profile {
 
     fn artifact_suffix(&self, build_type: BuildType) -> &'static str;
 
+    /// Versioned file names the artifact is known by, most specific first (ex. `libname.so.1.2.3`,
+    /// `libname.so.1`): the first is linked, each other one and the artifact itself are symlinks
+    /// to the one before.
+    fn artifact_versions(&self, _config: &Configuration, _build_type: BuildType) -> Vec<Value> {
+        Vec::new()
+    }
+
     fn compiler_command(&self) -> &str;
 
-    fn compiler_arguments(
+    /// Command [`Self::link_arguments`] are given to, the compiler unless something else makes
+    /// the artifact (ex. `ar` for static libraries).
+    fn linker_command(&self, _build_type: BuildType) -> &str { self.compiler_command() }
+
+    /// Shown when `compiler_command` could not be found.
+    fn installation_hint(&self) -> &'static str {
+        "install the compiler and add it to PATH, or set `compiler_path` in the profile"
+    }
+
+    /// Whether arguments should be passed through a `@file` response file
+    /// instead of the command line (for long commands and paths with spaces).
+    fn uses_response_file(&self) -> bool { false }
+
+    fn object_suffix(&self) -> &'static str;
+
+    /// How ninja finds out which headers a source includes: `deps` kind,
+    /// and compile arguments making the compiler report them (into `depfile` for `gcc`).
+    fn ninja_deps(&self, _depfile: &Path) -> Option<(&'static str, Vec<OsString>)> { None }
+
+    /// Arguments for compiling `target.src_file` into `target.object_file`.
+    fn compile_arguments(
+        &self,
+        config: &Configuration,
+        target: &Target,
+        selected_profile: &str,
+    ) -> Result<Vec<OsString>, io::Error>;
+
+    /// Suffix of what `build -emit` leaves instead of an object, `None` if the compiler cannot
+    /// emit it.
+    fn emit_suffix(&self, _emit: Emit) -> Option<&'static str> { None }
+
+    /// Changes [`Self::compile_arguments`] (of a target whose `object_file` is `output_file`)
+    /// to emit `emit` into `output_file` instead of compiling.
+    fn emit_arguments(&self, _emit: Emit, _output_file: &Path, _arguments: &mut Vec<OsString>) {
+    }
+
+    /// Arguments added to [`Self::compile_arguments`] for only analyzing a source file
+    /// (for `buildpp analyze`), `None` if analysis is not enabled in the profile.
+    fn analysis_only_arguments(&self) -> Option<Vec<OsString>> { None }
+
+    /// Arguments for compiling the standard library module (for `import std;`) into
+    /// [`Configuration::target_std_module_file`] before `target`, `None` unless the profile
+    /// imports it (`import_std true`).
+    fn std_module_arguments(
+        &self,
+        _config: &Configuration,
+        _target: &Target,
+        _selected_profile: &str,
+    ) -> Result<Option<Vec<OsString>>, io::Error> {
+        Ok(None)
+    }
+
+    /// Arguments for linking `target.object_file` with dependencies into `target.artifact_file`.
+    fn link_arguments(
         &self,
         config: &Configuration,
-        build_type: BuildType,
+        target: &Target,
         selected_profile: &str,
-    ) -> Result<Vec<Value>, io::Error>;
+    ) -> Result<Vec<OsString>, io::Error>;
+
+    // run
+
+    /// Command that runs artifacts instead of the host (ex. `qemu-arm`, `node`),
+    /// for when they cannot be executed natively.
+    fn runner(&self) -> Option<Rc<[Value]>>;
+
+    /// Variables set for artifacts started by `run` and `test`, unless they are set already
+    /// (ex. options of sanitizers).
+    fn run_environment(&self) -> Vec<(&'static str, &'static str)> { Vec::new() }
+
+    /// Environment the compiler (or ninja running it) is started with.
+    fn environment(&self) -> &Environment;
+
+    // inspect
+
+    /// Compiler type, as written in `is`.
+    fn kind(&self) -> &'static str;
+
+    /// Keys of the profile with their resolved values (after inheritance),
+    /// as they would be written in a configuration.
+    fn settings(&self) -> Vec<(&'static str, Value)>;
+
+    // export
+
+    /// Name of the sources' language in CMake (ex. `CXX`, `CUDA`).
+    fn cmake_language(&self) -> &'static str;
+
+    /// `add_library` kind matching the profile (`SHARED` or `STATIC`).
+    fn cmake_library_kind(&self) -> &'static str;
+
+    /// CMake target properties matching the profile (ex. `CXX_STANDARD 17`).
+    fn cmake_properties(&self) -> Vec<(&'static str, Value)>;
+
+    /// Macros of `define`, which [`Configuration::defines`] combines with those of dependencies
+    /// and targets.
+    fn defines(&self) -> &Defines;
+
+    /// Flags matching the profile as understood by clang (and so clangd), except include dirs
+    /// and defines.
+    fn clang_flags(&self) -> Vec<Value>;
 
-    // TODO gnu_cpp::Profile
     // TODO clang::Profile
 }
@@ -1,20 +1,37 @@
+use std::env;
+use std::ffi::OsString;
 use std::fmt::Display;
 use std::fs;
 use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Command;
+use std::process::Stdio;
 use std::rc::Rc;
 use std::str::FromStr;
 
+use super::Defines;
+use super::Environment;
 use super::ParseError;
+use super::Sanitizer;
+use super::Warnings;
+use crate::configuration;
 use crate::configuration::Configuration;
 use crate::key;
 use crate::lsd::LSDGetExt;
 use crate::lsd::Level;
 use crate::lsd::Value;
+use crate::lsd::LSD;
+use crate::util::concat_os;
 use crate::util::split_file_name;
+use crate::util::BoolGuardExt;
 use crate::util::PushFrom;
 use crate::util::SplitIntoTwoWordsExt;
 use crate::util::TryReplace;
 use crate::BuildType;
+use crate::Dir;
+use crate::Emit;
+use crate::Target;
 
 //
 // Standard
@@ -136,6 +153,57 @@ impl FromStr for Optimize {
     }
 }
 
+//
+// Exceptions
+//
+
+/// Exception handling model of cl (`/EH`), without which C++ exceptions are not unwound.
+#[derive(Clone, Copy)]
+enum Exceptions {
+    /// Standard C++ exceptions only, with `extern "C"` functions assumed not to throw.
+    Sync,
+    /// Also structured (SEH) exceptions, ex. access violations.
+    Async,
+}
+
+impl Display for Exceptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use Exceptions::*;
+        write!(
+            f,
+            "{}",
+            match self {
+                Sync => "sync",
+                Async => "async",
+            }
+        )
+    }
+}
+
+impl FromStr for Exceptions {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use Exceptions::*;
+        let s = s.to_lowercase();
+        match s.as_str() {
+            "sync" | "sc" | "ehsc" | "/ehsc" | "c++" | "cpp" | "standard" => Ok(Sync),
+            "async" | "a" | "eha" | "/eha" | "seh" | "structured" => Ok(Async),
+            _ => Err(()),
+        }
+    }
+}
+
+impl Exceptions {
+    fn argument(self) -> &'static str {
+        use Exceptions::*;
+        match self {
+            Sync => "/EHsc",
+            Async => "/EHa",
+        }
+    }
+}
+
 //
 // LibraryType
 //
@@ -165,17 +233,498 @@ impl FromStr for LibraryType {
     }
 }
 
+//
+// Machine
+//
+
+/// Linker `/MACHINE` matching architecture of a target triple (ex. `aarch64-pc-windows-msvc`).
+fn machine(target: &str) -> Result<&'static str, io::Error> {
+    let (arch, _) = target
+        .split_once('-')
+        .unwrap_or((target, ""));
+    match arch {
+        "x86_64" | "amd64" | "x64" => Ok("X64"),
+        "i386" | "i586" | "i686" | "x86" => Ok("X86"),
+        "aarch64" | "arm64" => Ok("ARM64"),
+        "arm" | "armv7" | "thumbv7a" => Ok("ARM"),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "msvc cannot target architecture `{}` of toolchain target `{}`",
+                arch, target
+            ),
+        )),
+    }
+}
+
+//
+// DpiAwareness
+//
+
+/// How Windows scales windows of the application, declared in its manifest.
+#[derive(Clone, Copy)]
+enum DpiAwareness {
+    Unaware,
+    System,
+    PerMonitor,
+    PerMonitorV2,
+}
+
+impl Display for DpiAwareness {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use DpiAwareness::*;
+        write!(
+            f,
+            "{}",
+            match self {
+                Unaware => "unaware",
+                System => "system",
+                PerMonitor => "per_monitor",
+                PerMonitorV2 => "per_monitor_v2",
+            }
+        )
+    }
+}
+
+impl FromStr for DpiAwareness {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use DpiAwareness::*;
+        let s = s
+            .to_lowercase()
+            .replace(['-', ' '], "_");
+        match s.as_str() {
+            "unaware" | "false" => Ok(Unaware),
+            "system" | "true" => Ok(System),
+            "per_monitor" | "permonitor" => Ok(PerMonitor),
+            "per_monitor_v2" | "permonitorv2" => Ok(PerMonitorV2),
+            _ => Err(()),
+        }
+    }
+}
+
+impl DpiAwareness {
+    /// `dpiAware` (Windows 8.1 and older) and `dpiAwareness` (Windows 10 and newer) values.
+    fn manifest_values(self) -> (&'static str, &'static str) {
+        use DpiAwareness::*;
+        match self {
+            Unaware => ("false", "unaware"),
+            System => ("true", "system"),
+            PerMonitor => ("true/pm", "PerMonitor"),
+            PerMonitorV2 => (
+                "true/pm",
+                "PerMonitorV2, PerMonitor",
+            ),
+        }
+    }
+}
+
+//
+// UacLevel
+//
+
+/// Privileges the application asks for when started, declared in its manifest.
+#[derive(Clone, Copy)]
+enum UacLevel {
+    AsInvoker,
+    HighestAvailable,
+    RequireAdministrator,
+}
+
+impl Display for UacLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use UacLevel::*;
+        write!(
+            f,
+            "{}",
+            match self {
+                AsInvoker => "as_invoker",
+                HighestAvailable => "highest_available",
+                RequireAdministrator => "require_administrator",
+            }
+        )
+    }
+}
+
+impl FromStr for UacLevel {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use UacLevel::*;
+        let s = s
+            .to_lowercase()
+            .replace(['-', ' '], "_");
+        match s.as_str() {
+            "as_invoker" | "asinvoker" | "invoker" => Ok(AsInvoker),
+            "highest_available" | "highestavailable" | "highest" => Ok(HighestAvailable),
+            "require_administrator" | "requireadministrator" | "administrator" | "admin" =>
+                Ok(RequireAdministrator),
+            _ => Err(()),
+        }
+    }
+}
+
+impl UacLevel {
+    fn manifest_value(self) -> &'static str {
+        use UacLevel::*;
+        match self {
+            AsInvoker => "asInvoker",
+            HighestAvailable => "highestAvailable",
+            RequireAdministrator => "requireAdministrator",
+        }
+    }
+}
+
+//
+// Analyze
+//
+
+/// Code analysis of cl during builds (`/analyze`), also used by `buildpp analyze`.
+/// Written as `analyze true`, or as `analyze { ruleset ...  plugins [ ... ] }`.
+#[derive(Default, Clone)]
+struct Analyze {
+    enabled: bool,
+    /// `.ruleset` selecting the reported warnings, relative to the project if it is there
+    /// (ex. `NativeRecommendedRules.ruleset` of Visual Studio otherwise).
+    ruleset: Option<Value>,
+    /// Analysis plugins (ex. `EspXEngine.dll` for C++ Core Guidelines checks), found like
+    /// `ruleset`.
+    plugins: Rc<[Value]>,
+}
+
+impl Analyze {
+    fn parse(lsd: LSD) -> Result<Analyze, ParseError> {
+        use ParseError::*;
+
+        match lsd {
+            LSD::Value(enabled) => Ok(Analyze {
+                enabled: enabled
+                    .parse()
+                    .map_err(|_| InvalidValueForKey("analyze"))?,
+                ..Default::default()
+            }),
+
+            LSD::Level(level) => Ok(Analyze {
+                enabled: true,
+                ruleset: level.get_value(
+                    key!(ruleset),
+                    InvalidValueForKey("analyze.ruleset"),
+                )?,
+                plugins: match level.get_inner(key!(plugins)) {
+                    // Parse `plugins "plugins separated with spaces"`
+                    Some(LSD::Value(value)) => value
+                        .split_whitespace()
+                        .map(Rc::from)
+                        .collect(),
+
+                    // Parse `plugins [ each list item being a plugin ]`
+                    Some(LSD::Level(list)) => list
+                        .values()
+                        .map(|plugin| {
+                            plugin
+                                .to_value()
+                                .ok_or(InvalidValueForKey(
+                                    "analyze.plugins",
+                                ))
+                        })
+                        .collect::<Result<_, _>>()?,
+
+                    None => Rc::from([]),
+                },
+            }),
+        }
+    }
+
+    /// `file` relative to the project if it is there, as given otherwise (for cl to find it).
+    fn resolve(config: &Configuration, file: &str) -> OsString {
+        let in_project = config
+            .project_dir()
+            .join(file);
+        match in_project.is_file() {
+            true => in_project.into(),
+            false => file.into(),
+        }
+    }
+
+    fn arguments(&self, config: &Configuration) -> Vec<OsString> {
+        let mut args = Vec::new();
+        if !self.enabled {
+            return args;
+        }
+
+        args.push_from("/analyze");
+        if let Some(ruleset) = &self.ruleset {
+            args.push(concat_os(&[
+                "/analyze:ruleset".as_ref(),
+                &Self::resolve(config, ruleset),
+            ]));
+        }
+        for plugin in self
+            .plugins
+            .iter()
+        {
+            args.push(concat_os(&[
+                "/analyze:plugin".as_ref(),
+                &Self::resolve(config, plugin),
+            ]));
+        }
+        args
+    }
+}
+
+//
+// Driver
+//
+
+/// Compiler taking cl flags, cl itself or a drop-in replacement for it.
+#[derive(Default, Clone, Copy, PartialEq, Eq)]
+enum Driver {
+    #[default]
+    Cl,
+    /// LLVM's `clang-cl`, using the headers, libraries and linker of MSVC.
+    ClangCl,
+}
+
 //
 // Profile
 //
 
 #[derive(Default, Clone)]
 pub(crate) struct Profile {
+    driver: Driver,
     compiler_path: Option<Value>,
     standard: Option<Standard>,
     optimize: Option<Optimize>, // optional because we can omit flag
     openmp: bool,
+    /// Source and execution character sets are UTF-8 (`/utf-8`), instead of the code page of
+    /// the system.
+    utf8: bool,
+    exceptions: Option<Exceptions>,
+    /// Standard conformance of `/permissive-` (written as `permissive false`).
+    conformance: bool,
     library_type: LibraryType,
+    /// Emit debug information.
+    debug: bool,
+    /// Define `NDEBUG`, disabling `assert`s (written as `assertions false`).
+    ndebug: bool,
+    warnings: Option<Warnings>,
+    /// `/WX`.
+    warnings_as_errors: bool,
+    /// Embedded into the manifest of binaries.
+    dpi_awareness: Option<DpiAwareness>,
+    /// Embedded into the manifest of binaries.
+    uac_level: Option<UacLevel>,
+    /// `.ico` file (relative to the project) embedded into binaries as their icon.
+    icon: Option<Value>,
+    /// Ignored by clang-cl, `buildpp analyze` uses cppcheck for it instead.
+    analyze: Analyze,
+    runner: Option<Rc<[Value]>>,
+    environment: Environment,
+    defines: Defines,
+    /// Appended to compiler arguments as they are, for what the profile has no key for
+    /// (`extra_compiler_flags [ -march=native ]`).
+    extra_compiler_flags: Rc<[Value]>,
+    /// Appended to linker arguments as they are, except when archiving static libraries.
+    extra_linker_flags: Rc<[Value]>,
+    /// Compile the standard library of MSVC as the `std` module for `import std;`
+    /// (`import_std true`), which needs `standard c++latest` (the default with it). Only cl can
+    /// import it.
+    import_std: bool,
+    /// `sanitize address`, the only sanitizer of MSVC.
+    sanitizers: Rc<[Sanitizer]>,
+}
+
+impl Profile {
+    /// Same keys as the msvc profile, compiled by `clang-cl` instead of `cl`.
+    pub(super) fn create_clang_cl() -> Rc<dyn super::Profile> {
+        Rc::new(Self {
+            driver: Driver::ClangCl,
+            ..Self::default()
+        })
+    }
+
+    /// `/analyze` arguments, which only cl understands.
+    fn analyze_arguments(&self, config: &Configuration) -> Vec<OsString> {
+        match self.driver {
+            Driver::Cl => self
+                .analyze
+                .arguments(config),
+            Driver::ClangCl => Vec::new(),
+        }
+    }
+
+    /// Arguments affecting how code is compiled, which sources importing the standard library
+    /// module share with it.
+    fn code_arguments(&self) -> Vec<OsString> {
+        let mut args = Vec::new();
+
+        if self.openmp {
+            args.push_from("/openmp");
+        }
+
+        if !self
+            .sanitizers
+            .is_empty()
+        {
+            args.push_from("/fsanitize=address");
+        }
+
+        if let Some(opt_level) = &self.optimize {
+            args.push_from(format!("/O{}", opt_level));
+        }
+
+        // embedded into objects, so that there are no .pdb files to keep track of
+        if self.debug {
+            args.push_from("/Z7");
+        }
+
+        if self.ndebug {
+            args.push_from("/DNDEBUG");
+        }
+
+        for define in self
+            .defines
+            .iter()
+        {
+            args.push_from(format!("/D{}", define));
+        }
+
+        if let Some(std) = &self.standard {
+            args.push_from(format!("/std:{}", std));
+        }
+
+        if self.utf8 {
+            args.push_from("/utf-8");
+        }
+
+        if let Some(exceptions) = self.exceptions {
+            args.push_from(exceptions.argument());
+        }
+
+        if self.conformance {
+            args.push_from("/permissive-");
+        }
+
+        args
+    }
+
+    /// `std.ixx` of the MSVC toolset, found through `VCToolsInstallDir` (set by the Developer
+    /// Command Prompt).
+    fn std_module_source(&self) -> Result<PathBuf, io::Error> {
+        let source = env::var_os("VCToolsInstallDir")
+            .map(|tools_dir| {
+                Path::new(&tools_dir)
+                    .join("modules")
+                    .join("std.ixx")
+            })
+            .filter(|source| source.is_file());
+        source.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                "`import_std` needs `modules/std.ixx` of `VCToolsInstallDir` (Visual Studio 2022 \
+                 17.5 or newer, started from the Developer Command Prompt)",
+            )
+        })
+    }
+
+    /// Has link.exe generate the manifest of a binary and embed it, with `dpi_awareness`
+    /// merged in from a fragment written next to the object.
+    fn push_manifest_arguments(
+        &self,
+        target: &Target,
+        args: &mut Vec<OsString>,
+    ) -> Result<(), io::Error> {
+        if self
+            .dpi_awareness
+            .is_none()
+            && self
+                .uac_level
+                .is_none()
+        {
+            return Ok(());
+        }
+
+        args.push_from("/MANIFEST:EMBED");
+
+        if let Some(uac_level) = self.uac_level {
+            args.push_from(format!(
+                "/MANIFESTUAC:level='{}' uiAccess='false'",
+                uac_level.manifest_value()
+            ));
+        }
+
+        if let Some(dpi_awareness) = self.dpi_awareness {
+            let (dpi_aware, dpi_awareness) = dpi_awareness.manifest_values();
+            let fragment = target
+                .object_file
+                .with_extension("manifest");
+            fs::write(
+                &fragment,
+                format!(
+                    concat!(
+                        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n",
+                        "<assembly xmlns=\"urn:schemas-microsoft-com:asm.v1\" manifestVersion=\"1.0\">\n",
+                        "  <application xmlns=\"urn:schemas-microsoft-com:asm.v3\">\n",
+                        "    <windowsSettings>\n",
+                        "      <dpiAware xmlns=\"http://schemas.microsoft.com/SMI/2005/WindowsSettings\">{}</dpiAware>\n",
+                        "      <dpiAwareness xmlns=\"http://schemas.microsoft.com/SMI/2016/WindowsSettings\">{}</dpiAwareness>\n",
+                        "    </windowsSettings>\n",
+                        "  </application>\n",
+                        "</assembly>\n",
+                    ),
+                    dpi_aware, dpi_awareness
+                ),
+            )?;
+            args.push(concat_os(&[
+                "/MANIFESTINPUT:".as_ref(),
+                fragment.as_os_str(),
+            ]));
+        }
+
+        Ok(())
+    }
+}
+
+/// Compiles a resource script with `icon` as the application icon using `rc`,
+/// returns the `.res` to link with.
+fn compile_icon_resource(icon: &Path, target: &Target) -> Result<PathBuf, io::Error> {
+    let script = target
+        .object_file
+        .with_extension("rc");
+    let resource = target
+        .object_file
+        .with_extension("res");
+
+    // resource script strings escape backslashes
+    fs::write(
+        &script,
+        format!(
+            "1 ICON \"{}\"\n",
+            icon.to_string_lossy()
+                .replace('\\', "\\\\")
+        ),
+    )?;
+
+    let (stdout, stderr) = configuration::tool_output()?;
+    let status = Command::new("rc")
+        .arg("/nologo")
+        .arg("/fo")
+        .arg(&resource)
+        .arg(&script)
+        .stdin(Stdio::null())
+        .stdout(stdout)
+        .stderr(stderr)
+        .status()?;
+    if !status.success() {
+        return Err(io::Error::other(format!(
+            "rc could not compile the icon `{}` ({})",
+            icon.display(),
+            status
+        )));
+    }
+
+    Ok(resource)
 }
 
 impl super::Profile for Profile {
@@ -218,16 +767,146 @@ impl super::Profile for Profile {
                 InvalidValueForKey("openmp"),
             )?);
 
+        self.utf8
+            .try_replace(level.get_parse(
+                key!(utf8),
+                InvalidValueForKey("utf8"),
+            )?);
+
+        self.exceptions
+            .try_replace(level.get_parse(
+                key!(exceptions),
+                InvalidValueForKey("exceptions"),
+            )?);
+
+        self.conformance
+            .try_replace(
+                level
+                    .get_parse(
+                        key!(permissive),
+                        InvalidValueForKey("permissive"),
+                    )?
+                    .map(|permissive: bool| !permissive),
+            );
+
         self.library_type
             .try_replace(level.get_parse(
                 key!(library),
                 InvalidValueForKey("library"),
             )?);
 
+        self.debug
+            .try_replace(level.get_parse(
+                key!(debug),
+                InvalidValueForKey("debug"),
+            )?);
+
+        self.ndebug
+            .try_replace(
+                level
+                    .get_parse(
+                        key!(assertions),
+                        InvalidValueForKey("assertions"),
+                    )?
+                    .map(|assertions: bool| !assertions),
+            );
+
+        let (warnings, warnings_as_errors) = super::parse_warnings(&level)?;
+        self.warnings
+            .try_replace(warnings);
+        self.warnings_as_errors
+            .try_replace(warnings_as_errors);
+
+        self.dpi_awareness
+            .try_replace(level.get_parse(
+                key!(dpi_awareness),
+                InvalidValueForKey("dpi_awareness"),
+            )?);
+
+        self.uac_level
+            .try_replace(level.get_parse(
+                key!(uac_level),
+                InvalidValueForKey("uac_level"),
+            )?);
+
+        self.icon
+            .try_replace(level.get_value(
+                key!(icon),
+                InvalidValueForKey("icon"),
+            )?);
+
+        if let Some(analyze) = level.get_inner(key!(analyze)) {
+            self.analyze = Analyze::parse(analyze)?;
+        }
+
+        self.runner
+            .try_replace(super::parse_runner(&level)?);
+
+        self.environment
+            .apply(&level)?;
+
+        self.defines
+            .apply(&level)?;
+
+        self.extra_compiler_flags
+            .try_replace(super::parse_arguments(
+                &level,
+                "extra_compiler_flags",
+            )?);
+        self.extra_linker_flags
+            .try_replace(super::parse_arguments(
+                &level,
+                "extra_linker_flags",
+            )?);
+
+        self.import_std
+            .try_replace(level.get_parse(
+                key!(import_std),
+                InvalidValueForKey("import_std"),
+            )?);
+        if self.import_std {
+            (self.driver == Driver::Cl).or_err(InvalidValueForKey(
+                "import_std",
+            ))?;
+            // unless given, the standard is the one the module needs
+            match self.standard {
+                None => self.standard = Some(Standard::CPPLatest),
+                Some(standard) => (standard == Standard::CPPLatest).or_err(
+                    ImportStdNeedsStandard("c++latest"),
+                )?,
+            }
+        }
+
+        self.sanitizers
+            .try_replace(super::parse_sanitizers(
+                &level,
+            )?);
+        self.sanitizers
+            .iter()
+            .all(|sanitizer| *sanitizer == Sanitizer::Address)
+            .or_err(InvalidValueForKey("sanitize"))?;
+
         Ok(())
     }
 
-    fn src_file_suffix(&self) -> &'static str { ".cpp" }
+    fn preset(&self, name: &str) -> Level {
+        let preset: &[(&str, &str)] = match name {
+            "release" => &[("optimize", "2"), ("assertions", "false")],
+            "debug" => &[("debug", "true")],
+            _ => &[],
+        };
+        preset
+            .iter()
+            .map(|(key, value)| {
+                (
+                    Value::from(*key),
+                    LSD::Value(Value::from(*value)),
+                )
+            })
+            .collect()
+    }
+
+    fn src_file_suffixes(&self) -> &'static [&'static str] { &[".cpp", ".cc", ".cxx", ".c++"] }
 
     fn artifact_prefix(&self, _build_type: BuildType) -> &'static str { "" }
 
@@ -247,117 +926,655 @@ impl super::Profile for Profile {
         self.compiler_path
             .as_ref()
             .map(Rc::as_ref)
-            .unwrap_or("cl")
+            .unwrap_or(match self.driver {
+                Driver::Cl => "cl",
+                Driver::ClangCl => "clang-cl",
+            })
+    }
+
+    fn uses_response_file(&self) -> bool { true }
+
+    fn installation_hint(&self) -> &'static str {
+        match (self.driver, cfg!(windows)) {
+            (Driver::ClangCl, true) =>
+                "install LLVM (ex. `winget install LLVM.LLVM`) and Visual Studio (or Build Tools) \
+                 for the headers and libraries, then run from a Developer Command Prompt so that \
+                 `clang-cl` finds them, or set `compiler_path` in the profile",
+            (Driver::ClangCl, false) =>
+                "install LLVM (ex. `apt install clang-tools`) and point `sysroot` of a toolchain \
+                 at the MSVC headers and libraries, or set `compiler_path` in the profile",
+            (Driver::Cl, true) =>
+                "install Visual Studio (or Build Tools) with the \"Desktop development with C++\" \
+                 workload, then run from a Developer Command Prompt so that `cl` is in PATH, \
+                 or set `compiler_path` in the profile",
+            (Driver::Cl, false) =>
+                "MSVC is only available on Windows, use a different profile on this platform \
+                 or set `compiler_path` to a compatible wrapper",
+        }
+    }
+
+    fn object_suffix(&self) -> &'static str { ".obj" }
+
+    fn ninja_deps(&self, _depfile: &Path) -> Option<(&'static str, Vec<OsString>)> {
+        Some((
+            "msvc",
+            vec!["/showIncludes".into()],
+        ))
+    }
+
+    fn emit_suffix(&self, emit: Emit) -> Option<&'static str> {
+        match emit {
+            Emit::Preprocessed => Some(".i"),
+            Emit::Asm => Some(".asm"),
+            Emit::Ir => None,
+        }
     }
 
-    fn compiler_arguments(
+    fn emit_arguments(&self, emit: Emit, output_file: &Path, arguments: &mut Vec<OsString>) {
+        let object = concat_os(&["/Fo".as_ref(), output_file.as_os_str()]);
+        for argument in arguments.iter_mut() {
+            match emit {
+                Emit::Preprocessed if argument == "/c" => *argument = "/P".into(),
+                Emit::Preprocessed if *argument == object =>
+                    *argument = concat_os(&["/Fi".as_ref(), output_file.as_os_str()]),
+                // still compiles, the object is kept next to the listing
+                Emit::Asm if *argument == object =>
+                    *argument = concat_os(&[
+                        "/Fo".as_ref(),
+                        output_file
+                            .with_extension("obj")
+                            .as_os_str(),
+                    ]),
+                _ => {},
+            }
+        }
+        if emit == Emit::Asm {
+            arguments.push_from("/FA");
+            arguments.push(concat_os(&[
+                "/Fa".as_ref(),
+                output_file.as_os_str(),
+            ]));
+        }
+    }
+
+    fn analysis_only_arguments(&self) -> Option<Vec<OsString>> {
+        (self.driver == Driver::Cl
+            && self
+                .analyze
+                .enabled)
+            .then(|| vec!["/analyze:only".into()])
+    }
+
+    fn compile_arguments(
         &self,
         config: &Configuration,
-        build_type: BuildType,
+        target: &Target,
         selected_profile: &str,
-    ) -> Result<Vec<Value>, io::Error> {
+    ) -> Result<Vec<OsString>, io::Error> {
         let mut args = Vec::new();
 
-        // Compiler
+        args.push_from("/c");
 
-        if self.openmp {
-            args.push_from("/openmp");
+        args.extend(self.code_arguments());
+
+        if let Some(warnings) = self.warnings {
+            for warning in warnings.msvc_arguments() {
+                args.push_from(*warning);
+            }
         }
 
-        if let Some(opt_level) = &self.optimize {
-            args.push_from(format!("/O{}", opt_level));
+        if self.warnings_as_errors {
+            args.push_from("/WX");
         }
 
-        if let Some(std) = &self.standard {
-            args.push_from(format!("/std:{}", std));
+        if self.import_std {
+            args.push_from("/reference");
+            args.push(concat_os(&[
+                "std=".as_ref(),
+                config
+                    .target_std_module_file(selected_profile, ".ifc")
+                    .as_os_str(),
+            ]));
         }
 
-        let mut include_dirs = Vec::new();
-        let mut lib_dirs = Vec::new();
-        let mut libs = Vec::new();
+        let include_dirs = config
+            .dependency_dirs(selected_profile)?
+            .into_iter()
+            .map(|(include_dir, _)| include_dir)
+            .chain(
+                target
+                    .include_dirs
+                    .iter()
+                    .cloned(),
+            );
+        let toolchain = config.toolchain();
+        let sysroot_include_dir = toolchain
+            .and_then(|toolchain| {
+                toolchain
+                    .sysroot
+                    .as_ref()
+            })
+            .map(|sysroot| Dir::from(sysroot.join("include")));
+        for include in include_dirs.chain(sysroot_include_dir) {
+            args.push_from("/I");
+            args.push_from(&*include);
+        }
+
+        if let Some(toolchain) = toolchain {
+            // cl targets what the cl being ran was built for, clang-cl can target anything
+            if let (Driver::ClangCl, Some(target)) = (self.driver, &toolchain.target) {
+                args.push_from(format!("--target={}", target));
+            }
+
+            for flag in toolchain
+                .flags
+                .iter()
+            {
+                args.push_from(&**flag);
+            }
+        }
+
+        args.extend(self.analyze_arguments(config));
 
-        for (alias, dep) in config
-            .dependencies()
+        args.push(concat_os(&[
+            "/Fo".as_ref(),
+            target
+                .object_file
+                .as_os_str(),
+        ]));
+
+        // cl only knows `.c++` sources are C++ when told so
+        match split_file_name(
+            target
+                .src_file
+                .as_os_str(),
+        )
+        .1 == "c++"
+        {
+            true => args.push(concat_os(&[
+                "/Tp".as_ref(),
+                target
+                    .src_file
+                    .as_os_str(),
+            ])),
+            false => args.push_from(&*target.src_file),
+        }
+
+        for flag in self
+            .extra_compiler_flags
             .iter()
         {
-            let version = dep.current_version()?; // TODO move this to dep's parse
-            let profile = dep.current_profile(selected_profile)?;
+            args.push_from(&**flag);
+        }
 
-            let include_dir = config.cache_dep_include_dir(
-                alias.clone(),
-                version.clone(),
-                &profile,
-            );
-            let lib_dir = config.cache_dep_lib_dir(
-                alias.clone(),
-                version.clone(),
-                &profile,
-            );
+        Ok(args)
+    }
 
-            include_dirs.push(format!(
-                "{}",
-                include_dir.display(),
-            ));
-            lib_dirs.push(format!(
-                "{}",
-                lib_dir.display(),
-            ));
+    fn std_module_arguments(
+        &self,
+        config: &Configuration,
+        _target: &Target,
+        selected_profile: &str,
+    ) -> Result<Option<Vec<OsString>>, io::Error> {
+        if !self.import_std {
+            return Ok(None);
+        }
+
+        let mut args = Vec::new();
+
+        args.push_from("/c");
+
+        args.extend(self.code_arguments());
+
+        if let Some(toolchain) = config.toolchain() {
+            for flag in toolchain
+                .flags
+                .iter()
+            {
+                args.push_from(&**flag);
+            }
+        }
+
+        args.push_from("/ifcOutput");
+        args.push_from(&*config.target_std_module_file(selected_profile, ".ifc"));
+
+        args.push(concat_os(&[
+            "/Fo".as_ref(),
+            config
+                .target_std_module_file(
+                    selected_profile,
+                    self.object_suffix(),
+                )
+                .as_os_str(),
+        ]));
+
+        args.push_from(self.std_module_source()?);
 
-            for lib in fs::read_dir(lib_dir)? {
-                let filename = lib?
-                    .file_name()
-                    .to_str()
-                    .unwrap()
-                    .to_string();
+        for flag in self
+            .extra_compiler_flags
+            .iter()
+        {
+            args.push_from(&**flag);
+        }
+
+        Ok(Some(args))
+    }
+
+    fn link_arguments(
+        &self,
+        config: &Configuration,
+        target: &Target,
+        selected_profile: &str,
+    ) -> Result<Vec<OsString>, io::Error> {
+        let mut args = Vec::new();
+
+        let mut lib_dirs = Vec::new();
+        let mut libs = Vec::new();
+        let mut whole_archives = Vec::new();
+
+        for (lib_dir, link) in config.dependency_lib_dirs(selected_profile)? {
+            for lib in fs::read_dir(&lib_dir)? {
+                let filename = lib?.file_name();
                 let (_, ext) = split_file_name(&filename);
-                if ext == "lib" || ext == "a" || ext == "exp" {
-                    libs.push(filename.to_string());
+                // `.exp` next to import libraries belongs to the DLL itself, not to its users
+                if ext == "lib" || ext == "a" {
+                    if link.whole_archive {
+                        whole_archives.push(filename.clone());
+                    }
+                    libs.push(filename);
                 }
             }
-        }
 
-        for include in include_dirs {
-            args.push_from("/I");
-            args.push_from(include);
+            lib_dirs.push(lib_dir);
         }
 
-        args.push_from(
-            config
-                .src_file(build_type, self)
-                .display()
-                .to_string(),
-        );
+        args.push_from(&*target.object_file);
 
-        // Linker
+        if self.import_std {
+            args.push_from(
+                &*config.target_std_module_file(
+                    selected_profile,
+                    self.object_suffix(),
+                ),
+            );
+        }
 
         for lib in libs {
             args.push_from(lib);
         }
 
+        if target.build_type == BuildType::Binary {
+            if let Some(icon) = &self.icon {
+                args.push_from(compile_icon_resource(
+                    &config
+                        .project_dir()
+                        .join(&**icon),
+                    target,
+                )?);
+            }
+        }
+
+        // cl picks the runtime of the sanitizer to link
+        if !self
+            .sanitizers
+            .is_empty()
+        {
+            args.push_from("/fsanitize=address");
+        }
+
         args.push_from("/link");
 
-        args.push_from(format!(
-            "/OUT:{}",
-            config
-                .target_artifact_file(build_type, selected_profile, self)
-                .display(),
-        ));
+        args.push(concat_os(&[
+            "/OUT:".as_ref(),
+            target
+                .artifact_file
+                .as_os_str(),
+        ]));
 
-        if build_type == BuildType::Library {
+        if target.build_type == BuildType::Library {
             use LibraryType::*;
             args.push_from(match self.library_type {
                 Shared => "/DLL",
                 Static => todo!("static msvc libs"),
             });
+
+            // written next to the DLL otherwise, `.exp` goes along with it
+            if let Shared = self.library_type {
+                args.push(concat_os(&[
+                    "/IMPLIB:".as_ref(),
+                    config
+                        .target_lib_dir(selected_profile)
+                        .join(format!(
+                            "{}.lib",
+                            config.project_name()
+                        ))
+                        .as_os_str(),
+                ]));
+            }
+        }
+
+        if self.debug {
+            args.push_from("/DEBUG");
+        }
+
+        if target.build_type == BuildType::Binary {
+            self.push_manifest_arguments(target, &mut args)?;
+        }
+
+        // optimized builds imply /OPT:REF, which does not go together with incremental linking,
+        // and neither does the address sanitizer
+        if self
+            .optimize
+            .is_none()
+            && self
+                .sanitizers
+                .is_empty()
+        {
+            args.push_from("/INCREMENTAL");
+        }
+
+        if let Some(toolchain) = config.toolchain() {
+            if let Some(target) = &toolchain.target {
+                args.push_from(format!(
+                    "/MACHINE:{}",
+                    machine(target)?
+                ));
+            }
+
+            if let Some(sysroot) = &toolchain.sysroot {
+                lib_dirs.push(Dir::from(sysroot.join("lib")));
+            }
         }
 
         for lib_dir in lib_dirs {
-            args.push_from(format!(
-                "/LIBPATH:{}",
-                lib_dir
-            ));
+            args.push(concat_os(&[
+                "/LIBPATH:".as_ref(),
+                lib_dir.as_os_str(),
+            ]));
+        }
+
+        for lib in whole_archives {
+            args.push(concat_os(&[
+                "/WHOLEARCHIVE:".as_ref(),
+                &lib,
+            ]));
+        }
+
+        for flag in self
+            .extra_linker_flags
+            .iter()
+        {
+            args.push_from(&**flag);
         }
 
         Ok(args)
     }
+
+    fn runner(&self) -> Option<Rc<[Value]>> {
+        self.runner
+            .clone()
+    }
+
+    fn run_environment(&self) -> Vec<(&'static str, &'static str)> {
+        super::sanitize_run_environment(&self.sanitizers)
+    }
+
+    fn environment(&self) -> &Environment { &self.environment }
+
+    fn cmake_language(&self) -> &'static str {
+        use Standard::*;
+        match self.standard {
+            Some(C11) | Some(C17) => "C",
+            _ => "CXX",
+        }
+    }
+
+    fn cmake_library_kind(&self) -> &'static str {
+        use LibraryType::*;
+        match self.library_type {
+            Shared => "SHARED",
+            Static => "STATIC",
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        match self.driver {
+            Driver::Cl => "msvc",
+            Driver::ClangCl => "clang-cl",
+        }
+    }
+
+    fn settings(&self) -> Vec<(&'static str, Value)> {
+        let mut settings = Vec::new();
+        if let Some(compiler_path) = &self.compiler_path {
+            settings.push((
+                "compiler_path",
+                compiler_path.clone(),
+            ));
+        }
+        if let Some(std) = &self.standard {
+            settings.push((
+                "standard",
+                std.to_string()
+                    .into(),
+            ));
+        }
+        if let Some(optimize) = &self.optimize {
+            settings.push((
+                "optimize",
+                optimize
+                    .to_string()
+                    .into(),
+            ));
+        }
+        settings.push((
+            "openmp",
+            self.openmp
+                .to_string()
+                .into(),
+        ));
+        settings.push((
+            "debug",
+            self.debug
+                .to_string()
+                .into(),
+        ));
+        settings.push((
+            "assertions",
+            (!self.ndebug)
+                .to_string()
+                .into(),
+        ));
+        settings.push((
+            "utf8",
+            self.utf8
+                .to_string()
+                .into(),
+        ));
+        if let Some(exceptions) = &self.exceptions {
+            settings.push((
+                "exceptions",
+                exceptions
+                    .to_string()
+                    .into(),
+            ));
+        }
+        settings.push((
+            "permissive",
+            (!self.conformance)
+                .to_string()
+                .into(),
+        ));
+        if let Some(dpi_awareness) = &self.dpi_awareness {
+            settings.push((
+                "dpi_awareness",
+                dpi_awareness
+                    .to_string()
+                    .into(),
+            ));
+        }
+        if let Some(uac_level) = &self.uac_level {
+            settings.push((
+                "uac_level",
+                uac_level
+                    .to_string()
+                    .into(),
+            ));
+        }
+        if let Some(icon) = &self.icon {
+            settings.push(("icon", icon.clone()));
+        }
+        if let Some(warnings) = &self.warnings {
+            settings.push((
+                "warnings",
+                warnings
+                    .to_string()
+                    .into(),
+            ));
+        }
+        settings.push((
+            "warnings_as_errors",
+            self.warnings_as_errors
+                .to_string()
+                .into(),
+        ));
+        settings.push((
+            "library",
+            match self.library_type {
+                LibraryType::Shared => "shared",
+                LibraryType::Static => "static",
+            }
+            .into(),
+        ));
+        if self
+            .analyze
+            .enabled
+        {
+            settings.push(("analyze", "true".into()));
+        }
+        if let Some(ruleset) = &self
+            .analyze
+            .ruleset
+        {
+            settings.push((
+                "analyze.ruleset",
+                ruleset.clone(),
+            ));
+        }
+        if !self
+            .analyze
+            .plugins
+            .is_empty()
+        {
+            settings.push((
+                "analyze.plugins",
+                self.analyze
+                    .plugins
+                    .join(" ")
+                    .into(),
+            ));
+        }
+        if let Some(runner) = &self.runner {
+            settings.push((
+                "runner",
+                runner
+                    .join(" ")
+                    .into(),
+            ));
+        }
+        self.environment
+            .settings(&mut settings);
+        self.defines
+            .settings(&mut settings);
+        if !self
+            .extra_compiler_flags
+            .is_empty()
+        {
+            settings.push((
+                "extra_compiler_flags",
+                self.extra_compiler_flags
+                    .join(" ")
+                    .into(),
+            ));
+        }
+        if !self
+            .extra_linker_flags
+            .is_empty()
+        {
+            settings.push((
+                "extra_linker_flags",
+                self.extra_linker_flags
+                    .join(" ")
+                    .into(),
+            ));
+        }
+        settings.push((
+            "import_std",
+            self.import_std
+                .to_string()
+                .into(),
+        ));
+        if !self
+            .sanitizers
+            .is_empty()
+        {
+            settings.push((
+                "sanitize",
+                super::sanitize_setting(&self.sanitizers),
+            ));
+        }
+        settings
+    }
+
+    fn cmake_properties(&self) -> Vec<(&'static str, Value)> {
+        use Standard::*;
+        let mut properties = Vec::new();
+        if let Some(std) = &self.standard {
+            properties.push(match std {
+                CPP14 => ("CXX_STANDARD", "14".into()),
+                CPP17 => ("CXX_STANDARD", "17".into()),
+                CPP20 => ("CXX_STANDARD", "20".into()),
+                CPPLatest => ("CXX_STANDARD", "23".into()),
+                C11 => ("C_STANDARD", "11".into()),
+                C17 => ("C_STANDARD", "17".into()),
+            });
+        }
+        properties
+    }
+
+    fn defines(&self) -> &Defines { &self.defines }
+
+    fn clang_flags(&self) -> Vec<Value> {
+        use Standard::*;
+        let mut flags = Vec::new();
+        flags.push(
+            match self.standard {
+                Some(C11) | Some(C17) => "-xc",
+                _ => "-xc++",
+            }
+            .into(),
+        );
+        if let Some(std) = &self.standard {
+            flags.push(
+                match std {
+                    CPP14 => "-std=c++14",
+                    CPP17 => "-std=c++17",
+                    CPP20 => "-std=c++20",
+                    CPPLatest => "-std=c++2c",
+                    C11 => "-std=c11",
+                    C17 => "-std=c17",
+                }
+                .into(),
+            );
+        }
+        if let Some(warnings) = self.warnings {
+            for warning in warnings.gcc_arguments() {
+                flags.push((*warning).into());
+            }
+        }
+        if self.openmp {
+            flags.push("-fopenmp".into());
+        }
+        flags
+    }
 }
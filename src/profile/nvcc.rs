@@ -1,20 +1,29 @@
+use std::ffi::OsString;
 use std::fmt::Display;
 use std::fs;
 use std::io;
+use std::path::Path;
 use std::rc::Rc;
 use std::str::FromStr;
 
+use super::Defines;
+use super::Environment;
 use super::ParseError;
+use super::Warnings;
 use crate::configuration::Configuration;
 use crate::key;
 use crate::lsd::LSDGetExt;
 use crate::lsd::Level;
 use crate::lsd::Value;
+use crate::lsd::LSD;
+use crate::util::concat_os;
 use crate::util::split_file_name;
 use crate::util::PushFrom;
 use crate::util::SplitIntoTwoWordsExt;
 use crate::util::TryReplace;
 use crate::BuildType;
+use crate::Emit;
+use crate::Target;
 
 //
 // Standard
@@ -180,6 +189,112 @@ impl FromStr for LibraryType {
     }
 }
 
+//
+// Output
+//
+
+/// What the artifact is, `emit` of the profile.
+#[derive(Default, Clone, Copy)]
+enum Output {
+    /// Binary or library, linked with the host code.
+    #[default]
+    Linked,
+    /// Device code modules, loaded at runtime by the CUDA driver (ex. `cuModuleLoad`).
+    Ptx,
+    Cubin,
+    Fatbin,
+}
+
+impl TryReplace for Output {
+    type With = Output;
+}
+
+impl Display for Output {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use Output::*;
+        write!(
+            f,
+            "{}",
+            match self {
+                Linked => "linked",
+                Ptx => "ptx",
+                Cubin => "cubin",
+                Fatbin => "fatbin",
+            }
+        )
+    }
+}
+
+impl FromStr for Output {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use Output::*;
+        let s = s.to_lowercase();
+        match s.as_str() {
+            "linked" | "link" => Ok(Linked),
+            "ptx" => Ok(Ptx),
+            "cubin" => Ok(Cubin),
+            "fatbin" => Ok(Fatbin),
+            _ => Err(()),
+        }
+    }
+}
+
+impl Output {
+    /// nvcc mode replacing `--compile` to make the module, `None` when linking instead.
+    fn mode(self) -> Option<&'static str> {
+        use Output::*;
+        match self {
+            Linked => None,
+            Ptx => Some("--ptx"),
+            Cubin => Some("--cubin"),
+            Fatbin => Some("--fatbin"),
+        }
+    }
+
+    fn suffix(self) -> Option<&'static str> {
+        use Output::*;
+        match self {
+            Linked => None,
+            Ptx => Some(".ptx"),
+            Cubin => Some(".cubin"),
+            Fatbin => Some(".fatbin"),
+        }
+    }
+}
+
+/// Flags of `key`, either separated with spaces or a list.
+fn parse_flags(level: &Level, key: &'static str) -> Result<Option<Rc<[Value]>>, ParseError> {
+    use ParseError::*;
+
+    level
+        .get_inner([Value::from(key)])
+        .map(|flags| match flags {
+            LSD::Value(value) => Ok(value
+                .split_whitespace()
+                .map(Rc::from)
+                .collect()),
+            LSD::Level(list) => list
+                .values()
+                .map(|flag| {
+                    flag.to_value()
+                        .ok_or(InvalidValueForKey(key))
+                })
+                .collect(),
+        })
+        .transpose()
+}
+
+/// `arguments` of [`super::Profile::compile_arguments`] with `--compile` replaced by `mode`.
+fn replace_mode(arguments: &mut [OsString], mode: &str) {
+    for argument in arguments.iter_mut() {
+        if argument == "--compile" {
+            *argument = mode.into();
+        }
+    }
+}
+
 //
 // Profile
 //
@@ -191,6 +306,69 @@ pub(crate) struct Profile {
     optimize: Option<Optimize>,
     optimize_device: bool,
     library_type: LibraryType,
+    /// Emit debug information.
+    debug: bool,
+    /// Define `NDEBUG`, disabling `assert`s (written as `assertions false`).
+    ndebug: bool,
+    /// Reported by the host compiler, nvcc itself only reports its own warnings or none.
+    warnings: Option<Warnings>,
+    /// `--Werror all-warnings`.
+    warnings_as_errors: bool,
+    /// Shared libraries are linked as `lib<name>.so.<major>.<minor>.<patch>` with soname
+    /// `lib<name>.so.<major>` (Linux only).
+    versioned_so: bool,
+    /// Artifact is made of device code only (ex. `ptx`), instead of linked.
+    output: Output,
+    /// Passed on to the host compiler (`-Xcompiler`), when compiling and linking.
+    host_flags: Rc<[Value]>,
+    /// Passed on to the device code assembler (`-Xptxas`).
+    device_flags: Rc<[Value]>,
+    runner: Option<Rc<[Value]>>,
+    environment: Environment,
+    defines: Defines,
+    /// Appended to compiler arguments as they are, for what the profile has no key for
+    /// (`extra_compiler_flags [ -march=native ]`).
+    extra_compiler_flags: Rc<[Value]>,
+    /// Appended to linker arguments as they are, except when archiving static libraries.
+    extra_linker_flags: Rc<[Value]>,
+}
+
+impl Profile {
+    fn host_arguments(&self) -> Vec<OsString> {
+        let mut args = Vec::new();
+        for flag in self
+            .host_flags
+            .iter()
+        {
+            args.push_from("--compiler-options");
+            args.push_from(&**flag);
+        }
+        args
+    }
+
+    /// Cross-compiling is done by the host compiler, which nvcc passes these on to.
+    fn toolchain_arguments(&self, config: &Configuration) -> Vec<OsString> {
+        let mut args = Vec::new();
+        let Some(toolchain) = config.toolchain() else {
+            return args;
+        };
+
+        // GNU cross compilers are named after their target (ex. `arm-none-eabi-g++`)
+        if let Some(target) = &toolchain.target {
+            args.push_from("--compiler-bindir");
+            args.push_from(format!("{}-g++", target));
+        }
+
+        if let Some(sysroot) = &toolchain.sysroot {
+            args.push_from("--compiler-options");
+            args.push(concat_os(&[
+                "--sysroot=".as_ref(),
+                sysroot.as_os_str(),
+            ]));
+        }
+
+        args
+    }
 }
 
 impl super::Profile for Profile {
@@ -239,10 +417,89 @@ impl super::Profile for Profile {
                 InvalidValueForKey("library"),
             )?);
 
+        self.debug
+            .try_replace(level.get_parse(
+                key!(debug),
+                InvalidValueForKey("debug"),
+            )?);
+
+        self.ndebug
+            .try_replace(
+                level
+                    .get_parse(
+                        key!(assertions),
+                        InvalidValueForKey("assertions"),
+                    )?
+                    .map(|assertions: bool| !assertions),
+            );
+
+        let (warnings, warnings_as_errors) = super::parse_warnings(&level)?;
+        self.warnings
+            .try_replace(warnings);
+        self.warnings_as_errors
+            .try_replace(warnings_as_errors);
+
+        self.versioned_so
+            .try_replace(level.get_parse(
+                key!(versioned_so),
+                InvalidValueForKey("versioned_so"),
+            )?);
+
+        self.output
+            .try_replace(level.get_parse(
+                key!(emit),
+                InvalidValueForKey("emit"),
+            )?);
+
+        if let Some(host_flags) = parse_flags(&level, "host_flags")? {
+            self.host_flags = host_flags;
+        }
+
+        if let Some(device_flags) = parse_flags(&level, "device_flags")? {
+            self.device_flags = device_flags;
+        }
+
+        self.runner
+            .try_replace(super::parse_runner(&level)?);
+
+        self.environment
+            .apply(&level)?;
+
+        self.defines
+            .apply(&level)?;
+
+        self.extra_compiler_flags
+            .try_replace(super::parse_arguments(
+                &level,
+                "extra_compiler_flags",
+            )?);
+        self.extra_linker_flags
+            .try_replace(super::parse_arguments(
+                &level,
+                "extra_linker_flags",
+            )?);
+
         Ok(())
     }
 
-    fn src_file_suffix(&self) -> &'static str { ".cu" }
+    fn preset(&self, name: &str) -> Level {
+        let preset: &[(&str, &str)] = match name {
+            "release" => &[("optimize", "2"), ("assertions", "false")],
+            "debug" => &[("optimize", "0"), ("debug", "true")],
+            _ => &[],
+        };
+        preset
+            .iter()
+            .map(|(key, value)| {
+                (
+                    Value::from(*key),
+                    LSD::Value(Value::from(*value)),
+                )
+            })
+            .collect()
+    }
+
+    fn src_file_suffixes(&self) -> &'static [&'static str] { &[".cu", ".cpp", ".cc", ".cxx"] }
 
     #[cfg(target_os = "windows")]
     fn artifact_prefix(&self, _build_type: BuildType) -> &'static str { "" }
@@ -251,6 +508,12 @@ impl super::Profile for Profile {
     fn artifact_suffix(&self, build_type: BuildType) -> &'static str {
         use BuildType::*;
         use LibraryType::*;
+        if let Some(suffix) = self
+            .output
+            .suffix()
+        {
+            return suffix;
+        }
         match build_type {
             Binary => ".exe",
             Library => match self.library_type {
@@ -260,28 +523,89 @@ impl super::Profile for Profile {
         }
     }
 
-    #[cfg(target_os = "linux")]
+    #[cfg(not(target_os = "windows"))]
     fn artifact_prefix(&self, build_type: BuildType) -> &'static str {
         use BuildType::*;
+        if self
+            .output
+            .suffix()
+            .is_some()
+        {
+            return "";
+        }
         match build_type {
             Binary => "",
             Library => "lib",
         }
     }
 
-    #[cfg(target_os = "linux")]
+    #[cfg(not(target_os = "windows"))]
     fn artifact_suffix(&self, build_type: BuildType) -> &'static str {
         use BuildType::*;
         use LibraryType::*;
+        if let Some(suffix) = self
+            .output
+            .suffix()
+        {
+            return suffix;
+        }
         match build_type {
             Binary => "",
-            Library => match self.library_type {
-                Shared => ".so",
-                Static => ".a",
+            Library => match (
+                self.library_type,
+                cfg!(target_os = "macos"),
+            ) {
+                (Shared, false) => ".so",
+                (Shared, true) => ".dylib",
+                (Static, _) => ".a",
             },
         }
     }
 
+    #[cfg(target_os = "linux")]
+    fn artifact_versions(&self, config: &Configuration, build_type: BuildType) -> Vec<Value> {
+        if !self.versioned_so
+            || build_type != BuildType::Library
+            || !matches!(self.output, Output::Linked)
+            || !matches!(
+                self.library_type,
+                LibraryType::Shared
+            )
+        {
+            return Vec::new();
+        }
+
+        // pre-release and build metadata (ex. `1.2.3-rc.1`) do not take part, missing parts are 0
+        let version = config.version();
+        let mut parts = version
+            .split(['-', '+'])
+            .next()
+            .unwrap_or_default()
+            .split('.');
+        let mut part = || {
+            parts
+                .next()
+                .filter(|part| !part.is_empty())
+                .unwrap_or("0")
+        };
+        let (major, minor, patch) = (part(), part(), part());
+
+        let name = format!(
+            "{}{}{}",
+            self.artifact_prefix(build_type),
+            config.project_name(),
+            self.artifact_suffix(build_type)
+        );
+        vec![
+            format!(
+                "{}.{}.{}.{}",
+                name, major, minor, patch
+            )
+            .into(),
+            format!("{}.{}", name, major).into(),
+        ]
+    }
+
     fn compiler_command(&self) -> &str {
         self.compiler_path
             .as_ref()
@@ -289,14 +613,64 @@ impl super::Profile for Profile {
             .unwrap_or("nvcc")
     }
 
-    fn compiler_arguments(
+    fn installation_hint(&self) -> &'static str {
+        match cfg!(windows) {
+            true =>
+                "install the CUDA Toolkit from https://developer.nvidia.com/cuda-downloads \
+                 (it adds `%CUDA_PATH%\\bin` to PATH), or set `compiler_path` in the profile",
+            false =>
+                "install the CUDA Toolkit from https://developer.nvidia.com/cuda-downloads \
+                 or your package manager, and add its `bin` directory (usually \
+                 `/usr/local/cuda/bin`) to PATH, or set `compiler_path` in the profile",
+        }
+    }
+
+    fn object_suffix(&self) -> &'static str {
+        match cfg!(windows) {
+            true => ".obj",
+            false => ".o",
+        }
+    }
+
+    fn ninja_deps(&self, depfile: &Path) -> Option<(&'static str, Vec<OsString>)> {
+        Some((
+            "gcc",
+            vec![
+                "--generate-dependencies-with-compile".into(),
+                "--dependency-output".into(),
+                depfile.into(),
+            ],
+        ))
+    }
+
+    fn emit_suffix(&self, emit: Emit) -> Option<&'static str> {
+        match emit {
+            Emit::Preprocessed => Some(".ii"),
+            // assembly of the device code, host code is only ever given to the host compiler
+            Emit::Asm => Some(".ptx"),
+            Emit::Ir => None,
+        }
+    }
+
+    fn emit_arguments(&self, emit: Emit, _output_file: &Path, arguments: &mut Vec<OsString>) {
+        let mode = match emit {
+            Emit::Preprocessed => "--preprocess",
+            Emit::Asm => "--ptx",
+            Emit::Ir => return,
+        };
+        replace_mode(arguments, mode);
+    }
+
+    fn compile_arguments(
         &self,
         config: &Configuration,
-        build_type: BuildType,
+        target: &Target,
         selected_profile: &str,
-    ) -> Result<Vec<Value>, io::Error> {
+    ) -> Result<Vec<OsString>, io::Error> {
         let mut args = Vec::new();
 
+        args.push_from("--compile");
+
         if let Some(opt_level) = &self.optimize {
             args.push_from("--optimize");
             args.push_from(format!("{}", opt_level));
@@ -306,80 +680,419 @@ impl super::Profile for Profile {
             args.push_from("--dopt");
         }
 
+        if self.debug {
+            args.push_from("--debug");
+            args.push_from("--device-debug");
+        }
+
+        if self.ndebug {
+            args.push_from("--define-macro");
+            args.push_from("NDEBUG");
+        }
+
+        for define in self
+            .defines
+            .iter()
+        {
+            args.push_from("--define-macro");
+            args.push_from(define);
+        }
+
         if let Some(std) = &self.standard {
             args.push_from("--std");
             args.push_from(format!("{}", std));
         }
 
-        if build_type == BuildType::Library {
+        match self.warnings {
+            Some(Warnings::Off) => args.push_from("--disable-warnings"),
+            Some(warnings) => {
+                let host_warnings = match cfg!(windows) {
+                    true => warnings.msvc_arguments(),
+                    false => warnings.gcc_arguments(),
+                };
+                for warning in host_warnings {
+                    args.push_from("--compiler-options");
+                    args.push_from(*warning);
+                }
+            },
+            None => {},
+        }
+
+        if self.warnings_as_errors {
+            args.push_from("--Werror");
+            args.push_from("all-warnings");
+            args.push_from("--compiler-options");
+            args.push_from(match cfg!(windows) {
+                true => "/WX",
+                false => "-Werror",
+            });
+        }
+
+        // objects of shared libraries have to be position independent
+        if target.build_type == BuildType::Library && !cfg!(windows) {
+            args.push_from("--compiler-options");
+            args.push_from("-fPIC");
+        }
+
+        let include_dirs = config
+            .dependency_dirs(selected_profile)?
+            .into_iter()
+            .map(|(include_dir, _)| include_dir)
+            .chain(
+                target
+                    .include_dirs
+                    .iter()
+                    .cloned(),
+            );
+        for include_dir in include_dirs {
+            args.push(concat_os(&[
+                "--include-path=".as_ref(),
+                include_dir.as_os_str(),
+            ]));
+        }
+
+        args.extend(self.toolchain_arguments(config));
+        if let Some(toolchain) = config.toolchain() {
+            for flag in toolchain
+                .flags
+                .iter()
+            {
+                args.push_from(&**flag);
+            }
+        }
+
+        args.extend(self.host_arguments());
+        for flag in self
+            .device_flags
+            .iter()
+        {
+            args.push_from("--ptxas-options");
+            args.push_from(&**flag);
+        }
+
+        args.push_from("--output-file");
+        args.push_from(&*target.object_file);
+
+        args.push_from(&*target.src_file);
+
+        for flag in self
+            .extra_compiler_flags
+            .iter()
+        {
+            args.push_from(&**flag);
+        }
+
+        Ok(args)
+    }
+
+    fn link_arguments(
+        &self,
+        config: &Configuration,
+        target: &Target,
+        selected_profile: &str,
+    ) -> Result<Vec<OsString>, io::Error> {
+        // modules are made from the source again, as there is nothing to link into them
+        if let Some(mode) = self
+            .output
+            .mode()
+        {
+            let mut args = self.compile_arguments(
+                config,
+                &Target {
+                    object_file: target
+                        .artifact_file
+                        .clone(),
+                    ..target.clone()
+                },
+                selected_profile,
+            )?;
+            replace_mode(&mut args, mode);
+            return Ok(args);
+        }
+
+        let mut args = Vec::new();
+
+        if target.build_type == BuildType::Library {
             use LibraryType::*;
             args.push_from(match self.library_type {
                 Shared => "--shared",
                 Static => todo!("static nvcc libs"),
             });
+
+            // link.exe writes it next to the DLL otherwise, `.exp` goes along with it
+            if cfg!(windows) && matches!(self.library_type, Shared) {
+                args.push_from("--linker-options");
+                args.push(concat_os(&[
+                    "/IMPLIB:".as_ref(),
+                    config
+                        .target_lib_dir(selected_profile)
+                        .join(format!(
+                            "{}.lib",
+                            config.project_name()
+                        ))
+                        .as_os_str(),
+                ]));
+            }
+        }
+
+        for (lib_dir, link) in config.dependency_lib_dirs(selected_profile)? {
+            args.push(concat_os(&[
+                "--library-path=".as_ref(),
+                lib_dir.as_os_str(),
+            ]));
+
+            for lib in fs::read_dir(&lib_dir)? {
+                let lib_filename = lib?.file_name();
+                let (filename, ext) = split_file_name(&lib_filename);
+                // `.exp` next to import libraries belongs to the DLL itself, not to its users
+                if ext != "lib" && ext != "a" {
+                    continue;
+                }
+                // passed on to the host linker
+                match (
+                    link.whole_archive,
+                    cfg!(windows),
+                ) {
+                    (true, true) => {
+                        args.push_from("--linker-options");
+                        args.push(concat_os(&[
+                            "/WHOLEARCHIVE:".as_ref(),
+                            &lib_filename,
+                        ]));
+                    },
+                    (true, false) => {
+                        args.push_from("--linker-options");
+                        args.push_from("--whole-archive");
+                    },
+                    (false, _) => {},
+                }
+                args.push(concat_os(&[
+                    "--library=".as_ref(),
+                    filename,
+                ]));
+                if link.whole_archive && !cfg!(windows) {
+                    args.push_from("--linker-options");
+                    args.push_from("--no-whole-archive");
+                }
+            }
+        }
+
+        args.extend(self.toolchain_arguments(config));
+        args.extend(self.host_arguments());
+
+        // linked under its most specific name, known to the loader by the next one
+        let versions = self.artifact_versions(config, target.build_type);
+        if let Some(soname) = versions.get(1) {
+            args.push_from("--linker-options");
+            args.push_from(format!("-soname,{}", soname));
+        }
+
+        args.push_from("--output-file");
+        match versions.first() {
+            Some(linked) => args.push_from(
+                target
+                    .artifact_file
+                    .with_file_name(&**linked),
+            ),
+            None => args.push_from(&*target.artifact_file),
         }
 
-        for (alias, dep) in config
-            .dependencies()
+        args.push_from(&*target.object_file);
+
+        for flag in self
+            .extra_linker_flags
             .iter()
         {
-            let version = dep.current_version()?;
-            let profile = dep.current_profile(selected_profile)?;
+            args.push_from(&**flag);
+        }
 
-            let include_dir = config.cache_dep_include_dir(
-                alias.clone(),
-                version.clone(),
-                &profile,
-            );
-            let lib_dir = config.cache_dep_lib_dir(
-                alias.clone(),
-                version.clone(),
-                &profile,
-            );
+        Ok(args)
+    }
+
+    fn runner(&self) -> Option<Rc<[Value]>> {
+        self.runner
+            .clone()
+    }
 
-            args.push_from(format!(
-                "--include-path=\"{}\"",
-                include_dir.display()
+    fn environment(&self) -> &Environment { &self.environment }
+
+    fn cmake_language(&self) -> &'static str { "CUDA" }
+
+    fn cmake_library_kind(&self) -> &'static str {
+        use LibraryType::*;
+        match self.library_type {
+            Shared => "SHARED",
+            Static => "STATIC",
+        }
+    }
+
+    fn kind(&self) -> &'static str { "nvcc" }
+
+    fn settings(&self) -> Vec<(&'static str, Value)> {
+        let mut settings = Vec::new();
+        if let Some(compiler_path) = &self.compiler_path {
+            settings.push((
+                "compiler_path",
+                compiler_path.clone(),
             ));
-            args.push_from(format!(
-                "--library-path=\"{}\"",
-                lib_dir.display()
+        }
+        if let Some(std) = &self.standard {
+            settings.push((
+                "standard",
+                std.to_string()
+                    .into(),
             ));
-
-            for lib in fs::read_dir(lib_dir)? {
-                let filename = lib?.file_name();
-                let (filename, ext) = split_file_name(
-                    filename
-                        .to_str()
-                        .unwrap(),
-                );
-                if ext == "lib" || ext == "a" || ext == "exp" {
-                    args.push_from(format!(
-                        "--library=\"{}\"",
-                        filename
-                    ));
-                }
+        }
+        if let Some(optimize) = &self.optimize {
+            settings.push((
+                "optimize",
+                optimize
+                    .to_string()
+                    .into(),
+            ));
+        }
+        settings.push((
+            "dopt",
+            self.optimize_device
+                .to_string()
+                .into(),
+        ));
+        settings.push((
+            "debug",
+            self.debug
+                .to_string()
+                .into(),
+        ));
+        settings.push((
+            "assertions",
+            (!self.ndebug)
+                .to_string()
+                .into(),
+        ));
+        if let Some(warnings) = &self.warnings {
+            settings.push((
+                "warnings",
+                warnings
+                    .to_string()
+                    .into(),
+            ));
+        }
+        settings.push((
+            "warnings_as_errors",
+            self.warnings_as_errors
+                .to_string()
+                .into(),
+        ));
+        settings.push((
+            "library",
+            match self.library_type {
+                LibraryType::Shared => "shared",
+                LibraryType::Static => "static",
             }
+            .into(),
+        ));
+        settings.push((
+            "versioned_so",
+            self.versioned_so
+                .to_string()
+                .into(),
+        ));
+        settings.push((
+            "emit",
+            self.output
+                .to_string()
+                .into(),
+        ));
+        if !self
+            .host_flags
+            .is_empty()
+        {
+            settings.push((
+                "host_flags",
+                self.host_flags
+                    .join(" ")
+                    .into(),
+            ));
+        }
+        if !self
+            .device_flags
+            .is_empty()
+        {
+            settings.push((
+                "device_flags",
+                self.device_flags
+                    .join(" ")
+                    .into(),
+            ));
+        }
+        if let Some(runner) = &self.runner {
+            settings.push((
+                "runner",
+                runner
+                    .join(" ")
+                    .into(),
+            ));
+        }
+        self.environment
+            .settings(&mut settings);
+        self.defines
+            .settings(&mut settings);
+        if !self
+            .extra_compiler_flags
+            .is_empty()
+        {
+            settings.push((
+                "extra_compiler_flags",
+                self.extra_compiler_flags
+                    .join(" ")
+                    .into(),
+            ));
         }
+        if !self
+            .extra_linker_flags
+            .is_empty()
+        {
+            settings.push((
+                "extra_linker_flags",
+                self.extra_linker_flags
+                    .join(" ")
+                    .into(),
+            ));
+        }
+        settings
+    }
 
-        args.push_from("--output-file");
-        args.push_from(
-            config
-                .target_artifact_file(
-                    build_type,
-                    selected_profile,
-                    self,
-                )
-                .to_string_lossy(),
-        );
+    fn cmake_properties(&self) -> Vec<(&'static str, Value)> {
+        use Standard::*;
+        let mut properties = Vec::new();
+        if let Some(std) = &self.standard {
+            properties.push((
+                "CUDA_STANDARD",
+                match std {
+                    CPP03 => "98",
+                    CPP11 => "11",
+                    CPP14 => "14",
+                    CPP17 => "17",
+                    CPP20 => "20",
+                }
+                .into(),
+            ));
+        }
+        properties
+    }
 
-        args.push_from(
-            config
-                .src_file(build_type, self)
-                .display()
-                .to_string(),
-        );
+    fn defines(&self) -> &Defines { &self.defines }
 
-        Ok(args)
+    fn clang_flags(&self) -> Vec<Value> {
+        let mut flags = vec!["-xcuda".into()];
+        if let Some(std) = &self.standard {
+            flags.push(format!("-std={}", std).into());
+        }
+        if let Some(warnings) = self.warnings {
+            for warning in warnings.gcc_arguments() {
+                flags.push((*warning).into());
+            }
+        }
+        flags
     }
 }
@@ -0,0 +1,111 @@
+use std::env;
+use std::io;
+use std::io::IsTerminal;
+use std::io::Write;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+use std::sync::Once;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+
+use crate::ci;
+
+/// Set to `plain` to always log steps line by line (ex. for concurrently building children,
+/// which would otherwise all redraw the same line).
+pub const PROGRESS_VARIABLE: &str = "BUILDPP_PROGRESS";
+
+const SPINNER: &[char] = &['|', '/', '-', '\\'];
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Steps currently in progress, innermost last (ex. a dependency being cached, then one of its
+/// translation units being compiled). Only the innermost one is shown.
+static STEPS: Mutex<Vec<(String, Instant)>> = Mutex::new(Vec::new());
+static TICKER: Once = Once::new();
+
+/// Whether steps are not shown at all (ex. `buildpp run -quiet`).
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+pub fn set_quiet(quiet: bool) { QUIET.store(quiet, Ordering::Relaxed); }
+
+fn redraws() -> bool {
+    io::stderr().is_terminal()
+        && !ci::is_enabled()
+        && env::var(PROGRESS_VARIABLE).map_or(true, |progress| {
+            progress != "plain"
+        })
+}
+
+fn draw(steps: &[(String, Instant)]) {
+    let mut stderr = io::stderr().lock();
+    let _ = match steps.last() {
+        Some((message, started)) => {
+            let elapsed = started.elapsed();
+            write!(
+                stderr,
+                "\r\x1b[2K{} {} [{:.1}s]",
+                SPINNER
+                    [(elapsed.as_millis() / REDRAW_INTERVAL.as_millis()) as usize % SPINNER.len()],
+                message,
+                elapsed.as_secs_f32()
+            )
+        },
+        None => write!(stderr, "\r\x1b[2K"),
+    };
+    let _ = stderr.flush();
+}
+
+/// Shown until dropped.
+#[must_use]
+pub struct Step(());
+
+/// Shows `message` (ex. `compiling src/main.cu`) as the current step: redrawn in place
+/// with elapsed time when on a terminal, logged as a line otherwise.
+pub fn step(message: impl Into<String>) -> Step {
+    let message = message.into();
+
+    if QUIET.load(Ordering::Relaxed) {
+        return Step(());
+    }
+
+    if !redraws() {
+        eprintln!("{}", message);
+        return Step(());
+    }
+
+    let mut steps = STEPS
+        .lock()
+        .unwrap_or_else(|err| err.into_inner());
+    steps.push((message, Instant::now()));
+    draw(&steps);
+
+    // keeps elapsed time going while a compiler is running
+    TICKER.call_once(|| {
+        thread::spawn(|| loop {
+            thread::sleep(REDRAW_INTERVAL);
+            let steps = STEPS
+                .lock()
+                .unwrap_or_else(|err| err.into_inner());
+            if !steps.is_empty() {
+                draw(&steps);
+            }
+        });
+    });
+
+    Step(())
+}
+
+impl Drop for Step {
+    fn drop(&mut self) {
+        if QUIET.load(Ordering::Relaxed) || !redraws() {
+            return;
+        }
+
+        let mut steps = STEPS
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        steps.pop();
+        draw(&steps);
+    }
+}
@@ -0,0 +1,295 @@
+use std::env;
+use std::ffi::OsStr;
+use std::fmt::Display;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::process::Command;
+use std::process::Stdio;
+use std::thread;
+use std::time::Duration;
+
+use crate::network;
+
+/// URL of an HTTP (or S3-compatible) endpoint that built dependency caches are shared through,
+/// as `<url>/<content hash>.tar.gz`. Unset to not use a remote cache.
+pub const REMOTE_CACHE_VARIABLE: &str = "BUILDPP_REMOTE_CACHE";
+
+/// Sent as a bearer token, instead of credentials for the endpoint's host from the global
+/// configuration. Caches are only uploaded when either is set, otherwise the remote cache
+/// is read-only.
+pub const REMOTE_CACHE_TOKEN_VARIABLE: &str = "BUILDPP_REMOTE_CACHE_TOKEN";
+
+/// How many times a failed download of a cache is tried again, waiting twice as long
+/// before each next attempt.
+pub const REMOTE_CACHE_RETRIES_VARIABLE: &str = "BUILDPP_REMOTE_CACHE_RETRIES";
+
+const DEFAULT_RETRIES: u32 = 3;
+const FIRST_BACKOFF: Duration = Duration::from_secs(1);
+
+/// curl exit codes of network failures that may go away when tried again
+/// (ex. could not connect, timed out, connection reset while receiving).
+const CURL_TRANSIENT_ERRORS: &[i32] = &[5, 6, 7, 18, 28, 35, 52, 55, 56];
+
+/// curl exit code for a server that does not support resuming (`--continue-at`).
+const CURL_CANNOT_RESUME: i32 = 33;
+
+pub fn endpoint() -> Option<String> {
+    env::var(REMOTE_CACHE_VARIABLE)
+        .ok()
+        .map(|endpoint| {
+            endpoint
+                .trim_end_matches('/')
+                .to_string()
+        })
+        .filter(|endpoint| !endpoint.is_empty())
+}
+
+fn token() -> Option<String> {
+    env::var(REMOTE_CACHE_TOKEN_VARIABLE)
+        .ok()
+        .filter(|token| !token.is_empty())
+}
+
+fn retries() -> u32 {
+    env::var(REMOTE_CACHE_RETRIES_VARIABLE)
+        .ok()
+        .and_then(|retries| {
+            retries
+                .trim()
+                .parse()
+                .ok()
+        })
+        .unwrap_or(DEFAULT_RETRIES)
+}
+
+fn url(endpoint: &str, hash: &str) -> String { format!("{}/{}.tar.gz", endpoint, hash) }
+
+/// Runs curl on `url` with the token (if any, otherwise with the credentials of the host
+/// from the global configuration), returns its exit code and stdout.
+fn curl(url: &str, args: &[&OsStr]) -> Result<(i32, String), io::Error> {
+    let output = network::curl(url, token().as_deref(), args)?;
+    let code = output
+        .status
+        .code()
+        .ok_or_else(|| io::Error::other("curl was killed"))?;
+    Ok((
+        code,
+        String::from_utf8_lossy(&output.stdout).to_string(),
+    ))
+}
+
+fn tar(args: &[&OsStr]) -> Result<(), io::Error> {
+    let status = Command::new("tar")
+        .args(args)
+        .stdin(Stdio::null())
+        .status()?;
+    match status.success() {
+        true => Ok(()),
+        false => Err(io::Error::other(format!(
+            "tar failed ({})",
+            status
+        ))),
+    }
+}
+
+/// A download that did not succeed even after retrying.
+#[derive(Debug)]
+pub struct FetchError {
+    pub url: String,
+    pub attempts: u32,
+    pub reason: io::Error,
+}
+
+impl Display for FetchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} failed after {} attempt{}: {}",
+            self.url,
+            self.attempts,
+            if self.attempts == 1 { "" } else { "s" },
+            self.reason
+        )
+    }
+}
+
+/// Outcome of one attempt at downloading.
+enum Attempt {
+    Downloaded,
+    Missing,
+    /// Worth trying again, ex. the network or the server failed.
+    Retry(io::Error),
+}
+
+/// Downloads `url` into `archive`, continuing a partial download left by a previous attempt.
+fn attempt_download(url: &str, archive: &Path) -> Result<Attempt, io::Error> {
+    let (code, http_code) = curl(
+        url,
+        &[
+            "--continue-at".as_ref(),
+            "-".as_ref(),
+            "--write-out".as_ref(),
+            "%{http_code}".as_ref(),
+            "--output".as_ref(),
+            archive.as_os_str(),
+        ],
+    )?;
+    let http_code = http_code
+        .trim()
+        .parse::<u16>()
+        .unwrap_or_default();
+
+    // error responses are written into the archive too, never resume from them
+    let discard_archive = || match archive.exists() {
+        true => fs::remove_file(archive),
+        false => Ok(()),
+    };
+    match (code, http_code) {
+        (0, 200..300) => Ok(Attempt::Downloaded),
+        (0, 404) => {
+            discard_archive()?;
+            Ok(Attempt::Missing)
+        },
+        (0, 408 | 429 | 500..600) => {
+            discard_archive()?;
+            Ok(Attempt::Retry(
+                io::Error::other(format!(
+                    "server responded with HTTP {}",
+                    http_code
+                )),
+            ))
+        },
+        (0, _) => {
+            discard_archive()?;
+            Err(io::Error::other(format!(
+                "server responded with HTTP {}",
+                http_code
+            )))
+        },
+        (CURL_CANNOT_RESUME, _) => {
+            discard_archive()?;
+            Ok(Attempt::Retry(
+                io::Error::other("server cannot resume the download, starting over"),
+            ))
+        },
+        (code, _) if CURL_TRANSIENT_ERRORS.contains(&code) => Ok(Attempt::Retry(
+            io::Error::other(format!(
+                "curl failed with exit code {}",
+                code
+            )),
+        )),
+        (code, _) => Err(io::Error::other(format!(
+            "curl failed with exit code {}",
+            code
+        ))),
+    }
+}
+
+/// Downloads the cache of `hash` and unpacks it into `dir`,
+/// `false` if the endpoint does not have it.
+///
+/// Network and server failures are retried with backoff (see [`REMOTE_CACHE_RETRIES_VARIABLE`]),
+/// continuing where the previous attempt stopped.
+pub fn fetch(endpoint: &str, hash: &str, dir: &Path) -> Result<bool, FetchError> {
+    let archive = dir.join(".remote.tar.gz");
+    let url = url(endpoint, hash);
+    let fail = |attempts, reason| FetchError {
+        url: url.clone(),
+        attempts,
+        reason,
+    };
+
+    // a partial download of some other cache cannot be continued
+    if archive.exists() {
+        fs::remove_file(&archive).map_err(|err| fail(0, err))?;
+    }
+
+    let retries = retries();
+    let mut backoff = FIRST_BACKOFF;
+    let mut attempts = 0;
+    let fetched = loop {
+        attempts += 1;
+        match attempt_download(&url, &archive) {
+            Ok(Attempt::Downloaded) => break Ok(true),
+            Ok(Attempt::Missing) => break Ok(false),
+            Ok(Attempt::Retry(reason)) if attempts <= retries => {
+                eprintln!(
+                    "warning: downloading {} failed ({}), retrying in {}s",
+                    url,
+                    reason,
+                    backoff.as_secs()
+                );
+                thread::sleep(backoff);
+                backoff *= 2;
+            },
+            Ok(Attempt::Retry(reason)) | Err(reason) => break Err(fail(attempts, reason)),
+        }
+    };
+
+    let fetched = fetched.and_then(|fetched| match fetched {
+        true => tar(&[
+            "-xzf".as_ref(),
+            archive.as_os_str(),
+            "-C".as_ref(),
+            dir.as_os_str(),
+        ])
+        .map(|()| true)
+        .map_err(|err| fail(attempts, err)),
+        false => Ok(false),
+    });
+
+    // partial downloads are only continued within the same fetch
+    if archive.exists() {
+        fs::remove_file(&archive).map_err(|err| fail(attempts, err))?;
+    }
+    fetched
+}
+
+/// Packs `entries` of `dir` and uploads them as the cache of `hash`,
+/// `false` if there are no credentials to upload with.
+pub fn store(endpoint: &str, hash: &str, dir: &Path, entries: &[&str]) -> Result<bool, io::Error> {
+    let credentials = network::credentials(endpoint);
+    if token().is_none()
+        && credentials
+            .token
+            .is_none()
+        && credentials
+            .user
+            .is_none()
+    {
+        return Ok(false);
+    }
+
+    let archive = dir.join(".remote.tar.gz");
+    let mut args: Vec<&OsStr> = vec![
+        "-czf".as_ref(),
+        archive.as_os_str(),
+        "-C".as_ref(),
+        dir.as_os_str(),
+    ];
+    args.extend(
+        entries
+            .iter()
+            .map(OsStr::new),
+    );
+    tar(&args)?;
+
+    let url = url(endpoint, hash);
+    let code = curl(
+        &url,
+        &[
+            "--fail".as_ref(),
+            "--upload-file".as_ref(),
+            archive.as_os_str(),
+        ],
+    );
+    fs::remove_file(&archive)?;
+    match code?.0 {
+        0 => Ok(true),
+        code => Err(io::Error::other(format!(
+            "curl failed with exit code {}",
+            code
+        ))),
+    }
+}
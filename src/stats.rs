@@ -0,0 +1,183 @@
+use std::cell::RefCell;
+use std::fmt::Display;
+use std::fs;
+use std::fs::OpenOptions;
+use std::io;
+use std::io::Write;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::lsd::Value;
+
+/// History of builds of a project, one line per build, kept in its `target` dir.
+pub const STATS_FILENAME: &str = ".stats";
+
+const STATS_HEADER: &str =
+    "# finished version profile duration_ms cache_hits cache_misses compiled artifact_bytes\n";
+
+/// What happened during a build, counted while it runs.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Counters {
+    /// Dependencies whose cache was up to date.
+    pub cache_hits: usize,
+    /// Dependencies that had to be cached (built or fetched) again.
+    pub cache_misses: usize,
+    /// Translation units that were compiled.
+    pub compiled: usize,
+}
+
+thread_local! {
+    /// Builds currently running, innermost (ex. a dependency being built) last.
+    static COUNTERS: RefCell<Vec<Counters>> = RefCell::default();
+}
+
+/// Starts counting for a build, until [`end`].
+pub fn begin() {
+    COUNTERS.with(|counters| {
+        counters
+            .borrow_mut()
+            .push(Counters::default())
+    });
+}
+
+/// Stops counting for the innermost build, returning what was counted.
+pub fn end() -> Counters {
+    COUNTERS.with(|counters| {
+        counters
+            .borrow_mut()
+            .pop()
+            .unwrap_or_default()
+    })
+}
+
+fn count(f: impl FnOnce(&mut Counters)) {
+    COUNTERS.with(|counters| {
+        if let Some(counters) = counters
+            .borrow_mut()
+            .last_mut()
+        {
+            f(counters);
+        }
+    });
+}
+
+pub fn cache_hit() { count(|counters| counters.cache_hits += 1); }
+
+pub fn cache_miss() { count(|counters| counters.cache_misses += 1); }
+
+pub fn compiled() { count(|counters| counters.compiled += 1); }
+
+/// A finished build, as a line of [`STATS_FILENAME`].
+#[derive(Debug, Clone)]
+pub struct Record {
+    /// Seconds since the Unix epoch.
+    pub finished: u64,
+    pub version: Value,
+    pub profile: Value,
+    pub duration: Duration,
+    pub counters: Counters,
+    /// Size of the artifact in bytes, `0` if there is none.
+    pub artifact_size: u64,
+}
+
+impl Display for Record {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} {} {} {} {} {} {}",
+            self.finished,
+            self.version,
+            self.profile,
+            self.duration
+                .as_millis(),
+            self.counters
+                .cache_hits,
+            self.counters
+                .cache_misses,
+            self.counters
+                .compiled,
+            self.artifact_size
+        )
+    }
+}
+
+impl FromStr for Record {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut pieces = s.split_whitespace();
+        let mut next = || {
+            pieces
+                .next()
+                .ok_or(())
+        };
+        let finished = next()?
+            .parse()
+            .map_err(|_| ())?;
+        let version = next()?.into();
+        let profile = next()?.into();
+        let duration = Duration::from_millis(
+            next()?
+                .parse()
+                .map_err(|_| ())?,
+        );
+        let counters = Counters {
+            cache_hits: next()?
+                .parse()
+                .map_err(|_| ())?,
+            cache_misses: next()?
+                .parse()
+                .map_err(|_| ())?,
+            compiled: next()?
+                .parse()
+                .map_err(|_| ())?,
+        };
+        let artifact_size = next()?
+            .parse()
+            .map_err(|_| ())?;
+        Ok(Record {
+            finished,
+            version,
+            profile,
+            duration,
+            counters,
+            artifact_size,
+        })
+    }
+}
+
+/// Appends `record` to the history in `target_dir`.
+pub fn append(target_dir: &Path, record: &Record) -> Result<(), io::Error> {
+    fs::create_dir_all(target_dir)?;
+    let stats_file = target_dir.join(STATS_FILENAME);
+    let is_new = !stats_file.is_file();
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(stats_file)?;
+    if is_new {
+        file.write_all(STATS_HEADER.as_bytes())?;
+    }
+    writeln!(file, "{}", record)
+}
+
+/// History in `target_dir`, oldest first. Lines that cannot be read
+/// (ex. written by another version of build++) are skipped.
+pub fn read(target_dir: &Path) -> Result<Vec<Record>, io::Error> {
+    let stats_file = target_dir.join(STATS_FILENAME);
+    if !stats_file.is_file() {
+        return Ok(Vec::new());
+    }
+
+    Ok(
+        fs::read_to_string(stats_file)?
+            .lines()
+            .filter(|line| !line.starts_with('#'))
+            .filter_map(|line| {
+                line.parse()
+                    .ok()
+            })
+            .collect(),
+    )
+}
@@ -0,0 +1,685 @@
+use std::fs;
+use std::io;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Command;
+use std::process::Stdio;
+use std::rc::Rc;
+
+use indexmap::IndexMap;
+
+use super::Flag;
+use crate::configuration;
+use crate::configuration::Configuration;
+use crate::error_code::Code;
+use crate::error_code::ErrorCode;
+use crate::lsd::Value;
+use crate::profile;
+use crate::profile::Profile;
+use crate::profile::DEFAULT_PROFILE;
+use crate::util::json_quote;
+use crate::util::BoolGuardExt;
+use crate::BuildError;
+use crate::Dir;
+
+/// Used when neither `-analyzer` nor `analyze { analyzer }` is set.
+///
+/// Other analyzers have to accept the same arguments (ex. a wrapper script).
+const DEFAULT_ANALYZER: &str = "cppcheck";
+
+/// Used by `-iwyu` when neither `-analyzer` nor `analyze { analyzer }` is set.
+const DEFAULT_IWYU: &str = "include-what-you-use";
+
+/// Applies suggestions of include-what-you-use, reading them from stdin.
+const FIX_INCLUDES: &str = "fix_includes.py";
+
+/// Reported by include-what-you-use for files without suggestions.
+const IWYU_CORRECT: &str = "has correct #includes/fwd-decls";
+
+/// Separates fields of each finding, so that paths with colons (ex. `C:\`) stay intact.
+const FIELD_SEPARATOR: char = '\t';
+
+pub struct Subcommand {
+    profile: profile::Name,
+
+    mode: Mode,
+
+    /// Apply suggested fixes (`-iwyu` only).
+    fix: bool,
+
+    analyzer: Option<Rc<[Value]>>,
+
+    suppressions: Option<Value>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Mode {
+    /// Used unless the profile has compiler analysis enabled (ex. `analyze true` of msvc).
+    Cppcheck,
+    /// `-iwyu`, include-what-you-use per translation unit.
+    IncludeWhatYouUse,
+}
+
+#[derive(Debug, Clone)]
+enum InnerParseError {
+    FixRequiresIwyu,
+}
+
+impl super::InnerParseError for InnerParseError {
+}
+
+impl From<InnerParseError> for Rc<dyn super::InnerParseError> {
+    fn from(value: InnerParseError) -> Self { Rc::new(value) }
+}
+
+#[derive(Debug, Clone)]
+enum InnerExecuteError {
+    InvalidCurrentDir(Rc<io::Error>),
+
+    CannotLoadConfiguration(configuration::LoadError),
+
+    BuildError(BuildError),
+
+    CouldNotReadDependencies(Rc<io::Error>),
+    CouldNotListSources(Rc<io::Error>),
+    NoSourcesToAnalyze,
+    CouldNotWriteCompileDatabase(Rc<io::Error>),
+
+    AnalyzerNotFound(Value),
+    AnalyzerFailedSpawn(Rc<io::Error>),
+    AnalyzerFailedRead(Rc<io::Error>),
+    AnalyzerFailedWrite(Rc<io::Error>),
+    AnalyzerFailedWait(Rc<io::Error>),
+    AnalyzerFailedExitCode(i32),
+    AnalyzerKilled,
+
+    FoundIssues(usize),
+}
+
+impl super::InnerExecuteError for InnerExecuteError {
+    fn code(&self) -> Option<Code> {
+        use InnerExecuteError::*;
+        match self {
+            CannotLoadConfiguration(err) => Some(err.code()),
+            BuildError(err) => Some(err.code()),
+            _ => None,
+        }
+    }
+
+    fn note(&self) -> Option<String> {
+        use InnerExecuteError::*;
+        match self {
+            CannotLoadConfiguration(err) => err.note(),
+            InvalidCurrentDir(err)
+            | CouldNotReadDependencies(err)
+            | CouldNotListSources(err)
+            | CouldNotWriteCompileDatabase(err)
+            | AnalyzerFailedSpawn(err)
+            | AnalyzerFailedRead(err)
+            | AnalyzerFailedWrite(err)
+            | AnalyzerFailedWait(err) => Some(err.to_string()),
+            AnalyzerNotFound(analyzer) => Some(format!(
+                "`{}` is not on `PATH`",
+                analyzer
+            )),
+            AnalyzerFailedExitCode(code) => Some(format!(
+                "exited with code {}",
+                code
+            )),
+            FoundIssues(count) => Some(format!(
+                "{} issues found",
+                count
+            )),
+            _ => None,
+        }
+    }
+}
+
+impl From<InnerExecuteError> for Rc<dyn super::InnerExecuteError> {
+    fn from(value: InnerExecuteError) -> Self { Rc::new(value) }
+}
+
+/// Files in `dir` (recursively) ending with `suffix`, sorted for stable output.
+fn list_sources(dir: &Path, suffixes: &[&str]) -> Result<Vec<PathBuf>, io::Error> {
+    let mut sources = Vec::new();
+    if !dir.is_dir() {
+        return Ok(sources);
+    }
+
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            sources.extend(list_sources(&path, suffixes)?);
+        } else if suffixes
+            .iter()
+            .any(|suffix| {
+                path.to_string_lossy()
+                    .ends_with(suffix)
+            })
+        {
+            sources.push(path);
+        }
+    }
+
+    sources.sort();
+    Ok(sources)
+}
+
+/// Issue found by an analyzer, in the fields of cppcheck.
+struct Finding<'a> {
+    file: &'a str,
+    line: &'a str,
+    column: &'a str,
+    severity: &'a str,
+    message: &'a str,
+    id: &'a str,
+}
+
+/// Shown like compiler diagnostics, so that editors and terminals can link them,
+/// whichever analyzer found it.
+fn print_finding(project_dir: &Path, finding: Finding) {
+    let file = Path::new(finding.file);
+    let file = file
+        .strip_prefix(project_dir)
+        .unwrap_or(file);
+    eprintln!(
+        "{}:{}:{}: {}: {} [{}]",
+        file.display(),
+        finding.line,
+        finding.column,
+        finding.severity,
+        finding.message,
+        finding.id
+    );
+}
+
+/// Finding in a line of `cl /analyze` output
+/// (ex. `src\main.cpp(3,5): warning C6011: Dereferencing NULL pointer 'p'.`).
+fn parse_msvc_finding(line: &str) -> Option<Finding<'_>> {
+    let (location, rest) = line.split_once("): ")?;
+    let (file, position) = location.rsplit_once('(')?;
+    let (line, column) = position
+        .split_once(',')
+        .unwrap_or((position, "0"));
+    let (diagnostic, message) = rest.split_once(": ")?;
+    let (severity, id) = diagnostic.split_once(' ')?;
+    matches!(
+        severity,
+        "warning" | "error" | "note"
+    )
+    .then_some(Finding {
+        file: file.trim(),
+        line,
+        column,
+        severity,
+        message,
+        id,
+    })
+}
+
+const FLAGS: &[Flag] = &[
+    Flag::one(
+        "profile",
+        "name",
+        "Profile to use, `default` when not given",
+    ),
+    Flag::many(
+        "analyzer",
+        "command",
+        "Analyzer to run instead of the one of the configuration",
+    ),
+    Flag::one(
+        "suppressions",
+        "path",
+        "File of findings cppcheck does not report",
+    ),
+    Flag::switch(
+        "iwyu",
+        "Run include-what-you-use instead of cppcheck",
+    ),
+    Flag::switch(
+        "fix",
+        "Apply fixes include-what-you-use suggests",
+    ),
+];
+
+impl super::Subcommand for Subcommand {
+    fn parse(
+        mut flags: IndexMap<Value, Rc<[Value]>>,
+        _post_dash_dash: impl Iterator<Item = String>,
+    ) -> Result<Rc<dyn super::Subcommand>, Rc<dyn super::InnerParseError>> {
+        use InnerParseError::*;
+
+        let profile = flags
+            .shift_remove("profile")
+            .and_then(super::single_value)
+            .unwrap_or_else(|| DEFAULT_PROFILE.into());
+
+        let analyzer = flags.shift_remove("analyzer");
+
+        let suppressions = flags
+            .shift_remove("suppressions")
+            .and_then(super::single_value);
+
+        let mode = match flags.shift_remove("iwyu") {
+            Some(_) => Mode::IncludeWhatYouUse,
+            None => Mode::Cppcheck,
+        };
+
+        let fix = match flags.shift_remove("fix") {
+            Some(_) => {
+                matches!(mode, Mode::IncludeWhatYouUse).or_err(FixRequiresIwyu)?;
+                true
+            },
+            None => false,
+        };
+
+        Ok(Rc::new(Subcommand {
+            profile,
+            mode,
+            fix,
+            analyzer,
+            suppressions,
+        }))
+    }
+
+    fn flags() -> &'static [Flag] { FLAGS }
+
+    fn execute(&self) -> Result<(), Rc<dyn super::InnerExecuteError>> {
+        use InnerExecuteError::*;
+
+        let project_dir = Dir::from(
+            std::env::current_dir()
+                .map_err(Rc::new)
+                .map_err(InvalidCurrentDir)?,
+        );
+
+        let config = Configuration::load(project_dir.clone()).map_err(CannotLoadConfiguration)?;
+
+        let profile = config
+            .profile(&self.profile)
+            .ok_or_else(|| {
+                crate::BuildError::InvalidProfile(
+                    self.profile
+                        .clone(),
+                )
+            })
+            .map_err(BuildError)?;
+
+        // dependencies' headers are referred to from the cache, so it has to exist
+        config
+            .prepare_dependencies(&self.profile)
+            .map_err(BuildError)?;
+
+        let mut flags = Vec::new();
+        for include_dir in config.include_dirs() {
+            flags.push(format!(
+                "-I{}",
+                include_dir.display()
+            ));
+        }
+        for (include_dir, _) in config
+            .dependency_dirs(&self.profile)
+            .map_err(Rc::new)
+            .map_err(CouldNotReadDependencies)?
+        {
+            flags.push(format!(
+                "-I{}",
+                include_dir.display()
+            ));
+        }
+
+        for define in config
+            .defines(None, &self.profile, profile)
+            .map_err(BuildError)?
+        {
+            flags.push(format!("-D{}", define));
+        }
+
+        let mut sources = Vec::new();
+        for dir in [config.src_dir(), config.tests_dir()] {
+            sources.extend(
+                list_sources(
+                    &dir,
+                    profile.src_file_suffixes(),
+                )
+                .map_err(Rc::new)
+                .map_err(CouldNotListSources)?,
+            );
+        }
+        sources.retain(|source| !config.is_excluded(source));
+        (!sources.is_empty()).or_err(NoSourcesToAnalyze)?;
+
+        // an analyzer given explicitly wins over the one of the compiler
+        let uses_compiler = matches!(self.mode, Mode::Cppcheck)
+            && self
+                .analyzer
+                .is_none()
+            && config
+                .analyzer()
+                .is_none()
+            && profile
+                .analysis_only_arguments()
+                .is_some();
+
+        match self.mode {
+            Mode::Cppcheck if uses_compiler => self.compiler(&config, &sources)?,
+            Mode::Cppcheck => self.cppcheck(
+                &config, profile, &flags, &sources,
+            )?,
+            Mode::IncludeWhatYouUse => self.include_what_you_use(
+                &config, profile, &flags, &sources,
+            )?,
+        }
+
+        println!(
+            "analyzed {} files ... ok",
+            sources.len()
+        );
+
+        Ok(())
+    }
+}
+
+impl Subcommand {
+    fn analyzer(&self, config: &Configuration, default: &str) -> Rc<[Value]> {
+        self.analyzer
+            .clone()
+            .or_else(|| config.analyzer())
+            .unwrap_or_else(|| Rc::from([Value::from(default)]))
+    }
+
+    fn cppcheck(
+        &self,
+        config: &Configuration,
+        profile: &dyn Profile,
+        flags: &[String],
+        sources: &[PathBuf],
+    ) -> Result<(), InnerExecuteError> {
+        use InnerExecuteError::*;
+
+        let project_dir = config.project_dir();
+        let analyzer = self.analyzer(config, DEFAULT_ANALYZER);
+
+        let mut command = Command::new(&*analyzer[0]);
+        command
+            .args(
+                analyzer[1..]
+                    .iter()
+                    .map(Rc::as_ref),
+            )
+            .arg("--quiet")
+            .arg("--inline-suppr")
+            .arg("--enable=warning,style,performance,portability")
+            .arg(format!(
+                "--template={{file}}{0}{{line}}{0}{{column}}{0}{{severity}}{0}{{message}}{0}{{id}}",
+                FIELD_SEPARATOR
+            ))
+            // `.cu` is not known to cppcheck, but is close enough to C++
+            .arg(
+                match profile.cmake_language() {
+                    "C" => "--language=c",
+                    _ => "--language=c++",
+                },
+            );
+
+        // relative to where build++ was ran from, like other flags
+        let suppressions = self
+            .suppressions
+            .as_ref()
+            .map(|suppressions| Dir::from(project_dir.join(&**suppressions)))
+            .or_else(|| config.analyze_suppressions());
+        if let Some(suppressions) = suppressions {
+            command.arg(format!(
+                "--suppressions-list={}",
+                suppressions.display()
+            ));
+        }
+
+        command
+            .args(flags)
+            .args(sources);
+
+        // findings are reported on stderr
+        let mut child = command
+            .current_dir(&*project_dir)
+            .stdin(Stdio::null())
+            .stdout(Stdio::inherit())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|err| match err.kind() {
+                io::ErrorKind::NotFound => AnalyzerNotFound(analyzer[0].clone()),
+                _ => AnalyzerFailedSpawn(Rc::new(err)),
+            })?;
+
+        let mut issues = 0;
+        if let Some(stderr) = child
+            .stderr
+            .take()
+        {
+            for line in BufReader::new(stderr).lines() {
+                let line = line
+                    .map_err(Rc::new)
+                    .map_err(AnalyzerFailedRead)?;
+
+                let fields = line
+                    .splitn(6, FIELD_SEPARATOR)
+                    .collect::<Vec<_>>();
+                let [file, line, column, severity, message, id] = fields[..] else {
+                    // not a finding (ex. a note about the analyzer itself)
+                    eprintln!("{}", line);
+                    continue;
+                };
+
+                print_finding(
+                    &project_dir,
+                    Finding {
+                        file,
+                        line,
+                        column,
+                        severity,
+                        message,
+                        id,
+                    },
+                );
+
+                if severity != "information" {
+                    issues += 1;
+                }
+            }
+        }
+
+        let code = child
+            .wait()
+            .map_err(Rc::new)
+            .map_err(AnalyzerFailedWait)?
+            .code()
+            .ok_or(AnalyzerKilled)?;
+
+        (issues == 0).or_err(FoundIssues(issues))?;
+        (code == 0).or_err(AnalyzerFailedExitCode(code))?;
+
+        Ok(())
+    }
+
+    /// Analysis of the compiler of the profile, one translation unit at a time.
+    fn compiler(
+        &self,
+        config: &Configuration,
+        sources: &[PathBuf],
+    ) -> Result<(), InnerExecuteError> {
+        use InnerExecuteError::*;
+
+        let project_dir = config.project_dir();
+
+        let mut issues = 0;
+        let mut failed_code = None;
+        for source in sources {
+            let Some((code, output)) = config
+                .compiler_analysis(&self.profile, source)
+                .map_err(BuildError)?
+            else {
+                continue;
+            };
+
+            for line in output.lines() {
+                let Some(finding) = parse_msvc_finding(line) else {
+                    // not a finding (ex. name of the file being compiled)
+                    if !line
+                        .trim()
+                        .is_empty()
+                    {
+                        eprintln!("{}", line);
+                    }
+                    continue;
+                };
+
+                if finding.severity != "note" {
+                    issues += 1;
+                }
+                print_finding(&project_dir, finding);
+            }
+
+            if code != 0 {
+                failed_code.get_or_insert(code);
+            }
+        }
+
+        (issues == 0).or_err(FoundIssues(issues))?;
+        if let Some(code) = failed_code {
+            return Err(AnalyzerFailedExitCode(code));
+        }
+
+        Ok(())
+    }
+
+    fn include_what_you_use(
+        &self,
+        config: &Configuration,
+        profile: &dyn Profile,
+        flags: &[String],
+        sources: &[PathBuf],
+    ) -> Result<(), InnerExecuteError> {
+        use InnerExecuteError::*;
+
+        let project_dir = config.project_dir();
+        let analyzer = self.analyzer(config, DEFAULT_IWYU);
+
+        // iwyu is clang-based, so it gets the same flags as clangd would
+        let arguments = profile
+            .clang_flags()
+            .iter()
+            .map(|flag| flag.to_string())
+            .chain(
+                flags
+                    .iter()
+                    .cloned(),
+            )
+            .collect::<Vec<_>>();
+
+        // also useful for other clang tools, so it is kept next to build outputs
+        let target_dir = config.target_dir(&self.profile);
+        fs::create_dir_all(&target_dir)
+            .map_err(Rc::new)
+            .map_err(CouldNotWriteCompileDatabase)?;
+        let database = sources
+            .iter()
+            .map(|source| {
+                let arguments = [String::from("clang++")]
+                    .into_iter()
+                    .chain(arguments.iter().cloned())
+                    .chain([source.display().to_string()])
+                    .map(|argument| json_quote(&argument))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                format!(
+                    "  {{\n    \"directory\": {},\n    \"file\": {},\n    \"arguments\": [{}]\n  }}",
+                    json_quote(&project_dir.display().to_string()),
+                    json_quote(&source.display().to_string()),
+                    arguments
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(",\n");
+        fs::write(
+            target_dir.join("compile_commands.json"),
+            format!("[\n{}\n]\n", database),
+        )
+        .map_err(Rc::new)
+        .map_err(CouldNotWriteCompileDatabase)?;
+
+        // each translation unit separately, suggestions are reported on stderr
+        let mut suggestions = String::new();
+        let mut issues = 0;
+        for source in sources {
+            let output = Command::new(&*analyzer[0])
+                .args(
+                    analyzer[1..]
+                        .iter()
+                        .map(Rc::as_ref),
+                )
+                .args(&arguments)
+                .arg(source)
+                .current_dir(&*project_dir)
+                .stdin(Stdio::null())
+                .output()
+                .map_err(|err| match err.kind() {
+                    io::ErrorKind::NotFound => AnalyzerNotFound(analyzer[0].clone()),
+                    _ => AnalyzerFailedSpawn(Rc::new(err)),
+                })?;
+
+            let output = String::from_utf8_lossy(&output.stderr);
+            if !output.contains(IWYU_CORRECT) {
+                eprint!("{}", output);
+                issues += 1;
+            }
+            suggestions.push_str(&output);
+        }
+
+        if issues == 0 {
+            return Ok(());
+        }
+
+        if !self.fix {
+            return Err(FoundIssues(issues));
+        }
+
+        let mut child = Command::new(FIX_INCLUDES)
+            .current_dir(&*project_dir)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::inherit())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .map_err(|err| match err.kind() {
+                io::ErrorKind::NotFound => AnalyzerNotFound(FIX_INCLUDES.into()),
+                _ => AnalyzerFailedSpawn(Rc::new(err)),
+            })?;
+        if let Some(mut stdin) = child
+            .stdin
+            .take()
+        {
+            stdin
+                .write_all(suggestions.as_bytes())
+                .map_err(Rc::new)
+                .map_err(AnalyzerFailedWrite)?;
+        }
+        let code = child
+            .wait()
+            .map_err(Rc::new)
+            .map_err(AnalyzerFailedWait)?
+            .code()
+            .ok_or(AnalyzerKilled)?;
+
+        // fix_includes.py exits with the number of files it changed
+        (code >= 0).or_err(AnalyzerFailedExitCode(code))?;
+        println!(
+            "fixed includes of {} files",
+            code
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,520 @@
+use std::io;
+use std::path::Path;
+use std::process::Command;
+use std::process::Stdio;
+use std::rc::Rc;
+use std::time::Duration;
+use std::time::Instant;
+
+use indexmap::IndexMap;
+
+use super::Flag;
+use crate::configuration;
+use crate::configuration::Configuration;
+use crate::error_code::Code;
+use crate::error_code::ErrorCode;
+use crate::lsd::Value;
+use crate::profile;
+use crate::profile::DEFAULT_PROFILE;
+use crate::util::BoolGuardExt;
+use crate::BuildError;
+use crate::BuildType;
+use crate::Dir;
+
+const DEFAULT_RUNS: usize = 5;
+
+pub struct Subcommand {
+    build_type: Option<BuildType>,
+
+    profile: profile::Name,
+
+    /// Profile to compare against, or a dir with bench binaries of an earlier build.
+    baseline: Option<Value>,
+
+    /// How many times each bench is run, the median time being compared.
+    runs: usize,
+
+    /// Percent a bench may be slower than the baseline before the command fails.
+    max_regression: Option<f64>,
+}
+
+#[derive(Debug, Clone)]
+enum InnerParseError {
+    UnknownBuildType,
+
+    InvalidRuns(Value),
+
+    InvalidMaxRegression(Value),
+}
+
+impl super::InnerParseError for InnerParseError {
+    fn note(&self) -> Option<String> {
+        use InnerParseError::*;
+        match self {
+            InvalidRuns(runs) => Some(format!(
+                "`{}` is not a positive number",
+                runs
+            )),
+            InvalidMaxRegression(max_regression) => Some(format!(
+                "`{}` is not a percentage (ex. `5` or `5%`)",
+                max_regression
+            )),
+            _ => None,
+        }
+    }
+}
+
+impl From<InnerParseError> for Rc<dyn super::InnerParseError> {
+    fn from(value: InnerParseError) -> Self { Rc::new(value) }
+}
+
+#[derive(Debug, Clone)]
+enum InnerExecuteError {
+    InvalidCurrentDir(Rc<io::Error>),
+
+    CannotLoadConfiguration(configuration::LoadError),
+
+    BuildError(BuildError),
+    CouldNotListBenches(Rc<io::Error>),
+    NoBenches,
+
+    /// Neither a profile nor a dir.
+    BaselineNotFound(Value),
+
+    BenchFailedSpawn(Value, Rc<io::Error>),
+    BenchFailedWait(Value, Rc<io::Error>),
+    BenchFailedExitCode(Value, i32),
+    BenchKilled(Value),
+
+    BenchesRegressed(Rc<[Value]>),
+}
+
+impl super::InnerExecuteError for InnerExecuteError {
+    fn code(&self) -> Option<Code> {
+        use InnerExecuteError::*;
+        match self {
+            CannotLoadConfiguration(err) => Some(err.code()),
+            BuildError(err) => Some(err.code()),
+            _ => None,
+        }
+    }
+
+    fn note(&self) -> Option<String> {
+        use InnerExecuteError::*;
+        match self {
+            CannotLoadConfiguration(err) => err.note(),
+            InvalidCurrentDir(err) | CouldNotListBenches(err) => Some(err.to_string()),
+            BaselineNotFound(baseline) => Some(format!(
+                "no baseline `{}` was saved",
+                baseline
+            )),
+            BenchFailedSpawn(bench, err) | BenchFailedWait(bench, err) => Some(format!(
+                "`{}`: {}",
+                bench, err
+            )),
+            BenchFailedExitCode(bench, code) => Some(format!(
+                "`{}` exited with code {}",
+                bench, code
+            )),
+            BenchKilled(bench) => Some(format!(
+                "`{}` was killed by a signal",
+                bench
+            )),
+            BenchesRegressed(benches) => Some(format!(
+                "regressed: {}",
+                benches.join(", ")
+            )),
+            _ => None,
+        }
+    }
+}
+
+impl From<InnerExecuteError> for Rc<dyn super::InnerExecuteError> {
+    fn from(value: InnerExecuteError) -> Self { Rc::new(value) }
+}
+
+fn parse_build_type(build_type: Value) -> Result<BuildType, InnerParseError> {
+    use InnerParseError::*;
+
+    Ok(build_type
+        .parse()
+        .map_err(|()| UnknownBuildType)?)
+}
+
+fn parse_runs(runs: Value) -> Result<usize, InnerParseError> {
+    use InnerParseError::*;
+
+    runs.parse()
+        .ok()
+        .filter(|runs| *runs > 0)
+        .ok_or_else(|| InvalidRuns(runs.clone()))
+}
+
+fn parse_max_regression(max_regression: Value) -> Result<f64, InnerParseError> {
+    use InnerParseError::*;
+
+    // `5` or `5%`
+    max_regression
+        .trim_end_matches('%')
+        .parse()
+        .ok()
+        .filter(|max_regression: &f64| *max_regression >= 0.0)
+        .ok_or_else(|| InvalidMaxRegression(max_regression.clone()))
+}
+
+/// A built bench binary, with the runner of the profile it was built with.
+struct Bench {
+    name: Value,
+    artifact_file: Dir,
+    runner: Vec<Value>,
+}
+
+/// Builds the project and its benches with `profile_name`.
+fn build_benches(
+    config: &Configuration,
+    build_type: Option<BuildType>,
+    profile_name: &str,
+) -> Result<Vec<Bench>, InnerExecuteError> {
+    use InnerExecuteError::*;
+
+    let profile = config
+        .build(
+            build_type,
+            profile_name,
+            false,
+        )
+        .map_err(BuildError)?;
+    let targets = config
+        .bench_targets(profile_name, profile)
+        .map_err(Rc::new)
+        .map_err(CouldNotListBenches)?;
+    config
+        .build_programs(
+            profile,
+            profile_name,
+            &targets,
+        )
+        .map_err(BuildError)?;
+
+    let runner = profile
+        .runner()
+        .iter()
+        .flat_map(|runner| runner.iter())
+        .cloned()
+        .collect::<Vec<_>>();
+    Ok(targets
+        .into_iter()
+        .map(|(name, target)| Bench {
+            name,
+            artifact_file: target.artifact_file,
+            runner: runner.clone(),
+        })
+        .collect())
+}
+
+/// How long one run of `bench` takes, its output is not shown so that it does not
+/// get in the way of the comparison.
+fn time_run(bench: &Bench, project_dir: &Path) -> Result<Duration, InnerExecuteError> {
+    use InnerExecuteError::*;
+
+    let mut command = match bench
+        .runner
+        .split_first()
+    {
+        Some((runner, runner_args)) => {
+            let mut command = Command::new(&**runner);
+            command
+                .args(
+                    runner_args
+                        .iter()
+                        .map(Rc::as_ref),
+                )
+                .arg(&*bench.artifact_file);
+            command
+        },
+        None => Command::new(&*bench.artifact_file),
+    };
+
+    let started = Instant::now();
+    let code = command
+        .current_dir(project_dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::inherit())
+        .spawn()
+        .map_err(Rc::new)
+        .map_err(|err| {
+            BenchFailedSpawn(
+                bench
+                    .name
+                    .clone(),
+                err,
+            )
+        })?
+        .wait()
+        .map_err(Rc::new)
+        .map_err(|err| {
+            BenchFailedWait(
+                bench
+                    .name
+                    .clone(),
+                err,
+            )
+        })?
+        .code()
+        .ok_or_else(|| {
+            BenchKilled(
+                bench
+                    .name
+                    .clone(),
+            )
+        })?;
+    let elapsed = started.elapsed();
+    (code == 0).or_else_err(|| {
+        BenchFailedExitCode(
+            bench
+                .name
+                .clone(),
+            code,
+        )
+    })?;
+
+    Ok(elapsed)
+}
+
+fn median(mut durations: Vec<Duration>) -> Duration {
+    durations.sort();
+    durations[durations.len() / 2]
+}
+
+fn millis(duration: Duration) -> String {
+    format!(
+        "{:.2}ms",
+        duration.as_secs_f64() * 1000.0
+    )
+}
+
+const FLAGS: &[Flag] = &[
+    Flag::one(
+        "is",
+        "type",
+        "Build type (`binary` or `library`) when the project is both",
+    ),
+    Flag::one(
+        "profile",
+        "name",
+        "Profile to use, `default` when not given",
+    ),
+    Flag::one(
+        "baseline",
+        "profile or dir",
+        "Profile, or dir of earlier bench binaries, to compare to",
+    ),
+    Flag::one(
+        "runs",
+        "count",
+        "How many times each bench is run",
+    ),
+    Flag::one(
+        "max-regression",
+        "percent",
+        "How much slower than the baseline a bench may be",
+    ),
+];
+
+impl super::Subcommand for Subcommand {
+    fn parse(
+        mut flags: IndexMap<Value, Rc<[Value]>>,
+        _post_dash_dash: impl Iterator<Item = String>,
+    ) -> Result<Rc<dyn super::Subcommand>, Rc<dyn super::InnerParseError>> {
+        let build_type = flags
+            .shift_remove("is")
+            .and_then(super::single_value)
+            .map(parse_build_type)
+            .transpose()?;
+
+        let profile = flags
+            .shift_remove("profile")
+            .and_then(super::single_value)
+            .unwrap_or_else(|| DEFAULT_PROFILE.into());
+
+        let baseline = flags
+            .shift_remove("baseline")
+            .and_then(super::single_value);
+
+        let runs = flags
+            .shift_remove("runs")
+            .and_then(super::single_value)
+            .map(parse_runs)
+            .transpose()?
+            .unwrap_or(DEFAULT_RUNS);
+
+        let max_regression = flags
+            .shift_remove("max-regression")
+            .and_then(super::single_value)
+            .map(parse_max_regression)
+            .transpose()?;
+
+        Ok(Rc::new(Subcommand {
+            build_type,
+            profile,
+            baseline,
+            runs,
+            max_regression,
+        }))
+    }
+
+    fn flags() -> &'static [Flag] { FLAGS }
+
+    fn execute(&self) -> Result<(), Rc<dyn super::InnerExecuteError>> {
+        use InnerExecuteError::*;
+
+        let project_dir = Dir::from(
+            std::env::current_dir()
+                .map_err(Rc::new)
+                .map_err(InvalidCurrentDir)?,
+        );
+
+        let config = Configuration::load(project_dir.clone()).map_err(CannotLoadConfiguration)?;
+
+        let benches = build_benches(
+            &config,
+            self.build_type,
+            &self.profile,
+        )?;
+        (!benches.is_empty()).or_err(NoBenches)?;
+
+        // a profile is built like the current one, a dir is expected to have
+        // bench binaries of an earlier build (ex. a copy of `target/<version>/<profile>/bench`)
+        let baselines = match &self.baseline {
+            None => Vec::new(),
+            Some(baseline)
+                if config
+                    .profile(baseline)
+                    .is_some() =>
+                build_benches(
+                    &config,
+                    self.build_type,
+                    baseline,
+                )?,
+            Some(baseline) => {
+                let baseline_dir = project_dir.join(&**baseline);
+                baseline_dir
+                    .is_dir()
+                    .or_else_err(|| BaselineNotFound(baseline.clone()))?;
+                benches
+                    .iter()
+                    .filter_map(|bench| {
+                        let artifact_file = baseline_dir.join(
+                            bench
+                                .artifact_file
+                                .file_name()?,
+                        );
+                        artifact_file
+                            .is_file()
+                            .then(|| Bench {
+                                name: bench
+                                    .name
+                                    .clone(),
+                                artifact_file: artifact_file.into(),
+                                runner: bench
+                                    .runner
+                                    .clone(),
+                            })
+                    })
+                    .collect()
+            },
+        };
+
+        let mut results = Vec::new();
+        for bench in &benches {
+            let baseline = baselines
+                .iter()
+                .find(|baseline| baseline.name == bench.name);
+
+            println!(
+                "running bench {} ({} runs)",
+                bench.name, self.runs
+            );
+            // alternated, so that the machine getting busier affects both the same
+            let mut times = Vec::new();
+            let mut baseline_times = Vec::new();
+            for _ in 0..self.runs {
+                if let Some(baseline) = baseline {
+                    baseline_times.push(time_run(
+                        baseline,
+                        &project_dir,
+                    )?);
+                }
+                times.push(time_run(bench, &project_dir)?);
+            }
+
+            results.push((
+                bench
+                    .name
+                    .clone(),
+                median(times),
+                baseline.map(|_| median(baseline_times)),
+            ));
+        }
+
+        let mut regressed = Vec::new();
+        match &self.baseline {
+            None => {
+                println!(
+                    "{:<24} {:>12}",
+                    "bench", "median"
+                );
+                for (name, time, _) in &results {
+                    println!(
+                        "{:<24} {:>12}",
+                        name,
+                        millis(*time)
+                    );
+                }
+            },
+            Some(baseline) => {
+                println!(
+                    "{:<24} {:>12} {:>12} {:>9}",
+                    "bench", "baseline", "current", "delta"
+                );
+                for (name, time, baseline_time) in &results {
+                    let Some(baseline_time) = baseline_time else {
+                        println!(
+                            "{:<24} {:>12} {:>12} {:>9}",
+                            name,
+                            "-",
+                            millis(*time),
+                            "new"
+                        );
+                        continue;
+                    };
+                    let delta = (time.as_secs_f64() / baseline_time.as_secs_f64() - 1.0) * 100.0;
+                    println!(
+                        "{:<24} {:>12} {:>12} {:>8.1}%",
+                        name,
+                        millis(*baseline_time),
+                        millis(*time),
+                        delta
+                    );
+                    if self
+                        .max_regression
+                        .is_some_and(|max_regression| delta > max_regression)
+                    {
+                        regressed.push(name.clone());
+                    }
+                }
+                println!(
+                    "compared against {}",
+                    baseline
+                );
+            },
+        }
+
+        regressed
+            .is_empty()
+            .or_else_err(|| BenchesRegressed(regressed.into()))?;
+
+        Ok(())
+    }
+}
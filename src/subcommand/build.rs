@@ -1,35 +1,105 @@
+use std::fs::File;
 use std::io;
+use std::num::NonZeroUsize;
+use std::process::Command;
+use std::process::Stdio;
 use std::rc::Rc;
+use std::thread;
 
 use indexmap::IndexMap;
 
+use super::Flag;
+use crate::ci;
 use crate::configuration;
+use crate::configuration::BuildOptions;
+use crate::configuration::ConfigSource;
 use crate::configuration::Configuration;
+use crate::error_code::Code;
+use crate::error_code::ErrorCode;
+use crate::error_code::Exit;
+use crate::jobserver;
 use crate::lsd::Value;
 use crate::profile;
 use crate::profile::DEFAULT_PROFILE;
+use crate::progress;
 use crate::util::BoolGuardExt;
 use crate::BuildError;
 use crate::BuildType;
 use crate::Dir;
+use crate::Emit;
 
 pub struct Subcommand {
     build_type: Option<BuildType>,
 
-    profile: profile::Name,
+    profiles: Profiles,
+
+    container_image: Option<Value>,
+
+    toolchain: Option<Value>,
+
+    /// Show output of dependency builds even when they succeed.
+    verbose: bool,
+
+    /// Fail when a vendored dependency differs from its contents pinned in `build++.lock`.
+    strict: bool,
+
+    /// Fail when files have more compiler warnings than in the baseline of the profile.
+    deny_new_warnings: bool,
+
+    /// Rebuild from scratch instead of only what changed.
+    force: bool,
+
+    /// Jobs (ex. compilers, or builds of profiles) that may run at once, through a jobserver
+    /// shared with everything build++ runs. Unlimited (or limited by the jobserver build++
+    /// was ran with, ex. by `make -j8`) when not given.
+    jobs: Option<NonZeroUsize>,
+
+    /// Group (from `group` of the configuration) whose tests and benches are built as well.
+    group: Option<Value>,
+
+    /// What to leave in the target dir instead of building (ex. assembly).
+    emit: Option<Emit>,
+
+    extra_flags: Rc<[Value]>,
+
+    /// Manifest to load instead of `build++.lsd`, `-` for stdin.
+    config: Option<Value>,
+}
+
+enum Profiles {
+    One(profile::Name),
+    Many(Rc<[profile::Name]>),
+    /// Every profile of the configuration (unless it has a profile named `all`).
+    All,
 }
 
+/// Special `-profile` value for building every profile.
+const ALL_PROFILES: &str = "all";
+
 #[derive(Debug, Clone)]
 enum InnerParseError {
-    FoundExtraFlags(Rc<[Value]>),
-
-    BuildTypeHasToHaveExactlyOneValue,
     UnknownBuildType,
 
-    ProfileHasToHaveExactlyOneValue,
+    ProfilesHasToHaveAValue,
+    ProfileAndProfilesCannotBeUsedTogether,
+
+    UnknownEmit,
+    EmitAndGroupCannotBeUsedTogether,
+
+    InvalidJobs(Value),
 }
 
 impl super::InnerParseError for InnerParseError {
+    fn note(&self) -> Option<String> {
+        use InnerParseError::*;
+        match self {
+            InvalidJobs(jobs) => Some(format!(
+                "`{}` is not a positive number",
+                jobs
+            )),
+            _ => None,
+        }
+    }
 }
 
 impl From<InnerParseError> for Rc<dyn super::InnerParseError> {
@@ -43,80 +113,255 @@ enum InnerExecuteError {
     CannotLoadConfiguration(configuration::LoadError),
 
     BuildError(BuildError),
+
+    CouldNotUseJobserver(Rc<io::Error>),
+
+    CouldNotFindOwnExecutable(Rc<io::Error>),
+    ProfileBuildFailedSpawn(profile::Name, Rc<io::Error>),
+    ProfileBuildFailedWait(profile::Name, Rc<io::Error>),
+    /// With the highest exit code of their builds.
+    ProfilesFailed(Rc<[profile::Name]>, Exit),
 }
 
 impl super::InnerExecuteError for InnerExecuteError {
+    fn code(&self) -> Option<Code> {
+        use InnerExecuteError::*;
+        match self {
+            CannotLoadConfiguration(err) => Some(err.code()),
+            BuildError(err) => Some(err.code()),
+            _ => None,
+        }
+    }
+
+    fn exit(&self) -> Exit {
+        match self {
+            InnerExecuteError::ProfilesFailed(_, exit) => *exit,
+            _ => self
+                .code()
+                .map(Exit::from)
+                .unwrap_or(Exit::Failure),
+        }
+    }
+
+    fn note(&self) -> Option<String> {
+        use InnerExecuteError::*;
+        match self {
+            CannotLoadConfiguration(err) => err.note(),
+            InvalidCurrentDir(err) | CouldNotUseJobserver(err) | CouldNotFindOwnExecutable(err) =>
+                Some(err.to_string()),
+            ProfileBuildFailedSpawn(profile, err) | ProfileBuildFailedWait(profile, err) => Some(
+                format!("`{}`: {}", profile, err),
+            ),
+            ProfilesFailed(profiles, _) => Some(format!(
+                "failed: {}",
+                profiles.join(", ")
+            )),
+            _ => None,
+        }
+    }
 }
 
 impl From<InnerExecuteError> for Rc<dyn super::InnerExecuteError> {
     fn from(value: InnerExecuteError) -> Self { Rc::new(value) }
 }
 
-fn parse_build_type(build_type: Rc<[Value]>) -> Result<BuildType, InnerParseError> {
-    use InnerParseError::*;
-
-    let mut build_type_values = build_type.iter();
-    let build_type = build_type_values
-        .next()
-        .ok_or(BuildTypeHasToHaveExactlyOneValue)?;
-    build_type_values
-        .next()
-        .is_none()
-        .ok_or(BuildTypeHasToHaveExactlyOneValue)?;
-
-    Ok(build_type
+fn parse_build_type(build_type: Value) -> Result<BuildType, InnerParseError> {
+    build_type
         .parse()
-        .map_err(|()| UnknownBuildType)?)
+        .map_err(|()| InnerParseError::UnknownBuildType)
 }
 
-fn parse_profile(profile: Rc<[Value]>) -> Result<Rc<str>, InnerParseError> {
-    use InnerParseError::*;
+fn parse_profiles(profiles: Rc<[Value]>) -> Rc<[profile::Name]> {
+    // both `-profiles a,b,c` and `-profiles a b c`
+    profiles
+        .iter()
+        .flat_map(|profiles| profiles.split(','))
+        .map(str::trim)
+        .filter(|profile| !profile.is_empty())
+        .map(profile::Name::from)
+        .collect()
+}
 
-    let mut profile_values = profile.iter();
-    let profile = profile_values
-        .next()
-        .ok_or(ProfileHasToHaveExactlyOneValue)?;
-    profile_values
-        .next()
-        .is_none()
-        .ok_or(ProfileHasToHaveExactlyOneValue)?;
+fn parse_jobs(jobs: Value) -> Result<NonZeroUsize, InnerParseError> {
+    jobs.parse()
+        .map_err(|_| InnerParseError::InvalidJobs(jobs.clone()))
+}
 
-    Ok(profile.clone())
+fn parse_emit(emit: Value) -> Result<Emit, InnerParseError> {
+    emit.parse()
+        .map_err(|()| InnerParseError::UnknownEmit)
 }
 
+const FLAGS: &[Flag] = &[
+    Flag::one(
+        "is",
+        "type",
+        "Build type (`binary` or `library`) when the project is both",
+    ),
+    Flag::one(
+        "profile",
+        "name",
+        "Profile to use, `default` when not given",
+    ),
+    Flag::many(
+        "profiles",
+        "name",
+        "Profiles to build, concurrently",
+    ),
+    Flag::one(
+        "in-container",
+        "image",
+        "Container image to build inside of",
+    ),
+    Flag::one(
+        "toolchain",
+        "name",
+        "Toolchain of the configuration to cross-compile with",
+    ),
+    Flag::optional(
+        "config",
+        "path",
+        "Manifest to load instead of `build++.lsd`, stdin without a path",
+    ),
+    Flag::switch(
+        "verbose",
+        "Show output of dependency builds even when they succeed",
+    )
+    .aliases(&["v"]),
+    Flag::switch(
+        "strict",
+        "Fail when a vendored dependency differs from `build++.lock`",
+    ),
+    Flag::switch(
+        "deny-new-warnings",
+        "Fail when files have more warnings than in the baseline",
+    ),
+    Flag::switch(
+        "force",
+        "Rebuild from scratch instead of only what changed",
+    ),
+    Flag::one(
+        "group",
+        "name",
+        "Group whose tests and benches are built as well",
+    ),
+    Flag::one(
+        "emit",
+        "kind",
+        "Emit `pp`, `asm` or `ir` of the sources instead of building",
+    ),
+    Flag::one(
+        "jobs",
+        "count",
+        "How many jobs may run at once",
+    )
+    .aliases(&["j"]),
+];
+
 impl super::Subcommand for Subcommand {
     fn parse(
         mut flags: IndexMap<Value, Rc<[Value]>>,
-        _post_dash_dash: impl Iterator<Item = String>,
+        post_dash_dash: impl Iterator<Item = String>,
     ) -> Result<Rc<dyn super::Subcommand>, Rc<dyn super::InnerParseError>> {
         use InnerParseError::*;
 
         let build_type = flags
-            .remove("is")
+            .shift_remove("is")
+            .and_then(super::single_value)
             .map(parse_build_type)
             .transpose()?;
 
         let profile = flags
-            .remove("profile")
-            .map(parse_profile)
-            .transpose()?
-            .unwrap_or_else(|| DEFAULT_PROFILE.into());
-
-        let extra_flags = flags.into_keys();
-        if extra_flags.len() > 0 {
-            return Err(FoundExtraFlags(
-                extra_flags
-                    .collect::<Vec<_>>()
-                    .into(),
-            ))?;
+            .shift_remove("profile")
+            .and_then(super::single_value);
+
+        let many_profiles = flags
+            .shift_remove("profiles")
+            .map(parse_profiles);
+        if many_profiles
+            .as_ref()
+            .is_some_and(|profiles| profiles.is_empty())
+        {
+            return Err(ProfilesHasToHaveAValue)?;
         }
 
+        let profiles = match (profile, many_profiles) {
+            (Some(_), Some(_)) => return Err(ProfileAndProfilesCannotBeUsedTogether)?,
+            (Some(profile), None) if profile.eq_ignore_ascii_case(ALL_PROFILES) => Profiles::All,
+            (Some(profile), None) => Profiles::One(profile),
+            (None, Some(profiles)) => Profiles::Many(profiles),
+            (None, None) => Profiles::One(DEFAULT_PROFILE.into()),
+        };
+
+        let container_image = flags
+            .shift_remove("in-container")
+            .and_then(super::single_value);
+
+        let toolchain = flags
+            .shift_remove("toolchain")
+            .and_then(super::single_value);
+
+        let config = flags
+            .shift_remove("config")
+            .map(super::config_value);
+
+        let verbose = flags
+            .shift_remove("verbose")
+            .is_some();
+
+        let strict = flags
+            .shift_remove("strict")
+            .is_some();
+
+        let deny_new_warnings = flags
+            .shift_remove("deny-new-warnings")
+            .is_some();
+
+        let force = flags
+            .shift_remove("force")
+            .is_some();
+
+        let group = flags
+            .shift_remove("group")
+            .and_then(super::single_value);
+
+        let emit = flags
+            .shift_remove("emit")
+            .and_then(super::single_value)
+            .map(parse_emit)
+            .transpose()?;
+        (group.is_none() || emit.is_none()).or_err(EmitAndGroupCannotBeUsedTogether)?;
+
+        let jobs = flags
+            .shift_remove("jobs")
+            .and_then(super::single_value)
+            .map(parse_jobs)
+            .transpose()?;
+
+        // passed to the compiler as-is
+        let compiler_flags = post_dash_dash
+            .map(Value::from)
+            .collect();
+
         Ok(Rc::new(Subcommand {
             build_type,
-            profile,
+            profiles,
+            container_image,
+            toolchain,
+            verbose,
+            strict,
+            deny_new_warnings,
+            force,
+            emit,
+            group,
+            jobs,
+            extra_flags: compiler_flags,
+            config,
         }))
     }
 
+    fn flags() -> &'static [Flag] { FLAGS }
+
     fn execute(&self) -> Result<(), Rc<dyn super::InnerExecuteError>> {
         use InnerExecuteError::*;
 
@@ -126,15 +371,267 @@ impl super::Subcommand for Subcommand {
                 .map_err(InvalidCurrentDir)?,
         );
 
-        let config = Configuration::load(project_dir).map_err(CannotLoadConfiguration)?;
+        // started before anything runs, so that builds of dependencies and profiles share it
+        let _jobserver = self
+            .jobs
+            .and_then(|jobs| {
+                jobserver::start(jobs)
+                    .map_err(|err| {
+                        eprintln!(
+                            "warning: could not start a jobserver, building without a limit of jobs: {}",
+                            err
+                        )
+                    })
+                    .ok()
+            });
+
+        configuration::set_build_options(BuildOptions {
+            container_image: self
+                .container_image
+                .clone(),
+            // relative to where build++ was ran from
+            toolchain: self
+                .toolchain
+                .as_ref()
+                .map(|toolchain| Dir::from(project_dir.join(&**toolchain))),
+            verbose: self.verbose,
+            quiet: false,
+            strict: self.strict,
+            deny_new_warnings: self.deny_new_warnings,
+        });
+
+        let source = self
+            .config
+            .as_ref()
+            .map(|config| ConfigSource::from_argument(&project_dir, config))
+            .unwrap_or_default();
+        let config = Configuration::load_from(project_dir.clone(), source)
+            .map_err(CannotLoadConfiguration)?
+            .with_extra_flags(
+                self.extra_flags
+                    .clone(),
+            );
+
+        let profiles = match &self.profiles {
+            Profiles::One(profile) => return self.build_one(&config, profile),
+            Profiles::All
+                if config
+                    .profile(ALL_PROFILES)
+                    .is_some() =>
+                return self.build_one(&config, ALL_PROFILES),
+            Profiles::All => config
+                .profiles()
+                .keys()
+                .cloned()
+                .collect(),
+            Profiles::Many(profiles) => profiles.clone(),
+        };
+
+        // dependencies may be shared between profiles (ex. those with a fixed profile),
+        // so they are all resolved and cached here first, one at a time
+        for profile in profiles.iter() {
+            config
+                .profile(profile)
+                .ok_or_else(|| crate::BuildError::InvalidProfile(profile.clone()))
+                .map_err(BuildError)?;
+            config
+                .prepare_dependencies(profile)
+                .map_err(BuildError)?;
+        }
+
+        // target dirs of profiles do not overlap, so their builds can run concurrently
+        let executable = std::env::current_exe()
+            .map_err(Rc::new)
+            .map_err(CouldNotFindOwnExecutable)?;
+        // each build takes a job slot, this build++ only waits for them meanwhile
+        let lent = jobserver::lend()
+            .map_err(Rc::new)
+            .map_err(CouldNotUseJobserver)?;
+        let mut builds = Vec::new();
+        for profile in profiles.iter() {
+            let token = jobserver::acquire()
+                .map_err(Rc::new)
+                .map_err(CouldNotUseJobserver)?;
+
+            let mut command = Command::new(&executable);
+            command
+                .arg("build")
+                .arg("-profile")
+                .arg(&**profile);
+            if let Some(build_type) = self.build_type {
+                command
+                    .arg("-is")
+                    .arg(build_type.to_string());
+            }
+            if let Some(toolchain) = &self.toolchain {
+                command
+                    .arg("-toolchain")
+                    .arg(&**toolchain);
+            }
+            if let Some(image) = &self.container_image {
+                command
+                    .arg("-in-container")
+                    .arg(&**image);
+            }
+            if self.verbose {
+                command.arg("-verbose");
+            }
+            if self.strict {
+                command.arg("-strict");
+            }
+            if self.deny_new_warnings {
+                command.arg("-deny-new-warnings");
+            }
+            if self.force {
+                command.arg("-force");
+            }
+            if ci::is_enabled() {
+                command.arg("-ci");
+            }
+            if let Some(emit) = self.emit {
+                command
+                    .arg("-emit")
+                    .arg(emit.to_string());
+            }
+            if let Some(group) = &self.group {
+                command
+                    .arg("-group")
+                    .arg(&**group);
+            }
+            // stdin can only be read once, so children read the kept copy of it
+            let stdin = match self
+                .config
+                .as_deref()
+            {
+                Some(ConfigSource::STDIN) => Stdio::from(
+                    File::open(config.config_file())
+                        .map_err(Rc::new)
+                        .map_err(|err| ProfileBuildFailedSpawn(profile.clone(), err))?,
+                ),
+                _ => Stdio::null(),
+            };
+            if let Some(config) = &self.config {
+                command.arg("-config");
+                if &**config != ConfigSource::STDIN {
+                    command.arg(&**config);
+                }
+            }
+            if !self
+                .extra_flags
+                .is_empty()
+            {
+                command
+                    .arg("--")
+                    .args(
+                        self.extra_flags
+                            .iter()
+                            .map(Rc::as_ref),
+                    );
+            }
+            jobserver::share_with(&mut command);
+            let mut child = command
+                .env(
+                    progress::PROGRESS_VARIABLE,
+                    "plain",
+                )
+                .current_dir(&*project_dir)
+                .stdin(stdin)
+                .spawn()
+                .map_err(Rc::new)
+                .map_err(|err| ProfileBuildFailedSpawn(profile.clone(), err))?;
+
+            // the slot is given back as soon as the build is done, not once it is reported
+            let waiting = thread::spawn(move || {
+                let status = child.wait();
+                drop(token);
+                status
+            });
+            builds.push((profile, waiting));
+        }
+
+        let mut failed = Vec::new();
+        let mut exit = Exit::Failure;
+        for (profile, waiting) in builds {
+            let status = waiting
+                .join()
+                .unwrap_or_else(|_| {
+                    Err(io::Error::other(
+                        "waiting for the build panicked",
+                    ))
+                })
+                .map_err(Rc::new)
+                .map_err(|err| ProfileBuildFailedWait(profile.clone(), err))?;
+            match status.success() {
+                true => println!("profile {} ... ok", profile),
+                false => {
+                    println!(
+                        "profile {} ... FAILED",
+                        profile
+                    );
+                    failed.push(profile.clone());
+                    exit = exit.max(Exit::from_status(
+                        status.code(),
+                    ));
+                },
+            }
+        }
+
+        drop(lent);
+
+        failed
+            .is_empty()
+            .or_else_err(|| ProfilesFailed(failed.into(), exit))?;
+
+        Ok(())
+    }
+}
+
+impl Subcommand {
+    fn build_one(
+        &self,
+        config: &Configuration,
+        profile: &str,
+    ) -> Result<(), Rc<dyn super::InnerExecuteError>> {
+        use InnerExecuteError::*;
 
-        config
-            .build(
-                self.build_type,
-                &self.profile,
-                true,
-            )
-            .map_err(BuildError)?;
+        if let Some(emit) = self.emit {
+            let emitted = config
+                .emit(self.build_type, profile, emit)
+                .map_err(BuildError)?;
+            println!(
+                "emitted {}",
+                emitted.display()
+            );
+            return Ok(());
+        }
+
+        match &self.group {
+            Some(group) => drop(
+                config
+                    .build_group(
+                        group,
+                        self.build_type,
+                        profile,
+                        self.force,
+                    )
+                    .map_err(BuildError)?,
+            ),
+            None => drop(
+                config
+                    .build(
+                        self.build_type,
+                        profile,
+                        self.force,
+                    )
+                    .map_err(BuildError)?,
+            ),
+        }
+
+        if config.deploys_after_build(profile) {
+            config
+                .deploy(self.build_type, profile)
+                .map_err(BuildError)?;
+        }
 
         Ok(())
     }
@@ -0,0 +1,267 @@
+use std::env;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::process::Command;
+use std::rc::Rc;
+
+use indexmap::IndexMap;
+
+use super::Flag;
+use crate::configuration;
+use crate::configuration::Configuration;
+use crate::error_code::Code;
+use crate::error_code::ErrorCode;
+use crate::lsd::LSDParseError;
+use crate::lsd::Value;
+use crate::lsd::LSD;
+use crate::util::BoolGuardExt;
+use crate::Dir;
+use crate::Version;
+
+pub struct Subcommand {
+    to: Bump,
+
+    /// Whether to create a git tag `v<version>` for the new version.
+    tag: bool,
+}
+
+#[derive(Debug, Clone)]
+enum Bump {
+    Major,
+    Minor,
+    Patch,
+    Exact(Version),
+}
+
+#[derive(Debug, Clone)]
+enum InnerParseError {
+    MissingTo,
+    InvalidVersion(Value),
+}
+
+impl super::InnerParseError for InnerParseError {
+    fn note(&self) -> Option<String> {
+        use InnerParseError::*;
+        match self {
+            InvalidVersion(to) => Some(format!(
+                "`{}` is not `major`, `minor`, `patch` or `major.minor.patch`",
+                to
+            )),
+            _ => None,
+        }
+    }
+}
+
+impl From<InnerParseError> for Rc<dyn super::InnerParseError> {
+    fn from(value: InnerParseError) -> Self { Rc::new(value) }
+}
+
+#[derive(Debug, Clone)]
+enum InnerExecuteError {
+    InvalidCurrentDir(Rc<io::Error>),
+
+    CannotLoadConfiguration(configuration::LoadError),
+
+    CurrentVersionIsNotMajorMinorPatch(Version),
+
+    CouldNotReadConfiguration(Rc<io::Error>),
+    CouldNotParseConfiguration(LSDParseError),
+    CouldNotWriteConfiguration(Rc<io::Error>),
+
+    GitNotFound,
+    GitFailedSpawn(Rc<io::Error>),
+    GitFailedExitCode(i32),
+    GitKilled,
+}
+
+impl super::InnerExecuteError for InnerExecuteError {
+    fn code(&self) -> Option<Code> {
+        use InnerExecuteError::*;
+        match self {
+            CannotLoadConfiguration(err) => Some(err.code()),
+            _ => None,
+        }
+    }
+
+    fn note(&self) -> Option<String> {
+        use InnerExecuteError::*;
+        match self {
+            CannotLoadConfiguration(err) => err.note(),
+            InvalidCurrentDir(err)
+            | CouldNotReadConfiguration(err)
+            | CouldNotWriteConfiguration(err)
+            | GitFailedSpawn(err) => Some(err.to_string()),
+            CurrentVersionIsNotMajorMinorPatch(version) => Some(format!(
+                "`{}` is not `major.minor.patch`",
+                version
+            )),
+            CouldNotParseConfiguration(err) => Some(err.to_string()),
+            GitFailedExitCode(code) => Some(format!(
+                "`git` exited with code {}",
+                code
+            )),
+            _ => None,
+        }
+    }
+}
+
+impl From<InnerExecuteError> for Rc<dyn super::InnerExecuteError> {
+    fn from(value: InnerExecuteError) -> Self { Rc::new(value) }
+}
+
+/// `major.minor.patch` of `version`, pre-release and build metadata (ex. `-rc.1`) are ignored.
+fn major_minor_patch(version: &str) -> Option<(u64, u64, u64)> {
+    let mut parts = version
+        .split(['-', '+'])
+        .next()?
+        .split('.');
+    let mut part = || {
+        parts
+            .next()?
+            .parse()
+            .ok()
+    };
+    let major_minor_patch = (part()?, part()?, part()?);
+    parts
+        .next()
+        .is_none()
+        .then_some(major_minor_patch)
+}
+
+fn parse_to(to: Value) -> Result<Bump, InnerParseError> {
+    use InnerParseError::*;
+
+    Ok(match &*to.to_lowercase() {
+        "major" => Bump::Major,
+        "minor" => Bump::Minor,
+        "patch" => Bump::Patch,
+        _ => {
+            major_minor_patch(&to).ok_or_else(|| InvalidVersion(to.clone()))?;
+            Bump::Exact(to)
+        },
+    })
+}
+
+impl Bump {
+    fn apply(&self, current: &Version) -> Result<Version, InnerExecuteError> {
+        use InnerExecuteError::*;
+
+        if let Bump::Exact(version) = self {
+            return Ok(version.clone());
+        }
+
+        let (major, minor, patch) = major_minor_patch(current)
+            .ok_or_else(|| CurrentVersionIsNotMajorMinorPatch(current.clone()))?;
+        Ok(match self {
+            Bump::Major => format!("{}.0.0", major + 1),
+            Bump::Minor => format!("{}.{}.0", major, minor + 1),
+            Bump::Patch => format!(
+                "{}.{}.{}",
+                major,
+                minor,
+                patch + 1
+            ),
+            Bump::Exact(_) => unreachable!(),
+        }
+        .into())
+    }
+}
+
+const FLAGS: &[Flag] = &[
+    Flag::one(
+        "to",
+        "version",
+        "`major`, `minor`, `patch` or an exact version",
+    ),
+    Flag::switch(
+        "tag",
+        "Create a git tag `v<version>` for the new version",
+    ),
+];
+
+impl super::Subcommand for Subcommand {
+    fn parse(
+        mut flags: IndexMap<Value, Rc<[Value]>>,
+        _post_dash_dash: impl Iterator<Item = String>,
+    ) -> Result<Rc<dyn super::Subcommand>, Rc<dyn super::InnerParseError>> {
+        use InnerParseError::*;
+
+        let to = flags
+            .shift_remove("to")
+            .and_then(super::single_value)
+            .ok_or(MissingTo)?;
+        let to = parse_to(to)?;
+
+        let tag = flags
+            .shift_remove("tag")
+            .is_some();
+
+        Ok(Rc::new(Subcommand {
+            to,
+            tag,
+        }))
+    }
+
+    fn flags() -> &'static [Flag] { FLAGS }
+
+    fn execute(&self) -> Result<(), Rc<dyn super::InnerExecuteError>> {
+        use InnerExecuteError::*;
+
+        let project_dir = Dir::from(
+            env::current_dir()
+                .map_err(Rc::new)
+                .map_err(InvalidCurrentDir)?,
+        );
+
+        let config = Configuration::load(project_dir.clone()).map_err(CannotLoadConfiguration)?;
+
+        let old_version = config.version();
+        let new_version = self
+            .to
+            .apply(&old_version)?;
+
+        // the rest of the configuration is written back as it was read
+        let config_file = config.config_file();
+        let mut lsd = File::open(&config_file)
+            .map_err(Rc::new)
+            .map_err(CouldNotReadConfiguration)
+            .and_then(|file| LSD::parse(file).map_err(CouldNotParseConfiguration))?;
+        if let LSD::Level(level) = &mut lsd {
+            level.insert(
+                "version".into(),
+                LSD::Value(new_version.clone()),
+            );
+        }
+        fs::write(&config_file, lsd.to_string())
+            .map_err(Rc::new)
+            .map_err(CouldNotWriteConfiguration)?;
+
+        println!(
+            "bumped {} from {} to {}",
+            config.project_name(),
+            old_version,
+            new_version
+        );
+
+        if self.tag {
+            let tag = format!("v{}", new_version);
+            let code = Command::new("git")
+                .arg("tag")
+                .arg(&tag)
+                .current_dir(&project_dir)
+                .status()
+                .map_err(|err| match err.kind() {
+                    io::ErrorKind::NotFound => GitNotFound,
+                    _ => GitFailedSpawn(Rc::new(err)),
+                })?
+                .code()
+                .ok_or(GitKilled)?;
+            (code == 0).or_err(GitFailedExitCode(code))?;
+
+            println!("tagged {}", tag);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,146 @@
+use std::env;
+use std::io;
+use std::path::Component;
+use std::path::Path;
+use std::rc::Rc;
+
+use indexmap::IndexMap;
+
+use super::Flag;
+use crate::configuration;
+use crate::configuration::Configuration;
+use crate::error_code::Code;
+use crate::error_code::ErrorCode;
+use crate::lsd::Value;
+use crate::util::BoolGuardExt;
+use crate::Dir;
+
+pub struct Subcommand {
+    alias: Value,
+}
+
+#[derive(Debug, Clone)]
+enum InnerParseError {
+    MissingAlias,
+    /// Aliases name dirs of the cache dir, anything else (ex. `..`) would remove more.
+    InvalidAlias(Value),
+}
+
+impl super::InnerParseError for InnerParseError {
+    fn note(&self) -> Option<String> {
+        use InnerParseError::*;
+        match self {
+            InvalidAlias(alias) => Some(format!(
+                "`{}` is not a single name",
+                alias
+            )),
+            _ => None,
+        }
+    }
+}
+
+impl From<InnerParseError> for Rc<dyn super::InnerParseError> {
+    fn from(value: InnerParseError) -> Self { Rc::new(value) }
+}
+
+#[derive(Debug, Clone)]
+enum InnerExecuteError {
+    InvalidCurrentDir(Rc<io::Error>),
+
+    CannotLoadConfiguration(configuration::LoadError),
+
+    CouldNotRemoveCache(Rc<io::Error>),
+}
+
+impl super::InnerExecuteError for InnerExecuteError {
+    fn code(&self) -> Option<Code> {
+        use InnerExecuteError::*;
+        match self {
+            CannotLoadConfiguration(err) => Some(err.code()),
+            _ => None,
+        }
+    }
+
+    fn note(&self) -> Option<String> {
+        use InnerExecuteError::*;
+        match self {
+            CannotLoadConfiguration(err) => err.note(),
+            InvalidCurrentDir(err) | CouldNotRemoveCache(err) => Some(err.to_string()),
+        }
+    }
+}
+
+impl From<InnerExecuteError> for Rc<dyn super::InnerExecuteError> {
+    fn from(value: InnerExecuteError) -> Self { Rc::new(value) }
+}
+
+fn parse_alias(alias: Value) -> Result<Value, InnerParseError> {
+    use InnerParseError::*;
+
+    let mut components = Path::new(&*alias).components();
+    matches!(
+        (
+            components.next(),
+            components.next()
+        ),
+        (
+            Some(Component::Normal(_)),
+            None
+        )
+    )
+    .or_else_err(|| InvalidAlias(alias.clone()))?;
+
+    Ok(alias.clone())
+}
+
+const FLAGS: &[Flag] = &[Flag::one(
+    "alias",
+    "alias",
+    "Dependency to remove every cached version and profile of",
+)];
+
+impl super::Subcommand for Subcommand {
+    fn parse(
+        mut flags: IndexMap<Value, Rc<[Value]>>,
+        _post_dash_dash: impl Iterator<Item = String>,
+    ) -> Result<Rc<dyn super::Subcommand>, Rc<dyn super::InnerParseError>> {
+        use InnerParseError::*;
+
+        let alias = flags
+            .shift_remove("alias")
+            .and_then(super::single_value)
+            .map(parse_alias)
+            .transpose()?
+            .ok_or(MissingAlias)?;
+
+        Ok(Rc::new(Subcommand { alias }))
+    }
+
+    fn flags() -> &'static [Flag] { FLAGS }
+
+    fn execute(&self) -> Result<(), Rc<dyn super::InnerExecuteError>> {
+        use InnerExecuteError::*;
+
+        let project_dir = Dir::from(
+            env::current_dir()
+                .map_err(Rc::new)
+                .map_err(InvalidCurrentDir)?,
+        );
+
+        let config = Configuration::load(project_dir).map_err(CannotLoadConfiguration)?;
+
+        match config
+            .uncache_dependency(&self.alias)
+            .map_err(Rc::new)
+            .map_err(CouldNotRemoveCache)?
+        {
+            true => println!(
+                "removed {} from the cache, the next build caches it anew",
+                self.alias
+            ),
+            false => println!("{} is not cached", self.alias),
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,187 @@
+use std::env;
+use std::io;
+use std::rc::Rc;
+
+use indexmap::IndexMap;
+
+use super::cache_list::last_used;
+use super::Flag;
+use crate::configuration;
+use crate::configuration::Configuration;
+use crate::error_code::Code;
+use crate::error_code::ErrorCode;
+use crate::lsd::Value;
+use crate::util;
+use crate::Dir;
+
+pub struct Subcommand {
+    alias: Value,
+}
+
+#[derive(Debug, Clone)]
+enum InnerParseError {
+    MissingAlias,
+}
+
+impl super::InnerParseError for InnerParseError {
+}
+
+impl From<InnerParseError> for Rc<dyn super::InnerParseError> {
+    fn from(value: InnerParseError) -> Self { Rc::new(value) }
+}
+
+#[derive(Debug, Clone)]
+enum InnerExecuteError {
+    InvalidCurrentDir(Rc<io::Error>),
+
+    CannotLoadConfiguration(configuration::LoadError),
+
+    CouldNotReadCache(Rc<io::Error>),
+
+    /// With the alias it was likely meant to be.
+    NotCached(Value, Option<Value>),
+}
+
+impl super::InnerExecuteError for InnerExecuteError {
+    fn code(&self) -> Option<Code> {
+        use InnerExecuteError::*;
+        match self {
+            CannotLoadConfiguration(err) => Some(err.code()),
+            _ => None,
+        }
+    }
+
+    fn note(&self) -> Option<String> {
+        use InnerExecuteError::*;
+        match self {
+            CannotLoadConfiguration(err) => err.note(),
+            InvalidCurrentDir(err) | CouldNotReadCache(err) => Some(err.to_string()),
+            NotCached(alias, Some(suggestion)) => Some(format!(
+                "`{}` is not cached, did you mean `{}`?",
+                alias, suggestion
+            )),
+            NotCached(alias, None) => Some(format!(
+                "`{}` is not cached",
+                alias
+            )),
+        }
+    }
+}
+
+impl From<InnerExecuteError> for Rc<dyn super::InnerExecuteError> {
+    fn from(value: InnerExecuteError) -> Self { Rc::new(value) }
+}
+
+const FLAGS: &[Flag] = &[Flag::one(
+    "alias",
+    "alias",
+    "Dependency to show cached versions and profiles of",
+)];
+
+impl super::Subcommand for Subcommand {
+    fn parse(
+        mut flags: IndexMap<Value, Rc<[Value]>>,
+        _post_dash_dash: impl Iterator<Item = String>,
+    ) -> Result<Rc<dyn super::Subcommand>, Rc<dyn super::InnerParseError>> {
+        use InnerParseError::*;
+
+        let alias = flags
+            .shift_remove("alias")
+            .and_then(super::single_value)
+            .ok_or(MissingAlias)?;
+
+        Ok(Rc::new(Subcommand { alias }))
+    }
+
+    fn flags() -> &'static [Flag] { FLAGS }
+
+    fn execute(&self) -> Result<(), Rc<dyn super::InnerExecuteError>> {
+        use InnerExecuteError::*;
+
+        let project_dir = Dir::from(
+            env::current_dir()
+                .map_err(Rc::new)
+                .map_err(InvalidCurrentDir)?,
+        );
+
+        let config = Configuration::load(project_dir).map_err(CannotLoadConfiguration)?;
+
+        let cached = config
+            .cached_dependencies()
+            .map_err(Rc::new)
+            .map_err(CouldNotReadCache)?;
+        let (of_alias, others) = cached
+            .into_iter()
+            .partition::<Vec<_>, _>(|cached| cached.alias == self.alias);
+        if of_alias.is_empty() {
+            let suggestion = util::closest_match(
+                &self.alias,
+                others
+                    .iter()
+                    .map(|cached| &*cached.alias),
+            )
+            .map(Value::from);
+            return Err(NotCached(
+                self.alias
+                    .clone(),
+                suggestion,
+            ))?;
+        }
+
+        println!("{}", self.alias);
+        match config
+            .dependencies()
+            .get(&self.alias)
+        {
+            Some(dependency) => {
+                println!(
+                    "    source {}",
+                    dependency.source()
+                );
+                if let Ok(version) = dependency.current_version() {
+                    if !version.is_empty() {
+                        println!(
+                            "    current version {}",
+                            version
+                        );
+                    }
+                }
+            },
+            None => println!("    no longer a dependency, `buildpp cache clean` removes it"),
+        }
+
+        for cached in of_alias {
+            let size = util::dir_size(&cached.dir)
+                .map_err(Rc::new)
+                .map_err(CouldNotReadCache)?;
+            println!();
+            match cached
+                .parts
+                .as_slice()
+            {
+                [] => println!("    cached"),
+                [version] => println!("    version {}", version),
+                [version, profile, ..] => println!(
+                    "    version {} profile {}",
+                    version, profile
+                ),
+            }
+            println!(
+                "        dir {}",
+                cached
+                    .dir
+                    .display()
+            );
+            println!(
+                "        size {}",
+                util::format_size(size)
+            );
+            println!(
+                "        used {}",
+                last_used(cached.last_used)
+            );
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,136 @@
+use std::env;
+use std::io;
+use std::rc::Rc;
+use std::time::SystemTime;
+
+use indexmap::IndexMap;
+
+use super::Flag;
+use crate::configuration;
+use crate::configuration::Configuration;
+use crate::error_code::Code;
+use crate::error_code::ErrorCode;
+use crate::lsd::Value;
+use crate::util;
+use crate::Dir;
+
+pub struct Subcommand;
+
+#[derive(Debug, Clone)]
+enum InnerExecuteError {
+    InvalidCurrentDir(Rc<io::Error>),
+
+    CannotLoadConfiguration(configuration::LoadError),
+
+    CouldNotReadCache(Rc<io::Error>),
+}
+
+impl super::InnerExecuteError for InnerExecuteError {
+    fn code(&self) -> Option<Code> {
+        use InnerExecuteError::*;
+        match self {
+            CannotLoadConfiguration(err) => Some(err.code()),
+            _ => None,
+        }
+    }
+
+    fn note(&self) -> Option<String> {
+        use InnerExecuteError::*;
+        match self {
+            CannotLoadConfiguration(err) => err.note(),
+            InvalidCurrentDir(err) | CouldNotReadCache(err) => Some(err.to_string()),
+        }
+    }
+}
+
+impl From<InnerExecuteError> for Rc<dyn super::InnerExecuteError> {
+    fn from(value: InnerExecuteError) -> Self { Rc::new(value) }
+}
+
+/// Seconds since the epoch, for [`util::format_ago`].
+pub(super) fn epoch_seconds(time: SystemTime) -> u64 {
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs())
+}
+
+/// When a cached dependency was last used (ex. `5m ago`), `unknown` if it is not known.
+pub(super) fn last_used(last_used: Option<SystemTime>) -> String {
+    last_used.map_or(
+        "unknown".to_string(),
+        |last_used| {
+            util::format_ago(
+                epoch_seconds(SystemTime::now()),
+                epoch_seconds(last_used),
+            )
+        },
+    )
+}
+
+impl super::Subcommand for Subcommand {
+    fn parse(
+        _flags: IndexMap<Value, Rc<[Value]>>,
+        _post_dash_dash: impl Iterator<Item = String>,
+    ) -> Result<Rc<dyn super::Subcommand>, Rc<dyn super::InnerParseError>> {
+        Ok(Rc::new(Subcommand))
+    }
+
+    fn flags() -> &'static [Flag] { &[] }
+
+    fn execute(&self) -> Result<(), Rc<dyn super::InnerExecuteError>> {
+        use InnerExecuteError::*;
+
+        let project_dir = Dir::from(
+            env::current_dir()
+                .map_err(Rc::new)
+                .map_err(InvalidCurrentDir)?,
+        );
+
+        let config = Configuration::load(project_dir).map_err(CannotLoadConfiguration)?;
+
+        let cached = config
+            .cached_dependencies()
+            .map_err(Rc::new)
+            .map_err(CouldNotReadCache)?;
+        if cached.is_empty() {
+            println!(
+                "no dependencies of {} are cached",
+                config.project_name()
+            );
+            return Ok(());
+        }
+
+        let dependencies = config.dependencies();
+        println!(
+            "{:<16} {:<10} {:<12} {:>10} {:>10}",
+            "dependency", "version", "profile", "size", "used"
+        );
+        for cached in cached {
+            let size = util::dir_size(&cached.dir)
+                .map_err(Rc::new)
+                .map_err(CouldNotReadCache)?;
+            let mut parts = cached
+                .parts
+                .iter()
+                .map(Rc::as_ref);
+            println!(
+                "{:<16} {:<10} {:<12} {:>10} {:>10}{}",
+                cached.alias,
+                parts
+                    .next()
+                    .unwrap_or("-"),
+                parts
+                    .next()
+                    .unwrap_or("-"),
+                util::format_size(size),
+                last_used(cached.last_used),
+                // left behind by a dependency that was removed or renamed
+                match dependencies.contains_key(&cached.alias) {
+                    true => "",
+                    false => "  (no longer a dependency)",
+                }
+            );
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,110 @@
+use std::env;
+use std::io;
+use std::rc::Rc;
+
+use indexmap::IndexMap;
+
+use super::Flag;
+use crate::configuration;
+use crate::configuration::Configuration;
+use crate::error_code::Code;
+use crate::error_code::ErrorCode;
+use crate::lsd::Value;
+use crate::util;
+use crate::Dir;
+
+pub struct Subcommand {
+    /// Only remove dirs of versions and profiles no longer in the configuration, instead of
+    /// the whole `target` dir.
+    old: bool,
+}
+
+#[derive(Debug, Clone)]
+enum InnerExecuteError {
+    InvalidCurrentDir(Rc<io::Error>),
+
+    CannotLoadConfiguration(configuration::LoadError),
+
+    CouldNotRemoveTarget(Rc<io::Error>),
+}
+
+impl super::InnerExecuteError for InnerExecuteError {
+    fn code(&self) -> Option<Code> {
+        use InnerExecuteError::*;
+        match self {
+            CannotLoadConfiguration(err) => Some(err.code()),
+            _ => None,
+        }
+    }
+
+    fn note(&self) -> Option<String> {
+        use InnerExecuteError::*;
+        match self {
+            CannotLoadConfiguration(err) => err.note(),
+            InvalidCurrentDir(err) | CouldNotRemoveTarget(err) => Some(err.to_string()),
+        }
+    }
+}
+
+impl From<InnerExecuteError> for Rc<dyn super::InnerExecuteError> {
+    fn from(value: InnerExecuteError) -> Self { Rc::new(value) }
+}
+
+const FLAGS: &[Flag] = &[Flag::switch(
+    "old",
+    "Only remove versions and profiles no longer in the configuration",
+)];
+
+impl super::Subcommand for Subcommand {
+    fn parse(
+        mut flags: IndexMap<Value, Rc<[Value]>>,
+        _post_dash_dash: impl Iterator<Item = String>,
+    ) -> Result<Rc<dyn super::Subcommand>, Rc<dyn super::InnerParseError>> {
+        let old = flags
+            .shift_remove("old")
+            .is_some();
+
+        Ok(Rc::new(Subcommand { old }))
+    }
+
+    fn flags() -> &'static [Flag] { FLAGS }
+
+    fn execute(&self) -> Result<(), Rc<dyn super::InnerExecuteError>> {
+        use InnerExecuteError::*;
+
+        let project_dir = Dir::from(
+            env::current_dir()
+                .map_err(Rc::new)
+                .map_err(InvalidCurrentDir)?,
+        );
+
+        let config = Configuration::load(project_dir).map_err(CannotLoadConfiguration)?;
+
+        if !self.old {
+            let target_dir = config
+                .project_dir()
+                .join("target");
+            util::remove_dir_all(&target_dir)
+                .map_err(Rc::new)
+                .map_err(CouldNotRemoveTarget)?;
+            println!(
+                "removed {}",
+                target_dir.display()
+            );
+            return Ok(());
+        }
+
+        let removed = config
+            .clean_old_targets()
+            .map_err(Rc::new)
+            .map_err(CouldNotRemoveTarget)?;
+        if removed.is_empty() {
+            println!("no old versions or profiles to remove");
+        }
+        for dir in removed {
+            println!("removed {}", dir.display());
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,315 @@
+use std::rc::Rc;
+use std::str::FromStr;
+
+use indexmap::IndexMap;
+use indexmap::IndexSet;
+
+use super::Entry;
+use super::Flag;
+use super::GLOBAL_FLAGS;
+use super::SUBCOMMANDS;
+use crate::lsd::Value;
+
+pub struct Subcommand {
+    shell: Shell,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Shell {
+    Bash,
+    /// Through `bashcompinit`, with the same script as bash.
+    Zsh,
+    Fish,
+}
+
+impl FromStr for Shell {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use Shell::*;
+        match s
+            .to_lowercase()
+            .as_str()
+        {
+            "bash" => Ok(Bash),
+            "zsh" => Ok(Zsh),
+            "fish" => Ok(Fish),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum InnerParseError {
+    MissingShell,
+    UnknownShell(Value),
+}
+
+impl super::InnerParseError for InnerParseError {
+    fn note(&self) -> Option<String> {
+        use InnerParseError::*;
+        match self {
+            UnknownShell(shell) => Some(format!(
+                "`{}` is not a supported shell",
+                shell
+            )),
+            _ => None,
+        }
+    }
+}
+
+impl From<InnerParseError> for Rc<dyn super::InnerParseError> {
+    fn from(value: InnerParseError) -> Self { Rc::new(value) }
+}
+
+fn parse_shell(shell: Value) -> Result<Shell, InnerParseError> {
+    use InnerParseError::*;
+
+    shell
+        .parse()
+        .map_err(|_| UnknownShell(shell.clone()))
+}
+
+//
+// bash
+//
+
+/// Every name an entry is typed as.
+fn names(entry: &Entry) -> impl Iterator<Item = &'static str> {
+    [entry.name]
+        .into_iter()
+        .chain(
+            entry
+                .aliases
+                .iter()
+                .copied(),
+        )
+}
+
+/// Flags of a subcommand, and those of every subcommand.
+fn flag_words(flags: &[Flag]) -> String {
+    flags
+        .iter()
+        .chain(GLOBAL_FLAGS)
+        .map(|flag| format!("-{}", flag.name))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Completes subcommands, group members and flags by the words typed before the current one,
+/// falling back to paths (ex. for flag values).
+fn bash_script() -> String {
+    // subcommands grouped under a word (ex. `export` of `export cmake`), with their members
+    let mut groups = IndexMap::<&str, Vec<&str>>::new();
+    for name in SUBCOMMANDS
+        .iter()
+        .flat_map(names)
+    {
+        if let Some((group, member)) = name.split_once(' ') {
+            let members = groups
+                .entry(group)
+                .or_default();
+            if !members.contains(&member) {
+                members.push(member);
+            }
+        }
+    }
+
+    let mut arms = vec![format!(
+        "        \"\") candidates=\"{}\" ;;",
+        SUBCOMMANDS
+            .iter()
+            .filter_map(|entry| {
+                entry
+                    .name
+                    .split(' ')
+                    .next()
+            })
+            .collect::<IndexSet<_>>()
+            .into_iter()
+            .collect::<Vec<_>>()
+            .join(" ")
+    )];
+
+    // `profiles` is both a subcommand and a group
+    for (group, members) in &groups {
+        let mut candidates = members.join(" ");
+        if let Some(entry) = super::find_subcommand(group) {
+            candidates.push(' ');
+            candidates.push_str(&flag_words((entry.flags)()));
+        }
+        arms.push(format!(
+            "        \"{}\") candidates=\"{}\" ;;",
+            group, candidates
+        ));
+    }
+
+    // longer names first, so that `export cmake` is not taken for `export`
+    let mut entries = SUBCOMMANDS
+        .iter()
+        .collect::<Vec<_>>();
+    entries.sort_by_key(|entry| {
+        !entry
+            .name
+            .contains(' ')
+    });
+    for entry in entries {
+        let patterns = names(entry)
+            .map(|name| format!("\"{}\"|\"{} \"*", name, name))
+            .collect::<Vec<_>>()
+            .join("|");
+        arms.push(format!(
+            "        {}) candidates=\"{}\" ;;",
+            patterns,
+            flag_words((entry.flags)())
+        ));
+    }
+
+    format!(
+        r#"_buildpp() {{
+    local cur="${{COMP_WORDS[COMP_CWORD]}}"
+    local words="${{COMP_WORDS[*]:1:COMP_CWORD-1}}"
+    local candidates=""
+    case "$words" in
+{}
+    esac
+    COMPREPLY=($(compgen -W "$candidates" -- "$cur"))
+}}
+complete -o default -F _buildpp buildpp
+"#,
+        arms.join("\n")
+    )
+}
+
+//
+// fish
+//
+
+fn fish_quote(text: &str) -> String {
+    format!(
+        "'{}'",
+        text.replace('\\', "\\\\")
+            .replace('\'', "\\'")
+    )
+}
+
+fn fish_script() -> String {
+    let mut lines = vec!["complete -c buildpp -f".to_string()];
+
+    for flag in GLOBAL_FLAGS {
+        lines.push(format!(
+            "complete -c buildpp -o {} -d {}",
+            flag.name,
+            fish_quote(flag.description)
+        ));
+    }
+
+    for entry in SUBCOMMANDS {
+        let mut words = entry
+            .name
+            .split(' ');
+        let first = words
+            .next()
+            .unwrap_or_default();
+        let member = words.next();
+
+        // aliases of the first word (ex. `e` of `export cmake`)
+        let firsts = names(entry)
+            .filter_map(|name| {
+                name.split(' ')
+                    .next()
+            })
+            .collect::<IndexSet<_>>()
+            .into_iter()
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        let (offer, seen) = match member {
+            None => (
+                "__fish_use_subcommand".to_string(),
+                format!(
+                    "__fish_seen_subcommand_from {}",
+                    firsts
+                ),
+            ),
+            Some(member) => (
+                format!(
+                    "__fish_seen_subcommand_from {}; and not __fish_seen_subcommand_from {}",
+                    firsts, member
+                ),
+                format!(
+                    "__fish_seen_subcommand_from {}; and __fish_seen_subcommand_from {}",
+                    firsts, member
+                ),
+            ),
+        };
+
+        let offered = member.unwrap_or(first);
+        lines.push(format!(
+            "complete -c buildpp -n {} -a {} -d {}",
+            fish_quote(&offer),
+            offered,
+            fish_quote(entry.description)
+        ));
+
+        for flag in (entry.flags)() {
+            let mut line = format!(
+                "complete -c buildpp -n {} -o {}",
+                fish_quote(&seen),
+                flag.name
+            );
+            for alias in flag.aliases {
+                line.push_str(&format!(" -o {}", alias));
+            }
+            if flag.arity != super::Arity::None {
+                line.push_str(" -r -F");
+            }
+            line.push_str(&format!(
+                " -d {}",
+                fish_quote(flag.description)
+            ));
+            lines.push(line);
+        }
+    }
+
+    lines.join("\n") + "\n"
+}
+
+const FLAGS: &[Flag] = &[Flag::one(
+    "shell",
+    "shell",
+    "Shell to complete for (`bash`, `zsh` or `fish`)",
+)];
+
+impl super::Subcommand for Subcommand {
+    fn parse(
+        mut flags: IndexMap<Value, Rc<[Value]>>,
+        _post_dash_dash: impl Iterator<Item = String>,
+    ) -> Result<Rc<dyn super::Subcommand>, Rc<dyn super::InnerParseError>> {
+        use InnerParseError::*;
+
+        let shell = flags
+            .shift_remove("shell")
+            .and_then(super::single_value)
+            .map(parse_shell)
+            .transpose()?
+            .ok_or(MissingShell)?;
+
+        Ok(Rc::new(Subcommand { shell }))
+    }
+
+    fn flags() -> &'static [Flag] { FLAGS }
+
+    fn execute(&self) -> Result<(), Rc<dyn super::InnerExecuteError>> {
+        use Shell::*;
+        match self.shell {
+            Bash => print!("{}", bash_script()),
+            Zsh => print!(
+                "autoload -U +X bashcompinit && bashcompinit\n{}",
+                bash_script()
+            ),
+            Fish => print!("{}", fish_script()),
+        }
+        Ok(())
+    }
+}
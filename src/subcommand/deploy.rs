@@ -0,0 +1,146 @@
+use std::env;
+use std::io;
+use std::rc::Rc;
+
+use indexmap::IndexMap;
+
+use super::Flag;
+use crate::configuration;
+use crate::configuration::Configuration;
+use crate::error_code::Code;
+use crate::error_code::ErrorCode;
+use crate::lsd::Value;
+use crate::profile;
+use crate::BuildError;
+use crate::BuildType;
+use crate::Dir;
+
+pub struct Subcommand {
+    build_type: Option<BuildType>,
+
+    /// `deploy.profile` of the configuration when not given.
+    profile: Option<profile::Name>,
+}
+
+#[derive(Debug, Clone)]
+enum InnerParseError {
+    UnknownBuildType,
+}
+
+impl super::InnerParseError for InnerParseError {
+}
+
+impl From<InnerParseError> for Rc<dyn super::InnerParseError> {
+    fn from(value: InnerParseError) -> Self { Rc::new(value) }
+}
+
+#[derive(Debug, Clone)]
+enum InnerExecuteError {
+    InvalidCurrentDir(Rc<io::Error>),
+
+    CannotLoadConfiguration(configuration::LoadError),
+
+    BuildError(BuildError),
+}
+
+impl super::InnerExecuteError for InnerExecuteError {
+    fn code(&self) -> Option<Code> {
+        use InnerExecuteError::*;
+        match self {
+            CannotLoadConfiguration(err) => Some(err.code()),
+            BuildError(err) => Some(err.code()),
+            _ => None,
+        }
+    }
+
+    fn note(&self) -> Option<String> {
+        use InnerExecuteError::*;
+        match self {
+            CannotLoadConfiguration(err) => err.note(),
+            InvalidCurrentDir(err) => Some(err.to_string()),
+            _ => None,
+        }
+    }
+}
+
+impl From<InnerExecuteError> for Rc<dyn super::InnerExecuteError> {
+    fn from(value: InnerExecuteError) -> Self { Rc::new(value) }
+}
+
+fn parse_build_type(build_type: Value) -> Result<BuildType, InnerParseError> {
+    use InnerParseError::*;
+
+    Ok(build_type
+        .parse()
+        .map_err(|()| UnknownBuildType)?)
+}
+
+const FLAGS: &[Flag] = &[
+    Flag::one(
+        "is",
+        "type",
+        "Build type (`binary` or `library`) when the project is both",
+    ),
+    Flag::one(
+        "profile",
+        "name",
+        "Profile to use, `deploy.profile` when not given",
+    ),
+];
+
+impl super::Subcommand for Subcommand {
+    fn parse(
+        mut flags: IndexMap<Value, Rc<[Value]>>,
+        _post_dash_dash: impl Iterator<Item = String>,
+    ) -> Result<Rc<dyn super::Subcommand>, Rc<dyn super::InnerParseError>> {
+        let build_type = flags
+            .shift_remove("is")
+            .and_then(super::single_value)
+            .map(parse_build_type)
+            .transpose()?;
+
+        let profile = flags
+            .shift_remove("profile")
+            .and_then(super::single_value);
+
+        Ok(Rc::new(Subcommand {
+            build_type,
+            profile,
+        }))
+    }
+
+    fn flags() -> &'static [Flag] { FLAGS }
+
+    fn execute(&self) -> Result<(), Rc<dyn super::InnerExecuteError>> {
+        use InnerExecuteError::*;
+
+        let project_dir = Dir::from(
+            env::current_dir()
+                .map_err(Rc::new)
+                .map_err(InvalidCurrentDir)?,
+        );
+
+        let config = Configuration::load(project_dir).map_err(CannotLoadConfiguration)?;
+
+        let deploy_profile = config
+            .deploy_profile()
+            .map_err(BuildError)?;
+        let profile = self
+            .profile
+            .clone()
+            .unwrap_or(deploy_profile);
+
+        config
+            .build(
+                self.build_type,
+                &profile,
+                false,
+            )
+            .map_err(BuildError)?;
+        config
+            .deploy(self.build_type, &profile)
+            .map_err(BuildError)?;
+
+        Ok(())
+    }
+}
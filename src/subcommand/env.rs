@@ -0,0 +1,312 @@
+use std::env;
+use std::io;
+use std::rc::Rc;
+use std::str::FromStr;
+
+use indexmap::IndexMap;
+
+use super::Flag;
+use crate::configuration;
+use crate::configuration::Configuration;
+use crate::error_code::Code;
+use crate::error_code::ErrorCode;
+use crate::lsd::Value;
+use crate::profile;
+use crate::profile::DEFAULT_PROFILE;
+use crate::util::json_quote;
+use crate::util::posix_quote;
+use crate::BuildError;
+use crate::BuildType;
+use crate::Dir;
+
+pub struct Subcommand {
+    build_type: Option<BuildType>,
+
+    profile: profile::Name,
+
+    format: Format,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+enum Format {
+    /// `export NAME='value'` lines, for `eval "$(buildpp env)"`.
+    #[default]
+    Shell,
+    /// Single object, lists kept as arrays.
+    Json,
+}
+
+impl FromStr for Format {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use Format::*;
+        match s
+            .to_lowercase()
+            .as_str()
+        {
+            "sh" | "shell" | "bash" | "eval" => Ok(Shell),
+            "json" => Ok(Json),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum InnerParseError {
+    UnknownBuildType,
+
+    UnknownFormat,
+}
+
+impl super::InnerParseError for InnerParseError {
+}
+
+impl From<InnerParseError> for Rc<dyn super::InnerParseError> {
+    fn from(value: InnerParseError) -> Self { Rc::new(value) }
+}
+
+#[derive(Debug, Clone)]
+enum InnerExecuteError {
+    InvalidCurrentDir(Rc<io::Error>),
+
+    CannotLoadConfiguration(configuration::LoadError),
+
+    BuildError(BuildError),
+
+    CouldNotReadDependencies(Rc<io::Error>),
+}
+
+impl super::InnerExecuteError for InnerExecuteError {
+    fn code(&self) -> Option<Code> {
+        use InnerExecuteError::*;
+        match self {
+            CannotLoadConfiguration(err) => Some(err.code()),
+            BuildError(err) => Some(err.code()),
+            _ => None,
+        }
+    }
+
+    fn note(&self) -> Option<String> {
+        use InnerExecuteError::*;
+        match self {
+            CannotLoadConfiguration(err) => err.note(),
+            InvalidCurrentDir(err) | CouldNotReadDependencies(err) => Some(err.to_string()),
+            _ => None,
+        }
+    }
+}
+
+impl From<InnerExecuteError> for Rc<dyn super::InnerExecuteError> {
+    fn from(value: InnerExecuteError) -> Self { Rc::new(value) }
+}
+
+fn parse_build_type(build_type: Value) -> Result<BuildType, InnerParseError> {
+    use InnerParseError::*;
+
+    Ok(build_type
+        .parse()
+        .map_err(|()| UnknownBuildType)?)
+}
+
+fn parse_format(format: Value) -> Result<Format, InnerParseError> {
+    use InnerParseError::*;
+
+    Ok(format
+        .parse()
+        .map_err(|()| UnknownFormat)?)
+}
+
+const FLAGS: &[Flag] = &[
+    Flag::one(
+        "is",
+        "type",
+        "Build type (`binary` or `library`) when the project is both",
+    ),
+    Flag::one(
+        "profile",
+        "name",
+        "Profile to use, `default` when not given",
+    ),
+    Flag::one(
+        "as",
+        "format",
+        "Print as `shell` commands or `json`",
+    ),
+];
+
+impl super::Subcommand for Subcommand {
+    fn parse(
+        mut flags: IndexMap<Value, Rc<[Value]>>,
+        _post_dash_dash: impl Iterator<Item = String>,
+    ) -> Result<Rc<dyn super::Subcommand>, Rc<dyn super::InnerParseError>> {
+        let build_type = flags
+            .shift_remove("is")
+            .and_then(super::single_value)
+            .map(parse_build_type)
+            .transpose()?;
+
+        let profile = flags
+            .shift_remove("profile")
+            .and_then(super::single_value)
+            .unwrap_or_else(|| DEFAULT_PROFILE.into());
+
+        let format = flags
+            .shift_remove("as")
+            .and_then(super::single_value)
+            .map(parse_format)
+            .transpose()?
+            .unwrap_or_default();
+
+        Ok(Rc::new(Subcommand {
+            build_type,
+            profile,
+            format,
+        }))
+    }
+
+    fn flags() -> &'static [Flag] { FLAGS }
+
+    fn execute(&self) -> Result<(), Rc<dyn super::InnerExecuteError>> {
+        use InnerExecuteError::*;
+
+        let project_dir = Dir::from(
+            env::current_dir()
+                .map_err(Rc::new)
+                .map_err(InvalidCurrentDir)?,
+        );
+
+        let config = Configuration::load(project_dir.clone()).map_err(CannotLoadConfiguration)?;
+
+        let profile = config
+            .profile(&self.profile)
+            .ok_or_else(|| {
+                crate::BuildError::InvalidProfile(
+                    self.profile
+                        .clone(),
+                )
+            })
+            .map_err(BuildError)?;
+
+        let build_type = config
+            .detect_build_type(self.build_type, profile)
+            .map_err(BuildError)?;
+
+        // dependencies' dirs are in the cache, so it has to exist
+        config
+            .prepare_dependencies(&self.profile)
+            .map_err(BuildError)?;
+
+        let mut include_dirs = config.include_dirs();
+        let mut lib_dirs = Vec::new();
+        for (include_dir, lib_dir) in config
+            .dependency_dirs(&self.profile)
+            .map_err(Rc::new)
+            .map_err(CouldNotReadDependencies)?
+        {
+            include_dirs.push(include_dir);
+            lib_dirs.push(lib_dir);
+        }
+
+        let path = |dir: &Dir| Value::from(dir.to_string_lossy());
+        let variables: Vec<(&str, Vec<Value>)> = vec![
+            (
+                "BUILDPP_PROFILE",
+                vec![self
+                    .profile
+                    .clone()],
+            ),
+            (
+                "BUILDPP_INCLUDE_DIRS",
+                include_dirs
+                    .iter()
+                    .map(path)
+                    .collect(),
+            ),
+            (
+                "BUILDPP_LIB_DIRS",
+                lib_dirs
+                    .iter()
+                    .map(path)
+                    .collect(),
+            ),
+            (
+                "BUILDPP_DEFINES",
+                config
+                    .defines(
+                        Some(build_type),
+                        &self.profile,
+                        profile,
+                    )
+                    .map_err(BuildError)?,
+            ),
+            (
+                "BUILDPP_TARGET_INCLUDE_DIR",
+                vec![path(&config.target_include_dir(&self.profile))],
+            ),
+            (
+                "BUILDPP_ARTIFACT_DIR",
+                vec![path(&config.target_artifact_dir(&self.profile))],
+            ),
+            (
+                "BUILDPP_ARTIFACT",
+                vec![path(&config.target_artifact_file(
+                    build_type,
+                    &self.profile,
+                    profile,
+                ))],
+            ),
+        ];
+
+        match self.format {
+            Format::Shell => {
+                // lists of dirs are joined like PATH is, defines with spaces
+                let dir_separator = match cfg!(windows) {
+                    true => ";",
+                    false => ":",
+                };
+                for (name, values) in variables {
+                    let separator = match name {
+                        "BUILDPP_DEFINES" => " ",
+                        _ => dir_separator,
+                    };
+                    println!(
+                        "export {}={}",
+                        name,
+                        posix_quote(&values.join(separator))
+                    );
+                }
+            },
+            Format::Json => {
+                let fields = variables
+                    .into_iter()
+                    .map(|(name, values)| {
+                        let value = match name {
+                            "BUILDPP_INCLUDE_DIRS" | "BUILDPP_LIB_DIRS" | "BUILDPP_DEFINES" =>
+                                format!(
+                                    "[{}]",
+                                    values
+                                        .iter()
+                                        .map(|value| json_quote(value))
+                                        .collect::<Vec<_>>()
+                                        .join(", ")
+                                ),
+                            _ => json_quote(&values.concat()),
+                        };
+                        format!(
+                            "  {}: {}",
+                            json_quote(name),
+                            value
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                println!(
+                    "{{\n{}\n}}",
+                    fields.join(",\n")
+                );
+            },
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,109 @@
+use std::rc::Rc;
+
+use indexmap::IndexMap;
+
+use super::Flag;
+use crate::error_code;
+use crate::error_code::Code;
+use crate::error_code::Exit;
+use crate::lsd::Value;
+
+pub struct Subcommand {
+    /// Every code is listed when not given.
+    code: Option<Code>,
+}
+
+#[derive(Debug, Clone)]
+enum InnerParseError {
+    InvalidCode(Value),
+}
+
+impl super::InnerParseError for InnerParseError {
+    fn note(&self) -> Option<String> {
+        use InnerParseError::*;
+        match self {
+            InvalidCode(code) => Some(format!(
+                "`{}` is not an error code (ex. `BPP0042`)",
+                code
+            )),
+        }
+    }
+}
+
+impl From<InnerParseError> for Rc<dyn super::InnerParseError> {
+    fn from(value: InnerParseError) -> Self { Rc::new(value) }
+}
+
+#[derive(Debug, Clone)]
+enum InnerExecuteError {
+    UnknownCode(Code),
+}
+
+impl super::InnerExecuteError for InnerExecuteError {
+    fn note(&self) -> Option<String> {
+        use InnerExecuteError::*;
+        match self {
+            UnknownCode(code) => Some(format!(
+                "{} is not a build++ error code",
+                code
+            )),
+        }
+    }
+}
+
+impl From<InnerExecuteError> for Rc<dyn super::InnerExecuteError> {
+    fn from(value: InnerExecuteError) -> Self { Rc::new(value) }
+}
+
+fn parse_code(code: Value) -> Result<Code, InnerParseError> {
+    use InnerParseError::*;
+
+    code.parse()
+        .map_err(|()| InvalidCode(code.clone()))
+}
+
+const FLAGS: &[Flag] = &[Flag::one(
+    "code",
+    "code",
+    "Code to explain (ex. `BPP0042`), every code when not given",
+)];
+
+impl super::Subcommand for Subcommand {
+    fn parse(
+        mut flags: IndexMap<Value, Rc<[Value]>>,
+        _post_dash_dash: impl Iterator<Item = String>,
+    ) -> Result<Rc<dyn super::Subcommand>, Rc<dyn super::InnerParseError>> {
+        let code = flags
+            .shift_remove("code")
+            .and_then(super::single_value)
+            .map(parse_code)
+            .transpose()?;
+
+        Ok(Rc::new(Subcommand { code }))
+    }
+
+    fn flags() -> &'static [Flag] { FLAGS }
+
+    fn execute(&self) -> Result<(), Rc<dyn super::InnerExecuteError>> {
+        use InnerExecuteError::*;
+
+        let Some(code) = self.code else {
+            for (code, name, _) in error_code::all() {
+                println!("{} {}", code, name);
+            }
+            return Ok(());
+        };
+
+        let (name, explanation) = error_code::explain(code).ok_or(UnknownCode(code))?;
+        println!("{} {}", code, name);
+        println!();
+        println!("{}", explanation);
+        println!();
+        println!(
+            "build++ exits with {}",
+            Exit::from(code)
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,311 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use indexmap::IndexMap;
+
+use super::Flag;
+use crate::configuration;
+use crate::configuration::Configuration;
+use crate::error_code::Code;
+use crate::error_code::ErrorCode;
+use crate::lsd::Value;
+use crate::profile;
+use crate::profile::DEFAULT_PROFILE;
+use crate::util::BoolGuardExt;
+use crate::BuildError;
+use crate::BuildType;
+use crate::Dir;
+
+pub struct Subcommand {
+    profile: profile::Name,
+}
+
+#[derive(Debug, Clone)]
+enum InnerExecuteError {
+    InvalidCurrentDir(Rc<io::Error>),
+
+    CannotLoadConfiguration(configuration::LoadError),
+
+    BuildError(BuildError),
+
+    NotALibrary,
+    CouldNotReadHeaders(Rc<io::Error>),
+    NoHeadersToAmalgamate,
+    CouldNotWriteHeader(Rc<io::Error>),
+}
+
+impl super::InnerExecuteError for InnerExecuteError {
+    fn code(&self) -> Option<Code> {
+        use InnerExecuteError::*;
+        match self {
+            CannotLoadConfiguration(err) => Some(err.code()),
+            BuildError(err) => Some(err.code()),
+            _ => None,
+        }
+    }
+
+    fn note(&self) -> Option<String> {
+        use InnerExecuteError::*;
+        match self {
+            CannotLoadConfiguration(err) => err.note(),
+            InvalidCurrentDir(err) | CouldNotReadHeaders(err) | CouldNotWriteHeader(err) =>
+                Some(err.to_string()),
+            _ => None,
+        }
+    }
+}
+
+impl From<InnerExecuteError> for Rc<dyn super::InnerExecuteError> {
+    fn from(value: InnerExecuteError) -> Self { Rc::new(value) }
+}
+
+/// Headers in `dir` (recursively), sorted for stable output.
+fn list_headers(dir: &Path, config: &Configuration) -> Result<Vec<PathBuf>, io::Error> {
+    let mut headers = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            headers.extend(list_headers(&path, config)?);
+        } else if config.is_header(&path) && !config.is_excluded(&path) {
+            headers.push(path);
+        }
+    }
+
+    headers.sort();
+    Ok(headers)
+}
+
+/// Path of `#include "path"` or `#include <path>`, and whether it was quoted.
+fn parse_include(line: &str) -> Option<(&str, bool)> {
+    let directive = line
+        .trim_start()
+        .strip_prefix('#')?
+        .trim_start()
+        .strip_prefix("include")?
+        .trim();
+    if let Some(path) = directive.strip_prefix('"') {
+        return Some((
+            path.split_once('"')?
+                .0,
+            true,
+        ));
+    }
+    let path = directive.strip_prefix('<')?;
+    Some((
+        path.split_once('>')?
+            .0,
+        false,
+    ))
+}
+
+fn is_pragma_once(line: &str) -> bool {
+    line.trim_start()
+        .strip_prefix('#')
+        .and_then(|directive| {
+            directive
+                .trim_start()
+                .strip_prefix("pragma")
+        })
+        .is_some_and(|pragma| pragma.trim() == "once")
+}
+
+/// Concatenates headers of a project, inlining the ones it includes from itself.
+struct Amalgamator {
+    include_dir: PathBuf,
+    emitted: HashSet<PathBuf>,
+    lines: Vec<String>,
+}
+
+impl Amalgamator {
+    /// Resolves an include to a header of the project, if it is one.
+    fn resolve(&self, including_file: &Path, include: &str, quoted: bool) -> Option<PathBuf> {
+        // quoted includes are looked up next to the including file first
+        let next_to_file = quoted
+            .then(|| {
+                including_file
+                    .parent()
+                    .map(|dir| dir.join(include))
+            })
+            .flatten();
+
+        next_to_file
+            .into_iter()
+            .chain([self
+                .include_dir
+                .join(include)])
+            .filter(|path| path.is_file())
+            .filter_map(|path| {
+                path.canonicalize()
+                    .ok()
+            })
+            .find(|path| path.starts_with(&self.include_dir))
+    }
+
+    fn emit(&mut self, header: PathBuf) -> Result<(), io::Error> {
+        // include guards of the header itself are kept, but each header is inlined only once
+        if !self
+            .emitted
+            .insert(header.clone())
+        {
+            return Ok(());
+        }
+
+        let name = header
+            .strip_prefix(&self.include_dir)
+            .unwrap_or(&header)
+            .to_string_lossy()
+            .replace('\\', "/");
+        self.lines
+            .push(format!("// begin {}", name));
+
+        for line in fs::read_to_string(&header)?.lines() {
+            // only means something for the file it is written in
+            if is_pragma_once(line) {
+                continue;
+            }
+
+            match parse_include(line)
+                .and_then(|(include, quoted)| self.resolve(&header, include, quoted))
+            {
+                Some(included) => self.emit(included)?,
+                None => self
+                    .lines
+                    .push(line.to_string()),
+            }
+        }
+
+        self.lines
+            .push(format!("// end {}", name));
+        Ok(())
+    }
+}
+
+const FLAGS: &[Flag] = &[Flag::one(
+    "profile",
+    "name",
+    "Profile to use, `default` when not given",
+)];
+
+impl super::Subcommand for Subcommand {
+    fn parse(
+        mut flags: IndexMap<Value, Rc<[Value]>>,
+        _post_dash_dash: impl Iterator<Item = String>,
+    ) -> Result<Rc<dyn super::Subcommand>, Rc<dyn super::InnerParseError>> {
+        let profile = flags
+            .shift_remove("profile")
+            .and_then(super::single_value)
+            .unwrap_or_else(|| DEFAULT_PROFILE.into());
+
+        Ok(Rc::new(Subcommand {
+            profile,
+        }))
+    }
+
+    fn flags() -> &'static [Flag] { FLAGS }
+
+    fn execute(&self) -> Result<(), Rc<dyn super::InnerExecuteError>> {
+        use InnerExecuteError::*;
+
+        let project_dir = Dir::from(
+            std::env::current_dir()
+                .map_err(Rc::new)
+                .map_err(InvalidCurrentDir)?,
+        );
+
+        let config = Configuration::load(project_dir.clone()).map_err(CannotLoadConfiguration)?;
+
+        let profile = config
+            .profile(&self.profile)
+            .ok_or_else(|| {
+                crate::BuildError::InvalidProfile(
+                    self.profile
+                        .clone(),
+                )
+            })
+            .map_err(BuildError)?;
+
+        config
+            .src_file(BuildType::Library, profile)
+            .is_file()
+            .or_err(NotALibrary)?;
+
+        let include_dir = config
+            .include_dir()
+            .canonicalize()
+            .map_err(Rc::new)
+            .map_err(CouldNotReadHeaders)?;
+        let headers = list_headers(&include_dir, &config)
+            .map_err(Rc::new)
+            .map_err(CouldNotReadHeaders)?;
+        (!headers.is_empty()).or_err(NoHeadersToAmalgamate)?;
+
+        let name = config.project_name();
+        let guard = format!(
+            "{}_AMALGAMATED_H",
+            name.chars()
+                .map(
+                    |ch| match ch.is_ascii_alphanumeric() {
+                        true => ch.to_ascii_uppercase(),
+                        false => '_',
+                    }
+                )
+                .collect::<String>()
+        );
+
+        let mut amalgamator = Amalgamator {
+            include_dir,
+            emitted: HashSet::new(),
+            lines: vec![
+                format!(
+                    "// Generated by build++ from headers of {} {}, changes will be overwritten.",
+                    name,
+                    config.version()
+                ),
+                format!("#ifndef {}", guard),
+                format!("#define {}", guard),
+            ],
+        };
+        for header in headers {
+            amalgamator
+                .emit(header)
+                .map_err(Rc::new)
+                .map_err(CouldNotReadHeaders)?;
+        }
+        amalgamator
+            .lines
+            .push(format!("#endif // {}", guard));
+        amalgamator
+            .lines
+            .push(String::new());
+
+        let extension = match profile.cmake_language() {
+            "C" => "h",
+            "CUDA" => "cuh",
+            _ => "hpp",
+        };
+        let target_dir = config.target_dir(&self.profile);
+        fs::create_dir_all(&target_dir)
+            .map_err(Rc::new)
+            .map_err(CouldNotWriteHeader)?;
+        let file = target_dir.join(format!(
+            "{}.{}",
+            name, extension
+        ));
+        fs::write(
+            &file,
+            amalgamator
+                .lines
+                .join("\n"),
+        )
+        .map_err(Rc::new)
+        .map_err(CouldNotWriteHeader)?;
+
+        println!("exported {}", file.display());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,259 @@
+use std::fs;
+use std::io;
+use std::rc::Rc;
+use std::str::FromStr;
+
+use indexmap::IndexMap;
+
+use super::Flag;
+use crate::configuration;
+use crate::configuration::Configuration;
+use crate::error_code::Code;
+use crate::error_code::ErrorCode;
+use crate::lsd::Value;
+use crate::profile;
+use crate::profile::DEFAULT_PROFILE;
+use crate::BuildError;
+use crate::Dir;
+
+/// First line of generated `.clangd` files, so that hand-written ones are never overwritten.
+pub(super) const GENERATED_HEADER: &str = "# Generated by build++";
+
+pub struct Subcommand {
+    profile: profile::Name,
+
+    format: Format,
+
+    force: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+enum Format {
+    /// `compile_flags.txt`, one flag per line.
+    #[default]
+    CompileFlags,
+    /// `.clangd` configuration adding the flags.
+    Clangd,
+}
+
+impl FromStr for Format {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use Format::*;
+        match s
+            .to_lowercase()
+            .as_str()
+        {
+            "compile_flags" | "compile_flags.txt" | "flags" => Ok(CompileFlags),
+            "clangd" | ".clangd" => Ok(Clangd),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum InnerParseError {
+    UnknownFormat,
+}
+
+impl super::InnerParseError for InnerParseError {
+}
+
+impl From<InnerParseError> for Rc<dyn super::InnerParseError> {
+    fn from(value: InnerParseError) -> Self { Rc::new(value) }
+}
+
+#[derive(Debug, Clone)]
+enum InnerExecuteError {
+    InvalidCurrentDir(Rc<io::Error>),
+
+    CannotLoadConfiguration(configuration::LoadError),
+
+    BuildError(BuildError),
+
+    CouldNotReadDependencies(Rc<io::Error>),
+    ClangdIsNotGenerated(Dir),
+    CouldNotWriteFile(Rc<io::Error>),
+}
+
+impl super::InnerExecuteError for InnerExecuteError {
+    fn code(&self) -> Option<Code> {
+        use InnerExecuteError::*;
+        match self {
+            CannotLoadConfiguration(err) => Some(err.code()),
+            BuildError(err) => Some(err.code()),
+            _ => None,
+        }
+    }
+
+    fn note(&self) -> Option<String> {
+        use InnerExecuteError::*;
+        match self {
+            CannotLoadConfiguration(err) => err.note(),
+            InvalidCurrentDir(err) | CouldNotReadDependencies(err) | CouldNotWriteFile(err) =>
+                Some(err.to_string()),
+            ClangdIsNotGenerated(file) => Some(format!(
+                "`{}` was not written by build++, `-force` overwrites it",
+                file.display()
+            )),
+            _ => None,
+        }
+    }
+}
+
+impl From<InnerExecuteError> for Rc<dyn super::InnerExecuteError> {
+    fn from(value: InnerExecuteError) -> Self { Rc::new(value) }
+}
+
+fn parse_format(format: Value) -> Result<Format, InnerParseError> {
+    use InnerParseError::*;
+
+    Ok(format
+        .parse()
+        .map_err(|()| UnknownFormat)?)
+}
+
+/// Quotes a flag for YAML flow sequences.
+fn yaml_quote(flag: &str) -> String {
+    format!(
+        "\"{}\"",
+        flag.replace('\\', "\\\\")
+            .replace('"', "\\\"")
+    )
+}
+
+const FLAGS: &[Flag] = &[
+    Flag::one(
+        "profile",
+        "name",
+        "Profile to use, `default` when not given",
+    ),
+    Flag::one(
+        "as",
+        "format",
+        "Write `clangd` configuration or `compile_flags`",
+    ),
+    Flag::switch(
+        "force",
+        "Overwrite files that already exist",
+    ),
+];
+
+impl super::Subcommand for Subcommand {
+    fn parse(
+        mut flags: IndexMap<Value, Rc<[Value]>>,
+        _post_dash_dash: impl Iterator<Item = String>,
+    ) -> Result<Rc<dyn super::Subcommand>, Rc<dyn super::InnerParseError>> {
+        let profile = flags
+            .shift_remove("profile")
+            .and_then(super::single_value)
+            .unwrap_or_else(|| DEFAULT_PROFILE.into());
+
+        let format = flags
+            .shift_remove("as")
+            .and_then(super::single_value)
+            .map(parse_format)
+            .transpose()?
+            .unwrap_or_default();
+
+        let force = flags
+            .shift_remove("force")
+            .is_some();
+
+        Ok(Rc::new(Subcommand {
+            profile,
+            format,
+            force,
+        }))
+    }
+
+    fn flags() -> &'static [Flag] { FLAGS }
+
+    fn execute(&self) -> Result<(), Rc<dyn super::InnerExecuteError>> {
+        use InnerExecuteError::*;
+
+        let project_dir = Dir::from(
+            std::env::current_dir()
+                .map_err(Rc::new)
+                .map_err(InvalidCurrentDir)?,
+        );
+
+        let config = Configuration::load(project_dir.clone()).map_err(CannotLoadConfiguration)?;
+
+        let profile = config
+            .profile(&self.profile)
+            .ok_or_else(|| {
+                crate::BuildError::InvalidProfile(
+                    self.profile
+                        .clone(),
+                )
+            })
+            .map_err(BuildError)?;
+
+        // dependencies' headers are referred to from the cache, so it has to exist
+        config
+            .prepare_dependencies(&self.profile)
+            .map_err(BuildError)?;
+
+        let mut flags = profile.clang_flags();
+        for define in config
+            .defines(None, &self.profile, profile)
+            .map_err(BuildError)?
+        {
+            flags.push(format!("-D{}", define).into());
+        }
+        for include_dir in config.include_dirs() {
+            flags.push(format!("-I{}", include_dir.display()).into());
+        }
+        for (include_dir, _) in config
+            .dependency_dirs(&self.profile)
+            .map_err(Rc::new)
+            .map_err(CouldNotReadDependencies)?
+        {
+            flags.push(format!("-I{}", include_dir.display()).into());
+        }
+
+        let (file, contents) = match self.format {
+            Format::CompileFlags => (
+                Dir::from(project_dir.join("compile_flags.txt")),
+                flags
+                    .iter()
+                    .map(|flag| format!("{}\n", flag))
+                    .collect::<String>(),
+            ),
+            Format::Clangd => {
+                let file = Dir::from(project_dir.join(".clangd"));
+                if !self.force
+                    && file.is_file()
+                    && !fs::read_to_string(&file)
+                        .unwrap_or_default()
+                        .starts_with(GENERATED_HEADER)
+                {
+                    return Err(ClangdIsNotGenerated(file))?;
+                }
+
+                let contents = format!(
+                    "{} from build++.lsd (profile `{}`), regenerate with `buildpp export \
+                     clangd -as clangd`.\nCompileFlags:\n  Add: [{}]\n",
+                    GENERATED_HEADER,
+                    self.profile,
+                    flags
+                        .iter()
+                        .map(|flag| yaml_quote(flag))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                );
+                (file, contents)
+            },
+        };
+
+        fs::write(&file, contents)
+            .map_err(Rc::new)
+            .map_err(CouldNotWriteFile)?;
+
+        println!("exported {}", file.display());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,397 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::rc::Rc;
+
+use indexmap::IndexMap;
+
+use super::Flag;
+use crate::configuration;
+use crate::configuration::Configuration;
+use crate::error_code::Code;
+use crate::error_code::ErrorCode;
+use crate::lsd::Value;
+use crate::profile;
+use crate::profile::DEFAULT_PROFILE;
+use crate::util::split_file_name;
+use crate::BuildError;
+use crate::BuildType;
+use crate::Dir;
+
+const CMAKE_FILENAME: &str = "CMakeLists.txt";
+
+/// First line of generated files, so that hand-written ones are never overwritten.
+const GENERATED_HEADER: &str = "# Generated by build++";
+
+pub struct Subcommand {
+    build_type: Option<BuildType>,
+
+    profile: profile::Name,
+
+    force: bool,
+}
+
+#[derive(Debug, Clone)]
+enum InnerParseError {
+    UnknownBuildType,
+}
+
+impl super::InnerParseError for InnerParseError {
+}
+
+impl From<InnerParseError> for Rc<dyn super::InnerParseError> {
+    fn from(value: InnerParseError) -> Self { Rc::new(value) }
+}
+
+#[derive(Debug, Clone)]
+enum InnerExecuteError {
+    InvalidCurrentDir(Rc<io::Error>),
+
+    CannotLoadConfiguration(configuration::LoadError),
+
+    BuildError(BuildError),
+
+    CouldNotReadDependencies(Rc<io::Error>),
+    CMakeListsIsNotGenerated(Dir),
+    CouldNotWriteCMakeLists(Rc<io::Error>),
+}
+
+impl super::InnerExecuteError for InnerExecuteError {
+    fn code(&self) -> Option<Code> {
+        use InnerExecuteError::*;
+        match self {
+            CannotLoadConfiguration(err) => Some(err.code()),
+            BuildError(err) => Some(err.code()),
+            _ => None,
+        }
+    }
+
+    fn note(&self) -> Option<String> {
+        use InnerExecuteError::*;
+        match self {
+            CannotLoadConfiguration(err) => err.note(),
+            InvalidCurrentDir(err)
+            | CouldNotReadDependencies(err)
+            | CouldNotWriteCMakeLists(err) => Some(err.to_string()),
+            CMakeListsIsNotGenerated(file) => Some(format!(
+                "`{}` was not written by build++, `-force` overwrites it",
+                file.display()
+            )),
+            _ => None,
+        }
+    }
+}
+
+impl From<InnerExecuteError> for Rc<dyn super::InnerExecuteError> {
+    fn from(value: InnerExecuteError) -> Self { Rc::new(value) }
+}
+
+fn parse_build_type(build_type: Value) -> Result<BuildType, InnerParseError> {
+    use InnerParseError::*;
+
+    Ok(build_type
+        .parse()
+        .map_err(|()| UnknownBuildType)?)
+}
+
+/// Quotes a path for CMake, relative to the project dir when inside of it.
+fn cmake_path(project_dir: &Path, path: &Path) -> String {
+    let (prefix, path) = match path.strip_prefix(project_dir) {
+        Ok(relative) => (
+            "${CMAKE_CURRENT_SOURCE_DIR}/",
+            relative,
+        ),
+        Err(_) => ("", path),
+    };
+
+    // CMake paths always use forward slashes
+    let path = path
+        .to_string_lossy()
+        .replace('\\', "/");
+
+    format!(
+        "\"{}{}\"",
+        prefix,
+        cmake_escape(&path)
+    )
+}
+
+/// Whether `version` is what `project(VERSION)` accepts: one to four numbers separated by dots.
+fn is_cmake_version(version: &str) -> bool {
+    let parts = version
+        .split('.')
+        .collect::<Vec<_>>();
+    parts.len() <= 4
+        && parts
+            .iter()
+            .all(|part| {
+                !part.is_empty()
+                    && part
+                        .bytes()
+                        .all(|byte| byte.is_ascii_digit())
+            })
+}
+
+/// Escapes `text` to be put inside of a quoted CMake argument.
+fn cmake_escape(text: &str) -> String {
+    let mut escaped = String::new();
+    for ch in text.chars() {
+        if matches!(ch, '"' | '$' | ';' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(ch);
+    }
+    escaped
+}
+
+const FLAGS: &[Flag] = &[
+    Flag::one(
+        "is",
+        "type",
+        "Build type (`binary` or `library`) when the project is both",
+    ),
+    Flag::one(
+        "profile",
+        "name",
+        "Profile to use, `default` when not given",
+    ),
+    Flag::switch(
+        "force",
+        "Overwrite files that already exist",
+    ),
+];
+
+impl super::Subcommand for Subcommand {
+    fn parse(
+        mut flags: IndexMap<Value, Rc<[Value]>>,
+        _post_dash_dash: impl Iterator<Item = String>,
+    ) -> Result<Rc<dyn super::Subcommand>, Rc<dyn super::InnerParseError>> {
+        let build_type = flags
+            .shift_remove("is")
+            .and_then(super::single_value)
+            .map(parse_build_type)
+            .transpose()?;
+
+        let profile = flags
+            .shift_remove("profile")
+            .and_then(super::single_value)
+            .unwrap_or_else(|| DEFAULT_PROFILE.into());
+
+        let force = flags
+            .shift_remove("force")
+            .is_some();
+
+        Ok(Rc::new(Subcommand {
+            build_type,
+            profile,
+            force,
+        }))
+    }
+
+    fn flags() -> &'static [Flag] { FLAGS }
+
+    fn execute(&self) -> Result<(), Rc<dyn super::InnerExecuteError>> {
+        use InnerExecuteError::*;
+
+        let project_dir = Dir::from(
+            std::env::current_dir()
+                .map_err(Rc::new)
+                .map_err(InvalidCurrentDir)?,
+        );
+
+        let config = Configuration::load(project_dir.clone()).map_err(CannotLoadConfiguration)?;
+
+        let profile = config
+            .profile(&self.profile)
+            .ok_or_else(|| {
+                crate::BuildError::InvalidProfile(
+                    self.profile
+                        .clone(),
+                )
+            })
+            .map_err(BuildError)?;
+        let build_type = config
+            .detect_build_type(self.build_type, profile)
+            .map_err(BuildError)?;
+
+        // dependencies are referred to from the cache, so it has to exist
+        config
+            .prepare_dependencies(&self.profile)
+            .map_err(BuildError)?;
+
+        let cmake_file = Dir::from(project_dir.join(CMAKE_FILENAME));
+        if !self.force
+            && cmake_file.is_file()
+            && !fs::read_to_string(&cmake_file)
+                .unwrap_or_default()
+                .starts_with(GENERATED_HEADER)
+        {
+            return Err(CMakeListsIsNotGenerated(
+                cmake_file,
+            ))?;
+        }
+
+        // collect dependencies
+        let mut include_dirs = Vec::new();
+        let mut lib_dirs = Vec::new();
+        let mut libs = Vec::new();
+        for (include_dir, lib_dir) in config
+            .dependency_dirs(&self.profile)
+            .map_err(Rc::new)
+            .map_err(CouldNotReadDependencies)?
+        {
+            for lib in fs::read_dir(&lib_dir)
+                .map_err(Rc::new)
+                .map_err(CouldNotReadDependencies)?
+            {
+                let lib = lib
+                    .map_err(Rc::new)
+                    .map_err(CouldNotReadDependencies)?
+                    .path();
+                let (_, ext) = split_file_name(lib.as_os_str());
+                if ext == "lib" || ext == "a" || ext == "so" || ext == "dylib" {
+                    libs.push(lib);
+                }
+            }
+
+            include_dirs.push(include_dir);
+            lib_dirs.push(lib_dir);
+        }
+
+        // write out
+        let name = config.project_name();
+        let target_name = match build_type {
+            BuildType::Binary => format!("add_executable({}", name),
+            BuildType::Library => format!(
+                "add_library({} {}",
+                name,
+                profile.cmake_library_kind()
+            ),
+        };
+        // headers of libraries are a part of their interface
+        let visibility = match build_type {
+            BuildType::Binary => "PRIVATE",
+            BuildType::Library => "PUBLIC",
+        };
+        let path = |path: &Path| cmake_path(&project_dir, path);
+
+        let mut lines = vec![
+            format!(
+                "{} from build++.lsd (profile `{}`), regenerate with `buildpp export cmake`.",
+                GENERATED_HEADER, self.profile
+            ),
+            String::from("cmake_minimum_required(VERSION 3.18)"),
+            // CMake rejects versions it cannot parse (ex. `1.0.0-beta`)
+            match is_cmake_version(&config.version()) {
+                true => format!(
+                    "project({} VERSION {} LANGUAGES {})",
+                    name,
+                    config.version(),
+                    profile.cmake_language()
+                ),
+                false => format!(
+                    "project({} LANGUAGES {})",
+                    name,
+                    profile.cmake_language()
+                ),
+            },
+            String::new(),
+            format!(
+                "{} {})",
+                target_name,
+                path(&config.src_file(build_type, profile))
+            ),
+        ];
+
+        let properties = profile.cmake_properties();
+        if !properties.is_empty() {
+            lines.push(format!(
+                "set_target_properties({} PROPERTIES",
+                name
+            ));
+            for (key, value) in properties {
+                lines.push(format!(
+                    "    {} {}",
+                    key, value
+                ));
+            }
+            lines.push(String::from(")"));
+        }
+
+        let defines = config
+            .defines(
+                Some(build_type),
+                &self.profile,
+                profile,
+            )
+            .map_err(BuildError)?;
+        if !defines.is_empty() {
+            lines.push(format!(
+                "target_compile_definitions({} PRIVATE",
+                name
+            ));
+            for define in defines {
+                lines.push(format!(
+                    "    \"{}\"",
+                    cmake_escape(&define)
+                ));
+            }
+            lines.push(String::from(")"));
+        }
+
+        lines.push(format!(
+            "target_include_directories({} {}",
+            name, visibility
+        ));
+        for include_dir in config
+            .include_dirs()
+            .into_iter()
+            .chain(include_dirs)
+        {
+            lines.push(format!(
+                "    {}",
+                path(&include_dir)
+            ));
+        }
+        lines.push(String::from(")"));
+
+        if !lib_dirs.is_empty() {
+            lines.push(format!(
+                "target_link_directories({} PRIVATE",
+                name
+            ));
+            for lib_dir in lib_dirs {
+                lines.push(format!(
+                    "    {}",
+                    path(&lib_dir)
+                ));
+            }
+            lines.push(String::from(")"));
+        }
+
+        if !libs.is_empty() {
+            lines.push(format!(
+                "target_link_libraries({} PRIVATE",
+                name
+            ));
+            for lib in libs {
+                lines.push(format!("    {}", path(&lib)));
+            }
+            lines.push(String::from(")"));
+        }
+
+        lines.push(String::new());
+        let cmake = lines.join("\n");
+
+        fs::write(&cmake_file, cmake)
+            .map_err(Rc::new)
+            .map_err(CouldNotWriteCMakeLists)?;
+
+        println!(
+            "exported {}",
+            cmake_file.display()
+        );
+
+        Ok(())
+    }
+}
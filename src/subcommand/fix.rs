@@ -0,0 +1,116 @@
+use std::env;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::rc::Rc;
+
+use indexmap::IndexMap;
+
+use super::Flag;
+use crate::configuration;
+use crate::lsd::LSDParseError;
+use crate::lsd::Value;
+use crate::lsd::LSD;
+use crate::Dir;
+
+pub struct Subcommand {
+    /// Whether to only list what would be changed, without rewriting the configuration.
+    dry_run: bool,
+}
+
+#[derive(Debug, Clone)]
+enum InnerExecuteError {
+    InvalidCurrentDir(Rc<io::Error>),
+
+    CouldNotReadConfiguration(Rc<io::Error>),
+    CouldNotParseConfiguration(LSDParseError),
+    CouldNotWriteConfiguration(Rc<io::Error>),
+}
+
+impl super::InnerExecuteError for InnerExecuteError {
+    fn note(&self) -> Option<String> {
+        use InnerExecuteError::*;
+        match self {
+            InvalidCurrentDir(err)
+            | CouldNotReadConfiguration(err)
+            | CouldNotWriteConfiguration(err) => Some(err.to_string()),
+            CouldNotParseConfiguration(err) => Some(err.to_string()),
+        }
+    }
+}
+
+impl From<InnerExecuteError> for Rc<dyn super::InnerExecuteError> {
+    fn from(value: InnerExecuteError) -> Self { Rc::new(value) }
+}
+
+const FLAGS: &[Flag] = &[Flag::switch(
+    "dry-run",
+    "Only list what would be changed",
+)];
+
+impl super::Subcommand for Subcommand {
+    fn parse(
+        mut flags: IndexMap<Value, Rc<[Value]>>,
+        _post_dash_dash: impl Iterator<Item = String>,
+    ) -> Result<Rc<dyn super::Subcommand>, Rc<dyn super::InnerParseError>> {
+        let dry_run = flags
+            .shift_remove("dry-run")
+            .is_some();
+
+        Ok(Rc::new(Subcommand {
+            dry_run,
+        }))
+    }
+
+    fn flags() -> &'static [Flag] { FLAGS }
+
+    fn execute(&self) -> Result<(), Rc<dyn super::InnerExecuteError>> {
+        use InnerExecuteError::*;
+
+        let project_dir = Dir::from(
+            env::current_dir()
+                .map_err(Rc::new)
+                .map_err(InvalidCurrentDir)?,
+        );
+
+        // not loaded as a configuration, which would fail on the very keys being fixed
+        let config_file = project_dir.join(configuration::CONFIG_FILENAME);
+        let mut lsd = File::open(&config_file)
+            .map_err(Rc::new)
+            .map_err(CouldNotReadConfiguration)
+            .and_then(|file| LSD::parse(file).map_err(CouldNotParseConfiguration))?;
+
+        let migrations = configuration::migrate(&mut lsd);
+        if migrations.is_empty() {
+            println!(
+                "{} uses no deprecated keys",
+                config_file.display()
+            );
+            return Ok(());
+        }
+
+        for migration in &migrations {
+            println!("{}", migration);
+        }
+
+        if self.dry_run {
+            println!(
+                "dry run, not rewriting {}",
+                config_file.display()
+            );
+            return Ok(());
+        }
+
+        fs::write(&config_file, lsd.to_string())
+            .map_err(Rc::new)
+            .map_err(CouldNotWriteConfiguration)?;
+
+        println!(
+            "fixed {} deprecated keys in {}",
+            migrations.len(),
+            config_file.display()
+        );
+
+        Ok(())
+    }
+}
@@ -2,38 +2,166 @@ use std::rc::Rc;
 
 use indexmap::IndexMap;
 
+use super::Flag;
 use crate::lsd::Value;
-use crate::util::BoolGuardExt;
 
-pub struct Subcommand {}
+pub struct Subcommand {
+    /// Flags of this subcommand are shown instead of every subcommand (ex. `export cmake`).
+    subcommand: Option<Value>,
+}
 
 #[derive(Debug, Clone)]
-enum InnerParseError {
-    ExpectedNoFlags,
+enum InnerExecuteError {
+    /// With the subcommand it was likely meant to be.
+    UnknownSubcommand(Value, Option<&'static str>),
 }
 
-impl super::InnerParseError for InnerParseError {
+impl super::InnerExecuteError for InnerExecuteError {
+    fn note(&self) -> Option<String> {
+        use InnerExecuteError::*;
+        match self {
+            UnknownSubcommand(subcommand, Some(suggestion)) => Some(format!(
+                "`{}` is not a subcommand, did you mean `{}`?",
+                subcommand, suggestion
+            )),
+            UnknownSubcommand(subcommand, None) => Some(format!(
+                "`{}` is not a subcommand",
+                subcommand
+            )),
+        }
+    }
 }
 
-impl From<InnerParseError> for Rc<dyn super::InnerParseError> {
-    fn from(value: InnerParseError) -> Self { Rc::new(value) }
+impl From<InnerExecuteError> for Rc<dyn super::InnerExecuteError> {
+    fn from(value: InnerExecuteError) -> Self { Rc::new(value) }
 }
 
+fn parse_subcommand(subcommand: Rc<[Value]>) -> Value {
+    // `help export cmake`
+    subcommand
+        .join(" ")
+        .to_lowercase()
+        .into()
+}
+
+/// Rows of `help` output, with the first column padded to the widest of them.
+fn print_table(rows: &[(String, &str)]) {
+    let width = rows
+        .iter()
+        .map(|(name, _)| name.len())
+        .max()
+        .unwrap_or_default();
+    for (name, description) in rows {
+        println!(
+            "    {:<width$}  {}",
+            name, description
+        );
+    }
+}
+
+const FLAGS: &[Flag] = &[Flag::many(
+    "subcommand",
+    "word",
+    "Subcommand to show flags of (ex. `export cmake`)",
+)];
+
 impl super::Subcommand for Subcommand {
     fn parse(
-        flags: IndexMap<Value, Rc<[Value]>>,
+        mut flags: IndexMap<Value, Rc<[Value]>>,
         _post_dash_dash: impl Iterator<Item = String>,
     ) -> Result<Rc<dyn super::Subcommand>, Rc<dyn super::InnerParseError>> {
-        use InnerParseError::*;
+        let subcommand = flags
+            .shift_remove("subcommand")
+            .map(parse_subcommand);
 
-        flags
-            .is_empty()
-            .ok_or(ExpectedNoFlags)?;
-
-        Ok(Rc::new(Subcommand {}))
+        Ok(Rc::new(Subcommand {
+            subcommand,
+        }))
     }
 
+    fn flags() -> &'static [Flag] { FLAGS }
+
     fn execute(&self) -> Result<(), Rc<dyn super::InnerExecuteError>> {
-        todo!("help message");
+        use InnerExecuteError::*;
+
+        let Some(subcommand) = &self.subcommand else {
+            println!(
+                "build++ version {}",
+                env!("CARGO_PKG_VERSION")
+            );
+            println!();
+            println!("Usage: buildpp <subcommand> [flags...] [-- arguments...]");
+            println!();
+            println!("Subcommands:");
+            print_table(
+                &super::SUBCOMMANDS
+                    .iter()
+                    .map(|entry| {
+                        (
+                            entry
+                                .name
+                                .to_string(),
+                            entry.description,
+                        )
+                    })
+                    .collect::<Vec<_>>(),
+            );
+            println!();
+            println!("Flags of every subcommand:");
+            print_table(
+                &super::GLOBAL_FLAGS
+                    .iter()
+                    .map(|flag| (flag.usage(), flag.description))
+                    .collect::<Vec<_>>(),
+            );
+            println!();
+            println!("Run `buildpp help <subcommand>` to see its flags.");
+            return Ok(());
+        };
+
+        let entry = super::find_subcommand(subcommand).ok_or_else(|| {
+            UnknownSubcommand(
+                subcommand.clone(),
+                super::suggest_subcommand(subcommand),
+            )
+        })?;
+
+        let positional = entry
+            .positional
+            .and_then(|name| entry.flag(name))
+            .map(|flag| format!(" [{}]", flag.value))
+            .unwrap_or_default();
+        println!(
+            "Usage: buildpp {}{} [flags...]",
+            entry.name, positional
+        );
+        println!();
+        println!("{}.", entry.description);
+        if !entry
+            .aliases
+            .is_empty()
+        {
+            println!();
+            println!(
+                "Aliases: {}",
+                entry
+                    .aliases
+                    .join(", ")
+            );
+        }
+
+        let flags = (entry.flags)();
+        if !flags.is_empty() {
+            println!();
+            println!("Flags:");
+            print_table(
+                &flags
+                    .iter()
+                    .map(|flag| (flag.usage(), flag.description))
+                    .collect::<Vec<_>>(),
+            );
+        }
+
+        Ok(())
     }
 }
@@ -0,0 +1,357 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::Component;
+use std::path::Path;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use indexmap::IndexMap;
+
+use super::Flag;
+use crate::configuration;
+use crate::configuration::Configuration;
+use crate::error_code::Code;
+use crate::error_code::ErrorCode;
+use crate::lsd::Value;
+use crate::profile;
+use crate::profile::DEFAULT_PROFILE;
+use crate::util::replace_symlink;
+use crate::util::split_file_name;
+use crate::BuildError;
+use crate::BuildType;
+use crate::Dir;
+
+/// Staging root, prepended to every installed path but not recorded in the manifest
+/// (ex. for packaging `DESTDIR=pkg buildpp install -prefix /usr`).
+const DESTDIR_VARIABLE: &str = "DESTDIR";
+
+const DEFAULT_PREFIX: &str = match cfg!(windows) {
+    true => "C:\\Program Files",
+    false => "/usr/local",
+};
+
+pub struct Subcommand {
+    profile: profile::Name,
+
+    /// Relative to where build++ was ran from.
+    prefix: Option<Value>,
+}
+
+#[derive(Debug, Clone)]
+enum InnerExecuteError {
+    InvalidCurrentDir(Rc<io::Error>),
+
+    CannotLoadConfiguration(configuration::LoadError),
+
+    BuildError(BuildError),
+
+    CouldNotInstall(PathBuf, Rc<io::Error>),
+    CouldNotWriteManifest(PathBuf, Rc<io::Error>),
+}
+
+impl super::InnerExecuteError for InnerExecuteError {
+    fn code(&self) -> Option<Code> {
+        use InnerExecuteError::*;
+        match self {
+            CannotLoadConfiguration(err) => Some(err.code()),
+            BuildError(err) => Some(err.code()),
+            _ => None,
+        }
+    }
+
+    fn note(&self) -> Option<String> {
+        use InnerExecuteError::*;
+        match self {
+            CannotLoadConfiguration(err) => err.note(),
+            InvalidCurrentDir(err) => Some(err.to_string()),
+            CouldNotInstall(path, err) | CouldNotWriteManifest(path, err) => Some(format!(
+                "`{}`: {}",
+                path.display(),
+                err
+            )),
+            _ => None,
+        }
+    }
+}
+
+impl From<InnerExecuteError> for Rc<dyn super::InnerExecuteError> {
+    fn from(value: InnerExecuteError) -> Self { Rc::new(value) }
+}
+
+/// Copies files into the prefix (staged under `DESTDIR` if set), remembering where they went.
+struct Installer {
+    destdir: Option<PathBuf>,
+    installed: Vec<PathBuf>,
+}
+
+impl Installer {
+    /// Where `path` (under the prefix) is actually written to.
+    fn staged(&self, path: &Path) -> PathBuf {
+        match &self.destdir {
+            // roots and drives of the path are dropped, so it stays inside of `DESTDIR`
+            Some(destdir) => destdir.join(
+                path.components()
+                    .filter(|component| {
+                        matches!(
+                            component,
+                            Component::Normal(_)
+                        )
+                    })
+                    .collect::<PathBuf>(),
+            ),
+            None => path.to_path_buf(),
+        }
+    }
+
+    fn install_file(&mut self, src: &Path, dst: PathBuf) -> Result<(), InnerExecuteError> {
+        use InnerExecuteError::*;
+
+        let staged = self.staged(&dst);
+        staged
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|()| fs::copy(src, &staged))
+            .map_err(|err| CouldNotInstall(staged.clone(), Rc::new(err)))?;
+
+        println!(
+            "installed {}",
+            staged.display()
+        );
+        self.installed
+            .push(dst);
+        Ok(())
+    }
+
+    fn install_link(&mut self, link_target: &str, dst: PathBuf) -> Result<(), InnerExecuteError> {
+        use InnerExecuteError::*;
+
+        let staged = self.staged(&dst);
+        staged
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|()| replace_symlink(link_target, &staged))
+            .map_err(|err| CouldNotInstall(staged.clone(), Rc::new(err)))?;
+
+        println!(
+            "installed {} -> {}",
+            staged.display(),
+            link_target
+        );
+        self.installed
+            .push(dst);
+        Ok(())
+    }
+
+    fn install_headers(
+        &mut self,
+        config: &Configuration,
+        src: &Path,
+        dst: PathBuf,
+    ) -> Result<(), InnerExecuteError> {
+        use InnerExecuteError::*;
+
+        let mut entries = fs::read_dir(src)
+            .and_then(|entries| entries.collect::<Result<Vec<_>, _>>())
+            .map_err(|err| {
+                CouldNotInstall(
+                    src.to_path_buf(),
+                    Rc::new(err),
+                )
+            })?;
+        entries.sort_by_key(|entry| entry.file_name());
+
+        for entry in entries {
+            let path = entry.path();
+            if config.is_excluded(&path) {
+                continue;
+            }
+
+            let dst = dst.join(entry.file_name());
+            if path.is_dir() {
+                self.install_headers(config, &path, dst)?;
+            } else if config.is_header(&path) {
+                self.install_file(&path, dst)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+const FLAGS: &[Flag] = &[
+    Flag::one(
+        "profile",
+        "name",
+        "Profile to use, `default` when not given",
+    ),
+    Flag::one(
+        "prefix",
+        "dir",
+        "Dir to install under",
+    ),
+];
+
+impl super::Subcommand for Subcommand {
+    fn parse(
+        mut flags: IndexMap<Value, Rc<[Value]>>,
+        _post_dash_dash: impl Iterator<Item = String>,
+    ) -> Result<Rc<dyn super::Subcommand>, Rc<dyn super::InnerParseError>> {
+        let profile = flags
+            .shift_remove("profile")
+            .and_then(super::single_value)
+            .unwrap_or_else(|| DEFAULT_PROFILE.into());
+
+        let prefix = flags
+            .shift_remove("prefix")
+            .and_then(super::single_value);
+
+        Ok(Rc::new(Subcommand {
+            profile,
+            prefix,
+        }))
+    }
+
+    fn flags() -> &'static [Flag] { FLAGS }
+
+    fn execute(&self) -> Result<(), Rc<dyn super::InnerExecuteError>> {
+        use InnerExecuteError::*;
+
+        let project_dir = Dir::from(
+            env::current_dir()
+                .map_err(Rc::new)
+                .map_err(InvalidCurrentDir)?,
+        );
+
+        let config = Configuration::load(project_dir.clone()).map_err(CannotLoadConfiguration)?;
+
+        let profile = config
+            .build(
+                Some(BuildType::Library),
+                &self.profile,
+                false,
+            )
+            .map_err(BuildError)?;
+
+        let prefix = project_dir.join(
+            self.prefix
+                .as_deref()
+                .unwrap_or(DEFAULT_PREFIX),
+        );
+        let name = config.project_name();
+
+        let mut installer = Installer {
+            destdir: env::var_os(DESTDIR_VARIABLE)
+                .filter(|destdir| !destdir.is_empty())
+                .map(|destdir| project_dir.join(destdir)),
+            installed: Vec::new(),
+        };
+
+        installer.install_headers(
+            &config,
+            &config.include_dir(),
+            prefix
+                .join("include")
+                .join(&*name),
+        )?;
+
+        let artifact_file = config.target_artifact_file(
+            BuildType::Library,
+            &self.profile,
+            profile,
+        );
+        let lib_dir = prefix.join("lib");
+        let versions = profile.artifact_versions(&config, BuildType::Library);
+        match versions.split_first() {
+            None => installer.install_file(
+                &artifact_file,
+                lib_dir.join(
+                    artifact_file
+                        .file_name()
+                        .unwrap_or_default(),
+                ),
+            )?,
+
+            // versioned names are installed as links, like they are next to the artifact
+            Some((linked, versions)) => {
+                installer.install_file(
+                    &artifact_file.with_file_name(&**linked),
+                    lib_dir.join(&**linked),
+                )?;
+                let mut link_target = linked;
+                for version in versions {
+                    installer.install_link(
+                        link_target,
+                        lib_dir.join(&**version),
+                    )?;
+                    link_target = version;
+                }
+                installer.install_link(
+                    link_target,
+                    lib_dir.join(
+                        artifact_file
+                            .file_name()
+                            .unwrap_or_default(),
+                    ),
+                )?;
+            },
+        }
+
+        // import libraries of DLLs, their `.exp` is only needed while linking the DLL itself
+        let target_lib_dir = config.target_lib_dir(&self.profile);
+        if target_lib_dir.is_dir() {
+            let mut import_libs = fs::read_dir(&target_lib_dir)
+                .and_then(|entries| entries.collect::<Result<Vec<_>, _>>())
+                .map_err(|err| {
+                    CouldNotInstall(
+                        target_lib_dir.to_path_buf(),
+                        Rc::new(err),
+                    )
+                })?;
+            import_libs.sort_by_key(|entry| entry.file_name());
+            for entry in import_libs {
+                if split_file_name(&entry.file_name()).1 == "lib" {
+                    installer.install_file(
+                        &entry.path(),
+                        lib_dir.join(entry.file_name()),
+                    )?;
+                }
+            }
+        }
+
+        // paths without `DESTDIR`, as they will be once the staged files are moved into place
+        let manifest = prefix
+            .join("share")
+            .join("buildpp")
+            .join(format!("{}.manifest", name));
+        let staged_manifest = installer.staged(&manifest);
+        let contents = installer
+            .installed
+            .iter()
+            .chain([&manifest])
+            .map(|path| format!("{}\n", path.display()))
+            .collect::<String>();
+        staged_manifest
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|()| fs::write(&staged_manifest, contents))
+            .map_err(|err| {
+                CouldNotWriteManifest(
+                    staged_manifest.clone(),
+                    Rc::new(err),
+                )
+            })?;
+
+        println!(
+            "installed {} {} into {} ({} files, listed in {})",
+            name,
+            config.version(),
+            prefix.display(),
+            installer
+                .installed
+                .len(),
+            staged_manifest.display()
+        );
+
+        Ok(())
+    }
+}
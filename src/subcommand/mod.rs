@@ -1,27 +1,132 @@
 use std::fmt::Debug;
 use std::rc::Rc;
+use std::vec::IntoIter;
 
 use indexmap::IndexMap;
 
+use crate::ci;
+use crate::configuration::ConfigSource;
+use crate::error_code::Code;
+use crate::error_code::Exit;
 use crate::lsd::Value;
+use crate::util;
 use crate::util::BoolGuardExt;
 
+mod analyze;
+mod bench;
 mod build;
+mod bump;
+mod cache_clean;
+mod cache_info;
+mod cache_list;
+mod clean;
+mod completions;
+mod deploy;
+mod env;
+mod explain;
+mod export_amalgamate;
+mod export_clangd;
+mod export_cmake;
+mod fix;
 mod help;
+mod install;
 mod new;
+mod profiles;
+mod profiles_show;
+mod publish;
 mod run;
+mod stats;
+mod test;
 mod version;
+mod watch;
 
 #[derive(Debug, Clone)]
 pub enum Error {
     ParseRepeatedFlag,
+    /// Flags given a different number of values than their [`Arity`] allows.
+    ParseFlagHasNoValues(&'static str),
+    ParseFlagHasToHaveExactlyOneValue(&'static str),
+    ParseFlagHasToHaveAtMostOneValue(&'static str),
+    ParseFlagHasToHaveAValue(&'static str),
+    /// Flag the subcommand does not have, with the one it was likely meant to be.
+    ParseUnknownFlag(Value, Option<&'static str>),
     ParseUnexpectedFlagValueBeforeAnyFlags(Value),
-    ParseInvalidSubcommand(Value),
+    /// Subcommand that does not exist, with the one it was likely meant to be.
+    ParseInvalidSubcommand(Value, Option<&'static str>),
     ParseInner(Rc<dyn InnerParseError>),
 
     ExecuteInner(Rc<dyn InnerExecuteError>),
 }
 
+impl Error {
+    pub fn code(&self) -> Option<Code> {
+        match self {
+            Self::ExecuteInner(err) => err.code(),
+            _ => None,
+        }
+    }
+
+    /// What build++ exits with because of this error.
+    pub fn exit(&self) -> Exit {
+        match self {
+            Self::ExecuteInner(err) => err.exit(),
+            _ => Exit::Failure,
+        }
+    }
+
+    /// What caused the error, shown after it.
+    pub fn note(&self) -> Option<String> {
+        match self {
+            Self::ParseUnknownFlag(flag, _) => Some(format!(
+                "`-{}` is not a flag of this subcommand",
+                flag
+            )),
+            Self::ParseUnexpectedFlagValueBeforeAnyFlags(value) => Some(format!(
+                "`{}` has to follow a flag",
+                value
+            )),
+            Self::ParseInvalidSubcommand(subcommand, _) => Some(format!(
+                "`{}` is not a subcommand",
+                subcommand
+            )),
+            Self::ParseInner(err) => err.note(),
+            Self::ExecuteInner(err) => err.note(),
+            _ => None,
+        }
+    }
+
+    /// What the user likely meant, shown after the error.
+    pub fn hint(&self) -> Option<String> {
+        match self {
+            Self::ParseUnknownFlag(_, Some(suggestion)) => Some(format!(
+                "did you mean `-{}`?",
+                suggestion
+            )),
+            Self::ParseInvalidSubcommand(_, Some(suggestion)) => Some(format!(
+                "did you mean `{}`?",
+                suggestion
+            )),
+            Self::ParseFlagHasNoValues(flag) => Some(format!(
+                "`-{}` is a switch, values cannot follow it",
+                flag
+            )),
+            Self::ParseFlagHasToHaveExactlyOneValue(flag) => Some(format!(
+                "`-{}` takes exactly one value",
+                flag
+            )),
+            Self::ParseFlagHasToHaveAtMostOneValue(flag) => Some(format!(
+                "`-{}` takes one value, or none",
+                flag
+            )),
+            Self::ParseFlagHasToHaveAValue(flag) => Some(format!(
+                "`-{}` takes one or more values",
+                flag
+            )),
+            _ => None,
+        }
+    }
+}
+
 impl From<Rc<dyn InnerParseError>> for Error {
     fn from(value: Rc<dyn InnerParseError>) -> Self { Self::ParseInner(value) }
 }
@@ -34,39 +139,105 @@ impl From<Rc<dyn InnerExecuteError>> for Error {
 // Parse
 //
 
+/// Whether `arg` is a flag (ex. `--profile`, `-profile`, or `/profile` on Windows, where paths
+/// do not start with `/`, so that absolute paths can be given as values elsewhere).
+fn is_flag(arg: &str) -> bool { arg.starts_with("-") || (cfg!(windows) && arg.starts_with("/")) }
+
+/// Name of a flag or subcommand as typed, without the `--`, `-` or `/` it was given with.
+fn trim_flag(arg: &str) -> String {
+    arg.trim_start_matches("--")
+        .trim_start_matches("-")
+        .trim_start_matches("/")
+        .to_lowercase()
+}
+
 pub fn parse_and_execute(
     mut pre_dash_dash: impl Iterator<Item = String>,
-    post_dash_dash: impl Iterator<Item = String>,
+    post_dash_dash: IntoIter<String>,
 ) -> Result<(), Error> {
     use Error::*;
 
+    ci::init();
+
     // grab subcommand name (can be with --, -, /)
     let original_subcommand = pre_dash_dash.next();
     let subcommand = original_subcommand
-        .as_ref()
-        .map(|s| {
-            s.trim_start_matches("--")
-                .trim_start_matches("-")
-                .trim_start_matches("/")
-                .to_lowercase()
-        });
+        .as_deref()
+        .map(trim_flag);
     let subcommand = subcommand
         .as_ref()
         .map(String::as_str);
 
+    // some subcommands are grouped under a second word (ex. `export cmake`)
+    let mut pre_dash_dash = pre_dash_dash.peekable();
+    let original_group_member = match subcommand.is_some_and(is_group) {
+        true => pre_dash_dash.next_if(|arg| !is_flag(arg)),
+        false => None,
+    };
+    let group_member = original_group_member
+        .as_ref()
+        .map(|s| s.to_lowercase());
+
+    let entry = match (subcommand, &group_member) {
+        (None, _) => find_subcommand("help"),
+        (Some(subcommand), None) => find_subcommand(subcommand),
+        (Some(subcommand), Some(group_member)) => find_subcommand(&format!(
+            "{} {}",
+            subcommand, group_member
+        )),
+    }
+    .ok_or_else(|| {
+        let typed = format!(
+            "{} {}",
+            original_subcommand.unwrap_or_default(),
+            original_group_member.unwrap_or_default()
+        );
+        let typed = typed.trim();
+        ParseInvalidSubcommand(
+            typed.into(),
+            suggest_subcommand(&trim_flag(typed)),
+        )
+    })?;
+
+    // some subcommands take values without a flag (ex. `explain BPP0042` for `-code BPP0042`)
+    let positional = entry
+        .positional
+        .map(|flag| {
+            let mut values = Vec::new();
+            while let Some(value) = pre_dash_dash.next_if(|arg| !is_flag(arg)) {
+                values.push(Value::from(value));
+            }
+            (flag, values)
+        })
+        .filter(|(_, values)| !values.is_empty());
+
     // parse flags
     let mut flags = IndexMap::new();
     for arg in pre_dash_dash {
-        match /* arg.starts_with("--") || */ arg.starts_with("-") || arg.starts_with("/") {
+        match is_flag(&arg) {
             true => {
-                let flag = arg
-                    .trim_start_matches("--")
-                    .trim_start_matches("-")
-                    .trim_start_matches("/")
-                    .to_lowercase();
-
-                let old = flags.insert(flag, Vec::new());
-                old.is_none().ok_or(ParseRepeatedFlag)?;
+                let flag = trim_flag(&arg);
+                let flag = entry
+                    .flag(&flag)
+                    .or_else(|| global_flag(&flag))
+                    .ok_or_else(|| {
+                        let suggestion = util::closest_match(
+                            &flag,
+                            (entry.flags)()
+                                .iter()
+                                .chain(GLOBAL_FLAGS)
+                                .map(|flag| flag.name),
+                        );
+                        ParseUnknownFlag(
+                            flag.as_str()
+                                .into(),
+                            suggestion,
+                        )
+                    })?;
+
+                let old = flags.insert(flag.name, Vec::new());
+                old.is_none()
+                    .or_err(ParseRepeatedFlag)?;
             },
             false => {
                 let arg = Value::from(arg);
@@ -77,6 +248,34 @@ pub fn parse_and_execute(
             },
         }
     }
+    if let Some((flag, values)) = positional {
+        let old = flags.insert(flag, values);
+        old.is_none()
+            .or_err(ParseRepeatedFlag)?;
+    }
+
+    // values are only counted here, subcommands just parse them
+    for (&name, values) in &flags {
+        let arity = entry
+            .flag(name)
+            .or_else(|| global_flag(name))
+            .map_or(Arity::Many, |flag| flag.arity);
+        match (arity, values.len()) {
+            (Arity::None, 0) | (Arity::One, 1) | (Arity::Optional, 0 | 1) | (Arity::Many, 1..) => {
+            },
+            (Arity::None, _) => return Err(ParseFlagHasNoValues(name)),
+            (Arity::One, _) => return Err(ParseFlagHasToHaveExactlyOneValue(name)),
+            (Arity::Optional, _) => return Err(ParseFlagHasToHaveAtMostOneValue(name)),
+            (Arity::Many, _) => return Err(ParseFlagHasToHaveAValue(name)),
+        }
+    }
+
+    if flags
+        .shift_remove("ci")
+        .is_some()
+    {
+        ci::enable();
+    }
     let flags = flags
         .into_iter()
         .map(|(flag, values)| {
@@ -88,37 +287,422 @@ pub fn parse_and_execute(
         .collect();
 
     // parse subcommand
-    let subcommand = match subcommand {
-        // Add more implementations here...
-        None | Some("help") | Some("h") => help::Subcommand::parse(flags, post_dash_dash)?,
-        Some("version") | Some("ver") | Some("v") =>
-            version::Subcommand::parse(flags, post_dash_dash)?,
-        Some("build") | Some("b") => build::Subcommand::parse(flags, post_dash_dash)?,
-        Some("run") | Some("r") => run::Subcommand::parse(flags, post_dash_dash)?,
-        Some("new") | Some("n") | Some("create") | Some("c") =>
-            new::Subcommand::parse(flags, post_dash_dash)?,
-
-        Some(_) =>
-            return Err(ParseInvalidSubcommand(
-                original_subcommand
-                    // optionality of original_subcommand matches optionality of subcommand
-                    .unwrap()
-                    .into(),
-            ))?,
-    };
+    let subcommand = (entry.parse)(flags, post_dash_dash)?;
 
     subcommand.execute()?;
 
     Ok(())
 }
 
+//
+// Subcommands
+//
+
+/// Subcommand as it is typed, for parsing, `help` and completions.
+struct Entry {
+    /// Words selecting the subcommand, group included (ex. `export cmake`).
+    name: &'static str,
+    aliases: &'static [&'static str],
+    description: &'static str,
+    /// Flag the values right after the subcommand are given to (ex. `explain BPP0042`).
+    positional: Option<&'static str>,
+    flags: fn() -> &'static [Flag],
+    parse: Parse,
+}
+
+/// [`Subcommand::parse`] of the subcommand of an [`Entry`].
+type Parse = fn(
+    IndexMap<Value, Rc<[Value]>>,
+    IntoIter<String>,
+) -> Result<Rc<dyn Subcommand>, Rc<dyn InnerParseError>>;
+
+impl Entry {
+    const fn of<S: Subcommand>(
+        name: &'static str,
+        aliases: &'static [&'static str],
+        description: &'static str,
+    ) -> Self {
+        Self {
+            name,
+            aliases,
+            description,
+            positional: None,
+            flags: S::flags,
+            parse: S::parse,
+        }
+    }
+
+    const fn positional(mut self, flag: &'static str) -> Self {
+        self.positional = Some(flag);
+        self
+    }
+
+    /// Flag of this subcommand by its name or an alias of it.
+    fn flag(&self, name: &str) -> Option<&'static Flag> {
+        (self.flags)()
+            .iter()
+            .find(|flag| {
+                flag.name == name
+                    || flag
+                        .aliases
+                        .contains(&name)
+            })
+    }
+}
+
+// Add more implementations here...
+const SUBCOMMANDS: &[Entry] = &[
+    Entry::of::<help::Subcommand>(
+        "help",
+        &["h"],
+        "Show subcommands, or flags of one of them",
+    )
+    .positional("subcommand"),
+    Entry::of::<version::Subcommand>(
+        "version",
+        &["ver", "v"],
+        "Show version of build++",
+    ),
+    Entry::of::<completions::Subcommand>(
+        "completions",
+        &[],
+        "Print a script completing build++ commands for a shell",
+    )
+    .positional("shell"),
+    Entry::of::<new::Subcommand>(
+        "new",
+        &["n", "create", "c"],
+        "Create a project, or add a program to the current one",
+    ),
+    Entry::of::<build::Subcommand>(
+        "build",
+        &["b"],
+        "Build the project and its dependencies",
+    ),
+    Entry::of::<watch::Subcommand>(
+        "watch",
+        &["w"],
+        "Rebuild whenever the project or its local dependencies change",
+    ),
+    Entry::of::<clean::Subcommand>(
+        "clean",
+        &[],
+        "Remove the target dir, or only its old versions and profiles",
+    ),
+    Entry::of::<run::Subcommand>(
+        "run",
+        &["r"],
+        "Build and run the binary of the project",
+    ),
+    Entry::of::<test::Subcommand>(
+        "test",
+        &["t"],
+        "Build and run tests of the project",
+    ),
+    Entry::of::<bench::Subcommand>(
+        "bench",
+        &[],
+        "Build and run benches, comparing them to a baseline",
+    ),
+    Entry::of::<analyze::Subcommand>(
+        "analyze",
+        &["analyse", "a"],
+        "Run a static analyzer over sources of the project",
+    ),
+    Entry::of::<fix::Subcommand>(
+        "fix",
+        &[],
+        "Rewrite deprecated keys of the configuration",
+    ),
+    Entry::of::<env::Subcommand>(
+        "env",
+        &[],
+        "Print environment variables for using the built project",
+    ),
+    Entry::of::<install::Subcommand>(
+        "install",
+        &["i"],
+        "Build the project and copy its artifacts under a prefix",
+    ),
+    Entry::of::<deploy::Subcommand>(
+        "deploy",
+        &[],
+        "Build the project and deploy it as the configuration says",
+    ),
+    Entry::of::<publish::Subcommand>(
+        "publish",
+        &["p"],
+        "Package the project and upload it to a registry",
+    ),
+    Entry::of::<bump::Subcommand>(
+        "bump",
+        &[],
+        "Raise version of the project",
+    )
+    .positional("to"),
+    Entry::of::<stats::Subcommand>(
+        "stats",
+        &[],
+        "Show how long recent builds took",
+    ),
+    Entry::of::<explain::Subcommand>(
+        "explain",
+        &[],
+        "Explain an error code, or list all of them",
+    )
+    .positional("code"),
+    Entry::of::<profiles::Subcommand>(
+        "profiles",
+        &[],
+        "List profiles of the project",
+    ),
+    Entry::of::<profiles_show::Subcommand>(
+        "profiles show",
+        &[],
+        "Show settings of a profile after inheritance",
+    )
+    .positional("name"),
+    Entry::of::<cache_list::Subcommand>(
+        "cache list",
+        &[],
+        "List cached dependencies with their sizes and last use",
+    ),
+    Entry::of::<cache_info::Subcommand>(
+        "cache info",
+        &[],
+        "Show cached versions and profiles of a dependency",
+    )
+    .positional("alias"),
+    Entry::of::<cache_clean::Subcommand>(
+        "cache clean",
+        &[],
+        "Remove a dependency from the cache, so that it is cached anew",
+    )
+    .positional("alias"),
+    Entry::of::<export_cmake::Subcommand>(
+        "export cmake",
+        &["e cmake"],
+        "Write a CMakeLists.txt for the project",
+    ),
+    Entry::of::<export_clangd::Subcommand>(
+        "export clangd",
+        &["e clangd"],
+        "Write clangd configuration for the project",
+    ),
+    Entry::of::<export_amalgamate::Subcommand>(
+        "export amalgamate",
+        &["export single-header", "e amalgamate", "e single-header"],
+        "Write the library as a single header",
+    ),
+];
+
+/// Name of the subcommand a mistyped one was likely meant to be (ex. `build` for `biuld`),
+/// groups included (ex. `export` for `exprot`).
+fn suggest_subcommand(typed: &str) -> Option<&'static str> {
+    let names = SUBCOMMANDS
+        .iter()
+        .flat_map(|entry| {
+            [entry.name]
+                .into_iter()
+                .chain(
+                    entry
+                        .name
+                        .split_once(' ')
+                        .map(|(group, _)| group),
+                )
+        });
+    util::closest_match(typed, names)
+}
+
+/// Whether subcommands are grouped under `word` (ex. `export` and `e` of `export cmake`).
+fn is_group(word: &str) -> bool {
+    SUBCOMMANDS
+        .iter()
+        .flat_map(|entry| {
+            [entry.name]
+                .into_iter()
+                .chain(
+                    entry
+                        .aliases
+                        .iter()
+                        .copied(),
+                )
+        })
+        .any(|name| {
+            name.split_once(' ')
+                .is_some_and(|(group, _)| group == word)
+        })
+}
+
+/// Subcommand by its name or an alias of it (ex. `e cmake`).
+fn find_subcommand(name: &str) -> Option<&'static Entry> {
+    SUBCOMMANDS
+        .iter()
+        .find(|entry| {
+            entry.name == name
+                || entry
+                    .aliases
+                    .contains(&name)
+        })
+}
+
+//
+// Flags
+//
+
+/// Flags every subcommand accepts, taken out before it is parsed.
+const GLOBAL_FLAGS: &[Flag] = &[Flag::switch(
+    "ci",
+    "Run non-interactively with full logs, failing on denied warnings (also when `CI` is set)",
+)];
+
+/// Value of a flag taking [`Arity::One`] (or of an [`Arity::Optional`] one, if it was given),
+/// as [`parse_and_execute`] already counted the values.
+fn single_value(values: Rc<[Value]>) -> Option<Value> {
+    values
+        .first()
+        .cloned()
+}
+
+/// Value of `-config`, which is stdin when `-` (taken by argv as the end of flags) left it
+/// without one.
+fn config_value(values: Rc<[Value]>) -> Value {
+    single_value(values).unwrap_or_else(|| ConfigSource::STDIN.into())
+}
+
+fn global_flag(name: &str) -> Option<&'static Flag> {
+    GLOBAL_FLAGS
+        .iter()
+        .find(|flag| {
+            flag.name == name
+                || flag
+                    .aliases
+                    .contains(&name)
+        })
+}
+
+/// How many values a flag takes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Arity {
+    /// A switch (ex. `-verbose`).
+    None,
+    /// Exactly one value (ex. `-profile release`).
+    One,
+    /// A value that may be left out (ex. `-config` alone).
+    Optional,
+    /// At least one value (ex. `-profiles debug release`).
+    Many,
+}
+
+/// Flag a subcommand accepts.
+struct Flag {
+    name: &'static str,
+    aliases: &'static [&'static str],
+    arity: Arity,
+    /// What the values are, shown in `help` (ex. `<name>`).
+    value: &'static str,
+    description: &'static str,
+}
+
+impl Flag {
+    const fn new(
+        name: &'static str,
+        arity: Arity,
+        value: &'static str,
+        description: &'static str,
+    ) -> Self {
+        Self {
+            name,
+            aliases: &[],
+            arity,
+            value,
+            description,
+        }
+    }
+
+    const fn switch(name: &'static str, description: &'static str) -> Self {
+        Self::new(
+            name,
+            Arity::None,
+            "",
+            description,
+        )
+    }
+
+    const fn one(name: &'static str, value: &'static str, description: &'static str) -> Self {
+        Self::new(
+            name,
+            Arity::One,
+            value,
+            description,
+        )
+    }
+
+    const fn optional(name: &'static str, value: &'static str, description: &'static str) -> Self {
+        Self::new(
+            name,
+            Arity::Optional,
+            value,
+            description,
+        )
+    }
+
+    const fn many(name: &'static str, value: &'static str, description: &'static str) -> Self {
+        Self::new(
+            name,
+            Arity::Many,
+            value,
+            description,
+        )
+    }
+
+    const fn aliases(mut self, aliases: &'static [&'static str]) -> Self {
+        self.aliases = aliases;
+        self
+    }
+
+    /// How the flag is written (ex. `-profile, -p <name>`).
+    fn usage(&self) -> String {
+        let mut usage = format!("-{}", self.name);
+        for alias in self.aliases {
+            usage.push_str(&format!(", -{}", alias));
+        }
+        match self.arity {
+            Arity::None => {},
+            Arity::One => usage.push_str(&format!(" <{}>", self.value)),
+            Arity::Optional => usage.push_str(&format!(" [{}]", self.value)),
+            Arity::Many => usage.push_str(&format!(
+                " <{}>...",
+                self.value
+            )),
+        }
+        usage
+    }
+}
+
 //
 // Subcommand
 //
 
-pub trait InnerParseError: Debug {}
+pub trait InnerParseError: Debug {
+    /// What caused this error (ex. the value that could not be parsed), shown after it.
+    fn note(&self) -> Option<String> { None }
+}
+
+pub trait InnerExecuteError: Debug {
+    /// Code of the build++ error causing this one, if there is one.
+    fn code(&self) -> Option<Code> { None }
 
-pub trait InnerExecuteError: Debug {}
+    /// What caused this error (ex. the I/O error), shown after it.
+    fn note(&self) -> Option<String> { None }
+
+    /// What build++ exits with, by [`Self::code`] unless there is none.
+    fn exit(&self) -> Exit {
+        self.code()
+            .map(Exit::from)
+            .unwrap_or(Exit::Failure)
+    }
+}
 
 trait Subcommand {
     fn parse(
@@ -128,5 +712,10 @@ trait Subcommand {
     where
         Self: Sized;
 
+    /// Flags [`Self::parse`] accepts, unknown ones being rejected before it is called.
+    fn flags() -> &'static [Flag]
+    where
+        Self: Sized;
+
     fn execute(&self) -> Result<(), Rc<dyn InnerExecuteError>>;
 }
@@ -1,35 +1,92 @@
 use std::fs;
 use std::fs::File;
 use std::io;
+use std::io::IsTerminal;
 use std::io::Write;
+use std::path::Path;
+use std::process::Command;
 use std::rc::Rc;
 
 use indexmap::IndexMap;
 
+use super::Flag;
+use crate::ci;
+use crate::configuration;
+use crate::configuration::Configuration;
+use crate::error_code::Code;
+use crate::error_code::ErrorCode;
+use crate::key;
+use crate::lsd::LSDGetExt;
+use crate::lsd::LSDParseError;
 use crate::lsd::Value;
+use crate::lsd::LSD;
+use crate::profile::DEFAULT_PROFILE;
+use crate::util;
 use crate::util::format_multiline_code;
 use crate::util::BoolGuardExt;
 use crate::BuildType;
 use crate::Dir;
 
 pub struct Subcommand {
-    build_type: BuildType,
-    name: Value,
+    what: New,
+}
+
+enum New {
+    /// A whole project in a new dir named after it, from a template.
+    Project {
+        template: Value,
+        name: Value,
+        /// Given by `-var name=value`, prompted for otherwise.
+        variables: IndexMap<Value, Value>,
+        /// Language standard the tooling files are set up for (ex. `c++20`).
+        standard: Value,
+        /// Write `.editorconfig`, `.clang-format` and `.clangd` (unless `-no-tooling-files`).
+        tooling_files: bool,
+    },
+    /// A program added to the project in the current dir (`-bin` or `-example`).
+    Program { kind: Program, name: Value },
+}
+
+#[derive(Clone, Copy)]
+enum Program {
+    /// `src/bin/<name>`.
+    Bin,
+    /// `examples/<name>`.
+    Example,
 }
 
 #[derive(Debug, Clone)]
 enum InnerParseError {
-    FoundExtraFlags(Rc<[Value]>),
-
     MissingBuildType,
-    BuildTypeHasToHaveExactlyOneValue,
     UnknownBuildType,
 
+    BuildTypeAndTemplateCannotBeUsedTogether,
+
+    VarHasToBeNameEqualsValue(Value),
+
+    UnknownStandard(Value),
+
     MissingProjectName,
-    NameHasToHaveExactlyOneValue,
+
+    BinAndExampleCannotBeUsedTogether,
+    ProjectFlagsCannotBeUsedWithProgram,
 }
 
 impl super::InnerParseError for InnerParseError {
+    fn note(&self) -> Option<String> {
+        use InnerParseError::*;
+        match self {
+            VarHasToBeNameEqualsValue(var) => Some(format!(
+                "`{}` is not `name=value`",
+                var
+            )),
+            UnknownStandard(standard) => Some(format!(
+                "`{}` is not a C or C++ standard",
+                standard
+            )),
+            _ => None,
+        }
+    }
 }
 
 impl From<InnerParseError> for Rc<dyn super::InnerParseError> {
@@ -43,8 +100,27 @@ enum InnerExecuteError {
     CouldNotCheckProjectDir(Rc<io::Error>),
     ProjectDirAlreadyExistsAndHasFiles,
     CouldNotCreateProjectDir(Rc<io::Error>),
-    CouldNotCreateConfigurationFile(Rc<io::Error>),
-    CouldNotWriteConfigurationFile(Rc<io::Error>),
+
+    UnknownTemplate(Value),
+    CouldNotReadTemplate(Rc<io::Error>),
+    CouldNotParseTemplate(LSDParseError),
+    TemplateIsNotALevel,
+    TemplateVariablesIsNotALevel,
+    TemplateVariableIsInvalid(Value),
+    TemplatePostGenerateIsNotAList,
+    TemplatePostGeneratePieceIsNotAValue,
+    VariableNotDeclaredByTemplate(Value),
+    MissingTemplateVariable(Value),
+    CouldNotReadTemplateVariable(Rc<io::Error>),
+    UnknownTemplatePlaceholder(Value),
+    CouldNotWriteTemplateFile(Value, Rc<io::Error>),
+
+    PostGenerateFailedSpawn(Value, Rc<io::Error>),
+    PostGenerateFailedExitCode(Value, i32),
+    PostGenerateKilled(Value),
+
+    CannotLoadConfiguration(configuration::LoadError),
+    ProgramAlreadyExists(Value),
 
     CouldNotCreateSourceDir(Rc<io::Error>),
     CouldNotCreateSourceFile(Rc<io::Error>),
@@ -52,44 +128,592 @@ enum InnerExecuteError {
 }
 
 impl super::InnerExecuteError for InnerExecuteError {
+    fn code(&self) -> Option<Code> {
+        use InnerExecuteError::*;
+        match self {
+            CannotLoadConfiguration(err) => Some(err.code()),
+            _ => None,
+        }
+    }
+
+    fn note(&self) -> Option<String> {
+        use InnerExecuteError::*;
+        match self {
+            CannotLoadConfiguration(err) => err.note(),
+            CouldNotReadTemplateVariable(err)
+            | CouldNotCreateSourceDir(err)
+            | CouldNotCreateSourceFile(err)
+            | CouldNotWriteSourceFile(err)
+            | InvalidCurrentDir(err)
+            | CouldNotCheckProjectDir(err)
+            | CouldNotCreateProjectDir(err)
+            | CouldNotReadTemplate(err) => Some(err.to_string()),
+            UnknownTemplate(template) => Some(format!(
+                "`{}` is not a template",
+                template
+            )),
+            CouldNotParseTemplate(err) => Some(err.to_string()),
+            TemplateVariableIsInvalid(name) => Some(format!(
+                "`{}` has to be a default value, or a level of `prompt` and `default`",
+                name
+            )),
+            VariableNotDeclaredByTemplate(name) => Some(format!(
+                "`{}` is not a variable of the template",
+                name
+            )),
+            MissingTemplateVariable(name) => Some(format!(
+                "`{}` has no default, give it with `-var {}=...`",
+                name, name
+            )),
+            UnknownTemplatePlaceholder(name) => Some(format!(
+                "`{}` is used by the template without being declared",
+                name
+            )),
+            CouldNotWriteTemplateFile(path, err) => Some(format!("`{}`: {}", path, err)),
+            PostGenerateFailedSpawn(program, err) => Some(format!(
+                "`{}`: {}",
+                program, err
+            )),
+            PostGenerateFailedExitCode(program, code) => Some(format!(
+                "`{}` exited with code {}",
+                program, code
+            )),
+            PostGenerateKilled(program) => Some(format!(
+                "`{}` was killed by a signal",
+                program
+            )),
+            ProgramAlreadyExists(path) => Some(format!(
+                "`{}` already exists",
+                path
+            )),
+            _ => None,
+        }
+    }
 }
 
 impl From<InnerExecuteError> for Rc<dyn super::InnerExecuteError> {
     fn from(value: InnerExecuteError) -> Self { Rc::new(value) }
 }
 
-fn parse_build_type(build_type: Rc<[Value]>) -> Result<BuildType, InnerParseError> {
+fn parse_build_type(build_type: Value) -> Result<BuildType, InnerParseError> {
     use InnerParseError::*;
 
-    let mut build_type_values = build_type.iter();
-    let build_type = build_type_values
-        .next()
-        .ok_or(BuildTypeHasToHaveExactlyOneValue)?;
-    build_type_values
-        .next()
-        .is_none()
-        .ok_or(BuildTypeHasToHaveExactlyOneValue)?;
-
     Ok(build_type
         .parse()
         .map_err(|()| UnknownBuildType)?)
 }
 
-fn parse_name(name: Rc<[Value]>) -> Result<Value, InnerParseError> {
+fn parse_vars(vars: Rc<[Value]>) -> Result<IndexMap<Value, Value>, InnerParseError> {
+    use InnerParseError::*;
+
+    vars.iter()
+        .map(|var| {
+            let (name, value) = var
+                .split_once('=')
+                .ok_or_else(|| VarHasToBeNameEqualsValue(var.clone()))?;
+            Ok((
+                Value::from(name.trim()),
+                Value::from(value),
+            ))
+        })
+        .collect()
+}
+
+fn parse_standard(standard: Value) -> Result<Value, InnerParseError> {
     use InnerParseError::*;
 
-    let mut name_values = name.iter();
-    let name = name_values
-        .next()
-        .ok_or(NameHasToHaveExactlyOneValue)?;
-    name_values
-        .next()
-        .is_none()
-        .ok_or(NameHasToHaveExactlyOneValue)?;
+    let standard = standard.to_lowercase();
+    STANDARDS
+        .contains(&standard.as_str())
+        .or_else_err(|| {
+            UnknownStandard(
+                standard
+                    .as_str()
+                    .into(),
+            )
+        })?;
+
+    Ok(standard.into())
+}
+
+/// A hello world program greeting with `greeting`.
+fn hello(greeting: &str) -> String {
+    format_multiline_code(
+        &r#"
+            #include <iostream>
+
+            using std::cout;
+            using std::endl;
+
+            int main() {
+                cout << "GREETING" << endl;
+                return 0;
+            }
+        "#
+        .replace("GREETING", greeting),
+    ) + "\n"
+}
+
+//
+// Tooling files
+//
+
+/// Standards `-standard` accepts.
+const STANDARDS: &[&str] = &["c++11", "c++14", "c++17", "c++20", "c++23", "c11", "c17"];
+
+/// Used when `-standard` is not given.
+const DEFAULT_STANDARD: &str = "c++17";
+
+/// Editor and formatter settings for a new project written for `standard`, by file name.
+fn tooling_files_for(standard: &str) -> Vec<(&'static str, String)> {
+    let is_cpp = standard.starts_with("c++");
+
+    let editorconfig = format_multiline_code(
+        r#"
+            root = true
+
+            [*]
+            charset = utf-8
+            end_of_line = lf
+            insert_final_newline = true
+            trim_trailing_whitespace = true
+
+            [*.{c,cc,cpp,cxx,c++,cu,h,hh,hpp,hxx,cuh,lsd}]
+            indent_style = space
+            indent_size = 4
+        "#,
+    ) + "\n";
+
+    // clang-format only knows C++ standards, and calls the newest one `Latest`
+    let mut clang_format = String::from("BasedOnStyle: LLVM\nIndentWidth: 4\nColumnLimit: 100\n");
+    if is_cpp {
+        clang_format.push_str(&format!(
+            "Standard: {}\n",
+            match standard {
+                "c++23" => "Latest",
+                standard => standard,
+            }
+        ));
+    }
+
+    // until `buildpp export clangd -as clangd` replaces it with flags of a profile
+    let clangd = format!(
+        "{} for a new project, regenerate with `buildpp export clangd -as clangd`.\n\
+         CompileFlags:\n  Add: [\"{}\", \"-std={}\", \"-Isrc\"]\n",
+        super::export_clangd::GENERATED_HEADER,
+        match is_cpp {
+            true => "-xc++",
+            false => "-xc",
+        },
+        standard
+    );
+
+    vec![
+        (".editorconfig", editorconfig),
+        (".clang-format", clang_format),
+        (".clangd", clangd),
+    ]
+}
+
+//
+// Templates
+//
+
+/// Dir in the user dir with user templates, each in a dir named after it (ex. `qt-app/`),
+/// taking precedence over built-in templates of the same name.
+const TEMPLATES_DIRNAME: &str = "templates";
+
+/// Declares variables and post-generate commands of a user template, not copied itself:
+///
+/// ```lsd
+/// variables {
+///     author {
+///         prompt Author of the project
+///         default Anonymous
+///     }
+///     license MIT
+/// }
+/// post_generate [
+///     "git init"
+///     [ clang-format -i src/main.cpp ]
+/// ]
+/// ```
+const TEMPLATE_FILENAME: &str = "template.lsd";
+
+/// Version of new projects, also available to templates as `{{version}}`.
+const INITIAL_VERSION: &str = "0.1.0";
+
+struct Template {
+    /// Paths relative to the project and contents, both with `{{variable}}` placeholders.
+    files: Vec<(Value, Vec<u8>)>,
+    variables: Vec<Variable>,
+    /// Ran in the new project after its files are written (ex. `git init`).
+    post_generate: Vec<Rc<[Value]>>,
+}
+
+struct Variable {
+    name: Value,
+    /// Asked when the value is not given by `-var`, name of the variable otherwise.
+    prompt: Option<Value>,
+    default: Option<Value>,
+}
+
+impl Template {
+    /// `binary` or `library`, with only a configuration file and a hello world source.
+    fn builtin(name: &str) -> Option<Template> {
+        let build_type: BuildType = name
+            .parse()
+            .ok()?;
+        (build_type.to_string() == name).then_some(())?;
+
+        Some(Template {
+            files: vec![
+                (
+                    configuration::CONFIG_FILENAME.into(),
+                    Template::builtin_config().into(),
+                ),
+                (
+                    format!(
+                        "src/{}.cpp",
+                        build_type.src_filename()
+                    )
+                    .into(),
+                    hello("Hello world!").into(),
+                ),
+            ],
+            variables: Vec::new(),
+            post_generate: Vec::new(),
+        })
+    }
+
+    // FIXME fill .lsd file properly
+    fn builtin_config() -> &'static str { "name {{name}}\nversion {{version}}\n" }
+
+    /// Template in `templates/<name>/` of the user dir, `None` if there is none.
+    fn user(name: &str) -> Result<Option<Template>, InnerExecuteError> {
+        use InnerExecuteError::*;
+
+        let Some(dir) = util::user_dir()
+            .map(|user_dir| {
+                user_dir
+                    .join(TEMPLATES_DIRNAME)
+                    .join(name)
+            })
+            .filter(|dir| dir.is_dir())
+        else {
+            return Ok(None);
+        };
+
+        let mut template = match File::open(dir.join(TEMPLATE_FILENAME)) {
+            Ok(file) => Template::parse(LSD::parse(file).map_err(CouldNotParseTemplate)?)?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Template {
+                files: Vec::new(),
+                variables: Vec::new(),
+                post_generate: Vec::new(),
+            },
+            Err(err) =>
+                return Err(CouldNotReadTemplate(Rc::new(
+                    err,
+                ))),
+        };
+
+        read_template_files(
+            &dir,
+            &dir,
+            &mut template.files,
+        )
+        .map_err(Rc::new)
+        .map_err(CouldNotReadTemplate)?;
+        if !template
+            .files
+            .iter()
+            .any(|(path, _)| **path == *configuration::CONFIG_FILENAME)
+        {
+            template
+                .files
+                .insert(
+                    0,
+                    (
+                        configuration::CONFIG_FILENAME.into(),
+                        Template::builtin_config().into(),
+                    ),
+                );
+        }
+
+        Ok(Some(template))
+    }
+
+    fn parse(lsd: LSD) -> Result<Template, InnerExecuteError> {
+        use InnerExecuteError::*;
+
+        let level = lsd
+            .to_level()
+            .ok_or(TemplateIsNotALevel)?;
 
-    Ok(name.clone())
+        let variables = match level.get_level(
+            key!(variables),
+            TemplateVariablesIsNotALevel,
+        )? {
+            Some(variables) => variables
+                .into_iter()
+                .map(
+                    |(name, variable)| match variable {
+                        // Parse `name default`
+                        LSD::Value(default) => Ok(Variable {
+                            name,
+                            prompt: None,
+                            default: Some(default),
+                        }),
+
+                        // Parse `name { prompt ...  default ... }`
+                        LSD::Level(variable) => Ok(Variable {
+                            prompt: variable.get_value(
+                                key!(prompt),
+                                TemplateVariableIsInvalid(name.clone()),
+                            )?,
+                            default: variable.get_value(
+                                key!(default),
+                                TemplateVariableIsInvalid(name.clone()),
+                            )?,
+                            name,
+                        }),
+                    },
+                )
+                .collect::<Result<_, _>>()?,
+            None => Vec::new(),
+        };
+
+        let post_generate = match level.get_inner(key!(post_generate)) {
+            Some(LSD::Level(list)) => list
+                .values()
+                .map(|command| match command {
+                    // Parse `"command with spaces"`
+                    LSD::Value(value) => Ok(value
+                        .split_whitespace()
+                        .map(Rc::from)
+                        .collect::<Rc<[_]>>()),
+
+                    // Parse `[ each list item being a command or arg ]`
+                    LSD::Level(pieces) => pieces
+                        .values()
+                        .map(|piece| {
+                            piece
+                                .to_value()
+                                .ok_or(TemplatePostGeneratePieceIsNotAValue)
+                        })
+                        .collect(),
+                })
+                .filter(|command| {
+                    command
+                        .as_ref()
+                        .map_or(true, |command| {
+                            !command.is_empty()
+                        })
+                })
+                .collect::<Result<_, _>>()?,
+            Some(LSD::Value(_)) => return Err(TemplatePostGenerateIsNotAList),
+            None => Vec::new(),
+        };
+
+        Ok(Template {
+            files: Vec::new(),
+            variables,
+            post_generate,
+        })
+    }
+
+    /// Values of variables of the template, given in `given`, or else prompted for when ran
+    /// in a terminal, or else their defaults.
+    fn variables(
+        &self,
+        given: &IndexMap<Value, Value>,
+        builtin: IndexMap<Value, Value>,
+    ) -> Result<IndexMap<Value, Value>, InnerExecuteError> {
+        use InnerExecuteError::*;
+
+        if let Some(name) = given
+            .keys()
+            .find(|name| {
+                !builtin.contains_key(*name)
+                    && !self
+                        .variables
+                        .iter()
+                        .any(|variable| variable.name == **name)
+            })
+        {
+            return Err(VariableNotDeclaredByTemplate(
+                name.clone(),
+            ));
+        }
+
+        let mut values = builtin;
+        for (name, value) in given {
+            values.insert(name.clone(), value.clone());
+        }
+
+        let is_interactive = io::stdin().is_terminal() && !ci::is_enabled();
+        for variable in self
+            .variables
+            .iter()
+            .filter(|variable| !given.contains_key(&variable.name))
+        {
+            let mut value = None;
+            if is_interactive {
+                let prompt = variable
+                    .prompt
+                    .as_ref()
+                    .unwrap_or(&variable.name);
+                match &variable.default {
+                    Some(default) => eprint!("{} [{}]: ", prompt, default),
+                    None => eprint!("{}: ", prompt),
+                }
+                let mut line = String::new();
+                io::stdin()
+                    .read_line(&mut line)
+                    .map_err(Rc::new)
+                    .map_err(CouldNotReadTemplateVariable)?;
+                let line = line.trim();
+                if !line.is_empty() {
+                    value = Some(Value::from(line));
+                }
+            }
+
+            let value = value
+                .or_else(|| {
+                    variable
+                        .default
+                        .clone()
+                })
+                .ok_or_else(|| {
+                    MissingTemplateVariable(
+                        variable
+                            .name
+                            .clone(),
+                    )
+                })?;
+            values.insert(
+                variable
+                    .name
+                    .clone(),
+                value,
+            );
+        }
+
+        Ok(values)
+    }
 }
 
+/// Every file in `dir` (recursively) besides the [`TEMPLATE_FILENAME`] of the template,
+/// with `/`-separated paths relative to `root`.
+fn read_template_files(
+    root: &Path,
+    dir: &Path,
+    files: &mut Vec<(Value, Vec<u8>)>,
+) -> Result<(), io::Error> {
+    let mut entries = fs::read_dir(dir)?.collect::<Result<Vec<_>, _>>()?;
+    entries.sort_by_key(|entry| entry.path());
+
+    for entry in entries {
+        let path = entry.path();
+        if path.is_dir() {
+            read_template_files(root, &path, files)?;
+            continue;
+        }
+
+        let relative = path
+            .strip_prefix(root)
+            .unwrap_or(&path)
+            .components()
+            .map(|component| {
+                component
+                    .as_os_str()
+                    .to_string_lossy()
+                    .into_owned()
+            })
+            .collect::<Vec<_>>()
+            .join("/");
+        if relative == TEMPLATE_FILENAME {
+            continue;
+        }
+        files.push((
+            relative.into(),
+            fs::read(&path)?,
+        ));
+    }
+
+    Ok(())
+}
+
+/// `text` with `{{variable}}` placeholders replaced, or the first unknown variable.
+/// Unclosed placeholders are kept as they are.
+fn fill(text: &str, variables: &IndexMap<Value, Value>) -> Result<String, Value> {
+    let mut result = String::new();
+    let mut rest = text;
+    while let Some(position) = rest.find("{{") {
+        result.push_str(&rest[..position]);
+        rest = &rest[position..];
+
+        match rest[2..].split_once("}}") {
+            Some((name, after)) => {
+                let name = name.trim();
+                let value = variables
+                    .get(name)
+                    .ok_or_else(|| Value::from(name))?;
+                result.push_str(value);
+                rest = after;
+            },
+            None => {
+                result.push_str(&rest[..2]);
+                rest = &rest[2..];
+            },
+        }
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+const FLAGS: &[Flag] = &[
+    Flag::one(
+        "name",
+        "name",
+        "Name of the project, and of the dir it is created in",
+    ),
+    Flag::one(
+        "is",
+        "type",
+        "Build type (`binary` or `library`) of the project",
+    ),
+    Flag::one(
+        "template",
+        "name",
+        "Template to create the project from",
+    ),
+    Flag::many(
+        "var",
+        "name=value",
+        "Values of variables of the template",
+    ),
+    Flag::one(
+        "standard",
+        "standard",
+        "Language standard the tooling files are set up for",
+    ),
+    Flag::switch(
+        "no-tooling-files",
+        "Do not write `.editorconfig`, `.clang-format` and `.clangd`",
+    ),
+    Flag::one(
+        "bin",
+        "name",
+        "Add a binary to `src/bin` of the current project",
+    ),
+    Flag::one(
+        "example",
+        "name",
+        "Add an example to `examples` of the current project",
+    ),
+];
+
 impl super::Subcommand for Subcommand {
     fn parse(
         mut flags: IndexMap<Value, Rc<[Value]>>,
@@ -97,120 +721,307 @@ impl super::Subcommand for Subcommand {
     ) -> Result<Rc<dyn super::Subcommand>, Rc<dyn super::InnerParseError>> {
         use InnerParseError::*;
 
-        let build_type = flags
-            .remove("is")
-            .map(parse_build_type)
-            .transpose()?
-            .ok_or(MissingBuildType)?;
-
-        let name = flags
-            .remove("name")
-            .map(parse_name)
-            .transpose()?
-            .ok_or(MissingProjectName)?;
-
-        let extra_flags = flags.into_keys();
-        if extra_flags.len() > 0 {
-            return Err(FoundExtraFlags(
-                extra_flags
-                    .collect::<Vec<_>>()
-                    .into(),
-            ))?;
+        let bin = flags
+            .shift_remove("bin")
+            .and_then(super::single_value);
+        let example = flags
+            .shift_remove("example")
+            .and_then(super::single_value);
+
+        let what = match (bin, example) {
+            (Some(_), Some(_)) => return Err(BinAndExampleCannotBeUsedTogether)?,
+            (Some(name), None) => New::Program {
+                kind: Program::Bin,
+                name,
+            },
+            (None, Some(name)) => New::Program {
+                kind: Program::Example,
+                name,
+            },
+            (None, None) => {
+                let build_type = flags
+                    .shift_remove("is")
+                    .and_then(super::single_value)
+                    .map(parse_build_type)
+                    .transpose()?;
+                let template = flags
+                    .shift_remove("template")
+                    .and_then(super::single_value);
+                New::Project {
+                    template: match (build_type, template) {
+                        (Some(_), Some(_)) =>
+                            return Err(BuildTypeAndTemplateCannotBeUsedTogether)?,
+                        (Some(build_type), None) => Value::from(build_type.to_string()),
+                        (None, Some(template)) => template,
+                        (None, None) => return Err(MissingBuildType)?,
+                    },
+                    name: flags
+                        .shift_remove("name")
+                        .and_then(super::single_value)
+                        .ok_or(MissingProjectName)?,
+                    variables: flags
+                        .shift_remove("var")
+                        .map(parse_vars)
+                        .transpose()?
+                        .unwrap_or_default(),
+                    standard: flags
+                        .shift_remove("standard")
+                        .and_then(super::single_value)
+                        .map(parse_standard)
+                        .transpose()?
+                        .unwrap_or_else(|| DEFAULT_STANDARD.into()),
+                    tooling_files: flags
+                        .shift_remove("no-tooling-files")
+                        .is_none(),
+                }
+            },
+        };
+
+        if matches!(what, New::Program { .. }) {
+            [
+                "is",
+                "template",
+                "name",
+                "var",
+                "standard",
+                "no-tooling-files",
+            ]
+            .iter()
+            .all(|flag| !flags.contains_key(*flag))
+            .or_err(ProjectFlagsCannotBeUsedWithProgram)?;
         }
 
-        Ok(Rc::new(Subcommand {
-            build_type,
-            name,
-        }))
+        Ok(Rc::new(Subcommand { what }))
     }
 
+    fn flags() -> &'static [Flag] { FLAGS }
+
     fn execute(&self) -> Result<(), Rc<dyn super::InnerExecuteError>> {
-        use InnerExecuteError::*;
+        match &self.what {
+            New::Project {
+                template,
+                name,
+                variables,
+                standard,
+                tooling_files,
+            } => new_project(
+                template,
+                name,
+                variables,
+                standard,
+                *tooling_files,
+            )?,
+            New::Program { kind, name } => new_program(*kind, name)?,
+        }
+        Ok(())
+    }
+}
+
+fn new_project(
+    template_name: &str,
+    name: &Value,
+    variables: &IndexMap<Value, Value>,
+    standard: &Value,
+    tooling_files: bool,
+) -> Result<(), InnerExecuteError> {
+    use InnerExecuteError::*;
 
-        // FIXME do not override anything
+    let template = match Template::user(template_name)? {
+        Some(template) => template,
+        None =>
+            Template::builtin(template_name).ok_or_else(|| UnknownTemplate(template_name.into()))?,
+    };
 
-        // setup dir
-        let parent_dir = Dir::from(
-            std::env::current_dir()
+    // setup dir
+    let parent_dir = Dir::from(
+        std::env::current_dir()
+            .map_err(Rc::new)
+            .map_err(InvalidCurrentDir)?,
+    );
+
+    let project_dir = parent_dir.join(&**name);
+
+    if project_dir.exists()
+        && (project_dir.is_file()
+            || fs::read_dir(&project_dir)
                 .map_err(Rc::new)
-                .map_err(InvalidCurrentDir)?,
-        );
+                .map_err(CouldNotCheckProjectDir)?
+                .next()
+                .is_some())
+    {
+        return Err(ProjectDirAlreadyExistsAndHasFiles);
+    }
 
-        let project_dir = parent_dir.join(&*self.name);
+    let variables = template.variables(
+        variables,
+        IndexMap::from([
+            ("name".into(), name.clone()),
+            (
+                "version".into(),
+                INITIAL_VERSION.into(),
+            ),
+            (
+                "standard".into(),
+                standard.clone(),
+            ),
+        ]),
+    )?;
 
-        if project_dir.exists()
-            && (project_dir.is_file()
-                || fs::read_dir(&project_dir)
-                    .map_err(Rc::new)
-                    .map_err(CouldNotCheckProjectDir)?
-                    .next()
-                    .is_some())
-        {
-            return Err(ProjectDirAlreadyExistsAndHasFiles)?;
+    fs::create_dir_all(&project_dir)
+        .map_err(Rc::new)
+        .map_err(CouldNotCreateProjectDir)?;
+
+    for (path, contents) in &template.files {
+        let path = fill(path, &variables).map_err(UnknownTemplatePlaceholder)?;
+        // other files (ex. images) are copied as they are
+        let contents = match String::from_utf8(contents.clone()) {
+            Ok(text) => fill(&text, &variables)
+                .map_err(UnknownTemplatePlaceholder)?
+                .into_bytes(),
+            Err(_) => contents.clone(),
+        };
+
+        let file = project_dir.join(&path);
+        if let Some(dir) = file.parent() {
+            fs::create_dir_all(dir)
+                .map_err(Rc::new)
+                .map_err(|err| {
+                    CouldNotWriteTemplateFile(
+                        path.as_str()
+                            .into(),
+                        err,
+                    )
+                })?;
         }
+        fs::write(&file, contents)
+            .map_err(Rc::new)
+            .map_err(|err| {
+                CouldNotWriteTemplateFile(
+                    path.as_str()
+                        .into(),
+                    err,
+                )
+            })?;
+    }
 
-        fs::create_dir_all(&project_dir)
+    // those of the template win
+    if tooling_files {
+        for (path, contents) in tooling_files_for(standard) {
+            if template
+                .files
+                .iter()
+                .any(|(template_path, _)| **template_path == *path)
+            {
+                continue;
+            }
+            fs::write(
+                project_dir.join(path),
+                contents,
+            )
             .map_err(Rc::new)
-            .map_err(CouldNotCreateProjectDir)?;
+            .map_err(|err| CouldNotWriteTemplateFile(path.into(), err))?;
+        }
+    }
 
-        // create config
-        let config_path = project_dir.join("build++.lsd");
+    for command in &template.post_generate {
+        let command = command
+            .iter()
+            .map(|piece| fill(piece, &variables))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(UnknownTemplatePlaceholder)?;
+        let program = Value::from(command[0].as_str());
 
-        let mut config_file = File::create(config_path)
+        println!(
+            "running {}",
+            command.join(" ")
+        );
+        let code = Command::new(&command[0])
+            .args(&command[1..])
+            .current_dir(&project_dir)
+            .status()
             .map_err(Rc::new)
-            .map_err(CouldNotCreateConfigurationFile)?;
+            .map_err(|err| PostGenerateFailedSpawn(program.clone(), err))?
+            .code()
+            .ok_or_else(|| PostGenerateKilled(program.clone()))?;
+        (code == 0).or_else_err(|| PostGenerateFailedExitCode(program, code))?;
+    }
 
-        // FIXME fill .lsd file properly
-        writeln!(
-            config_file,
-            "name {}",
-            self.name
-        )
-        .map_err(Rc::new)
-        .map_err(CouldNotWriteConfigurationFile)?;
-        writeln!(config_file, "version 0.1.0")
-            .map_err(Rc::new)
-            .map_err(CouldNotWriteConfigurationFile)?;
+    Ok(())
+}
 
-        // create main
-        let src_dir = project_dir.join("src");
+/// Adds a program to the project in the current dir, with the source suffix of its default
+/// profile (ex. `src/bin/extra_tool.cpp`).
+fn new_program(kind: Program, name: &Value) -> Result<(), InnerExecuteError> {
+    use InnerExecuteError::*;
 
-        fs::create_dir_all(&src_dir)
+    let project_dir = Dir::from(
+        std::env::current_dir()
             .map_err(Rc::new)
-            .map_err(CouldNotCreateSourceDir)?;
+            .map_err(InvalidCurrentDir)?,
+    );
+    let config = Configuration::load(project_dir.clone()).map_err(CannotLoadConfiguration)?;
 
-        let src_path = src_dir.join(format!(
-            "{}.cpp",
-            self.build_type
-                .src_filename()
-        ));
+    let suffix = config
+        .profile(DEFAULT_PROFILE)
+        .and_then(|profile| {
+            profile
+                .src_file_suffixes()
+                .first()
+                .copied()
+        })
+        .unwrap_or(".cpp");
+    let (dir, group_kind) = match kind {
+        Program::Bin => (config.bins_dir(), "bins"),
+        Program::Example => (
+            config.examples_dir(),
+            "examples",
+        ),
+    };
 
-        let mut src_file = File::create(src_path)
-            .map_err(Rc::new)
-            .map_err(CouldNotCreateSourceFile)?;
-
-        writeln!(
-            src_file,
-            "{}",
-            format_multiline_code(
-                r#"
-                    #include <iostream>
-
-                    using std::cout;
-                    using std::endl;
-
-                    int main() {
-                        cout << "Hello world!" << endl;
-                        return 0;
-                    }
-                "#
-            )
+    let src_path = dir.join(format!("{}{}", name, suffix));
+    (!src_path.exists()).or_else_err(|| {
+        ProgramAlreadyExists(
+            src_path
+                .strip_prefix(&*project_dir)
+                .unwrap_or(&src_path)
+                .display()
+                .to_string()
+                .into(),
+        )
+    })?;
+
+    fs::create_dir_all(&dir)
+        .map_err(Rc::new)
+        .map_err(CouldNotCreateSourceDir)?;
+
+    let mut src_file = File::create(&src_path)
+        .map_err(Rc::new)
+        .map_err(CouldNotCreateSourceFile)?;
+    src_file
+        .write_all(
+            hello(&format!(
+                "Hello from {}!",
+                name
+            ))
+            .as_bytes(),
         )
         .map_err(Rc::new)
         .map_err(CouldNotWriteSourceFile)?;
 
-        // TODO init git
+    println!(
+        "created {}",
+        src_path
+            .strip_prefix(&*project_dir)
+            .unwrap_or(&src_path)
+            .display()
+    );
 
-        Ok(())
-    }
+    // plain `build` and `run` only build the project itself, programs are built by groups
+    println!(
+        "to build and run it, add `group.{} \"{}.{}\"` to the configuration, then run \
+         `buildpp run -group {}`",
+        name, group_kind, name, name
+    );
+
+    Ok(())
 }
@@ -0,0 +1,92 @@
+use std::env;
+use std::io;
+use std::rc::Rc;
+
+use indexmap::IndexMap;
+
+use super::Flag;
+use crate::configuration;
+use crate::configuration::Configuration;
+use crate::error_code::Code;
+use crate::error_code::ErrorCode;
+use crate::lsd::Value;
+use crate::util::find_executable;
+use crate::Dir;
+
+pub struct Subcommand {}
+
+#[derive(Debug, Clone)]
+enum InnerExecuteError {
+    InvalidCurrentDir(Rc<io::Error>),
+
+    CannotLoadConfiguration(configuration::LoadError),
+}
+
+impl super::InnerExecuteError for InnerExecuteError {
+    fn code(&self) -> Option<Code> {
+        use InnerExecuteError::*;
+        match self {
+            CannotLoadConfiguration(err) => Some(err.code()),
+            _ => None,
+        }
+    }
+
+    fn note(&self) -> Option<String> {
+        use InnerExecuteError::*;
+        match self {
+            CannotLoadConfiguration(err) => err.note(),
+            InvalidCurrentDir(err) => Some(err.to_string()),
+        }
+    }
+}
+
+impl From<InnerExecuteError> for Rc<dyn super::InnerExecuteError> {
+    fn from(value: InnerExecuteError) -> Self { Rc::new(value) }
+}
+
+impl super::Subcommand for Subcommand {
+    fn parse(
+        _flags: IndexMap<Value, Rc<[Value]>>,
+        _post_dash_dash: impl Iterator<Item = String>,
+    ) -> Result<Rc<dyn super::Subcommand>, Rc<dyn super::InnerParseError>> {
+        Ok(Rc::new(Subcommand {}))
+    }
+
+    fn flags() -> &'static [Flag] { &[] }
+
+    fn execute(&self) -> Result<(), Rc<dyn super::InnerExecuteError>> {
+        use InnerExecuteError::*;
+
+        let project_dir = Dir::from(
+            env::current_dir()
+                .map_err(Rc::new)
+                .map_err(InvalidCurrentDir)?,
+        );
+
+        let config = Configuration::load(project_dir).map_err(CannotLoadConfiguration)?;
+
+        let profiles = config.profiles();
+        let name_width = profiles
+            .keys()
+            .map(|name| name.len())
+            .max()
+            .unwrap_or_default();
+        for (name, profile) in profiles.iter() {
+            let compiler = profile.compiler_command();
+            let found = match find_executable(compiler) {
+                Some(path) => path
+                    .display()
+                    .to_string(),
+                None => format!("{} (not found)", compiler),
+            };
+            println!(
+                "{:name_width$}  {:4}  {}",
+                name,
+                profile.kind(),
+                found,
+            );
+        }
+
+        Ok(())
+    }
+}
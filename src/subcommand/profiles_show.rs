@@ -0,0 +1,151 @@
+use std::env;
+use std::io;
+use std::rc::Rc;
+
+use indexmap::IndexMap;
+
+use super::Flag;
+use crate::configuration;
+use crate::configuration::Configuration;
+use crate::error_code::Code;
+use crate::error_code::ErrorCode;
+use crate::lsd::Value;
+use crate::profile;
+use crate::util::find_executable;
+use crate::util::json_quote;
+use crate::BuildError;
+use crate::Dir;
+
+pub struct Subcommand {
+    name: profile::Name,
+}
+
+#[derive(Debug, Clone)]
+enum InnerParseError {
+    MissingName,
+}
+
+impl super::InnerParseError for InnerParseError {
+}
+
+impl From<InnerParseError> for Rc<dyn super::InnerParseError> {
+    fn from(value: InnerParseError) -> Self { Rc::new(value) }
+}
+
+#[derive(Debug, Clone)]
+enum InnerExecuteError {
+    InvalidCurrentDir(Rc<io::Error>),
+
+    CannotLoadConfiguration(configuration::LoadError),
+
+    BuildError(BuildError),
+}
+
+impl super::InnerExecuteError for InnerExecuteError {
+    fn code(&self) -> Option<Code> {
+        use InnerExecuteError::*;
+        match self {
+            CannotLoadConfiguration(err) => Some(err.code()),
+            BuildError(err) => Some(err.code()),
+            _ => None,
+        }
+    }
+
+    fn note(&self) -> Option<String> {
+        use InnerExecuteError::*;
+        match self {
+            CannotLoadConfiguration(err) => err.note(),
+            InvalidCurrentDir(err) => Some(err.to_string()),
+            _ => None,
+        }
+    }
+}
+
+impl From<InnerExecuteError> for Rc<dyn super::InnerExecuteError> {
+    fn from(value: InnerExecuteError) -> Self { Rc::new(value) }
+}
+
+/// Values are quoted only when LSD would otherwise split them.
+fn lsd_value(value: &str) -> String {
+    match value.is_empty() || value.contains(|ch: char| ch.is_whitespace() || "{}[]\"".contains(ch))
+    {
+        true => json_quote(value),
+        false => value.to_string(),
+    }
+}
+
+const FLAGS: &[Flag] = &[Flag::one(
+    "name",
+    "name",
+    "Profile to show",
+)];
+
+impl super::Subcommand for Subcommand {
+    fn parse(
+        mut flags: IndexMap<Value, Rc<[Value]>>,
+        _post_dash_dash: impl Iterator<Item = String>,
+    ) -> Result<Rc<dyn super::Subcommand>, Rc<dyn super::InnerParseError>> {
+        use InnerParseError::*;
+
+        let name = flags
+            .shift_remove("name")
+            .and_then(super::single_value)
+            .ok_or(MissingName)?;
+
+        Ok(Rc::new(Subcommand { name }))
+    }
+
+    fn flags() -> &'static [Flag] { FLAGS }
+
+    fn execute(&self) -> Result<(), Rc<dyn super::InnerExecuteError>> {
+        use InnerExecuteError::*;
+
+        let project_dir = Dir::from(
+            env::current_dir()
+                .map_err(Rc::new)
+                .map_err(InvalidCurrentDir)?,
+        );
+
+        let config = Configuration::load(project_dir).map_err(CannotLoadConfiguration)?;
+
+        let profile = config
+            .profile(&self.name)
+            .ok_or_else(|| {
+                crate::BuildError::InvalidProfile(
+                    self.name
+                        .clone(),
+                )
+            })
+            .map_err(BuildError)?;
+
+        // resolved as if written without `inherit`
+        println!("{} {{", lsd_value(&self.name));
+        println!("    is {}", profile.kind());
+        for (key, value) in profile.settings() {
+            println!(
+                "    {} {}",
+                key,
+                lsd_value(&value)
+            );
+        }
+        println!("}}");
+
+        println!();
+        let compiler = profile.compiler_command();
+        match find_executable(compiler) {
+            Some(path) => println!("compiler: {}", path.display()),
+            None => {
+                println!(
+                    "compiler: {} (not found)",
+                    compiler
+                );
+                println!(
+                    "hint: {}",
+                    profile.installation_hint()
+                );
+            },
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,576 @@
+use std::collections::HashSet;
+use std::env;
+use std::ffi::OsStr;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Command;
+use std::process::Stdio;
+use std::rc::Rc;
+
+use indexmap::IndexMap;
+
+use super::Flag;
+use crate::configuration;
+use crate::configuration::Configuration;
+use crate::dependency::Alias;
+use crate::dependency::Dependency;
+use crate::error_code::Code;
+use crate::error_code::ErrorCode;
+use crate::lsd::Level;
+use crate::lsd::Map;
+use crate::lsd::Value;
+use crate::lsd::LSD;
+use crate::network;
+use crate::util::BoolGuardExt;
+use crate::Dir;
+use crate::Version;
+
+/// Read when `-token` is not given, so that the token does not end up in shell history.
+/// `token` of the registry's host in the global configuration is used otherwise.
+const TOKEN_VARIABLE: &str = "BUILDPP_REGISTRY_TOKEN";
+
+/// Top-level files (by uppercase prefix) packaged alongside sources.
+const PACKAGED_FILE_PREFIXES: &[&str] = &["README", "LICENSE", "LICENCE", "COPYING", "NOTICE"];
+
+pub struct Subcommand {
+    registry: Option<Value>,
+
+    token: Option<Value>,
+
+    /// Package and validate, but do not upload.
+    dry_run: bool,
+}
+
+#[derive(Debug, Clone)]
+enum InnerExecuteError {
+    InvalidCurrentDir(Rc<io::Error>),
+
+    CannotLoadConfiguration(configuration::LoadError),
+
+    MissingLicense,
+    MissingDescription,
+    MissingRegistry,
+    MissingToken,
+
+    CouldNotListFiles(Rc<io::Error>),
+    CouldNotPrepareDirs(Rc<io::Error>),
+    CouldNotCollectDependency(Value, Rc<io::Error>),
+    CouldNotWriteNotices(Rc<io::Error>),
+    TarNotFound,
+    TarFailedSpawn(Rc<io::Error>),
+    TarFailedExitCode(i32),
+    TarKilled,
+
+    CurlNotFound,
+    CurlFailedSpawn(Rc<io::Error>),
+    CurlFailedExitCode(i32),
+    CurlKilled,
+    RegistryRespondedWith(Value),
+    AlreadyPublished(Value, Value),
+}
+
+impl super::InnerExecuteError for InnerExecuteError {
+    fn code(&self) -> Option<Code> {
+        use InnerExecuteError::*;
+        match self {
+            CannotLoadConfiguration(err) => Some(err.code()),
+            _ => None,
+        }
+    }
+
+    fn note(&self) -> Option<String> {
+        use InnerExecuteError::*;
+        match self {
+            CannotLoadConfiguration(err) => err.note(),
+            InvalidCurrentDir(err)
+            | CouldNotListFiles(err)
+            | CouldNotPrepareDirs(err)
+            | CouldNotWriteNotices(err)
+            | TarFailedSpawn(err)
+            | CurlFailedSpawn(err) => Some(err.to_string()),
+            CouldNotCollectDependency(alias, err) => Some(format!(
+                "`{}`: {}",
+                alias, err
+            )),
+            TarFailedExitCode(code) => Some(format!(
+                "`tar` exited with code {}",
+                code
+            )),
+            CurlFailedExitCode(code) => Some(format!(
+                "`curl` exited with code {}",
+                code
+            )),
+            RegistryRespondedWith(status) => Some(format!(
+                "registry responded with HTTP {}",
+                status
+            )),
+            AlreadyPublished(name, version) => Some(format!(
+                "`{}` {} is already in the registry",
+                name, version
+            )),
+            _ => None,
+        }
+    }
+}
+
+impl From<InnerExecuteError> for Rc<dyn super::InnerExecuteError> {
+    fn from(value: InnerExecuteError) -> Self { Rc::new(value) }
+}
+
+/// Runs curl on `url` through [`network::curl`], returns its stdout.
+fn curl(url: &str, token: &str, args: &[&OsStr]) -> Result<String, InnerExecuteError> {
+    use InnerExecuteError::*;
+
+    let output = network::curl(url, Some(token), args).map_err(|err| match err.kind() {
+        io::ErrorKind::NotFound => CurlNotFound,
+        _ => CurlFailedSpawn(Rc::new(err)),
+    })?;
+    let code = output
+        .status
+        .code()
+        .ok_or(CurlKilled)?;
+    (code == 0).or_err(CurlFailedExitCode(code))?;
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Generated into the package, listing every (also indirect) dependency.
+const DEPENDENCY_MANIFEST_FILENAME: &str = "DEPENDENCIES.lsd";
+
+/// Generated into the package, with license and notice files of every dependency.
+/// A `NOTICES` of the project itself is kept at its top.
+const NOTICES_FILENAME: &str = "NOTICES";
+
+/// What is known about a dependency for packaging.
+struct DependencyMetadata {
+    alias: Value,
+    name: Value,
+    version: Version,
+    source: Value,
+    license: Option<Value>,
+    notice_files: Vec<PathBuf>,
+}
+
+/// Every dependency of `dependencies`, then theirs (each only once), in the order they are
+/// declared.
+fn collect_dependencies(
+    dependencies: Map<Alias, Rc<dyn Dependency>>,
+    seen: &mut HashSet<Value>,
+    collected: &mut Vec<DependencyMetadata>,
+) -> Result<(), InnerExecuteError> {
+    use InnerExecuteError::*;
+
+    for (alias, dep) in dependencies.iter() {
+        if !seen.insert(dep.identity()) {
+            continue;
+        }
+
+        let collect = || -> Result<_, io::Error> {
+            Ok((
+                DependencyMetadata {
+                    alias: alias.clone(),
+                    name: dep
+                        .name()?
+                        .unwrap_or_else(|| alias.clone()),
+                    version: dep.current_version()?,
+                    source: dep.source(),
+                    license: dep.license()?,
+                    notice_files: dep.notice_files()?,
+                },
+                dep.dependencies()?,
+            ))
+        };
+        let (metadata, dependencies) = collect()
+            .map_err(Rc::new)
+            .map_err(|err| CouldNotCollectDependency(alias.clone(), err))?;
+        collected.push(metadata);
+        collect_dependencies(dependencies, seen, collected)?;
+    }
+    Ok(())
+}
+
+fn dependency_manifest(dependencies: &[DependencyMetadata]) -> LSD {
+    let list = dependencies
+        .iter()
+        .enumerate()
+        .map(|(i, dep)| {
+            let mut level = Level::new();
+            let mut insert = |key: &str, value: &Value| {
+                level.insert(
+                    key.into(),
+                    LSD::Value(value.clone()),
+                );
+            };
+            insert("alias", &dep.alias);
+            insert("name", &dep.name);
+            // prebuilt dependencies have no version
+            if !dep
+                .version
+                .is_empty()
+            {
+                insert("version", &dep.version);
+            }
+            insert("source", &dep.source);
+            if let Some(license) = &dep.license {
+                insert("license", license);
+            }
+            (
+                Value::from(i.to_string()),
+                LSD::Level(level),
+            )
+        })
+        .collect();
+
+    let mut manifest = Level::new();
+    manifest.insert(
+        "dependency".into(),
+        LSD::Level(list),
+    );
+    LSD::Level(manifest)
+}
+
+fn notices(
+    config: &Configuration,
+    own_notices: Option<String>,
+    dependencies: &[DependencyMetadata],
+) -> Result<String, io::Error> {
+    const SEPARATOR: &str =
+        "================================================================================";
+
+    let mut notices = own_notices.unwrap_or_default();
+    if !notices.is_empty() && !notices.ends_with('\n') {
+        notices.push('\n');
+    }
+    notices.push_str(&format!(
+        "{} {} uses the following third-party dependencies.\n",
+        config.project_name(),
+        config.version()
+    ));
+
+    for dep in dependencies {
+        notices.push_str(&format!(
+            "\n{}\n{}{}{} ({})\nsource: {}\n",
+            SEPARATOR,
+            dep.name,
+            if dep
+                .version
+                .is_empty()
+            {
+                ""
+            } else {
+                " "
+            },
+            dep.version,
+            dep.license
+                .as_deref()
+                .unwrap_or("license unknown"),
+            dep.source
+        ));
+        for file in &dep.notice_files {
+            let text = fs::read_to_string(file)?;
+            notices.push_str(&format!(
+                "\n--- {} ---\n\n{}",
+                file.file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy(),
+                text
+            ));
+            if !text.ends_with('\n') {
+                notices.push('\n');
+            }
+        }
+    }
+    Ok(notices)
+}
+
+const FLAGS: &[Flag] = &[
+    Flag::one(
+        "registry",
+        "url",
+        "Registry to upload to",
+    ),
+    Flag::one(
+        "token",
+        "token",
+        "Token to authenticate to the registry with",
+    ),
+    Flag::switch(
+        "dry-run",
+        "Package and validate, but do not upload",
+    ),
+];
+
+impl super::Subcommand for Subcommand {
+    fn parse(
+        mut flags: IndexMap<Value, Rc<[Value]>>,
+        _post_dash_dash: impl Iterator<Item = String>,
+    ) -> Result<Rc<dyn super::Subcommand>, Rc<dyn super::InnerParseError>> {
+        let registry = flags
+            .shift_remove("registry")
+            .and_then(super::single_value);
+
+        let token = flags
+            .shift_remove("token")
+            .and_then(super::single_value);
+
+        let dry_run = flags
+            .shift_remove("dry-run")
+            .is_some();
+
+        Ok(Rc::new(Subcommand {
+            registry,
+            token,
+            dry_run,
+        }))
+    }
+
+    fn flags() -> &'static [Flag] { FLAGS }
+
+    fn execute(&self) -> Result<(), Rc<dyn super::InnerExecuteError>> {
+        use InnerExecuteError::*;
+
+        let project_dir = Dir::from(
+            env::current_dir()
+                .map_err(Rc::new)
+                .map_err(InvalidCurrentDir)?,
+        );
+
+        let config = Configuration::load(project_dir.clone()).map_err(CannotLoadConfiguration)?;
+
+        // validate metadata
+        let name = config.project_name();
+        let version = config.version();
+        config
+            .license()
+            .ok_or(MissingLicense)?;
+        config
+            .description()
+            .ok_or(MissingDescription)?;
+        let registry = self
+            .registry
+            .clone()
+            .or_else(|| config.registry())
+            .ok_or(MissingRegistry)?;
+        let registry = registry.trim_end_matches('/');
+
+        // package sources and manifest
+        let mut entries = vec![config
+            .config_file()
+            .file_name()
+            .unwrap_or_default()
+            .to_os_string()];
+        for dir in [config.src_dir(), config.include_dir(), config.tests_dir()] {
+            // `include_dir` may be nested, its top dir is packaged
+            let Some(entry) = dir
+                .strip_prefix(config.project_dir())
+                .ok()
+                .and_then(|dir| {
+                    dir.components()
+                        .next()
+                })
+            else {
+                continue;
+            };
+            let entry = entry
+                .as_os_str()
+                .to_os_string();
+            if dir.is_dir() && !entries.contains(&entry) {
+                entries.push(entry);
+            }
+        }
+        for entry in fs::read_dir(&project_dir)
+            .map_err(Rc::new)
+            .map_err(CouldNotListFiles)?
+        {
+            let entry = entry
+                .map_err(Rc::new)
+                .map_err(CouldNotListFiles)?;
+            let file_name = entry.file_name();
+            let upper = file_name
+                .to_string_lossy()
+                .to_uppercase();
+            if entry
+                .path()
+                .is_file()
+                && PACKAGED_FILE_PREFIXES
+                    .iter()
+                    .any(|prefix| upper.starts_with(prefix))
+            {
+                entries.push(file_name);
+            }
+        }
+        entries.sort();
+
+        let package_dir = project_dir
+            .join("target")
+            .join("package");
+        fs::create_dir_all(&package_dir)
+            .map_err(Rc::new)
+            .map_err(CouldNotPrepareDirs)?;
+        let archive = package_dir.join(format!(
+            "{}-{}.tar.gz",
+            name, version
+        ));
+
+        // dependency manifest and notices are generated next to the archive, and packaged
+        // as if they were in the project
+        let mut dependencies = Vec::new();
+        collect_dependencies(
+            config.dependencies(),
+            &mut HashSet::new(),
+            &mut dependencies,
+        )?;
+        for dep in &dependencies {
+            if dep
+                .license
+                .is_none()
+            {
+                eprintln!(
+                    "warning: dependency `{}` has no license, it is listed as unknown in {}",
+                    dep.alias, NOTICES_FILENAME
+                );
+            }
+        }
+
+        let generated_dir = package_dir.join(format!(
+            "{}-{}",
+            name, version
+        ));
+        fs::create_dir_all(&generated_dir)
+            .map_err(Rc::new)
+            .map_err(CouldNotPrepareDirs)?;
+
+        let own_notices = match entries
+            .iter()
+            .position(|entry| entry == NOTICES_FILENAME)
+        {
+            Some(position) => Some(
+                fs::read_to_string(project_dir.join(entries.remove(position)))
+                    .map_err(Rc::new)
+                    .map_err(CouldNotWriteNotices)?,
+            ),
+            None => None,
+        };
+        notices(
+            &config,
+            own_notices,
+            &dependencies,
+        )
+        .and_then(|notices| {
+            fs::write(
+                generated_dir.join(NOTICES_FILENAME),
+                notices,
+            )
+        })
+        .and_then(|()| {
+            fs::write(
+                generated_dir.join(DEPENDENCY_MANIFEST_FILENAME),
+                dependency_manifest(&dependencies).to_string(),
+            )
+        })
+        .map_err(Rc::new)
+        .map_err(CouldNotWriteNotices)?;
+
+        let generated = [DEPENDENCY_MANIFEST_FILENAME, NOTICES_FILENAME];
+        let code = Command::new("tar")
+            .arg("-czf")
+            .arg(&archive)
+            .arg("-C")
+            .arg(&*project_dir)
+            .args(&entries)
+            .arg("-C")
+            .arg(&generated_dir)
+            .args(generated)
+            .stdin(Stdio::null())
+            .stdout(Stdio::inherit())
+            .stderr(Stdio::inherit())
+            .status()
+            .map_err(|err| match err.kind() {
+                io::ErrorKind::NotFound => TarNotFound,
+                _ => TarFailedSpawn(Rc::new(err)),
+            })?
+            .code()
+            .ok_or(TarKilled)?;
+        (code == 0).or_err(TarFailedExitCode(code))?;
+
+        println!(
+            "packaged {} ({})",
+            archive.display(),
+            entries
+                .iter()
+                .map(|entry| entry.to_string_lossy())
+                .chain(generated.map(Into::into))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+
+        if self.dry_run {
+            println!(
+                "dry run, not uploading to {}",
+                registry
+            );
+            return Ok(());
+        }
+
+        let token = self
+            .token
+            .clone()
+            .or_else(|| {
+                env::var(TOKEN_VARIABLE)
+                    .ok()
+                    .map(Value::from)
+            })
+            .or_else(|| network::credentials(registry).token)
+            .ok_or(MissingToken)?;
+
+        // versions are immutable once published
+        let url = format!(
+            "{}/{}/{}",
+            registry, name, version
+        );
+        let null = Path::new(match cfg!(windows) {
+            true => "NUL",
+            false => "/dev/null",
+        });
+        let status = curl(
+            &url,
+            &token,
+            &[
+                "--output".as_ref(),
+                null.as_os_str(),
+                "--write-out".as_ref(),
+                "%{http_code}".as_ref(),
+            ],
+        )?;
+        match status.trim() {
+            "404" => {},
+            "200" =>
+                return Err(AlreadyPublished(
+                    name, version,
+                ))?,
+            status =>
+                return Err(RegistryRespondedWith(
+                    status.into(),
+                ))?,
+        }
+
+        curl(
+            &url,
+            &token,
+            &[
+                "--fail".as_ref(),
+                "--upload-file".as_ref(),
+                archive.as_os_str(),
+            ],
+        )?;
+
+        println!(
+            "published {} {} to {}",
+            name, version, registry
+        );
+
+        Ok(())
+    }
+}
@@ -3,8 +3,15 @@ use std::rc::Rc;
 
 use indexmap::IndexMap;
 
+use super::Flag;
+use crate::ci;
 use crate::configuration;
+use crate::configuration::BuildOptions;
+use crate::configuration::ConfigSource;
 use crate::configuration::Configuration;
+use crate::error_code::Code;
+use crate::error_code::ErrorCode;
+use crate::error_code::Exit;
 use crate::lsd::Value;
 use crate::profile;
 use crate::profile::DEFAULT_PROFILE;
@@ -16,13 +23,23 @@ pub struct Subcommand {
     additional_args: Rc<[Value]>,
 
     profile_name: profile::Name,
+
+    /// Manifest to load instead of `build++.lsd`, `-` for stdin.
+    config: Option<Value>,
+
+    /// Whether to only show output of the program, ex. when it is piped into other tools.
+    quiet: bool,
+
+    /// Group (from `group` of the configuration) whose targets are ran one after another.
+    group: Option<Value>,
+
+    /// Host (ex. `user@gpu-server`) to copy the binary to and run it on over SSH.
+    remote: Option<Value>,
 }
 
 #[derive(Debug, Clone)]
 enum InnerParseError {
-    FoundExtraFlags(Rc<[Value]>),
-
-    ProfileHasToHaveExactlyOneValue,
+    GroupAndRemoteCannotBeUsedTogether,
 }
 
 impl super::InnerParseError for InnerParseError {
@@ -42,26 +59,73 @@ enum InnerExecuteError {
 }
 
 impl super::InnerExecuteError for InnerExecuteError {
+    fn code(&self) -> Option<Code> {
+        use InnerExecuteError::*;
+        match self {
+            CannotLoadConfiguration(err) => Some(err.code()),
+            RunError(crate::RunError::BuildError(err)) => Some(err.code()),
+            _ => None,
+        }
+    }
+
+    fn exit(&self) -> Exit {
+        use crate::RunError::*;
+        match self {
+            InnerExecuteError::RunError(
+                CouldNotOpenStdin(..)
+                | CouldNotCopyToRemote(_)
+                | FailedSpawn(_)
+                | FailedWait(_)
+                | Killed,
+            ) => Exit::Run,
+            _ => self
+                .code()
+                .map(Exit::from)
+                .unwrap_or(Exit::Failure),
+        }
+    }
+
+    fn note(&self) -> Option<String> {
+        use InnerExecuteError::*;
+        match self {
+            CannotLoadConfiguration(err) => err.note(),
+            InvalidCurrentDir(err) => Some(err.to_string()),
+            _ => None,
+        }
+    }
 }
 
 impl From<InnerExecuteError> for Rc<dyn super::InnerExecuteError> {
     fn from(value: InnerExecuteError) -> Self { Rc::new(value) }
 }
 
-fn parse_profile(profile: Rc<[Value]>) -> Result<Rc<str>, InnerParseError> {
-    use InnerParseError::*;
-
-    let mut profile_values = profile.iter();
-    let profile = profile_values
-        .next()
-        .ok_or(ProfileHasToHaveExactlyOneValue)?;
-    profile_values
-        .next()
-        .is_none()
-        .ok_or(ProfileHasToHaveExactlyOneValue)?;
-
-    Ok(profile.clone())
-}
+const FLAGS: &[Flag] = &[
+    Flag::one(
+        "profile",
+        "name",
+        "Profile to use, `default` when not given",
+    ),
+    Flag::optional(
+        "config",
+        "path",
+        "Manifest to load instead of `build++.lsd`, stdin without a path",
+    ),
+    Flag::switch(
+        "quiet",
+        "Only show output of the program",
+    )
+    .aliases(&["q"]),
+    Flag::one(
+        "group",
+        "name",
+        "Group whose targets are ran one after another",
+    ),
+    Flag::one(
+        "remote",
+        "host",
+        "Host to copy the binary to and run it on over SSH",
+    ),
+];
 
 impl super::Subcommand for Subcommand {
     fn parse(
@@ -75,26 +139,39 @@ impl super::Subcommand for Subcommand {
             .collect();
 
         let profile = flags
-            .remove("profile")
-            .map(parse_profile)
-            .transpose()?
+            .shift_remove("profile")
+            .and_then(super::single_value)
             .unwrap_or_else(|| DEFAULT_PROFILE.into());
 
-        let extra_flags = flags.into_keys();
-        if extra_flags.len() > 0 {
-            return Err(FoundExtraFlags(
-                extra_flags
-                    .collect::<Vec<_>>()
-                    .into(),
-            ))?;
-        }
+        let config = flags
+            .shift_remove("config")
+            .map(super::config_value);
+
+        let quiet = flags
+            .shift_remove("quiet")
+            .is_some();
+
+        let group = flags
+            .shift_remove("group")
+            .and_then(super::single_value);
+
+        let remote = flags
+            .shift_remove("remote")
+            .and_then(super::single_value);
+        (group.is_none() || remote.is_none()).or_err(GroupAndRemoteCannotBeUsedTogether)?;
 
         Ok(Rc::new(Subcommand {
             additional_args,
             profile_name: profile,
+            config,
+            quiet,
+            group,
+            remote,
         }))
     }
 
+    fn flags() -> &'static [Flag] { FLAGS }
+
     fn execute(&self) -> Result<(), Rc<dyn super::InnerExecuteError>> {
         use InnerExecuteError::*;
 
@@ -104,17 +181,51 @@ impl super::Subcommand for Subcommand {
                 .map_err(InvalidCurrentDir)?,
         );
 
-        let config = Configuration::load(project_dir).map_err(CannotLoadConfiguration)?;
+        let source = self
+            .config
+            .as_ref()
+            .map(|config| ConfigSource::from_argument(&project_dir, config))
+            .unwrap_or_default();
+        configuration::set_build_options(BuildOptions {
+            quiet: self.quiet,
+            ..Default::default()
+        });
+        let config =
+            Configuration::load_from(project_dir, source).map_err(CannotLoadConfiguration)?;
+        // only known once loaded, so dependencies loaded with it could still show progress
+        if config.run_quiet() {
+            configuration::set_build_options(BuildOptions {
+                quiet: true,
+                ..Default::default()
+            });
+        }
 
-        let exit_code = config
-            .run(
+        let exit_code = match (&self.group, &self.remote) {
+            (Some(group), _) => config.run_group(
+                group,
+                self.profile_name
+                    .clone(),
+                self.additional_args
+                    .clone(),
+            ),
+            (None, Some(remote)) => config.run_remote(
+                remote,
+                self.profile_name
+                    .clone(),
+                self.additional_args
+                    .clone(),
+            ),
+            (None, None) => config.run(
                 self.profile_name
                     .clone(),
                 self.additional_args
                     .clone(),
-            )
-            .map_err(RunError)?;
+            ),
+        }
+        .map_err(RunError)?;
 
+        // exits with the code of the program instead of returning to main
+        ci::print_summary(exit_code, None);
         std::process::exit(exit_code)
     }
 }
@@ -0,0 +1,262 @@
+use std::env;
+use std::io;
+use std::rc::Rc;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use indexmap::IndexMap;
+
+use super::Flag;
+use crate::configuration;
+use crate::configuration::Configuration;
+use crate::error_code::Code;
+use crate::error_code::ErrorCode;
+use crate::lsd::Value;
+use crate::profile;
+use crate::stats;
+use crate::stats::Record;
+use crate::util;
+use crate::Dir;
+
+const DEFAULT_LAST: usize = 10;
+
+pub struct Subcommand {
+    /// Builds of every profile are shown when not given.
+    profile: Option<profile::Name>,
+
+    /// How many of the latest builds are shown, and compared to as many before them.
+    last: usize,
+}
+
+#[derive(Debug, Clone)]
+enum InnerParseError {
+    InvalidLast(Value),
+}
+
+impl super::InnerParseError for InnerParseError {
+    fn note(&self) -> Option<String> {
+        use InnerParseError::*;
+        match self {
+            InvalidLast(last) => Some(format!(
+                "`{}` is not a positive number",
+                last
+            )),
+        }
+    }
+}
+
+impl From<InnerParseError> for Rc<dyn super::InnerParseError> {
+    fn from(value: InnerParseError) -> Self { Rc::new(value) }
+}
+
+#[derive(Debug, Clone)]
+enum InnerExecuteError {
+    InvalidCurrentDir(Rc<io::Error>),
+
+    CannotLoadConfiguration(configuration::LoadError),
+
+    CouldNotReadStats(Rc<io::Error>),
+}
+
+impl super::InnerExecuteError for InnerExecuteError {
+    fn code(&self) -> Option<Code> {
+        use InnerExecuteError::*;
+        match self {
+            CannotLoadConfiguration(err) => Some(err.code()),
+            _ => None,
+        }
+    }
+
+    fn note(&self) -> Option<String> {
+        use InnerExecuteError::*;
+        match self {
+            CannotLoadConfiguration(err) => err.note(),
+            InvalidCurrentDir(err) | CouldNotReadStats(err) => Some(err.to_string()),
+        }
+    }
+}
+
+impl From<InnerExecuteError> for Rc<dyn super::InnerExecuteError> {
+    fn from(value: InnerExecuteError) -> Self { Rc::new(value) }
+}
+
+fn parse_last(last: Value) -> Result<usize, InnerParseError> {
+    use InnerParseError::*;
+
+    last.parse()
+        .ok()
+        .filter(|last| *last > 0)
+        .ok_or_else(|| InvalidLast(last.clone()))
+}
+
+fn average_duration(records: &[&Record]) -> Option<Duration> {
+    let count = u32::try_from(records.len()).ok()?;
+    (count > 0).then(|| {
+        records
+            .iter()
+            .map(|record| record.duration)
+            .sum::<Duration>()
+            / count
+    })
+}
+
+const FLAGS: &[Flag] = &[
+    Flag::one(
+        "profile",
+        "name",
+        "Profile to show builds of, every profile when not given",
+    ),
+    Flag::one(
+        "last",
+        "count",
+        "How many of the latest builds are shown",
+    ),
+];
+
+impl super::Subcommand for Subcommand {
+    fn parse(
+        mut flags: IndexMap<Value, Rc<[Value]>>,
+        _post_dash_dash: impl Iterator<Item = String>,
+    ) -> Result<Rc<dyn super::Subcommand>, Rc<dyn super::InnerParseError>> {
+        let profile = flags
+            .shift_remove("profile")
+            .and_then(super::single_value);
+
+        let last = flags
+            .shift_remove("last")
+            .and_then(super::single_value)
+            .map(parse_last)
+            .transpose()?
+            .unwrap_or(DEFAULT_LAST);
+
+        Ok(Rc::new(Subcommand {
+            profile,
+            last,
+        }))
+    }
+
+    fn flags() -> &'static [Flag] { FLAGS }
+
+    fn execute(&self) -> Result<(), Rc<dyn super::InnerExecuteError>> {
+        use InnerExecuteError::*;
+
+        let project_dir = Dir::from(
+            env::current_dir()
+                .map_err(Rc::new)
+                .map_err(InvalidCurrentDir)?,
+        );
+
+        let config = Configuration::load(project_dir).map_err(CannotLoadConfiguration)?;
+
+        let records = stats::read(
+            &config
+                .project_dir()
+                .join("target"),
+        )
+        .map_err(Rc::new)
+        .map_err(CouldNotReadStats)?;
+        let records = records
+            .iter()
+            .filter(|record| {
+                self.profile
+                    .as_ref()
+                    .is_none_or(|profile| record.profile == *profile)
+            })
+            .collect::<Vec<_>>();
+
+        if records.is_empty() {
+            println!(
+                "no builds of {} recorded yet",
+                config.project_name()
+            );
+            return Ok(());
+        }
+
+        let shown = &records[records
+            .len()
+            .saturating_sub(self.last)..];
+        println!(
+            "last {} of {} builds of {}",
+            shown.len(),
+            records.len(),
+            config.project_name()
+        );
+
+        let now = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs());
+        println!(
+            "{:<10} {:<12} {:<10} {:>10} {:>8} {:>8} {:>12}",
+            "finished", "profile", "version", "duration", "cached", "compiled", "artifact"
+        );
+        for record in shown {
+            println!(
+                "{:<10} {:<12} {:<10} {:>9.2}s {:>8} {:>8} {:>12}",
+                util::format_ago(now, record.finished),
+                record.profile,
+                record.version,
+                record
+                    .duration
+                    .as_secs_f64(),
+                format!(
+                    "{}/{}",
+                    record
+                        .counters
+                        .cache_hits,
+                    record
+                        .counters
+                        .cache_hits
+                        + record
+                            .counters
+                            .cache_misses
+                ),
+                record
+                    .counters
+                    .compiled,
+                util::format_size(record.artifact_size)
+            );
+        }
+
+        // builds that had nothing to do would hide how long the actual work takes
+        let rebuilds = records
+            .iter()
+            .copied()
+            .filter(|record| {
+                record
+                    .counters
+                    .compiled
+                    > 0
+            })
+            .collect::<Vec<_>>();
+        let latest = &rebuilds[rebuilds
+            .len()
+            .saturating_sub(self.last)..];
+        let before = &rebuilds[..rebuilds.len() - latest.len()];
+        let before = &before[before
+            .len()
+            .saturating_sub(self.last)..];
+        match (
+            average_duration(latest),
+            average_duration(before),
+        ) {
+            (Some(latest_average), Some(before_average)) => println!(
+                "trend: last {} builds that compiled took {:.2}s on average, \
+                 {} before them {:.2}s ({:+.1}%)",
+                latest.len(),
+                latest_average.as_secs_f64(),
+                before.len(),
+                before_average.as_secs_f64(),
+                (latest_average.as_secs_f64() / before_average.as_secs_f64() - 1.0) * 100.0
+            ),
+            (Some(latest_average), None) => println!(
+                "trend: last {} builds that compiled took {:.2}s on average, \
+                 not enough builds before them to compare",
+                latest.len(),
+                latest_average.as_secs_f64()
+            ),
+            _ => println!("trend: no builds that compiled anything recorded yet"),
+        }
+
+        Ok(())
+    }
+}
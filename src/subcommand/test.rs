@@ -0,0 +1,434 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::rc::Rc;
+
+use indexmap::IndexMap;
+
+use super::Flag;
+use crate::configuration;
+use crate::configuration::runner_preset;
+use crate::configuration::ConfigSource;
+use crate::configuration::Configuration;
+use crate::configuration::TestOutcome;
+use crate::configuration::TestSelection;
+use crate::error_code::Code;
+use crate::error_code::ErrorCode;
+use crate::lsd::Value;
+use crate::profile;
+use crate::profile::DEFAULT_PROFILE;
+use crate::util::BoolGuardExt;
+use crate::BuildType;
+use crate::Dir;
+use crate::TestError;
+
+pub struct Subcommand {
+    build_type: Option<BuildType>,
+
+    profile: profile::Name,
+
+    runner: Option<Rc<[Value]>>,
+
+    /// Manifest to load instead of `build++.lsd`, `-` for stdin.
+    config: Option<Value>,
+
+    /// Where to write a JUnit XML report of the tests.
+    junit_report: Option<Value>,
+
+    /// Tests to run by their tags (`-tag gpu -skip-tag slow`).
+    selection: TestSelection,
+}
+
+/// Report written by `-report junit` without a path.
+const DEFAULT_JUNIT_REPORT: &str = "target/junit.xml";
+
+#[derive(Debug, Clone)]
+enum InnerParseError {
+    UnknownBuildType,
+
+    UnknownReportFormat(Value),
+}
+
+impl super::InnerParseError for InnerParseError {
+    fn note(&self) -> Option<String> {
+        use InnerParseError::*;
+        match self {
+            UnknownReportFormat(format) => Some(format!(
+                "`{}` is not a report format (only `junit` is)",
+                format
+            )),
+            _ => None,
+        }
+    }
+}
+
+impl From<InnerParseError> for Rc<dyn super::InnerParseError> {
+    fn from(value: InnerParseError) -> Self { Rc::new(value) }
+}
+
+#[derive(Debug, Clone)]
+enum InnerExecuteError {
+    InvalidCurrentDir(Rc<io::Error>),
+
+    CannotLoadConfiguration(configuration::LoadError),
+
+    TestError(TestError),
+
+    CouldNotWriteReport(Rc<io::Error>),
+
+    TestsFailed(Rc<[Value]>),
+}
+
+impl super::InnerExecuteError for InnerExecuteError {
+    fn code(&self) -> Option<Code> {
+        use InnerExecuteError::*;
+        match self {
+            CannotLoadConfiguration(err) => Some(err.code()),
+            TestError(crate::TestError::BuildError(err)) => Some(err.code()),
+            _ => None,
+        }
+    }
+
+    fn note(&self) -> Option<String> {
+        use InnerExecuteError::*;
+        match self {
+            CannotLoadConfiguration(err) => err.note(),
+            InvalidCurrentDir(err) | CouldNotWriteReport(err) => Some(err.to_string()),
+            TestsFailed(tests) => Some(format!(
+                "failed: {}",
+                tests.join(", ")
+            )),
+            _ => None,
+        }
+    }
+}
+
+impl From<InnerExecuteError> for Rc<dyn super::InnerExecuteError> {
+    fn from(value: InnerExecuteError) -> Self { Rc::new(value) }
+}
+
+fn parse_build_type(build_type: Value) -> Result<BuildType, InnerParseError> {
+    use InnerParseError::*;
+
+    Ok(build_type
+        .parse()
+        .map_err(|()| UnknownBuildType)?)
+}
+
+fn parse_runner(runner: Rc<[Value]>) -> Rc<[Value]> {
+    // `-runner valgrind` is a preset, `-runner some wrapper` is used as is
+    match &*runner {
+        [name] => runner_preset(name).unwrap_or(runner),
+        _ => runner,
+    }
+}
+
+fn parse_report(report: Value) -> Result<Value, InnerParseError> {
+    use InnerParseError::*;
+
+    // `junit=path.xml`, or `junit` for the default path
+    let (format, path) = report
+        .split_once('=')
+        .unwrap_or((&report, DEFAULT_JUNIT_REPORT));
+    format
+        .eq_ignore_ascii_case("junit")
+        .or_else_err(|| UnknownReportFormat(format.into()))?;
+
+    Ok(path.into())
+}
+
+/// Tags given as separate values or separated by commas (ex. `-tag gpu,cuda slow`).
+fn parse_tags(tags: Rc<[Value]>) -> Vec<Value> {
+    tags.iter()
+        .flat_map(|tags| tags.split(','))
+        .filter(|tag| !tag.is_empty())
+        .map(Value::from)
+        .collect()
+}
+
+/// `text` usable in XML attributes and text, without characters XML does not allow.
+fn xml_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push(ch),
+            ch if ch.is_control() => {},
+            ch => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+/// JUnit XML report of `outcomes`, a suite per test binary with a single case in it.
+fn junit_report(project_name: &str, outcomes: &[TestOutcome]) -> String {
+    let failures = outcomes
+        .iter()
+        .filter(|outcome| !outcome.passed())
+        .count();
+    let mut report = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    report.push_str(&format!(
+        "<testsuites name=\"{}\" tests=\"{}\" failures=\"{}\" time=\"{:.3}\">\n",
+        xml_escape(project_name),
+        outcomes.len(),
+        failures,
+        outcomes
+            .iter()
+            .map(|outcome| {
+                outcome
+                    .duration
+                    .as_secs_f64()
+            })
+            .sum::<f64>()
+    ));
+
+    for outcome in outcomes {
+        let name = xml_escape(&outcome.name);
+        let time = outcome
+            .duration
+            .as_secs_f64();
+        report.push_str(&format!(
+            "  <testsuite name=\"{}\" tests=\"1\" failures=\"{}\" time=\"{:.3}\">\n",
+            name,
+            u8::from(!outcome.passed()),
+            time
+        ));
+        report.push_str(&format!(
+            "    <testcase name=\"{}\" classname=\"{}\" time=\"{:.3}\">\n",
+            name,
+            xml_escape(project_name),
+            time
+        ));
+        let stdout = xml_escape(&String::from_utf8_lossy(
+            &outcome.stdout,
+        ));
+        let stderr = xml_escape(&String::from_utf8_lossy(
+            &outcome.stderr,
+        ));
+        if !outcome.passed() {
+            let message = match outcome.code {
+                Some(code) => format!("exit code {}", code),
+                None => "killed".to_string(),
+            };
+            report.push_str(&format!(
+                "      <failure message=\"{}\">{}{}</failure>\n",
+                message, stdout, stderr
+            ));
+        }
+        if !stdout.is_empty() {
+            report.push_str(&format!(
+                "      <system-out>{}</system-out>\n",
+                stdout
+            ));
+        }
+        if !stderr.is_empty() {
+            report.push_str(&format!(
+                "      <system-err>{}</system-err>\n",
+                stderr
+            ));
+        }
+        report.push_str("    </testcase>\n");
+        report.push_str("  </testsuite>\n");
+    }
+
+    report.push_str("</testsuites>\n");
+    report
+}
+
+fn write_report(path: &Path, report: &str) -> Result<(), io::Error> {
+    if let Some(parent) = path
+        .parent()
+        .filter(|parent| {
+            !parent
+                .as_os_str()
+                .is_empty()
+        })
+    {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, report)
+}
+
+const FLAGS: &[Flag] = &[
+    Flag::one(
+        "is",
+        "type",
+        "Build type (`binary` or `library`) when the project is both",
+    ),
+    Flag::one(
+        "profile",
+        "name",
+        "Profile to use, `default` when not given",
+    ),
+    Flag::optional(
+        "config",
+        "path",
+        "Manifest to load instead of `build++.lsd`, stdin without a path",
+    ),
+    Flag::many(
+        "runner",
+        "command",
+        "Command tests are ran with (ex. `valgrind`)",
+    ),
+    Flag::optional(
+        "report",
+        "junit=path",
+        "Write a JUnit XML report of the tests",
+    ),
+    Flag::many(
+        "tag",
+        "tag",
+        "Only run tests with these tags",
+    ),
+    Flag::many(
+        "skip-tag",
+        "tag",
+        "Do not run tests with these tags",
+    ),
+];
+
+impl super::Subcommand for Subcommand {
+    fn parse(
+        mut flags: IndexMap<Value, Rc<[Value]>>,
+        _post_dash_dash: impl Iterator<Item = String>,
+    ) -> Result<Rc<dyn super::Subcommand>, Rc<dyn super::InnerParseError>> {
+        let build_type = flags
+            .shift_remove("is")
+            .and_then(super::single_value)
+            .map(parse_build_type)
+            .transpose()?;
+
+        let profile = flags
+            .shift_remove("profile")
+            .and_then(super::single_value)
+            .unwrap_or_else(|| DEFAULT_PROFILE.into());
+
+        let runner = flags
+            .shift_remove("runner")
+            .map(parse_runner);
+
+        let config = flags
+            .shift_remove("config")
+            .map(super::config_value);
+
+        let junit_report = flags
+            .shift_remove("report")
+            .and_then(super::single_value)
+            .map(parse_report)
+            .transpose()?;
+
+        let selection = TestSelection {
+            tags: flags
+                .shift_remove("tag")
+                .map(parse_tags)
+                .unwrap_or_default(),
+            skip_tags: flags
+                .shift_remove("skip-tag")
+                .map(parse_tags)
+                .unwrap_or_default(),
+        };
+
+        Ok(Rc::new(Subcommand {
+            build_type,
+            profile,
+            runner,
+            config,
+            junit_report,
+            selection,
+        }))
+    }
+
+    fn flags() -> &'static [Flag] { FLAGS }
+
+    fn execute(&self) -> Result<(), Rc<dyn super::InnerExecuteError>> {
+        use InnerExecuteError::*;
+
+        let project_dir = Dir::from(
+            std::env::current_dir()
+                .map_err(Rc::new)
+                .map_err(InvalidCurrentDir)?,
+        );
+
+        let source = self
+            .config
+            .as_ref()
+            .map(|config| ConfigSource::from_argument(&project_dir, config))
+            .unwrap_or_default();
+        let config =
+            Configuration::load_from(project_dir, source).map_err(CannotLoadConfiguration)?;
+
+        // flag overrides configuration
+        let runner = self
+            .runner
+            .clone()
+            .or_else(|| config.test_runner());
+
+        let outcomes = config
+            .test(
+                self.build_type,
+                &self.profile,
+                runner,
+                &self.selection,
+                self.junit_report
+                    .is_some(),
+            )
+            .map_err(TestError)?;
+
+        let mut failed = Vec::new();
+        for outcome in outcomes.iter() {
+            match outcome.code {
+                Some(0) => println!("test {} ... ok", outcome.name),
+                Some(code) => println!(
+                    "test {} ... FAILED (exit code {})",
+                    outcome.name, code
+                ),
+                None => println!(
+                    "test {} ... FAILED (killed)",
+                    outcome.name
+                ),
+            }
+            if !outcome.passed() {
+                failed.push(
+                    outcome
+                        .name
+                        .clone(),
+                );
+            }
+        }
+
+        println!(
+            "test result: {} passed; {} failed",
+            outcomes.len() - failed.len(),
+            failed.len()
+        );
+
+        if let Some(path) = &self.junit_report {
+            let path = config
+                .project_dir()
+                .join(&**path);
+            write_report(
+                &path,
+                &junit_report(
+                    &config.project_name(),
+                    &outcomes,
+                ),
+            )
+            .map_err(Rc::new)
+            .map_err(CouldNotWriteReport)?;
+            println!(
+                "wrote test report to {}",
+                path.display()
+            );
+        }
+
+        failed
+            .is_empty()
+            .or_else_err(|| TestsFailed(failed.into()))?;
+
+        Ok(())
+    }
+}
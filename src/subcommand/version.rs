@@ -2,6 +2,7 @@ use std::rc::Rc;
 
 use indexmap::IndexMap;
 
+use super::Flag;
 use crate::lsd::Value;
 use crate::util::BoolGuardExt;
 
@@ -28,11 +29,13 @@ impl super::Subcommand for Subcommand {
 
         flags
             .is_empty()
-            .ok_or(ExpectedNoFlags)?;
+            .or_err(ExpectedNoFlags)?;
 
         Ok(Rc::new(Subcommand {}))
     }
 
+    fn flags() -> &'static [Flag] { &[] }
+
     fn execute(&self) -> Result<(), Rc<dyn super::InnerExecuteError>> {
         println!(
             "build++ version {}",
@@ -0,0 +1,277 @@
+use std::env;
+use std::io;
+use std::path::PathBuf;
+use std::process::Command;
+use std::rc::Rc;
+use std::thread;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use indexmap::IndexMap;
+
+use super::Flag;
+use crate::configuration;
+use crate::configuration::Configuration;
+use crate::lsd::Value;
+use crate::profile;
+use crate::profile::DEFAULT_PROFILE;
+use crate::util;
+use crate::BuildType;
+use crate::Dir;
+
+/// How often watched paths are checked for changes, in milliseconds.
+const DEFAULT_INTERVAL: u64 = 500;
+
+pub struct Subcommand {
+    build_type: Option<BuildType>,
+
+    profile: profile::Name,
+
+    interval: Duration,
+
+    /// Show output of dependency builds even when they succeed.
+    verbose: bool,
+
+    extra_flags: Rc<[Value]>,
+}
+
+#[derive(Debug, Clone)]
+enum InnerParseError {
+    UnknownBuildType,
+
+    InvalidInterval(Value),
+}
+
+impl super::InnerParseError for InnerParseError {
+    fn note(&self) -> Option<String> {
+        use InnerParseError::*;
+        match self {
+            InvalidInterval(interval) => Some(format!(
+                "`{}` is not a positive number of milliseconds",
+                interval
+            )),
+            _ => None,
+        }
+    }
+}
+
+impl From<InnerParseError> for Rc<dyn super::InnerParseError> {
+    fn from(value: InnerParseError) -> Self { Rc::new(value) }
+}
+
+#[derive(Debug, Clone)]
+enum InnerExecuteError {
+    InvalidCurrentDir(Rc<io::Error>),
+
+    CouldNotFindOwnExecutable(Rc<io::Error>),
+    BuildFailedSpawn(Rc<io::Error>),
+}
+
+impl super::InnerExecuteError for InnerExecuteError {
+    fn note(&self) -> Option<String> {
+        use InnerExecuteError::*;
+        match self {
+            InvalidCurrentDir(err) | CouldNotFindOwnExecutable(err) | BuildFailedSpawn(err) =>
+                Some(err.to_string()),
+        }
+    }
+}
+
+impl From<InnerExecuteError> for Rc<dyn super::InnerExecuteError> {
+    fn from(value: InnerExecuteError) -> Self { Rc::new(value) }
+}
+
+fn parse_build_type(build_type: Value) -> Result<BuildType, InnerParseError> {
+    use InnerParseError::*;
+
+    Ok(build_type
+        .parse()
+        .map_err(|()| UnknownBuildType)?)
+}
+
+fn parse_interval(interval: Value) -> Result<Duration, InnerParseError> {
+    use InnerParseError::*;
+
+    interval
+        .parse()
+        .ok()
+        .filter(|interval| *interval > 0)
+        .map(Duration::from_millis)
+        .ok_or_else(|| InvalidInterval(interval.clone()))
+}
+
+/// Paths of the project and its `local build` and `local pair` dependencies (recursively),
+/// or just the configuration files while it does not load (ex. while it is being edited).
+fn watched_paths(project_dir: &Dir) -> Vec<PathBuf> {
+    let fallback = || {
+        vec![
+            project_dir.join(configuration::CONFIG_FILENAME),
+            project_dir.join(configuration::LOCAL_CONFIG_FILENAME),
+        ]
+    };
+    match Configuration::load(project_dir.clone()) {
+        Ok(config) => config
+            .watched_paths()
+            .unwrap_or_else(|err| {
+                eprintln!(
+                    "warning: could not find what dependencies to watch: {}",
+                    err
+                );
+                fallback()
+            }),
+        Err(_) => fallback(),
+    }
+}
+
+/// Last change of each path, `None` for those that do not exist,
+/// so that removing or creating one is noticed too.
+fn snapshot(paths: &[PathBuf]) -> Vec<Option<SystemTime>> {
+    // remembered times would hide the changes being waited for
+    util::forget_last_modified();
+    paths
+        .iter()
+        .map(|path| util::last_modified_recursive(path).ok())
+        .collect()
+}
+
+const FLAGS: &[Flag] = &[
+    Flag::one(
+        "is",
+        "type",
+        "Build type (`binary` or `library`) when the project is both",
+    ),
+    Flag::one(
+        "profile",
+        "name",
+        "Profile to use, `default` when not given",
+    ),
+    Flag::one(
+        "interval",
+        "ms",
+        "How often to check for changes, 500 milliseconds when not given",
+    ),
+    Flag::switch(
+        "verbose",
+        "Show output of dependency builds even when they succeed",
+    )
+    .aliases(&["v"]),
+];
+
+impl super::Subcommand for Subcommand {
+    fn parse(
+        mut flags: IndexMap<Value, Rc<[Value]>>,
+        post_dash_dash: impl Iterator<Item = String>,
+    ) -> Result<Rc<dyn super::Subcommand>, Rc<dyn super::InnerParseError>> {
+        let build_type = flags
+            .shift_remove("is")
+            .and_then(super::single_value)
+            .map(parse_build_type)
+            .transpose()?;
+
+        let profile = flags
+            .shift_remove("profile")
+            .and_then(super::single_value)
+            .unwrap_or_else(|| DEFAULT_PROFILE.into());
+
+        let interval = flags
+            .shift_remove("interval")
+            .and_then(super::single_value)
+            .map(parse_interval)
+            .transpose()?
+            .unwrap_or(Duration::from_millis(
+                DEFAULT_INTERVAL,
+            ));
+
+        let verbose = flags
+            .shift_remove("verbose")
+            .is_some();
+
+        // passed to the compiler as-is
+        let extra_flags = post_dash_dash
+            .map(Value::from)
+            .collect();
+
+        Ok(Rc::new(Subcommand {
+            build_type,
+            profile,
+            interval,
+            verbose,
+            extra_flags,
+        }))
+    }
+
+    fn flags() -> &'static [Flag] { FLAGS }
+
+    fn execute(&self) -> Result<(), Rc<dyn super::InnerExecuteError>> {
+        use InnerExecuteError::*;
+
+        let project_dir = Dir::from(
+            env::current_dir()
+                .map_err(Rc::new)
+                .map_err(InvalidCurrentDir)?,
+        );
+
+        // every build runs in its own build++, so that configurations are loaded anew and
+        // changed dependencies are recached as in any other build
+        let executable = env::current_exe()
+            .map_err(Rc::new)
+            .map_err(CouldNotFindOwnExecutable)?;
+
+        let mut paths = watched_paths(&project_dir);
+        let mut last = None;
+        loop {
+            let current = snapshot(&paths);
+            if last.as_ref() == Some(&current) {
+                thread::sleep(self.interval);
+                continue;
+            }
+            if last.is_some() {
+                println!("watch: changes found, rebuilding");
+            }
+
+            let mut command = Command::new(&executable);
+            command
+                .arg("build")
+                .arg("-profile")
+                .arg(&*self.profile);
+            if let Some(build_type) = self.build_type {
+                command
+                    .arg("-is")
+                    .arg(build_type.to_string());
+            }
+            if self.verbose {
+                command.arg("-verbose");
+            }
+            if !self
+                .extra_flags
+                .is_empty()
+            {
+                command
+                    .arg("--")
+                    .args(
+                        self.extra_flags
+                            .iter()
+                            .map(Rc::as_ref),
+                    );
+            }
+            let status = command
+                .current_dir(&*project_dir)
+                .status()
+                .map_err(Rc::new)
+                .map_err(BuildFailedSpawn)?;
+            match status.success() {
+                true => println!("watch: build ok, waiting for changes"),
+                false => println!("watch: build FAILED, waiting for changes"),
+            }
+
+            // dependencies may have been added or removed by the change; otherwise the snapshot
+            // from before the build is kept, so that changes made during it cause another one
+            let new_paths = watched_paths(&project_dir);
+            last = match new_paths == paths {
+                true => Some(current),
+                false => Some(snapshot(&new_paths)),
+            };
+            paths = new_paths;
+        }
+    }
+}
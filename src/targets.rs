@@ -0,0 +1,96 @@
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+use indexmap::IndexMap;
+
+use crate::lsd::Level;
+use crate::lsd::Value;
+use crate::lsd::LSD;
+
+/// Versions and profiles that were built, kept in the `target` dir of a project, so that dirs
+/// of those no longer in the configuration can be told apart from other files of the `target`
+/// dir and removed (see `buildpp clean -old`).
+pub const RECORD_FILENAME: &str = "targets.lsd";
+
+/// Built profiles by version.
+pub type Built = IndexMap<Value, Vec<Value>>;
+
+/// What was recorded in `target_dir`, nothing if it cannot be read.
+pub fn read(target_dir: &Path) -> Built {
+    File::open(target_dir.join(RECORD_FILENAME))
+        .ok()
+        .and_then(|file| LSD::parse(file).ok())
+        .and_then(|lsd| lsd.to_level())
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(version, profiles)| {
+            (
+                version,
+                profiles
+                    .to_list()
+                    .unwrap_or_default()
+                    .iter()
+                    .filter_map(|profile| profile.to_value())
+                    .collect(),
+            )
+        })
+        .collect()
+}
+
+fn write(target_dir: &Path, built: &Built) -> Result<(), io::Error> {
+    let level = built
+        .iter()
+        .map(|(version, profiles)| {
+            (
+                version.clone(),
+                LSD::Level(
+                    profiles
+                        .iter()
+                        .enumerate()
+                        .map(|(i, profile)| {
+                            (
+                                i.to_string()
+                                    .into(),
+                                LSD::Value(profile.clone()),
+                            )
+                        })
+                        .collect(),
+                ),
+            )
+        })
+        .collect::<Level>();
+
+    fs::create_dir_all(target_dir)?;
+    fs::write(
+        target_dir.join(RECORD_FILENAME),
+        LSD::Level(level).to_string(),
+    )
+}
+
+/// Adds `profile` of `version` to the record, if it is not there yet.
+pub fn record(target_dir: &Path, version: &str, profile: &str) -> Result<(), io::Error> {
+    let mut built = read(target_dir);
+    let profiles = built
+        .entry(version.into())
+        .or_default();
+    if profiles
+        .iter()
+        .any(|recorded| **recorded == *profile)
+    {
+        return Ok(());
+    }
+    profiles.push(profile.into());
+    write(target_dir, &built)
+}
+
+/// Forgets everything that `keep` returns `false` for (ex. after removing its dir).
+pub fn retain(target_dir: &Path, keep: impl Fn(&str, &str) -> bool) -> Result<(), io::Error> {
+    let mut built = read(target_dir);
+    for (version, profiles) in built.iter_mut() {
+        profiles.retain(|profile| keep(version, profile));
+    }
+    built.retain(|_, profiles| !profiles.is_empty());
+    write(target_dir, &built)
+}
@@ -1,64 +1,108 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::env;
+use std::ffi::OsStr;
+use std::ffi::OsString;
 use std::fs;
 use std::io;
+use std::num::NonZeroUsize;
 use std::path::Path;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::thread;
 use std::time::SystemTime;
 
 //
 // split_file_name
 //
 
-pub fn split_file_name(filename: &str) -> (&str, &str) {
-    let parts = filename
-        .split('.')
-        .collect::<Vec<_>>();
-    match parts.len() {
-        1 => ("", ""),
-        _ => {
-            let ext = parts
-                .last()
-                .unwrap();
-            (
-                filename
-                    .split_at(filename.len() - (ext.len() + 1))
-                    .0,
-                ext,
-            )
-        },
+pub fn split_file_name(filename: &OsStr) -> (&OsStr, &OsStr) {
+    let path = Path::new(filename);
+    match path.extension() {
+        Some(ext) => (
+            path.file_stem()
+                .unwrap_or_default(),
+            ext,
+        ),
+        None => (OsStr::new(""), OsStr::new("")),
     }
 }
 
+//
+// concat_os
+//
+
+/// Like `format!` with only `{}`s, but without losing non-UTF-8 parts of paths.
+pub fn concat_os(parts: &[&OsStr]) -> OsString {
+    let mut result = OsString::new();
+    for part in parts {
+        result.push(part);
+    }
+    result
+}
+
+/// Like `str::replace`, but `to` does not have to be valid UTF-8.
+pub fn replace_os(text: &str, from: &str, to: &OsStr) -> OsString {
+    let mut result = OsString::new();
+    let mut pieces = text.split(from);
+    if let Some(first) = pieces.next() {
+        result.push(first);
+    }
+    for piece in pieces {
+        result.push(to);
+        result.push(piece);
+    }
+    result
+}
+
 //
 // copy_dir_all
 //
 
-pub fn copy_dir_all_filter_extension(
+// NOTE: `DirEntry::file_type` does not follow symlinks, so links are never traversed,
+//       which keeps copying and removal inside of the given root and safe from link cycles
+
+/// Whether files with this extension are headers (and so are part of a library's includes).
+pub fn is_header_extension(extension: &OsStr) -> bool {
+    // https://gcc.gnu.org/onlinedocs/gcc/Overall-Options.html
+    extension == "h" // c
+        || extension == "cuh" // cuda
+        || extension == "hh"
+        || extension == "H"
+        || extension == "hp"
+        || extension == "hxx"
+        || extension == "hpp"
+        || extension == "HPP"
+        || extension == "h++"
+        || extension == "tcc"
+}
+
+/// Copies files for which `filter` holds, given their path and whether they are a directory
+/// (directories filtered out are not entered).
+pub fn copy_dir_all_filter(
     src: impl AsRef<Path>,
     dst: impl AsRef<Path>,
-    extension_filter: &impl Fn(&str) -> bool,
+    filter: &impl Fn(&Path, bool) -> bool,
 ) -> Result<(), io::Error> {
+    forget_last_modified();
     fs::create_dir_all(&dst)?;
     for entry in fs::read_dir(src)? {
         let entry = entry?;
-        if entry
-            .file_type()?
-            .is_dir()
-        {
-            copy_dir_all_filter_extension(
+        let ty = entry.file_type()?;
+        if !filter(&entry.path(), ty.is_dir()) {
+            continue;
+        }
+
+        if ty.is_dir() {
+            copy_dir_all_filter(
                 entry.path(),
                 dst.as_ref()
                     .join(entry.file_name()),
-                extension_filter,
+                filter,
             )?;
-        } else if extension_filter(
-            split_file_name(
-                entry
-                    .file_name()
-                    .to_str()
-                    .unwrap(),
-            )
-            .1,
-        ) {
-            fs::copy(
+        } else {
+            copy_entry(
+                ty.is_symlink(),
                 entry.path(),
                 dst.as_ref()
                     .join(entry.file_name()),
@@ -69,20 +113,20 @@ pub fn copy_dir_all_filter_extension(
 }
 
 pub fn copy_dir_all(src: impl AsRef<Path>, dst: impl AsRef<Path>) -> Result<(), io::Error> {
+    forget_last_modified();
     fs::create_dir_all(&dst)?;
     for entry in fs::read_dir(src)? {
         let entry = entry?;
-        if entry
-            .file_type()?
-            .is_dir()
-        {
+        let ty = entry.file_type()?;
+        if ty.is_dir() {
             copy_dir_all(
                 entry.path(),
                 dst.as_ref()
                     .join(entry.file_name()),
             )?;
         } else {
-            fs::copy(
+            copy_entry(
+                ty.is_symlink(),
                 entry.path(),
                 dst.as_ref()
                     .join(entry.file_name()),
@@ -92,84 +136,300 @@ pub fn copy_dir_all(src: impl AsRef<Path>, dst: impl AsRef<Path>) -> Result<(),
     Ok(())
 }
 
-//
-// remove_dir_all
-//
-
-pub fn remove_dir_all_filter_extension(
+fn copy_entry(
+    is_symlink: bool,
+    src: impl AsRef<Path>,
     dst: impl AsRef<Path>,
-    extension_filter: &impl Fn(&str) -> bool,
 ) -> Result<(), io::Error> {
-    if !dst
+    if !is_symlink {
+        // unchanged files keep their mtime, so that whatever depends on them is not outdated
+        if !is_same_file_content(&src, &dst)? {
+            fs::copy(src, dst)?;
+        }
+        return Ok(());
+    }
+
+    // copy symlink as a link, not as whatever it points to
+    let link_target = fs::read_link(&src)?;
+    if dst
         .as_ref()
-        .exists()
+        .symlink_metadata()
+        .is_ok()
     {
-        return Ok(());
+        remove_file_or_link(&dst)?;
     }
-    for entry in fs::read_dir(dst)? {
-        let entry = entry?;
-        let ty = entry.file_type()?;
-        if ty.is_dir() {
-            remove_dir_all(entry.path())?;
-        } else if extension_filter(
-            split_file_name(
-                entry
-                    .file_name()
-                    .to_str()
-                    .unwrap(),
-            )
-            .1,
-        ) {
-            fs::remove_file(entry.path())?;
-        }
+    if let Err(err) = create_symlink(&link_target, &src, &dst) {
+        eprintln!(
+            "warning: skipping symlink {} (could not recreate it: {})",
+            src.as_ref()
+                .display(),
+            err
+        );
     }
-    // fs::remove_dir(dst)?;
     Ok(())
 }
 
+/// Whether `dst` is a previous copy of `src` that did not change since (same size, not older).
+fn is_same_file_content(src: impl AsRef<Path>, dst: impl AsRef<Path>) -> Result<bool, io::Error> {
+    let dst = match dst
+        .as_ref()
+        .symlink_metadata()
+    {
+        Ok(metadata) if metadata.is_file() => metadata,
+        Ok(_) => return Ok(false),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(false),
+        Err(err) => return Err(err),
+    };
+    let src = src
+        .as_ref()
+        .metadata()?;
+    Ok(src.len() == dst.len() && src.modified()? <= dst.modified()?)
+}
+
+/// Makes `dst` a symlink to `link_target` (relative to the dir of `dst`), replacing what was there.
+pub fn replace_symlink(
+    link_target: impl AsRef<Path>,
+    dst: impl AsRef<Path>,
+) -> Result<(), io::Error> {
+    let dst = dst.as_ref();
+    if dst
+        .symlink_metadata()
+        .is_ok()
+    {
+        remove_file_or_link(dst)?;
+    }
+    let src = dst
+        .parent()
+        .unwrap_or(Path::new(""))
+        .join(&link_target);
+    create_symlink(link_target, src, dst)
+}
+
+#[cfg(unix)]
+fn create_symlink(
+    link_target: impl AsRef<Path>,
+    _src: impl AsRef<Path>,
+    dst: impl AsRef<Path>,
+) -> Result<(), io::Error> {
+    std::os::unix::fs::symlink(link_target, dst)
+}
+
+#[cfg(windows)]
+fn create_symlink(
+    link_target: impl AsRef<Path>,
+    src: impl AsRef<Path>,
+    dst: impl AsRef<Path>,
+) -> Result<(), io::Error> {
+    // windows needs to know what kind of link it is
+    match src
+        .as_ref()
+        .is_dir()
+    {
+        true => std::os::windows::fs::symlink_dir(link_target, dst),
+        false => std::os::windows::fs::symlink_file(link_target, dst),
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+fn create_symlink(
+    _link_target: impl AsRef<Path>,
+    _src: impl AsRef<Path>,
+    _dst: impl AsRef<Path>,
+) -> Result<(), io::Error> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+//
+// remove_dir_all
+//
+
 pub fn remove_dir_all(dst: impl AsRef<Path>) -> Result<(), io::Error> {
-    if !dst
+    let ty = match dst
         .as_ref()
-        .exists()
+        .symlink_metadata()
     {
-        return Ok(());
+        Ok(metadata) => metadata.file_type(),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(err),
+    };
+    forget_last_modified();
+
+    // only the link itself is removed, never what it points to
+    if ty.is_symlink() {
+        return remove_file_or_link(dst);
     }
+
     for entry in fs::read_dir(dst.as_ref())? {
         let entry = entry?;
-        if entry
-            .file_type()?
-            .is_dir()
-        {
+        let ty = entry.file_type()?;
+        if ty.is_dir() {
             remove_dir_all(entry.path())?;
         } else {
-            fs::remove_file(entry.path())?;
+            remove_file_or_link(entry.path())?;
         }
     }
     fs::remove_dir(dst)?;
     Ok(())
 }
 
+/// Removes a file or a link (directory links on windows can only be removed as directories).
+fn remove_file_or_link(dst: impl AsRef<Path>) -> Result<(), io::Error> {
+    forget_last_modified();
+    fs::remove_file(&dst).or_else(|err| fs::remove_dir(&dst).map_err(|_| err))
+}
+
 //
 // last_modified_recursive
 //
 
+thread_local! {
+    /// Results of `last_modified_recursive` during this invocation.
+    ///
+    /// Trees are scanned multiple times per build (per dependency, then for the target check),
+    /// so each is only walked once until something is written with `forget_last_modified`.
+    static LAST_MODIFIED: RefCell<HashMap<PathBuf, SystemTime>> = RefCell::default();
+}
+
 pub fn last_modified_recursive(entry: impl AsRef<Path>) -> Result<SystemTime, io::Error> {
+    let entry = entry.as_ref();
+    if let Some(modified) = LAST_MODIFIED.with_borrow(|memo| {
+        memo.get(entry)
+            .copied()
+    }) {
+        return Ok(modified);
+    }
+
+    let modified = last_modified_parallel(entry)?;
+    LAST_MODIFIED.with_borrow_mut(|memo| memo.insert(entry.to_path_buf(), modified));
+    Ok(modified)
+}
+
+/// Has to be called after writing files, since any of the remembered trees may have changed.
+pub fn forget_last_modified() { LAST_MODIFIED.with_borrow_mut(|memo| memo.clear()) }
+
+thread_local! {
+    /// Results of `tree_hash` during this invocation, by tree and its modification time.
+    static TREE_HASHES: RefCell<HashMap<(PathBuf, SystemTime), String>> = RefCell::default();
+}
+
+/// Hash of names and contents of all files in `dir` (nothing if it does not exist).
+///
+/// Only read again once something in the tree is modified.
+pub fn tree_hash(dir: &Path) -> Result<String, io::Error> {
+    if !dir.is_dir() {
+        return Ok(sha256_hex(&[]));
+    }
+    let key = (
+        dir.to_path_buf(),
+        last_modified_recursive(dir)?,
+    );
+    if let Some(hash) = TREE_HASHES.with_borrow(|memo| {
+        memo.get(&key)
+            .cloned()
+    }) {
+        return Ok(hash);
+    }
+
+    let mut files = Vec::new();
+    list_files(dir, &mut files)?;
+    files.sort();
+    let mut lines = Vec::new();
+    for file in files {
+        lines.push(format!(
+            "{} {}",
+            file.display(),
+            sha256_hex(&fs::read(&file)?)
+        ));
+    }
+    let hash = sha256_hex(
+        lines
+            .join("\n")
+            .as_bytes(),
+    );
+    TREE_HASHES.with_borrow_mut(|memo| memo.insert(key, hash.clone()));
+    Ok(hash)
+}
+
+fn last_modified_parallel(entry: &Path) -> Result<SystemTime, io::Error> {
     let mut modified = entry
-        .as_ref()
         .metadata()?
         .modified()?;
-    if entry
-        .as_ref()
-        .is_dir()
-    {
+    if !entry.is_dir() {
+        return Ok(modified);
+    }
+
+    // files are cheap to stat here, subdirs are split between threads
+    let mut subdirs = Vec::new();
+    for entry in fs::read_dir(entry)? {
+        let entry = entry?;
+        if entry
+            .file_type()?
+            .is_dir()
+        {
+            subdirs.push(entry.path());
+        } else {
+            modified = modified.max(
+                entry
+                    .metadata()?
+                    .modified()?,
+            );
+        }
+    }
+
+    let threads = thread::available_parallelism()
+        .map_or(1, NonZeroUsize::get)
+        .min(subdirs.len());
+    if threads <= 1 {
+        for subdir in subdirs {
+            modified = modified.max(last_modified_walk(&subdir)?);
+        }
+        return Ok(modified);
+    }
+
+    thread::scope(|scope| {
+        let handles = subdirs
+            .chunks(
+                subdirs
+                    .len()
+                    .div_ceil(threads),
+            )
+            .map(|chunk| {
+                scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .try_fold(
+                            SystemTime::UNIX_EPOCH,
+                            |modified, subdir| {
+                                Ok::<_, io::Error>(modified.max(last_modified_walk(subdir)?))
+                            },
+                        )
+                })
+            })
+            .collect::<Vec<_>>();
+        for handle in handles {
+            modified = modified.max(
+                handle
+                    .join()
+                    .unwrap()?,
+            );
+        }
+        Ok(modified)
+    })
+}
+
+fn last_modified_walk(entry: &Path) -> Result<SystemTime, io::Error> {
+    let mut modified = entry
+        .metadata()?
+        .modified()?;
+    if entry.is_dir() {
         for entry in fs::read_dir(entry)? {
             let entry = entry?;
             if entry
                 .file_type()?
                 .is_dir()
             {
-                modified = modified.max(last_modified_recursive(
-                    entry.path(),
+                modified = modified.max(last_modified_walk(
+                    &entry.path(),
                 )?);
             } else {
                 modified = modified.max(
@@ -184,22 +444,561 @@ pub fn last_modified_recursive(entry: impl AsRef<Path>) -> Result<SystemTime, io
 }
 
 //
-// ok_or
+// list_files
+//
+
+/// Files in `dir` and its subdirs, nothing if it does not exist.
+///
+/// Links to directories are not entered, as they may point back up (ex. to `..`).
+pub fn list_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), io::Error> {
+    if !dir.is_dir() {
+        return Ok(());
+    }
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let ty = entry.file_type()?;
+        let path = entry.path();
+        if ty.is_dir() {
+            list_files(&path, files)?;
+        } else if !(ty.is_symlink() && path.is_dir()) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Total size of files in `dir` and its subdirs.
+pub fn dir_size(dir: &Path) -> Result<u64, io::Error> {
+    let mut files = Vec::new();
+    list_files(dir, &mut files)?;
+    let mut size = 0;
+    for file in files {
+        size += file
+            .metadata()?
+            .len();
+    }
+    Ok(size)
+}
+
+//
+// format_size
+//
+
+pub fn format_size(bytes: u64) -> String {
+    match bytes {
+        0..1024 => format!("{} B", bytes),
+        1024..1048576 => format!(
+            "{:.1} KiB",
+            bytes as f64 / 1024.0
+        ),
+        _ => format!(
+            "{:.1} MiB",
+            bytes as f64 / 1048576.0
+        ),
+    }
+}
+
+/// How long ago `then` was, roughly (ex. `5m ago`), both in seconds since the epoch.
+pub fn format_ago(now: u64, then: u64) -> String {
+    let seconds = now.saturating_sub(then);
+    match seconds {
+        0..60 => format!("{}s ago", seconds),
+        60..3600 => format!("{}m ago", seconds / 60),
+        3600..86400 => format!("{}h ago", seconds / 3600),
+        _ => format!("{}d ago", seconds / 86400),
+    }
+}
+
+//
+// glob_match
+//
+
+/// Whether `path` (`/`-separated) matches `pattern`, where `?` matches a character,
+/// `*` any characters but `/`, and `**` any characters including `/`
+/// (`src/**/detail.hpp` also matches `src/detail.hpp`, `src/detail/**` also matches `src/detail`).
+pub fn glob_match(pattern: &str, path: &str) -> bool {
+    fn matches(pattern: &[char], path: &[char]) -> bool {
+        match pattern {
+            [] => path.is_empty(),
+            ['/', '*', '*'] if path.is_empty() => true,
+            ['*', '*', '/', rest @ ..] =>
+                matches(rest, path)
+                    || (0..path.len())
+                        .filter(|&i| path[i] == '/')
+                        .any(|i| matches(rest, &path[i + 1..])),
+            ['*', '*', rest @ ..] => (0..=path.len()).any(|i| matches(rest, &path[i..])),
+            ['*', rest @ ..] => (0..=path.len())
+                .take_while(|&i| i == 0 || path[i - 1] != '/')
+                .any(|i| matches(rest, &path[i..])),
+            ['?', rest @ ..] => path
+                .split_first()
+                .is_some_and(|(&c, path)| c != '/' && matches(rest, path)),
+            [c, rest @ ..] => path
+                .split_first()
+                .is_some_and(|(d, path)| c == d && matches(rest, path)),
+        }
+    }
+
+    matches(
+        &pattern
+            .chars()
+            .collect::<Vec<_>>(),
+        &path
+            .chars()
+            .collect::<Vec<_>>(),
+    )
+}
+
+//
+// closest_match
+//
+
+/// Candidate a mistyped `name` was likely meant to be, if any is close enough to it
+/// (ex. `profile` for `profiel`).
+pub fn closest_match<'a>(
+    name: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+) -> Option<&'a str> {
+    candidates
+        .into_iter()
+        .map(|candidate| {
+            (
+                edit_distance(name, candidate),
+                candidate,
+            )
+        })
+        // a third of the letters may be wrong (at least one, for short names)
+        .filter(|(distance, candidate)| {
+            *distance
+                <= (candidate
+                    .chars()
+                    .count()
+                    / 3)
+                .max(1)
+        })
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// How many characters have to be inserted, removed, replaced or swapped with the next one
+/// to turn `from` into `to` (ex. 1 for `biuld` and `build`).
+pub fn edit_distance(from: &str, to: &str) -> usize {
+    let from = from
+        .chars()
+        .collect::<Vec<_>>();
+    let to = to
+        .chars()
+        .collect::<Vec<_>>();
+
+    // distances[i][j] is the distance between the first i chars of `from` and j chars of `to`
+    let mut distances = vec![vec![0; to.len() + 1]; from.len() + 1];
+    for (i, row) in distances
+        .iter_mut()
+        .enumerate()
+    {
+        row[0] = i;
+    }
+    for (j, distance) in distances[0]
+        .iter_mut()
+        .enumerate()
+    {
+        *distance = j;
+    }
+
+    for i in 1..=from.len() {
+        for j in 1..=to.len() {
+            let replaced = distances[i - 1][j - 1] + usize::from(from[i - 1] != to[j - 1]);
+            let mut distance = replaced
+                .min(distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1);
+            if i > 1 && j > 1 && from[i - 1] == to[j - 2] && from[i - 2] == to[j - 1] {
+                distance = distance.min(distances[i - 2][j - 2] + 1);
+            }
+            distances[i][j] = distance;
+        }
+    }
+
+    distances[from.len()][to.len()]
+}
+
+//
+// find_in_path
+//
+
+pub fn search_path() -> Vec<PathBuf> {
+    env::var_os("PATH")
+        .map(|path| env::split_paths(&path).collect())
+        .unwrap_or_default()
+}
+
+/// Where `command` would be run from: itself if it is a path, otherwise the first match in PATH.
+pub fn find_executable(command: &str) -> Option<PathBuf> {
+    let command = Path::new(command);
+    let candidates = match command
+        .parent()
+        .is_some_and(|parent| {
+            !parent
+                .as_os_str()
+                .is_empty()
+        }) {
+        true => vec![command.to_path_buf()],
+        false => search_path()
+            .into_iter()
+            .map(|dir| dir.join(command))
+            .collect(),
+    };
+
+    candidates
+        .into_iter()
+        .flat_map(|candidate| {
+            match cfg!(windows)
+                && candidate
+                    .extension()
+                    .is_none()
+            {
+                true => vec![candidate.with_extension("exe"), candidate],
+                false => vec![candidate],
+            }
+        })
+        .find(|candidate| is_executable(candidate))
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    fs::metadata(path).is_ok_and(|metadata| {
+        metadata.is_file()
+            && metadata
+                .permissions()
+                .mode()
+                & 0o111
+                != 0
+    })
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool { path.is_file() }
+
+//
+// user_dir
+//
+
+/// `~/.buildpp`, where build++ keeps files of the user (ex. the global configuration).
+pub fn user_dir() -> Option<PathBuf> {
+    env::var_os("HOME")
+        .or_else(|| env::var_os("USERPROFILE"))
+        .map(|home| PathBuf::from(home).join(".buildpp"))
+}
+
+//
+// response files
 //
 
+/// Quote an argument so that it is split back the same way by `CommandLineToArgvW` rules,
+/// which is what MSVC tools use for both command lines and response files.
+pub fn quote_argument(arg: &str) -> String {
+    if !arg.is_empty() && !arg.contains([' ', '\t', '\n', '"']) {
+        return arg.to_string();
+    }
+
+    let mut quoted = String::from('"');
+    let mut backslashes = 0;
+    for ch in arg.chars() {
+        match ch {
+            '\\' => backslashes += 1,
+            '"' => {
+                // escape all preceding backslashes and the quote itself
+                quoted.push_str(&"\\".repeat(backslashes * 2 + 1));
+                quoted.push('"');
+                backslashes = 0;
+            },
+            _ => {
+                quoted.push_str(&"\\".repeat(backslashes));
+                quoted.push(ch);
+                backslashes = 0;
+            },
+        }
+    }
+    // backslashes before the closing quote have to be escaped
+    quoted.push_str(&"\\".repeat(backslashes * 2));
+    quoted.push('"');
+    quoted
+}
+
+pub fn write_response_file(
+    path: impl AsRef<Path>,
+    args: &[impl AsRef<str>],
+) -> Result<(), io::Error> {
+    let text = args
+        .iter()
+        .map(|arg| quote_argument(arg.as_ref()))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    // UTF-16LE with BOM is read correctly regardless of the current code page
+    let mut bytes = vec![0xFF, 0xFE];
+    for unit in text.encode_utf16() {
+        bytes.extend(unit.to_le_bytes());
+    }
+
+    // rewriting the same arguments would make the file look changed (ex. for ninja)
+    if fs::read(&path).is_ok_and(|old| old == bytes) {
+        return Ok(());
+    }
+    fs::write(path, bytes)
+}
+
+//
+// ninja
+//
+
+/// Quote an argument for whatever runs ninja's commands (`/bin/sh -c` or `CreateProcess`).
+pub fn shell_quote(arg: &str) -> String {
+    if cfg!(windows) {
+        return quote_argument(arg);
+    }
+
+    posix_quote(arg)
+}
+
+/// Quote an argument for POSIX shells.
+pub fn posix_quote(arg: &str) -> String {
+    let is_plain = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || "-_./=:@+,%".contains(ch));
+    match is_plain {
+        true => arg.to_string(),
+        false => format!(
+            "'{}'",
+            arg.replace('\'', "'\\''")
+        ),
+    }
+}
+
+/// Escape text for ninja files, where `$` starts variables (`is_path` also escapes separators).
+pub fn ninja_escape(text: &str, is_path: bool) -> String {
+    let text = text.replace('$', "$$");
+    match is_path {
+        true => text
+            .replace(' ', "$ ")
+            .replace(':', "$:"),
+        false => text,
+    }
+}
+
+//
+// json
+//
+
+/// Quote a string for JSON.
+pub fn json_quote(text: &str) -> String {
+    let mut quoted = String::from("\"");
+    for ch in text.chars() {
+        match ch {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            ch if (ch as u32) < 0x20 => quoted.push_str(&format!(
+                "\\u{:04x}",
+                ch as u32
+            )),
+            ch => quoted.push(ch),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+//
+// path components
+//
+
+/// Names Windows reserves for devices, regardless of case and extension (ex. `nul.txt`).
+const RESERVED_WINDOWS_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Encodes `part` (ex. an alias or a version) into a single file name that is the same on
+/// Windows and Unix, so that it cannot point outside of the dir it is joined to.
+///
+/// Bytes of separators, drive colons and others not allowed in file names are written as `%XX`,
+/// as are leading and trailing dots (so that `.` and `..` cannot be named, nor hidden files) and
+/// the first letter of reserved Windows names. Ordinary names are left as they are.
+pub fn encode_path_component(part: &str) -> String {
+    if part.is_empty() {
+        return String::from("%");
+    }
+
+    let reserved = RESERVED_WINDOWS_NAMES.contains(
+        &part
+            .split('.')
+            .next()
+            .unwrap_or_default()
+            .to_uppercase()
+            .as_str(),
+    );
+
+    let mut encoded = String::new();
+    for (i, ch) in part.char_indices() {
+        let is_edge_dot = ch == '.' && (i == 0 || i == part.len() - 1);
+        let keep = match ch {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' | '+' | '@' | '~' =>
+                !(reserved && i == 0),
+            '.' => !is_edge_dot,
+            ch => !ch.is_ascii(),
+        };
+        match keep {
+            true => encoded.push(ch),
+            false => {
+                let mut bytes = [0; 4];
+                for byte in ch
+                    .encode_utf8(&mut bytes)
+                    .bytes()
+                {
+                    encoded.push_str(&format!("%{:02X}", byte));
+                }
+            },
+        }
+    }
+    encoded
+}
+
+/// Reverses [`encode_path_component`], leaving anything not encoded by it as it is.
+pub fn decode_path_component(encoded: &str) -> String {
+    if encoded == "%" {
+        return String::new();
+    }
+
+    let mut bytes = Vec::new();
+    let mut rest = encoded.as_bytes();
+    while let Some((&byte, after)) = rest.split_first() {
+        let hex = after
+            .get(..2)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (byte, hex) {
+            (b'%', Some(decoded)) => {
+                bytes.push(decoded);
+                rest = &after[2..];
+            },
+            _ => {
+                bytes.push(byte);
+                rest = after;
+            },
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+//
+// sha256
+//
+
+const SHA256_K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/// SHA-256 of `data` as lowercase hex.
+pub fn sha256_hex(data: &[u8]) -> String {
+    let mut state: [u32; 8] = [
+        0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab,
+        0x5be0cd19,
+    ];
+
+    // padding: 0x80, zeros, then length in bits, up to a multiple of 64 bytes
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend((data.len() as u64 * 8).to_be_bytes());
+
+    for block in message.chunks_exact(64) {
+        let mut w = [0u32; 64];
+        for (i, word) in block
+            .chunks_exact(4)
+            .enumerate()
+        {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(w[i - 7])
+                .wrapping_add(s1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = state;
+        for (k, w) in SHA256_K
+            .iter()
+            .zip(w)
+        {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ (!e & g);
+            let t1 = h
+                .wrapping_add(s1)
+                .wrapping_add(ch)
+                .wrapping_add(*k)
+                .wrapping_add(w);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(maj);
+
+            h = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.wrapping_add(t2);
+        }
+
+        for (state, value) in state
+            .iter_mut()
+            .zip([a, b, c, d, e, f, g, h])
+        {
+            *state = state.wrapping_add(value);
+        }
+    }
+
+    state
+        .iter()
+        .map(|word| format!("{:08x}", word))
+        .collect()
+}
+
+//
+// or_err
+//
+
+/// Named apart from the unstable `bool::ok_or`, which would otherwise take over once stable.
 pub trait BoolGuardExt {
-    fn ok_or<E>(self, error: E) -> Result<(), E>;
+    fn or_err<E>(self, error: E) -> Result<(), E>;
 
-    fn ok_or_else<E>(self, error: impl FnOnce() -> E) -> Result<(), E>;
+    fn or_else_err<E>(self, error: impl FnOnce() -> E) -> Result<(), E>;
 }
 
 impl BoolGuardExt for bool {
-    fn ok_or<E>(self, error: E) -> Result<(), E> {
+    fn or_err<E>(self, error: E) -> Result<(), E> {
         self.then_some(())
             .ok_or(error)
     }
 
-    fn ok_or_else<E>(self, error: impl FnOnce() -> E) -> Result<(), E> {
+    fn or_else_err<E>(self, error: impl FnOnce() -> E) -> Result<(), E> {
         self.then_some(())
             .ok_or_else(error)
     }
@@ -227,6 +1026,10 @@ impl TryReplace for bool {
     type With = bool;
 }
 
+impl<T: ?Sized> TryReplace for Rc<T> {
+    type With = Rc<T>;
+}
+
 // TODO TryReplace for all primitives
 
 //
@@ -324,16 +1127,22 @@ pub fn count_indent(text: &str) -> usize {
 }
 
 pub fn format_multiline_code(text: &str) -> String {
+    // blank lines have no indent to go by
     let to_trim = text
         .lines()
         .skip(1)
+        .filter(|line| {
+            !line
+                .trim()
+                .is_empty()
+        })
         .map(count_indent)
         .min()
         .unwrap_or_default();
     text.lines()
         .map(|line| {
-            line.split_at(to_trim)
-                .1
+            line.get(to_trim..)
+                .unwrap_or_default()
         })
         .fold(String::new(), |a, b| {
             a + b + "\n"
@@ -341,3 +1150,108 @@ pub fn format_multiline_code(text: &str) -> String {
         .trim()
         .to_string()
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn ordinary_path_components_are_kept() {
+        for part in [
+            "fmt",
+            "1.2.3",
+            "release",
+            "lib-foo_bar+2",
+            "v1.0.0-rc.1",
+            "ünïcode",
+        ] {
+            assert_eq!(
+                encode_path_component(part),
+                part
+            );
+        }
+    }
+
+    #[test]
+    fn hostile_path_components_are_single_file_names() {
+        for part in [
+            "",
+            ".",
+            "..",
+            "../..",
+            "../../etc/passwd",
+            "/absolute",
+            "a/b",
+            "a\\b",
+            "..\\..\\windows",
+            "C:",
+            "C:\\Windows",
+            "\\\\server\\share",
+            ".hidden",
+            "trailing.",
+            "nul",
+            "CON",
+            "com1.txt",
+            "what?*<>|\"",
+            "tab\tnewline\n",
+            "%41",
+        ] {
+            let encoded = encode_path_component(part);
+            assert!(
+                !encoded.is_empty(),
+                "{:?}",
+                part
+            );
+            assert!(
+                !encoded.contains(['/', '\\', ':', '*', '?', '"', '<', '>', '|']),
+                "{:?} as {:?}",
+                part,
+                encoded
+            );
+            assert!(
+                !encoded.starts_with('.') && !encoded.ends_with('.'),
+                "{:?} as {:?}",
+                part,
+                encoded
+            );
+            assert!(
+                !RESERVED_WINDOWS_NAMES.contains(
+                    &encoded
+                        .split('.')
+                        .next()
+                        .unwrap_or_default()
+                        .to_uppercase()
+                        .as_str()
+                ),
+                "{:?} as {:?}",
+                part,
+                encoded
+            );
+            assert_eq!(
+                Path::new(&encoded)
+                    .components()
+                    .count(),
+                1,
+                "{:?} as {:?}",
+                part,
+                encoded
+            );
+            assert_eq!(
+                decode_path_component(&encoded),
+                part
+            );
+        }
+    }
+
+    #[test]
+    fn distinct_path_components_stay_distinct() {
+        let parts = ["a/b", "a%2Fb", "a_b", "%", "", "..", "%2E%2E"];
+        let encoded = parts
+            .iter()
+            .map(|part| encode_path_component(part))
+            .collect::<HashSet<_>>();
+        assert_eq!(encoded.len(), parts.len());
+    }
+}
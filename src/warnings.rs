@@ -0,0 +1,193 @@
+use std::cell::RefCell;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+use indexmap::IndexMap;
+
+use crate::lsd::Level;
+use crate::lsd::Value;
+use crate::lsd::LSD;
+
+/// Warnings of the last builds per file, by profile, kept in the `target` dir of a project.
+/// Counts only go down on their own (as warnings are fixed), so that `-deny-new-warnings`
+/// keeps failing until new warnings are fixed. Remove the file to start over.
+pub const BASELINE_FILENAME: &str = "warnings.lsd";
+
+/// Warnings by the file they were reported in.
+pub type Counts = IndexMap<Value, usize>;
+
+thread_local! {
+    /// Builds currently counting warnings, innermost (ex. a dependency being built) last.
+    static COUNTS: RefCell<Vec<Counts>> = RefCell::default();
+}
+
+/// Starts counting warnings for a build, until [`end`].
+pub fn begin() {
+    COUNTS.with(|counts| {
+        counts
+            .borrow_mut()
+            .push(Counts::default())
+    });
+}
+
+/// Stops counting for the innermost build, returning what was counted.
+pub fn end() -> Counts {
+    COUNTS.with(|counts| {
+        counts
+            .borrow_mut()
+            .pop()
+            .unwrap_or_default()
+    })
+}
+
+pub fn is_counting() -> bool {
+    COUNTS.with(|counts| {
+        !counts
+            .borrow()
+            .is_empty()
+    })
+}
+
+/// File a line of compiler output reports a warning in, if it does
+/// (ex. `src/main.cu:3:5: warning: ...` or `src\main.cpp(3): warning C4100: ...`).
+fn warning_file(line: &str) -> Option<&str> {
+    let (location, _) = line.split_once(": warning")?;
+    let is_position = |position: &str| {
+        !position.is_empty()
+            && position
+                .chars()
+                .all(|ch| ch.is_ascii_digit() || ch == ',')
+    };
+
+    // `file(line)` or `file(line,column)` (ex. of nvcc or msvc)
+    if let Some((file, position)) = location
+        .strip_suffix(')')
+        .and_then(|location| location.rsplit_once('('))
+    {
+        return is_position(position).then_some(file);
+    }
+
+    // `file:line` or `file:line:column` (ex. of gcc or clang)
+    let (file, position) = location.rsplit_once(':')?;
+    if !is_position(position) {
+        return None;
+    }
+    Some(match file.rsplit_once(':') {
+        Some((file, line)) if is_position(line) => file,
+        _ => file,
+    })
+}
+
+/// Counts warnings in `output` of a compiler for the innermost build.
+pub fn record(output: &str) {
+    COUNTS.with(|counts| {
+        if let Some(counts) = counts
+            .borrow_mut()
+            .last_mut()
+        {
+            for file in output
+                .lines()
+                .filter_map(warning_file)
+            {
+                *counts
+                    .entry(
+                        file.trim()
+                            .into(),
+                    )
+                    .or_default() += 1;
+            }
+        }
+    });
+}
+
+fn read_all(target_dir: &Path) -> Level {
+    File::open(target_dir.join(BASELINE_FILENAME))
+        .ok()
+        .and_then(|file| LSD::parse(file).ok())
+        .and_then(|lsd| lsd.to_level())
+        .unwrap_or_default()
+}
+
+/// Baseline of `profile`, `None` if there is none yet.
+pub fn read_baseline(target_dir: &Path, profile: &str) -> Option<Counts> {
+    let level = read_all(target_dir)
+        .get(profile)?
+        .to_level()?;
+    Some(
+        level
+            .into_iter()
+            .filter_map(|(file, count)| {
+                count
+                    .to_value()?
+                    .parse()
+                    .ok()
+                    .map(|count| (file, count))
+            })
+            .collect(),
+    )
+}
+
+/// Replaces the baseline of `profile`, keeping those of other profiles.
+pub fn write_baseline(
+    target_dir: &Path,
+    profile: &str,
+    baseline: &Counts,
+) -> Result<(), io::Error> {
+    let mut level = read_all(target_dir);
+    level.insert(
+        profile.into(),
+        LSD::Level(
+            baseline
+                .iter()
+                .map(|(file, count)| {
+                    (
+                        file.clone(),
+                        LSD::Value(
+                            count
+                                .to_string()
+                                .into(),
+                        ),
+                    )
+                })
+                .collect(),
+        ),
+    );
+
+    fs::create_dir_all(target_dir)?;
+    fs::write(
+        target_dir.join(BASELINE_FILENAME),
+        LSD::Level(level).to_string(),
+    )
+}
+
+/// Files with more warnings than in `baseline` (where missing files have none),
+/// with how many they have and how many the baseline has.
+pub fn increased<'a>(counts: &'a Counts, baseline: &Counts) -> Vec<(&'a Value, usize, usize)> {
+    counts
+        .iter()
+        .filter_map(|(file, &count)| {
+            let allowed = baseline
+                .get(file)
+                .copied()
+                .unwrap_or(0);
+            (count > allowed).then_some((file, count, allowed))
+        })
+        .collect()
+}
+
+/// `baseline` lowered to `counts` wherever they have fewer warnings.
+pub fn lowered(baseline: &Counts, counts: &Counts) -> Counts {
+    baseline
+        .iter()
+        .filter_map(|(file, &allowed)| {
+            let count = counts
+                .get(file)
+                .copied()
+                .unwrap_or(0)
+                .min(allowed);
+            (count > 0).then(|| (file.clone(), count))
+        })
+        .collect()
+}